@@ -0,0 +1,200 @@
+//!
+//! Tokenization throughput on a few kinds of documents, against serde_json
+//! and simd-json building a tree of values from the same text.
+//!
+//! The corpora are generated, so that the benchmarks need no downloads:
+//! a search result shaped like twitter.json, mostly strings and small
+//! objects, a polygon shaped like canada.json, mostly numbers, and a
+//! thousand small telemetry messages, parsed one at a time. The real
+//! twitter.json and canada.json, from nativejson-benchmark, are used
+//! instead when they are found in benches/data.
+//!
+//! Run with `cargo bench`. To check a change for regressions, save a
+//! baseline before it with `cargo bench -- --save-baseline before`, then
+//! compare with `cargo bench -- --baseline before`.
+//!
+
+use std::fs;
+use std::hint::black_box;
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use jsmn_rs::count_tokens;
+use jsmn_rs::session::Session;
+
+/// A xorshift generator, so that the corpora are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn word(&mut self) -> String {
+        let len = 2 + self.below(8) as usize;
+        (0..len)
+            .map(|_| (b'a' + self.below(26) as u8) as char)
+            .collect()
+    }
+
+    fn sentence(&mut self, words: usize) -> String {
+        let words: Vec<_> = (0..words).map(|_| self.word()).collect();
+        words.join(" ")
+    }
+}
+
+/// Reads benches/data/`name`, or generates a document like it.
+fn corpus(name: &str, generate: fn(&mut Rng) -> String) -> String {
+    let path = format!("{}/benches/data/{}", env!("CARGO_MANIFEST_DIR"), name);
+    fs::read_to_string(path).unwrap_or_else(|_| generate(&mut Rng(0x2545_f491_4f6c_dd1d)))
+}
+
+fn twitter(rng: &mut Rng) -> String {
+    let statuses: Vec<_> = (0..400)
+        .map(|i| {
+            let id = 505_874_924_095_815_681 + rng.below(1 << 20);
+            let hashtags: Vec<_> = (0..rng.below(3))
+                .map(|_| format!(r#"{{"text": "{}", "indices": [0, 8]}}"#, rng.word()))
+                .collect();
+            format!(
+                r#"{{"created_at": "Sun Aug 31 00:29:15 +0000 2014", "id": {id}, "id_str": "{id}", "text": "{} été \"{}\"\n{}", "truncated": false, "in_reply_to_status_id": null, "user": {{"id": {}, "name": "{}", "screen_name": "{}", "location": "{}", "description": "{}", "followers_count": {}, "friends_count": {}, "verified": {}, "lang": "ja"}}, "geo": null, "entities": {{"hashtags": [{}], "symbols": [], "urls": [], "user_mentions": []}}, "retweet_count": {}, "favorite_count": {}, "favorited": false, "retweeted": false, "lang": "ja"}}"#,
+                rng.sentence(6),
+                rng.word(),
+                rng.sentence(8),
+                rng.below(1 << 31),
+                rng.word(),
+                rng.word(),
+                rng.sentence(2),
+                rng.sentence(12),
+                rng.below(100_000),
+                rng.below(5_000),
+                i % 7 == 0,
+                hashtags.join(", "),
+                rng.below(1000),
+                rng.below(1000),
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"statuses": [{}], "search_metadata": {{"completed_in": 0.087, "max_id": 505874924095815681, "query": "%E4%B8%80", "count": 400}}}}"#,
+        statuses.join(", ")
+    )
+}
+
+fn canada(rng: &mut Rng) -> String {
+    let rings: Vec<_> = (0..48)
+        .map(|_| {
+            let points: Vec<_> = (0..1000)
+                .map(|_| {
+                    let lon = -141.0 + rng.below(1 << 32) as f64 / (1u64 << 32) as f64 * 88.0;
+                    let lat = 41.0 + rng.below(1 << 32) as f64 / (1u64 << 32) as f64 * 42.0;
+                    format!("[{:.15},{:.15}]", lon, lat)
+                })
+                .collect();
+            format!("[{}]", points.join(","))
+        })
+        .collect();
+    format!(
+        r#"{{"type": "FeatureCollection", "features": [{{"type": "Feature", "properties": {{"name": "Canada"}}, "geometry": {{"type": "Polygon", "coordinates": [{}]}}}}]}}"#,
+        rings.join(",")
+    )
+}
+
+fn telemetry(rng: &mut Rng) -> Vec<String> {
+    (0..1000)
+        .map(|i| {
+            format!(
+                r#"{{"device": "sensor-{}", "ts": {}, "temp": {}.{}, "humidity": {}.{}, "battery": 3.{}, "ok": {}}}"#,
+                rng.below(64),
+                1_700_000_000_000u64 + i * 250,
+                15 + rng.below(15),
+                rng.below(10),
+                30 + rng.below(40),
+                rng.below(10),
+                rng.below(100),
+                rng.below(20) != 0,
+            )
+        })
+        .collect()
+}
+
+fn documents(c: &mut Criterion, name: &str, js: &str) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(js.len() as u64));
+
+    let mut session = Session::new();
+    group.bench_function("jsmn", |b| {
+        b.iter(|| session.parse(black_box(js)).unwrap().len())
+    });
+    group.bench_function("jsmn count", |b| {
+        b.iter(|| count_tokens(black_box(js)).unwrap())
+    });
+    group.bench_function("serde_json", |b| {
+        b.iter(|| serde_json::from_str::<serde_json::Value>(black_box(js)).unwrap())
+    });
+    group.bench_function("simd-json", |b| {
+        b.iter_batched(
+            || js.as_bytes().to_vec(),
+            |mut bytes| simd_json::to_borrowed_value(&mut bytes).is_ok(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn messages(c: &mut Criterion, name: &str, messages: &[String]) {
+    let mut group = c.benchmark_group(name);
+    let len = messages.iter().map(String::len).sum::<usize>();
+    group.throughput(Throughput::Bytes(len as u64));
+
+    let mut session = Session::new();
+    group.bench_function("jsmn", |b| {
+        b.iter(|| {
+            messages
+                .iter()
+                .map(|js| session.parse(black_box(js)).unwrap().len())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("serde_json", |b| {
+        b.iter(|| {
+            messages
+                .iter()
+                .filter(|js| serde_json::from_str::<serde_json::Value>(black_box(js)).is_ok())
+                .count()
+        })
+    });
+    group.bench_function("simd-json", |b| {
+        b.iter_batched(
+            || {
+                messages
+                    .iter()
+                    .map(|js| js.as_bytes().to_vec())
+                    .collect::<Vec<_>>()
+            },
+            |mut messages| {
+                messages
+                    .iter_mut()
+                    .filter_map(|bytes| simd_json::to_borrowed_value(bytes).ok())
+                    .count()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn tokenize(c: &mut Criterion) {
+    documents(c, "twitter", &corpus("twitter.json", twitter));
+    documents(c, "canada", &corpus("canada.json", canada));
+    messages(c, "telemetry", &telemetry(&mut Rng(0x9e37_79b9_7f4a_7c15)));
+}
+
+criterion_group!(benches, tokenize);
+criterion_main!(benches);
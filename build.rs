@@ -1,28 +1,53 @@
 extern crate cc;
 
+#[cfg(feature = "regenerate-bindings")]
 extern crate bindgen;
 
 use std::env;
-use std::path::PathBuf;
 
 fn main() {
     // Only regenerate if jsmn changed
     println!("cargo:rerun-if-changed=src/jsmn");
 
+    // WebAssembly targets rarely have a C toolchain, nor a libc for jsmn to
+    // link against, so they always use the port as if pure-rust was on
+    let wasm = env::var("CARGO_CFG_TARGET_FAMILY")
+        .is_ok_and(|family| family.split(',').any(|family| family == "wasm"));
+    if wasm {
+        println!("cargo:rustc-cfg=feature=\"pure-rust\"");
+    }
+
+    // The pure-rust feature replaces jsmn with a port, so that no C compiler
+    // is needed
+    if wasm || env::var_os("CARGO_FEATURE_PURE_RUST").is_some() {
+        return;
+    }
+
     // Build jsmn library, with optional compiler directives
     let mut build = cc::Build::new();
-    let mut builder = bindgen::Builder::default();
 
-    if std::env::var_os("CARGO_FEATURE_PARENT_LINKS").is_some() {
-        println!("cargo:rustc-cfg=feature=\"parent-links\"");
-        build.define("JSMN_PARENT_LINKS", None);
-        builder = builder.clang_arg("-DJSMN_PARENT_LINKS");
+    // Parent links are always on, so that tokens have the same layout
+    // whatever the features
+    build.define("JSMN_PARENT_LINKS", None);
+
+    // Build both modes again under names of their own, so that strictness
+    // can also be chosen for each parse
+    for mode in ["strict", "lenient"] {
+        let mut mode_build = build.clone();
+        if mode == "strict" {
+            mode_build.define("JSMN_STRICT", None);
+        }
+        mode_build
+            .define("jsmn_init", format!("jsmn_init_{}", mode).as_str())
+            .define("jsmn_parse", format!("jsmn_parse_{}", mode).as_str())
+            .file("src/jsmn/jsmn.c")
+            .include("src/jsmn")
+            .compile(&format!("jsmn_{}", mode));
     }
 
     if std::env::var_os("CARGO_FEATURE_STRICT").is_some() {
         println!("cargo:rustc-cfg=feature=\"strict\"");
         build.define("JSMN_STRICT", None);
-        builder = builder.clang_arg("-DJSMN_STRICT");
     }
 
     build
@@ -30,8 +55,18 @@ fn main() {
         .include("src/jsmn")
         .compile("jsmn");
 
-    // Generate bindings for jsmn
-    let bindings = builder
+    #[cfg(feature = "regenerate-bindings")]
+    generate_bindings();
+}
+
+// Generate bindings for jsmn, which src/bindings.rs is a copy of. JSMN_STRICT
+// is left out, as it changes nothing in jsmn.h.
+#[cfg(feature = "regenerate-bindings")]
+fn generate_bindings() {
+    use std::path::PathBuf;
+
+    let bindings = bindgen::Builder::default()
+        .clang_arg("-DJSMN_PARENT_LINKS")
         .header("src/jsmn/jsmn.h")
         .allowlist_type("jsmntype_t")
         .allowlist_type("jsmnerr")
@@ -0,0 +1,124 @@
+//!
+//! This crate provides `#[derive(FromTokens)]` for jsmn-rs, enabled there
+//! with the "derive" feature, so that structs can be read from tokens
+//! without serde or hand-written implementations.
+//!
+//! A struct with named fields is read from an object, each field from the
+//! member of the same name, through `jsmn_rs::from_tokens::field`. The
+//! `#[jsmn(rename = "name")]` attribute reads a field from another member,
+//! and `#[jsmn(default)]` fills it with `Default::default()` when the
+//! member is missing or `null`. A tuple struct with one field is read like
+//! that field, and one with more fields from an array, like a tuple.
+//!
+//! The generated code only calls the functions a hand-written
+//! implementation would, so it adds nothing to the binary beyond them.
+//!
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{Data, DeriveInput, Error, Field, Fields, LitStr, parse_macro_input, parse_quote};
+
+#[proc_macro_derive(FromTokens, attributes(jsmn))]
+pub fn derive_from_tokens(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.ident.span(),
+            "FromTokens can only be derived for structs",
+        ));
+    };
+
+    let body = match &data.fields {
+        Fields::Named(fields) => {
+            let fields = fields
+                .named
+                .iter()
+                .map(named_field)
+                .collect::<Result<Vec<_>, _>>()?;
+            quote!(Self { #(#fields,)* })
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            quote!(Self(::jsmn_rs::from_tokens::FromTokens::from_tokens(
+                js, tokens, idx
+            )?))
+        }
+        Fields::Unnamed(fields) => {
+            let types = fields.unnamed.iter().map(|field| &field.ty);
+            let names: Vec<_> = (0..fields.unnamed.len())
+                .map(|i| format_ident!("field{}", i))
+                .collect();
+            quote!({
+                let (#(#names,)*) =
+                    <(#(#types,)*) as ::jsmn_rs::from_tokens::FromTokens>::from_tokens(js, tokens, idx)?;
+                Self(#(#names),*)
+            })
+        }
+        Fields::Unit => {
+            return Err(Error::new(
+                input.ident.span(),
+                "FromTokens cannot be derived for unit structs",
+            ));
+        }
+    };
+
+    for param in input.generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::jsmn_rs::from_tokens::FromTokens));
+    }
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::jsmn_rs::from_tokens::FromTokens for #name #type_generics #where_clause {
+            fn from_tokens(
+                js: &str,
+                tokens: &[::jsmn_rs::JsmnTok],
+                idx: usize,
+            ) -> ::core::result::Result<Self, ::jsmn_rs::value::ValueErr> {
+                ::core::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+/// The initializer of one field of a struct with named fields.
+fn named_field(field: &Field) -> Result<TokenStream2, Error> {
+    let ident = field.ident.as_ref().unwrap();
+    let ty = &field.ty;
+    let mut key = ident.unraw().to_string();
+    let mut default = false;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("jsmn"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                key = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                default = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename` or `default`"))
+            }
+        })?;
+    }
+
+    Ok(if default {
+        let read = quote!(::jsmn_rs::from_tokens::field::<::core::option::Option<#ty>>);
+        quote!(#ident: #read(js, tokens, idx, #key)?.unwrap_or_default())
+    } else {
+        quote!(#ident: ::jsmn_rs::from_tokens::field::<#ty>(js, tokens, idx, #key)?)
+    })
+}
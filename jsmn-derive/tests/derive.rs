@@ -0,0 +1,77 @@
+use jsmn_rs::from_tokens::{FromTokens, from_root};
+use jsmn_rs::value::{ValueErr, ValueErrKind};
+use jsmn_rs::{JsmnParser, jsmn_parse_vec};
+
+#[derive(Debug, PartialEq, FromTokens)]
+struct Config {
+    name: String,
+    #[jsmn(rename = "baud-rate")]
+    baud: u32,
+    #[jsmn(default)]
+    retries: u8,
+    r#type: Option<String>,
+    pins: Vec<Pin>,
+}
+
+#[derive(Debug, PartialEq, FromTokens)]
+struct Pin(u8, bool);
+
+#[derive(Debug, PartialEq, FromTokens)]
+struct Id(u64);
+
+#[derive(Debug, PartialEq, FromTokens)]
+struct Tagged<T> {
+    tag: String,
+    value: T,
+}
+
+fn read<T: FromTokens>(js: &str) -> Result<T, ValueErr> {
+    let mut tokens = Vec::new();
+    jsmn_parse_vec(&mut JsmnParser::new(), js, &mut tokens).unwrap();
+    from_root(js, &tokens)
+}
+
+#[test]
+fn structs_from_objects() {
+    let js = r#"{"name": "uart0", "baud-rate": 115200, "type": "rs232",
+                 "pins": [[4, true], [5, false]]}"#;
+    assert_eq!(
+        read::<Config>(js),
+        Ok(Config {
+            name: "uart0".into(),
+            baud: 115200,
+            retries: 0,
+            r#type: Some("rs232".into()),
+            pins: vec![Pin(4, true), Pin(5, false)],
+        })
+    );
+
+    let js = r#"{"name": "spi", "baud-rate": 1, "retries": 3, "pins": []}"#;
+    let config = read::<Config>(js).unwrap();
+    assert_eq!((config.retries, config.r#type), (3, None));
+}
+
+#[test]
+fn tuple_and_generic_structs() {
+    assert_eq!(read::<Vec<Id>>("[1, 2]"), Ok(vec![Id(1), Id(2)]));
+    assert_eq!(
+        read::<Tagged<Vec<Id>>>(r#"{"tag": "ids", "value": [7]}"#),
+        Ok(Tagged {
+            tag: "ids".into(),
+            value: vec![Id(7)],
+        })
+    );
+}
+
+#[test]
+fn errors_point_at_values() {
+    let err = read::<Config>(r#"{"name": "x", "baud-rate": 9600, "pins": [[4, 1]]}"#);
+    assert_eq!(err.unwrap_err().pointer, "/pins/0/1");
+    assert_eq!(
+        read::<Config>(r#"{"name": "x", "pins": []}"#),
+        Err(ValueErr {
+            pointer: String::new(),
+            kind: ValueErrKind::Invalid(r#"missing field "baud-rate""#.into()),
+        })
+    );
+}
@@ -0,0 +1,363 @@
+//!
+//! This module converts arrays of JSON objects into Apache Arrow record
+//! batches. The columns are declared up front, and each row's values are
+//! appended to the column builders straight from the tokens, without
+//! building any intermediate representation of the rows.
+//!
+//! Every column is nullable: a missing member or a `null` value becomes a
+//! null, while a value of the wrong type is an error. Members that are not
+//! declared as columns are ignored.
+//!
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::BufRead;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+
+use crate::decode::unescape;
+use crate::ndjson::NdjsonReader;
+use crate::stream::StreamError;
+use crate::tree::children;
+use crate::value::is_number;
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// The Arrow type of a column.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColumnType {
+    /// Integer numbers that fit in an `i64`.
+    Int64,
+    /// Any number.
+    Float64,
+    /// `true` or `false`.
+    Boolean,
+    /// Strings, with their escapes decoded.
+    Utf8,
+}
+
+impl ColumnType {
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Utf8 => DataType::Utf8,
+        }
+    }
+}
+
+/// Error type for the conversions to record batches.
+#[derive(Debug)]
+pub enum BatchError {
+    /// The document is not valid JSON.
+    Parse(JsmnErr),
+    /// Reading or parsing an NDJSON stream failed.
+    Stream(StreamError),
+    /// The document is not an array.
+    NotArray,
+    /// The row is not an object.
+    NotObject { row: usize },
+    /// The value of a column in a row does not have the column's type.
+    WrongType { row: usize, column: String },
+    /// Arrow refused to assemble the batch.
+    Arrow(ArrowError),
+}
+
+impl From<StreamError> for BatchError {
+    fn from(err: StreamError) -> Self {
+        BatchError::Stream(err)
+    }
+}
+
+impl From<ArrowError> for BatchError {
+    fn from(err: ArrowError) -> Self {
+        BatchError::Arrow(err)
+    }
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchError::Parse(err) => write!(f, "{:?}", err),
+            BatchError::Stream(err) => write!(f, "{}", err),
+            BatchError::NotArray => write!(f, "document is not an array"),
+            BatchError::NotObject { row } => write!(f, "row {} is not an object", row),
+            BatchError::WrongType { row, column } => {
+                write!(f, "column {:?} has the wrong type in row {}", column, row)
+            }
+            BatchError::Arrow(err) => write!(f, "Arrow error: {}", err),
+        }
+    }
+}
+
+impl Error for BatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BatchError::Stream(err) => Some(err),
+            BatchError::Arrow(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Builds record batches with a declared set of columns, each filled from
+/// the member of the same name in every row.
+#[derive(Debug, Clone, Default)]
+pub struct BatchBuilder {
+    columns: Vec<(String, ColumnType)>,
+}
+
+impl BatchBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a column filled from the members named `name`.
+    pub fn column(&mut self, name: &str, typ: ColumnType) -> &mut Self {
+        self.columns.push((name.to_string(), typ));
+        self
+    }
+
+    /// The schema of the batches this builder produces.
+    pub fn schema(&self) -> Schema {
+        let fields: Vec<_> = self
+            .columns
+            .iter()
+            .map(|(name, typ)| Field::new(name.as_str(), typ.data_type(), true))
+            .collect();
+        Schema::new(fields)
+    }
+
+    /// Converts a document holding an array of objects, one row per object.
+    pub fn from_array(&self, js: &str) -> Result<RecordBatch, BatchError> {
+        let tokens = parse_to_vec(js).map_err(BatchError::Parse)?;
+        if tokens.first().map(|tok| tok.typ) != Some(JsmnType::JsmnArray) {
+            return Err(BatchError::NotArray);
+        }
+
+        let index = self.index();
+        let mut builders = self.builders(tokens[0].size as usize);
+        for (row, element) in children(&tokens, 0).enumerate() {
+            self.append_row(&index, &mut builders, js, &tokens, element, row)?;
+        }
+        self.finish(builders)
+    }
+
+    /// Converts an NDJSON stream, one row per line.
+    pub fn from_ndjson<R: BufRead>(&self, input: R) -> Result<RecordBatch, BatchError> {
+        let index = self.index();
+        let mut builders = self.builders(0);
+        for (row, record) in NdjsonReader::new(input).enumerate() {
+            let record = record?;
+            self.append_row(&index, &mut builders, &record.text, &record.tokens, 0, row)?;
+        }
+        self.finish(builders)
+    }
+
+    fn index(&self) -> HashMap<&str, usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect()
+    }
+
+    fn builders(&self, rows: usize) -> Vec<ColumnBuilder> {
+        self.columns
+            .iter()
+            .map(|&(_, typ)| ColumnBuilder::new(typ, rows))
+            .collect()
+    }
+
+    fn append_row(
+        &self,
+        index: &HashMap<&str, usize>,
+        builders: &mut [ColumnBuilder],
+        js: &str,
+        tokens: &[JsmnTok],
+        object: usize,
+        row: usize,
+    ) -> Result<(), BatchError> {
+        if tokens[object].typ != JsmnType::JsmnObject {
+            return Err(BatchError::NotObject { row });
+        }
+
+        let mut values = vec![None; builders.len()];
+        for key in children(tokens, object) {
+            let name = unescape(token_text(js, &tokens[key]));
+            if let Some(&column) = index.get(name.as_ref()) {
+                values[column] = tokens.get(key + 1);
+            }
+        }
+
+        for (column, (builder, value)) in builders.iter_mut().zip(values).enumerate() {
+            if !builder.append(js, value) {
+                return Err(BatchError::WrongType {
+                    row,
+                    column: self.columns[column].0.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self, builders: Vec<ColumnBuilder>) -> Result<RecordBatch, BatchError> {
+        let arrays = builders.into_iter().map(ColumnBuilder::finish).collect();
+        Ok(RecordBatch::try_new(Arc::new(self.schema()), arrays)?)
+    }
+}
+
+fn token_text<'a>(js: &'a str, tok: &JsmnTok) -> &'a str {
+    &js[tok.start as usize..tok.end as usize]
+}
+
+enum ColumnBuilder {
+    Int64(Int64Builder),
+    Float64(Float64Builder),
+    Boolean(BooleanBuilder),
+    Utf8(StringBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(typ: ColumnType, rows: usize) -> Self {
+        match typ {
+            ColumnType::Int64 => ColumnBuilder::Int64(Int64Builder::with_capacity(rows)),
+            ColumnType::Float64 => ColumnBuilder::Float64(Float64Builder::with_capacity(rows)),
+            ColumnType::Boolean => ColumnBuilder::Boolean(BooleanBuilder::with_capacity(rows)),
+            ColumnType::Utf8 => ColumnBuilder::Utf8(StringBuilder::with_capacity(rows, rows * 16)),
+        }
+    }
+
+    /// Appends a value, or a null if there is none. Returns false, without
+    /// appending anything, if the value does not have the column's type.
+    fn append(&mut self, js: &str, value: Option<&JsmnTok>) -> bool {
+        let value =
+            value.filter(|tok| tok.typ != JsmnType::JsmnPrimitive || token_text(js, tok) != "null");
+        let Some(tok) = value else {
+            match self {
+                ColumnBuilder::Int64(builder) => builder.append_null(),
+                ColumnBuilder::Float64(builder) => builder.append_null(),
+                ColumnBuilder::Boolean(builder) => builder.append_null(),
+                ColumnBuilder::Utf8(builder) => builder.append_null(),
+            }
+            return true;
+        };
+
+        let text = token_text(js, tok);
+        let number = tok.typ == JsmnType::JsmnPrimitive && is_number(text);
+        match self {
+            ColumnBuilder::Int64(builder) if number => match text.parse() {
+                Ok(n) => builder.append_value(n),
+                Err(_) => return false,
+            },
+            ColumnBuilder::Float64(builder) if number => match text.parse::<f64>() {
+                Ok(n) if n.is_finite() => builder.append_value(n),
+                _ => return false,
+            },
+            ColumnBuilder::Boolean(builder) if tok.typ == JsmnType::JsmnPrimitive => match text {
+                "true" => builder.append_value(true),
+                "false" => builder.append_value(false),
+                _ => return false,
+            },
+            ColumnBuilder::Utf8(builder) if tok.typ == JsmnType::JsmnString => {
+                builder.append_value(unescape(text))
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn finish(self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Int64(mut builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Float64(mut builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Boolean(mut builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Utf8(mut builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow::array::{Array, Float64Array, Int64Array, StringArray};
+
+    fn builder() -> BatchBuilder {
+        let mut builder = BatchBuilder::new();
+        builder
+            .column("id", ColumnType::Int64)
+            .column("name", ColumnType::Utf8)
+            .column("score", ColumnType::Float64);
+        builder
+    }
+
+    #[test]
+    fn array_of_objects() {
+        let js = r#"[
+            {"id": 1, "name": "a\nb", "score": 2.5, "extra": [1, 2]},
+            {"score": 3, "id": 2},
+            {"id": 3, "name": null, "score": -1e3}
+        ]"#;
+        let batch = builder().from_array(js).unwrap();
+        assert_eq!((batch.num_rows(), batch.num_columns()), (3, 3));
+
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!((ids.value(0), ids.value(2)), (1, 3));
+        let names = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(names.value(0), "a\nb");
+        assert!(names.is_null(1) && names.is_null(2));
+        let scores = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(scores.value(2), -1000.0);
+    }
+
+    #[test]
+    fn ndjson_rows_and_errors() {
+        let input = "{\"id\": 1}\n{\"id\": 2, \"name\": \"x\"}\n";
+        let batch = builder().from_ndjson(input.as_bytes()).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+
+        assert!(matches!(
+            builder().from_array(r#"[{"id": 1}, {"id": 1.5}]"#),
+            Err(BatchError::WrongType { row: 1, ref column }) if column == "id"
+        ));
+        assert!(matches!(
+            builder().from_array(r#"[{"id": "1"}]"#),
+            Err(BatchError::WrongType { row: 0, .. })
+        ));
+        assert!(matches!(
+            builder().from_array(r#"[{"score": 1}, {"score": 1e999}]"#),
+            Err(BatchError::WrongType { row: 1, ref column }) if column == "score"
+        ));
+        #[cfg(not(feature = "strict"))]
+        assert!(matches!(
+            builder().from_array(r#"[{"score": -inf}]"#),
+            Err(BatchError::WrongType { row: 0, .. })
+        ));
+        assert!(matches!(
+            builder().from_array(r#"[{}, 1]"#),
+            Err(BatchError::NotObject { row: 1 })
+        ));
+        assert!(matches!(
+            builder().from_array(r#"{}"#),
+            Err(BatchError::NotArray)
+        ));
+    }
+}
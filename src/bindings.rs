@@ -0,0 +1,58 @@
+/* automatically generated by rust-bindgen 0.71.1 */
+
+pub const jsmntype_t_JSMN_UNDEFINED: jsmntype_t = 0;
+pub const jsmntype_t_JSMN_OBJECT: jsmntype_t = 1;
+pub const jsmntype_t_JSMN_ARRAY: jsmntype_t = 2;
+pub const jsmntype_t_JSMN_STRING: jsmntype_t = 3;
+pub const jsmntype_t_JSMN_PRIMITIVE: jsmntype_t = 4;
+pub type jsmntype_t = ::std::os::raw::c_uint;
+pub const jsmnerr_JSMN_ERROR_NOMEM: jsmnerr = -1;
+pub const jsmnerr_JSMN_ERROR_INVAL: jsmnerr = -2;
+pub const jsmnerr_JSMN_ERROR_PART: jsmnerr = -3;
+pub type jsmnerr = ::std::os::raw::c_int;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct jsmntok_t {
+    pub type_: jsmntype_t,
+    pub start: ::std::os::raw::c_int,
+    pub end: ::std::os::raw::c_int,
+    pub size: ::std::os::raw::c_int,
+    pub parent: ::std::os::raw::c_int,
+}
+#[allow(clippy::unnecessary_operation, clippy::identity_op)]
+const _: () = {
+    ["Size of jsmntok_t"][::std::mem::size_of::<jsmntok_t>() - 20usize];
+    ["Alignment of jsmntok_t"][::std::mem::align_of::<jsmntok_t>() - 4usize];
+    ["Offset of field: jsmntok_t::type_"][::std::mem::offset_of!(jsmntok_t, type_) - 0usize];
+    ["Offset of field: jsmntok_t::start"][::std::mem::offset_of!(jsmntok_t, start) - 4usize];
+    ["Offset of field: jsmntok_t::end"][::std::mem::offset_of!(jsmntok_t, end) - 8usize];
+    ["Offset of field: jsmntok_t::size"][::std::mem::offset_of!(jsmntok_t, size) - 12usize];
+    ["Offset of field: jsmntok_t::parent"][::std::mem::offset_of!(jsmntok_t, parent) - 16usize];
+};
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct jsmn_parser {
+    pub pos: ::std::os::raw::c_uint,
+    pub toknext: ::std::os::raw::c_uint,
+    pub toksuper: ::std::os::raw::c_int,
+}
+#[allow(clippy::unnecessary_operation, clippy::identity_op)]
+const _: () = {
+    ["Size of jsmn_parser"][::std::mem::size_of::<jsmn_parser>() - 12usize];
+    ["Alignment of jsmn_parser"][::std::mem::align_of::<jsmn_parser>() - 4usize];
+    ["Offset of field: jsmn_parser::pos"][::std::mem::offset_of!(jsmn_parser, pos) - 0usize];
+    ["Offset of field: jsmn_parser::toknext"][::std::mem::offset_of!(jsmn_parser, toknext) - 4usize];
+    ["Offset of field: jsmn_parser::toksuper"][::std::mem::offset_of!(jsmn_parser, toksuper) - 8usize];
+};
+unsafe extern "C" {
+    pub fn jsmn_init(parser: *mut jsmn_parser);
+}
+unsafe extern "C" {
+    pub fn jsmn_parse(
+        parser: *mut jsmn_parser,
+        js: *const ::std::os::raw::c_char,
+        len: usize,
+        tokens: *mut jsmntok_t,
+        num_tokens: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_int;
+}
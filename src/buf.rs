@@ -0,0 +1,107 @@
+//!
+//! This module provides `TokenBuf`, a fixed number of tokens kept inline,
+//! on the stack or in a static, along with how many of them the last parse
+//! filled in. Parsing into it returns just those tokens, so there is no
+//! count to carry around next to the array and no stale tokens past it to
+//! trip over, while still allocating nothing, as on a microcontroller.
+//!
+
+use crate::storage::TokenStorage;
+use crate::{JsmnParser, JsmnTok, ParseError};
+
+/// Room for `N` tokens, and the tokens of the last document parsed into it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBuf<const N: usize> {
+    tokens: [JsmnTok; N],
+    len: usize,
+}
+
+impl<const N: usize> TokenBuf<N> {
+    pub fn new() -> Self {
+        TokenBuf {
+            tokens: [JsmnTok::default(); N],
+            len: 0,
+        }
+    }
+
+    /// Parses `js` with a parser of its own and returns its tokens. A
+    /// document needing more than `N` tokens fails with `JsmErrorNoMem`.
+    /// After an error the buffer holds no tokens.
+    pub fn parse(&mut self, js: &str) -> Result<&[JsmnTok], ParseError> {
+        self.parse_bytes(js.as_bytes())
+    }
+
+    /// Like [`TokenBuf::parse`], but for input that is not known to be
+    /// UTF-8, see [`crate::jsmn_parse_bytes`].
+    pub fn parse_bytes(&mut self, js: &[u8]) -> Result<&[JsmnTok], ParseError> {
+        self.len = 0;
+        let mut parser = JsmnParser::new();
+        self.len = parser
+            .parse_bytes(js, &mut self.tokens)
+            .map_err(|err| parser.error(err))?;
+        Ok(self.tokens())
+    }
+
+    /// The tokens of the last document parsed, or none if it failed.
+    pub fn tokens(&self) -> &[JsmnTok] {
+        &self.tokens[..self.len]
+    }
+
+    /// The most tokens a document may have, which is `N`.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Default for TokenBuf<N> {
+    fn default() -> Self {
+        TokenBuf::new()
+    }
+}
+
+impl<const N: usize> TokenStorage for TokenBuf<N> {
+    fn tokens_mut(&mut self) -> &mut [JsmnTok] {
+        &mut self.tokens
+    }
+
+    fn grow(&mut self, len: usize) -> bool {
+        len <= N
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = len.min(N);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsmnErr, JsmnType};
+
+    #[test]
+    fn filled_prefix() {
+        let mut buf = TokenBuf::<8>::new();
+        let js = r#"{"a": [1, 2]}"#;
+        let tokens = buf.parse(js).unwrap();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[2].typ, JsmnType::JsmnArray);
+        assert_eq!(tokens[4].as_str(js), "2");
+
+        assert_eq!(buf.parse("[]").unwrap().len(), 1);
+        assert_eq!(buf.tokens().len(), 1);
+        assert_eq!(buf.capacity(), 8);
+        assert_eq!(TokenBuf::<0>::new().parse("").unwrap(), &[]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut buf = TokenBuf::<2>::default();
+        assert_eq!(buf.parse("[1]").unwrap().len(), 2);
+        let err = buf.parse("[1, 2]").unwrap_err();
+        assert_eq!((err.kind, err.tokens), (JsmnErr::JsmErrorNoMem, 2));
+        assert!(buf.tokens().is_empty());
+
+        let err = buf.parse_bytes(b"[\"a").unwrap_err();
+        assert_eq!((err.kind, err.pos), (JsmnErr::JsmErrorPart, 1));
+    }
+}
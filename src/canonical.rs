@@ -0,0 +1,283 @@
+//!
+//! This module writes documents in the canonical form of the JSON
+//! Canonicalization Scheme, RFC 8785, so that documents meaning the same
+//! thing come out byte for byte the same and can be hashed or signed.
+//!
+//! The canonical form has no whitespace, object members sorted by their
+//! keys compared as UTF-16 code units, strings decoded and escaped again
+//! with only the escapes JSON requires, and numbers read as `f64` and
+//! written as ECMAScript prints them, like the writer module does with
+//! normalized numbers. The scheme only covers I-JSON, so documents that
+//! cannot be canonicalized are refused: numbers out of the range of
+//! `f64`, strings with unpaired surrogates or invalid escapes, objects
+//! with the same key twice, and the primitives jsmn lets through in
+//! lenient mode.
+//!
+
+use std::error::Error;
+use std::fmt;
+
+use crate::decode::hex4;
+use crate::ser::write_escaped;
+use crate::tree::subtree_end;
+use crate::value::is_number;
+use crate::write::format_float;
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Error type for canonicalization.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CanonicalErr {
+    /// The document is not valid JSON.
+    Parse(JsmnErr),
+    /// The document has no value, or more than one.
+    NotOneRoot,
+    /// A primitive at this byte offset is not `true`, `false`, `null` or a
+    /// number that fits in an `f64`, or is used as a key.
+    InvalidPrimitive { pos: usize },
+    /// The string at this byte offset has an invalid escape or an unpaired
+    /// surrogate.
+    InvalidString { pos: usize },
+    /// The key at this byte offset appears earlier in the same object.
+    DuplicateKey { pos: usize },
+}
+
+impl From<JsmnErr> for CanonicalErr {
+    fn from(err: JsmnErr) -> Self {
+        CanonicalErr::Parse(err)
+    }
+}
+
+impl fmt::Display for CanonicalErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CanonicalErr::Parse(err) => write!(f, "{:?}", err),
+            CanonicalErr::NotOneRoot => write!(f, "document does not have exactly one value"),
+            CanonicalErr::InvalidPrimitive { pos } => {
+                write!(f, "invalid primitive at byte {}", pos)
+            }
+            CanonicalErr::InvalidString { pos } => write!(f, "invalid string at byte {}", pos),
+            CanonicalErr::DuplicateKey { pos } => write!(f, "duplicate key at byte {}", pos),
+        }
+    }
+}
+
+impl Error for CanonicalErr {}
+
+/// Parses `js` and writes it in canonical form.
+pub fn canonicalize_str(js: &str) -> Result<String, CanonicalErr> {
+    let tokens = parse_to_vec(js)?;
+    canonicalize(js, &tokens)
+}
+
+/// Writes an already parsed document in canonical form.
+pub fn canonicalize(js: &str, tokens: &[JsmnTok]) -> Result<String, CanonicalErr> {
+    if tokens.is_empty() || subtree_end(tokens, 0) != tokens.len() {
+        return Err(CanonicalErr::NotOneRoot);
+    }
+    let mut out = String::with_capacity(js.len());
+    write_value(js, tokens, 0, &mut out)?;
+    Ok(out)
+}
+
+/// Writes the value at `idx` and returns the index of the token after it.
+fn write_value(
+    js: &str,
+    tokens: &[JsmnTok],
+    idx: usize,
+    out: &mut String,
+) -> Result<usize, CanonicalErr> {
+    let tok = &tokens[idx];
+    let text = tok.as_str(js);
+    match tok.typ {
+        JsmnType::JsmnObject => {
+            // Each member is its key, with the index of its value.
+            let mut members = Vec::with_capacity(tok.size.max(0) as usize);
+            let mut next = idx + 1;
+            for _ in 0..tok.size {
+                let key = &tokens[next];
+                if key.typ != JsmnType::JsmnString {
+                    return Err(CanonicalErr::InvalidPrimitive {
+                        pos: key.start as usize,
+                    });
+                }
+                members.push((decode(js, key)?, next));
+                next = subtree_end(tokens, next);
+            }
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            if let Some(pair) = members.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                let (first, second) = (pair[0].1, pair[1].1);
+                return Err(CanonicalErr::DuplicateKey {
+                    pos: tokens[first.max(second)].start as usize - 1,
+                });
+            }
+
+            out.push('{');
+            for (i, (key, key_idx)) in members.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_escaped(out, key).unwrap();
+                out.push(':');
+                write_value(js, tokens, key_idx + 1, out)?;
+            }
+            out.push('}');
+            Ok(next)
+        }
+        JsmnType::JsmnArray => {
+            out.push('[');
+            let mut next = idx + 1;
+            for i in 0..tok.size {
+                if i > 0 {
+                    out.push(',');
+                }
+                next = write_value(js, tokens, next, out)?;
+            }
+            out.push(']');
+            Ok(next)
+        }
+        JsmnType::JsmnString => {
+            write_escaped(out, &decode(js, tok)?).unwrap();
+            Ok(idx + 1)
+        }
+        _ => {
+            match text {
+                "true" | "false" | "null" => out.push_str(text),
+                _ => {
+                    let number = is_number(text)
+                        .then(|| text.parse::<f64>().ok())
+                        .flatten()
+                        .filter(|number| number.is_finite())
+                        .ok_or(CanonicalErr::InvalidPrimitive {
+                            pos: tok.start as usize,
+                        })?;
+                    // ECMAScript prints negative zero as 0.
+                    out.push_str(&format_float(number + 0.0));
+                }
+            }
+            Ok(idx + 1)
+        }
+    }
+}
+
+/// Decodes a string token, refusing what [`crate::decode::unescape`] would
+/// replace with U+FFFD.
+fn decode(js: &str, tok: &JsmnTok) -> Result<String, CanonicalErr> {
+    let invalid = CanonicalErr::InvalidString {
+        pos: tok.start as usize - 1,
+    };
+    let mut out = String::new();
+    let mut chars = tok.as_str(js).chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let high = hex4(&mut chars).ok_or(invalid)?;
+                let code = match high {
+                    0xD800..=0xDBFF => {
+                        let low = match (chars.next(), chars.next()) {
+                            (Some('\\'), Some('u')) => hex4(&mut chars),
+                            _ => None,
+                        };
+                        match low {
+                            Some(low @ 0xDC00..=0xDFFF) => {
+                                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                            }
+                            _ => return Err(invalid),
+                        }
+                    }
+                    _ => high,
+                };
+                char::from_u32(code).ok_or(invalid)?
+            }
+            _ => return Err(invalid),
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_examples() {
+        let js = r#"{
+  "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+  "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+  "literals": [null, true, false]
+}"#;
+        assert_eq!(
+            canonicalize_str(js).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+
+        let js = r#"{
+  "\u20ac": "Euro Sign",
+  "\r": "Carriage Return",
+  "\ufb33": "Hebrew Letter Dalet With Dagesh",
+  "1": "One",
+  "\ud83d\ude00": "Emoji: Grinning Face",
+  "\u0080": "Control",
+  "\u00f6": "Latin Small Letter O With Diaeresis"
+}"#;
+        let canonical = canonicalize_str(js).unwrap();
+        let tokens = parse_to_vec(&canonical).unwrap();
+        let values: Vec<_> = (1..8).map(|i| tokens[2 * i].as_str(&canonical)).collect();
+        assert_eq!(
+            values,
+            [
+                "Carriage Return",
+                "One",
+                "Control",
+                "Latin Small Letter O With Diaeresis",
+                "Euro Sign",
+                "Emoji: Grinning Face",
+                "Hebrew Letter Dalet With Dagesh"
+            ]
+        );
+        assert!(canonical.starts_with("{\"\\r\":"));
+    }
+
+    #[test]
+    fn numbers() {
+        let canonical = canonicalize_str("[-0, 0.0, 1e21, 1e-7, 100, 9007199254740993, -1.5E+2]");
+        assert_eq!(
+            canonical.unwrap(),
+            "[0,0,1e+21,1e-7,100,9007199254740992,-150]"
+        );
+    }
+
+    #[test]
+    fn refused_documents() {
+        let err = |js| canonicalize_str(js).unwrap_err();
+        assert_eq!(err("[1e400]"), CanonicalErr::InvalidPrimitive { pos: 1 });
+        assert_eq!(err(r#"["\ud800"]"#), CanonicalErr::InvalidString { pos: 1 });
+        assert_eq!(
+            err(r#"["a", "\udc00"]"#),
+            CanonicalErr::InvalidString { pos: 6 }
+        );
+        assert_eq!(
+            err(r#"{"b": 1, "a": 2, "b": 3}"#),
+            CanonicalErr::DuplicateKey { pos: 17 }
+        );
+        assert_eq!(
+            err(r#"{"\u0061": 1, "a": 2}"#),
+            CanonicalErr::DuplicateKey { pos: 14 }
+        );
+        assert_eq!(err("[1] [2]"), CanonicalErr::NotOneRoot);
+        assert_eq!(err(""), CanonicalErr::NotOneRoot);
+        assert_eq!(err("[1, 2"), CanonicalErr::Parse(JsmnErr::JsmErrorPart));
+        assert_eq!(err("[1, 2").to_string(), "JsmErrorPart");
+    }
+}
@@ -0,0 +1,425 @@
+//!
+//! This module produces a token-level change report between two versions
+//! of a document, so that editor features like highlight refresh or a
+//! semantic diff can update only the parts that actually changed.
+//!
+//! Both versions are compared structurally: every subtree is given a
+//! fingerprint built from its token types and the text of its scalars, so
+//! reformatting whitespace does not show up as a change. Object members are
+//! matched by key, array elements by a longest common subsequence of their
+//! fingerprints, and a removed subtree that reappears unchanged somewhere
+//! else is reported once as a move.
+//!
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use crate::decode::unescape;
+use crate::tree::{children, roots, subtree_ends, value_span};
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Arrays whose element counts multiply to more than this are compared
+/// position by position instead of with a full LCS table.
+const LCS_LIMIT: usize = 1 << 20;
+
+/// The kind of a single entry in a change report.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChangeKind {
+    /// The subtree only exists in the new document.
+    Inserted,
+    /// The subtree only exists in the old document.
+    Removed,
+    /// The subtree exists unchanged in both documents, but at another place.
+    Moved,
+    /// A scalar value, or a value whose type changed, was replaced in place.
+    Modified,
+}
+
+/// A range of tokens together with the bytes of source text they cover.
+/// For object members the range starts at the key token and the bytes
+/// cover both the key and its value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenRange {
+    pub tokens: Range<usize>,
+    pub bytes: Range<usize>,
+}
+
+/// A single entry of a change report. Insertions only have a `new` range,
+/// removals only have an `old` range, and moves and modifications have both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub old: Option<TokenRange>,
+    pub new: Option<TokenRange>,
+}
+
+/// Parses both versions of a document and reports the changes between them.
+pub fn change_report_str(old_js: &str, new_js: &str) -> Result<Vec<Change>, JsmnErr> {
+    let old_tokens = parse_to_vec(old_js)?;
+    let new_tokens = parse_to_vec(new_js)?;
+    Ok(change_report(old_js, &old_tokens, new_js, &new_tokens))
+}
+
+/// Reports the inserted, removed, moved and modified token ranges between
+/// two already parsed versions of a document. Changes are not reported in
+/// any particular order, sort them by their ranges if that matters.
+pub fn change_report(
+    old_js: &str,
+    old_tokens: &[JsmnTok],
+    new_js: &str,
+    new_tokens: &[JsmnTok],
+) -> Vec<Change> {
+    let old = Side::new(old_js, old_tokens);
+    let new = Side::new(new_js, new_tokens);
+    let mut changes = Vec::new();
+
+    let mut work = Vec::new();
+    diff_sequence(
+        &old,
+        &roots(old_tokens),
+        &new,
+        &roots(new_tokens),
+        &mut changes,
+        &mut work,
+    );
+    while let Some((o, n)) = work.pop() {
+        diff_pair(&old, o, &new, n, &mut changes, &mut work);
+    }
+
+    pair_moves(&old, &new, &mut changes);
+    changes
+}
+
+/// One version of the document, with its precomputed subtree information.
+struct Side<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+    ends: Vec<usize>,
+    prints: Vec<u64>,
+}
+
+impl<'a> Side<'a> {
+    fn new(js: &'a str, tokens: &'a [JsmnTok]) -> Self {
+        let ends = subtree_ends(tokens);
+        let mut prints = vec![0u64; tokens.len()];
+        for i in (0..tokens.len()).rev() {
+            let tok = &tokens[i];
+            let mut hasher = DefaultHasher::new();
+            (tok.typ as u32).hash(&mut hasher);
+            if tok.typ == JsmnType::JsmnString || tok.typ == JsmnType::JsmnPrimitive {
+                token_text(js, tok).hash(&mut hasher);
+            }
+            let mut child = i + 1;
+            for _ in 0..tok.size.max(0) {
+                if child >= tokens.len() {
+                    break;
+                }
+                prints[child].hash(&mut hasher);
+                child = ends[child];
+            }
+            prints[i] = hasher.finish();
+        }
+
+        Side {
+            js,
+            tokens,
+            ends,
+            prints,
+        }
+    }
+
+    fn range(&self, idx: usize) -> TokenRange {
+        TokenRange {
+            tokens: idx..self.ends[idx],
+            bytes: value_span(self.tokens, idx),
+        }
+    }
+
+    fn text(&self, idx: usize) -> &'a str {
+        token_text(self.js, &self.tokens[idx])
+    }
+
+    /// The decoded keys of the object at `idx`, each with its last key token.
+    fn keys(&self, idx: usize) -> HashMap<Cow<'a, str>, usize> {
+        children(self.tokens, idx)
+            .map(|key| (unescape(self.text(key)), key))
+            .collect()
+    }
+}
+
+fn token_text<'a>(js: &'a str, tok: &JsmnTok) -> &'a str {
+    &js[tok.start as usize..tok.end as usize]
+}
+
+fn diff_pair(
+    old: &Side,
+    o: usize,
+    new: &Side,
+    n: usize,
+    changes: &mut Vec<Change>,
+    work: &mut Vec<(usize, usize)>,
+) {
+    if old.prints[o] == new.prints[n] {
+        return;
+    }
+
+    let (old_tok, new_tok) = (&old.tokens[o], &new.tokens[n]);
+    if old_tok.typ != new_tok.typ {
+        changes.push(modified(old, o, new, n));
+        return;
+    }
+
+    match old_tok.typ {
+        JsmnType::JsmnObject => diff_object(old, o, new, n, changes, work),
+        JsmnType::JsmnArray => {
+            let old_items: Vec<usize> = children(old.tokens, o).collect();
+            let new_items: Vec<usize> = children(new.tokens, n).collect();
+            diff_sequence(old, &old_items, new, &new_items, changes, work);
+        }
+        // Keys are only paired up when they decode to the same name, so a
+        // difference between two keys is a difference between their values.
+        JsmnType::JsmnString if old_tok.size > 0 && new_tok.size > 0 => {
+            work.push((o + 1, n + 1));
+        }
+        _ => changes.push(modified(old, o, new, n)),
+    }
+}
+
+fn diff_object(
+    old: &Side,
+    o: usize,
+    new: &Side,
+    n: usize,
+    changes: &mut Vec<Change>,
+    work: &mut Vec<(usize, usize)>,
+) {
+    // Only the last of repeated keys is paired, the way it is the one
+    // readers of the document see.
+    let old_keys = old.keys(o);
+    let new_keys = new.keys(n);
+
+    for old_key in children(old.tokens, o) {
+        let name = unescape(old.text(old_key));
+        match new_keys.get(&name) {
+            Some(&new_key) if old_keys[&name] == old_key => work.push((old_key, new_key)),
+            _ => changes.push(removed(old, old_key)),
+        }
+    }
+
+    for new_key in children(new.tokens, n) {
+        let name = unescape(new.text(new_key));
+        if new_keys[&name] != new_key || !old_keys.contains_key(&name) {
+            changes.push(inserted(new, new_key));
+        }
+    }
+}
+
+/// Compares two sequences of sibling values (array elements or roots).
+/// Elements on the longest common subsequence are unchanged, and the gaps
+/// between them are paired up position by position.
+fn diff_sequence(
+    old: &Side,
+    old_items: &[usize],
+    new: &Side,
+    new_items: &[usize],
+    changes: &mut Vec<Change>,
+    work: &mut Vec<(usize, usize)>,
+) {
+    let anchors = if old_items.len().saturating_mul(new_items.len()) <= LCS_LIMIT {
+        lcs(old, old_items, new, new_items)
+    } else {
+        Vec::new()
+    };
+
+    let (mut i, mut j) = (0, 0);
+    for (ai, aj) in anchors
+        .into_iter()
+        .chain(std::iter::once((old_items.len(), new_items.len())))
+    {
+        while i < ai && j < aj {
+            work.push((old_items[i], new_items[j]));
+            i += 1;
+            j += 1;
+        }
+        for &item in &old_items[i..ai] {
+            changes.push(removed(old, item));
+        }
+        for &item in &new_items[j..aj] {
+            changes.push(inserted(new, item));
+        }
+        i = ai + 1;
+        j = aj + 1;
+    }
+}
+
+/// Returns the index pairs of the longest common subsequence of two item
+/// lists, comparing items by fingerprint.
+fn lcs(old: &Side, old_items: &[usize], new: &Side, new_items: &[usize]) -> Vec<(usize, usize)> {
+    let (rows, cols) = (old_items.len(), new_items.len());
+    let mut table = vec![0u32; (rows + 1) * (cols + 1)];
+    let at = |i: usize, j: usize| i * (cols + 1) + j;
+
+    for i in (0..rows).rev() {
+        for j in (0..cols).rev() {
+            table[at(i, j)] = if old.prints[old_items[i]] == new.prints[new_items[j]] {
+                table[at(i + 1, j + 1)] + 1
+            } else {
+                table[at(i + 1, j)].max(table[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < rows && j < cols {
+        if old.prints[old_items[i]] == new.prints[new_items[j]] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[at(i + 1, j)] >= table[at(i, j + 1)] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Collapses each removal whose subtree was inserted unchanged elsewhere
+/// into a single move.
+fn pair_moves(old: &Side, new: &Side, changes: &mut Vec<Change>) {
+    let mut i = 0;
+    while i < changes.len() {
+        if changes[i].kind != ChangeKind::Removed {
+            i += 1;
+            continue;
+        }
+
+        let print = old.prints[changes[i].old.as_ref().unwrap().tokens.start];
+        let partner = changes.iter().position(|c| {
+            c.kind == ChangeKind::Inserted
+                && new.prints[c.new.as_ref().unwrap().tokens.start] == print
+        });
+
+        if let Some(j) = partner {
+            let inserted = changes.remove(j);
+            if j < i {
+                i -= 1;
+            }
+            changes[i].kind = ChangeKind::Moved;
+            changes[i].new = inserted.new;
+        }
+        i += 1;
+    }
+}
+
+fn inserted(new: &Side, idx: usize) -> Change {
+    Change {
+        kind: ChangeKind::Inserted,
+        old: None,
+        new: Some(new.range(idx)),
+    }
+}
+
+fn removed(old: &Side, idx: usize) -> Change {
+    Change {
+        kind: ChangeKind::Removed,
+        old: Some(old.range(idx)),
+        new: None,
+    }
+}
+
+fn modified(old: &Side, o: usize, new: &Side, n: usize) -> Change {
+    Change {
+        kind: ChangeKind::Modified,
+        old: Some(old.range(o)),
+        new: Some(new.range(n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(changes: &[Change]) -> Vec<ChangeKind> {
+        changes.iter().map(|c| c.kind).collect()
+    }
+
+    #[test]
+    fn identical_documents_have_no_changes() {
+        let changes = change_report_str(r#"{"a": [1, 2]}"#, r#"{ "a":[1,2] }"#).unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn modified_scalar() {
+        let old = r#"{"a":1,"b":2}"#;
+        let new = r#"{"a":1,"b":3}"#;
+        let changes = change_report_str(old, new).unwrap();
+        assert_eq!(kinds(&changes), [ChangeKind::Modified]);
+
+        let change = &changes[0];
+        assert_eq!(change.old.as_ref().unwrap().tokens, 4..5);
+        assert_eq!(&old[change.old.as_ref().unwrap().bytes.clone()], "2");
+        assert_eq!(&new[change.new.as_ref().unwrap().bytes.clone()], "3");
+    }
+
+    #[test]
+    fn inserted_and_removed_members() {
+        let old = r#"{"a":1,"b":"x"}"#;
+        let new = r#"{"a":1,"c":[true]}"#;
+        let changes = change_report_str(old, new).unwrap();
+        assert_eq!(kinds(&changes), [ChangeKind::Removed, ChangeKind::Inserted]);
+        assert_eq!(
+            &old[changes[0].old.as_ref().unwrap().bytes.clone()],
+            r#""b":"x""#
+        );
+
+        let added = changes[1].new.as_ref().unwrap();
+        assert_eq!(added.tokens, 3..6);
+        assert_eq!(&new[added.bytes.clone()], r#""c":[true]"#);
+
+        // Keys pair up by their decoded name, the last of repeated ones.
+        let old = r#"{"\u0061":1,"b":2,"b":3}"#;
+        let new = r#"{"b":3,"a":1}"#;
+        let changes = change_report_str(old, new).unwrap();
+        assert_eq!(kinds(&changes), [ChangeKind::Removed]);
+        assert_eq!(
+            &old[changes[0].old.as_ref().unwrap().bytes.clone()],
+            r#""b":2"#
+        );
+    }
+
+    #[test]
+    fn array_insertion_keeps_other_elements() {
+        let changes = change_report_str("[1,2,3]", "[1,9,2,3]").unwrap();
+        assert_eq!(kinds(&changes), [ChangeKind::Inserted]);
+        assert_eq!(changes[0].new.as_ref().unwrap().tokens, 2..3);
+    }
+
+    #[test]
+    fn moved_subtree() {
+        let old = r#"[{"id":1},{"id":2},{"id":3}]"#;
+        let new = r#"[{"id":2},{"id":3},{"id":1}]"#;
+        let changes = change_report_str(old, new).unwrap();
+        assert_eq!(kinds(&changes), [ChangeKind::Moved]);
+
+        let change = &changes[0];
+        assert_eq!(
+            &old[change.old.as_ref().unwrap().bytes.clone()],
+            r#"{"id":1}"#
+        );
+        assert_eq!(change.new.as_ref().unwrap().bytes, 19..27);
+    }
+
+    #[test]
+    fn type_change_is_a_modification() {
+        let changes = change_report_str(r#"{"a":{"b":1}}"#, r#"{"a":[1]}"#).unwrap();
+        assert_eq!(kinds(&changes), [ChangeKind::Modified]);
+        assert_eq!(changes[0].old.as_ref().unwrap().tokens, 2..5);
+        assert_eq!(changes[0].new.as_ref().unwrap().tokens, 2..4);
+    }
+}
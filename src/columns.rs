@@ -0,0 +1,179 @@
+//!
+//! This module reads an array of objects column by column, for batches of
+//! many records with the same members, like sensor readings. Given the
+//! names of the fields wanted, one walk over the array collects, for each
+//! field, the token of its value in every record, so that a column can
+//! then be converted in a tight loop without looking anything up per row.
+//!
+//! Records of the same shape have their members in the same order, so the
+//! walk remembers which field each member position held in the previous
+//! record and checks that one first, which makes matching a key a single
+//! comparison in the common case. Keys are compared with their escapes
+//! decoded, the last member wins when a key appears several times, and a
+//! record without a field has None in its column.
+//!
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use crate::decode::raw_eq;
+use crate::tree::{children, value_span};
+use crate::{JsmnTok, JsmnType};
+
+/// Error type for columnar extraction.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColumnsErr {
+    /// The token given is not an array.
+    NotArray,
+    /// The element at index `row` of the array is not an object.
+    NotObject { row: usize },
+}
+
+impl fmt::Display for ColumnsErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnsErr::NotArray => write!(f, "not an array"),
+            ColumnsErr::NotObject { row } => write!(f, "row {} is not an object", row),
+        }
+    }
+}
+
+impl Error for ColumnsErr {}
+
+/// Returns, for each of `fields`, the token of its value in each object of
+/// the array at token `array`, or None for the objects without it.
+pub fn column_tokens(
+    js: &str,
+    tokens: &[JsmnTok],
+    array: usize,
+    fields: &[&str],
+) -> Result<Vec<Vec<Option<usize>>>, ColumnsErr> {
+    let tok = tokens.get(array).ok_or(ColumnsErr::NotArray)?;
+    if tok.typ != JsmnType::JsmnArray {
+        return Err(ColumnsErr::NotArray);
+    }
+
+    let rows = tok.size.max(0) as usize;
+    let mut columns = vec![Vec::with_capacity(rows); fields.len()];
+    // The field found at each member position of the previous record.
+    let mut guesses: Vec<Option<usize>> = Vec::new();
+
+    for (row, object) in children(tokens, array).enumerate() {
+        if tokens[object].typ != JsmnType::JsmnObject {
+            return Err(ColumnsErr::NotObject { row });
+        }
+        for column in &mut columns {
+            column.push(None);
+        }
+
+        for (member, key) in children(tokens, object).enumerate() {
+            if key + 1 >= tokens.len() {
+                break;
+            }
+            let Some(raw) = tokens[key].try_as_str(js) else {
+                continue;
+            };
+            if guesses.len() <= member {
+                guesses.resize(member + 1, None);
+            }
+            let field = match guesses[member] {
+                Some(field) if raw_eq(raw, fields[field]) => Some(field),
+                _ => fields.iter().position(|field| raw_eq(raw, field)),
+            };
+            guesses[member] = field;
+            if let Some(field) = field {
+                columns[field][row] = Some(key + 1);
+            }
+        }
+    }
+    Ok(columns)
+}
+
+/// Like [`column_tokens`], but with the byte spans of the values, quotes
+/// of strings included.
+pub fn column_spans(
+    js: &str,
+    tokens: &[JsmnTok],
+    array: usize,
+    fields: &[&str],
+) -> Result<Vec<Vec<Option<Range<usize>>>>, ColumnsErr> {
+    let columns = column_tokens(js, tokens, array, fields)?;
+    Ok(columns
+        .into_iter()
+        .map(|column| {
+            column
+                .into_iter()
+                .map(|idx| idx.map(|idx| value_span(tokens, idx)))
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_vec;
+    use crate::value::Value;
+
+    #[test]
+    fn columns_of_records() {
+        let js = r#"{"batch": [
+            {"t": 1, "temp": 20.5, "id": "a"},
+            {"t": 2, "temp": 21, "id": "b"},
+            {"id": "c", "t": 3},
+            {"t": 4, "temp": null, "t": 5, "extra": [1, 2]},
+            {"temp": 19}
+        ]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let batch = Value::new(js, &tokens, 0).unwrap().get("batch").unwrap();
+
+        let columns = column_spans(js, &tokens, batch.index(), &["t", "temp", "id"]).unwrap();
+        let texts: Vec<Vec<Option<&str>>> = columns
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|span| span.clone().map(|s| &js[s]))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                vec![Some("1"), Some("2"), Some("3"), Some("5"), None],
+                vec![Some("20.5"), Some("21"), None, Some("null"), Some("19")],
+                vec![Some(r#""a""#), Some(r#""b""#), Some(r#""c""#), None, None],
+            ]
+        );
+
+        let temps = &column_tokens(js, &tokens, batch.index(), &["temp"]).unwrap()[0];
+        let temps: Vec<_> = temps
+            .iter()
+            .map(|idx| idx.and_then(|idx| Value::new(js, &tokens, idx)?.as_f64_exact()))
+            .collect();
+        assert_eq!(temps, [Some(20.5), Some(21.0), None, None, Some(19.0)]);
+    }
+
+    #[test]
+    fn errors() {
+        let js = r#"[{"a": 1}, 2]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(
+            column_tokens(js, &tokens, 0, &["a"]),
+            Err(ColumnsErr::NotObject { row: 1 })
+        );
+        assert_eq!(
+            column_tokens(js, &tokens, 1, &["a"]),
+            Err(ColumnsErr::NotArray)
+        );
+        assert_eq!(
+            column_tokens(js, &tokens, 9, &["a"]),
+            Err(ColumnsErr::NotArray)
+        );
+        assert_eq!(
+            column_tokens("[]", &parse_to_vec("[]").unwrap(), 0, &["a"]),
+            Ok(vec![vec![]])
+        );
+    }
+}
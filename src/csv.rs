@@ -0,0 +1,281 @@
+//!
+//! This module flattens an array of objects into a table, one row per
+//! object and one column per field, for handing parsed records on to
+//! spreadsheets and other tools that take CSV. It works on the tokens
+//! only, on top of the columns module.
+//!
+//! `rows` gives the cells as they are written in the document, borrowed
+//! from it: strings with their quotes and escapes, and objects and arrays
+//! as their whole JSON text. `write_csv` writes the table as RFC 4180 CSV,
+//! with a header line of field names and CRLF line endings. Strings are
+//! decoded, cells holding commas, quotes or line breaks are quoted, and
+//! both `null` and missing fields are left empty. Nested objects and
+//! arrays are written as their JSON text.
+//!
+//! The fields can be given, or found with `fields`, which lists the keys
+//! of all the objects in the order they first appear.
+//!
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Write};
+
+use crate::columns::{ColumnsErr, column_tokens};
+use crate::decode::unescape;
+use crate::tree::{children, value_span};
+use crate::{JsmnTok, JsmnType};
+
+/// Error type for writing CSV.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CsvErr {
+    /// The tokens are not an array of objects.
+    Columns(ColumnsErr),
+    /// Writing the output failed.
+    Fmt(fmt::Error),
+}
+
+impl From<ColumnsErr> for CsvErr {
+    fn from(err: ColumnsErr) -> Self {
+        CsvErr::Columns(err)
+    }
+}
+
+impl From<fmt::Error> for CsvErr {
+    fn from(err: fmt::Error) -> Self {
+        CsvErr::Fmt(err)
+    }
+}
+
+impl fmt::Display for CsvErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvErr::Columns(err) => err.fmt(f),
+            CsvErr::Fmt(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for CsvErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CsvErr::Columns(err) => Some(err),
+            CsvErr::Fmt(err) => Some(err),
+        }
+    }
+}
+
+/// Iterator over the rows of an array of objects, returned by `rows`.
+#[derive(Debug, Clone)]
+pub struct Rows<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+    columns: Vec<Vec<Option<usize>>>,
+    row: usize,
+    rows: usize,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    /// The JSON text of each field, or None where the object lacks it.
+    type Item = Vec<Option<&'a str>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row >= self.rows {
+            return None;
+        }
+        let row = self.row;
+        self.row += 1;
+        Some(
+            self.columns
+                .iter()
+                .map(|column| {
+                    let idx = column[row]?;
+                    self.js.get(value_span(self.tokens, idx))
+                })
+                .collect(),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.rows - self.row;
+        (left, Some(left))
+    }
+}
+
+/// Returns the rows of the array of objects at token `array`, with a cell
+/// for each of `fields`.
+pub fn rows<'a>(
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+    array: usize,
+    fields: &[&str],
+) -> Result<Rows<'a>, ColumnsErr> {
+    let columns = column_tokens(js, tokens, array, fields)?;
+    let rows = row_count(tokens, array);
+    Ok(Rows {
+        js,
+        tokens,
+        columns,
+        row: 0,
+        rows,
+    })
+}
+
+/// Returns the decoded keys of the objects of the array at token `array`,
+/// each once, in the order they first appear. Elements that are not
+/// objects are passed over.
+pub fn fields(js: &str, tokens: &[JsmnTok], array: usize) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    if tokens.get(array).map(|tok| tok.typ) != Some(JsmnType::JsmnArray) {
+        return fields;
+    }
+    for object in children(tokens, array) {
+        if tokens[object].typ != JsmnType::JsmnObject {
+            continue;
+        }
+        for key in children(tokens, object) {
+            let Some(raw) = tokens[key].try_as_str(js) else {
+                continue;
+            };
+            let key = unescape(raw);
+            if !fields.iter().any(|field| *field == key) {
+                fields.push(key.into_owned());
+            }
+        }
+    }
+    fields
+}
+
+/// Writes the array of objects at token `array` as CSV, with a column for
+/// each of `fields`.
+pub fn write_csv<W: Write>(
+    out: &mut W,
+    js: &str,
+    tokens: &[JsmnTok],
+    array: usize,
+    fields: &[&str],
+) -> Result<(), CsvErr> {
+    let columns = column_tokens(js, tokens, array, fields)?;
+    write_line(out, fields.iter().copied())?;
+    for row in 0..row_count(tokens, array) {
+        let cells = columns.iter().map(|column| match column[row] {
+            Some(idx) => cell(js, tokens, idx),
+            None => "".into(),
+        });
+        write_line(out, cells)?;
+    }
+    Ok(())
+}
+
+/// Returns the array of objects at token `array` as CSV, with a column for
+/// every key found in its objects.
+pub fn to_csv(js: &str, tokens: &[JsmnTok], array: usize) -> Result<String, ColumnsErr> {
+    let fields = fields(js, tokens, array);
+    let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+    let mut out = String::new();
+    match write_csv(&mut out, js, tokens, array, &fields) {
+        Ok(()) => Ok(out),
+        Err(CsvErr::Columns(err)) => Err(err),
+        Err(CsvErr::Fmt(_)) => unreachable!("writing to a String cannot fail"),
+    }
+}
+
+/// The number of objects in the array at token `array`, counted as the
+/// columns were, so a truncated array gives no rows past its tokens. There
+/// may be no columns to count them from.
+fn row_count(tokens: &[JsmnTok], array: usize) -> usize {
+    children(tokens, array).count()
+}
+
+/// The text of a cell: strings decoded, `null` empty, and everything else
+/// as written.
+fn cell<'a>(js: &'a str, tokens: &[JsmnTok], idx: usize) -> Cow<'a, str> {
+    let tok = &tokens[idx];
+    let text = js.get(value_span(tokens, idx)).unwrap_or_default();
+    match tok.typ {
+        JsmnType::JsmnString => unescape(tok.try_as_str(js).unwrap_or_default()),
+        JsmnType::JsmnPrimitive if text == "null" => "".into(),
+        _ => text.into(),
+    }
+}
+
+fn write_line<W: Write, S: AsRef<str>>(out: &mut W, cells: impl Iterator<Item = S>) -> fmt::Result {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.write_char(',')?;
+        }
+        let cell = cell.as_ref();
+        if cell.contains([',', '"', '\r', '\n']) {
+            write!(out, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            out.write_str(cell)?;
+        }
+    }
+    out.write_str("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_vec;
+
+    const RECORDS: &str = r#"[
+        {"id": 1, "name": "Zoë", "note": "says \"hi\", twice"},
+        {"id": 2, "tags": ["a", "b"], "name": null},
+        {"name": "line\nbreak", "id": 3.5}
+    ]"#;
+
+    #[test]
+    fn rows_of_spans() {
+        let tokens = parse_to_vec(RECORDS).unwrap();
+        let rows: Vec<_> = rows(RECORDS, &tokens, 0, &["id", "name", "tags"])
+            .unwrap()
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                vec![Some("1"), Some(r#""Zoë""#), None],
+                vec![Some("2"), Some("null"), Some(r#"["a", "b"]"#)],
+                vec![Some("3.5"), Some(r#""line\nbreak""#), None],
+            ]
+        );
+        assert_eq!(fields(RECORDS, &tokens, 0), ["id", "name", "note", "tags"]);
+        assert!(fields(RECORDS, &tokens, 1).is_empty());
+    }
+
+    #[test]
+    fn csv_output() {
+        let tokens = parse_to_vec(RECORDS).unwrap();
+        assert_eq!(
+            to_csv(RECORDS, &tokens, 0).unwrap(),
+            concat!(
+                "id,name,note,tags\r\n",
+                "1,Zoë,\"says \"\"hi\"\", twice\",\r\n",
+                "2,,,\"[\"\"a\"\", \"\"b\"\"]\"\r\n",
+                "3.5,\"line\nbreak\",,\r\n",
+            )
+        );
+
+        let mut out = String::new();
+        write_csv(&mut out, RECORDS, &tokens, 0, &["id"]).unwrap();
+        assert_eq!(out, "id\r\n1\r\n2\r\n3.5\r\n");
+
+        let js = "[{}, {}]";
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(to_csv(js, &tokens, 0).unwrap(), "\r\n\r\n\r\n");
+        let empty: Vec<_> = rows(js, &tokens, 0, &[]).unwrap().collect();
+        assert_eq!(empty, [Vec::<Option<&str>>::new(), Vec::new()]);
+
+        let js = r#"[{"a": 1}, {"a": 2}]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let truncated: Vec<_> = rows(js, &tokens[..3], 0, &["a"]).unwrap().collect();
+        assert_eq!(truncated, [vec![None]]);
+        assert_eq!(to_csv(js, &tokens[..3], 0).unwrap(), "a\r\n\r\n");
+
+        let js = "[1]";
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(
+            to_csv(js, &tokens, 0),
+            Err(ColumnsErr::NotObject { row: 0 })
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//!
+//! Decoding of the escape sequences found inside JSON strings.
+//!
+//! jsmn only finds where strings are, so their text still holds escapes
+//! like `\n`, `\"` or `\u00e9`, and characters outside the Basic
+//! Multilingual Plane written as two `\u` escapes of a surrogate pair. The
+//! functions here decode all of them, and only allocate when there is an
+//! escape to decode.
+//!
+//! A `RawStr` is the text of a string together with whether it holds any
+//! escape, so that code can use the raw text where it is enough and decode
+//! it only where it has to. Working that out still takes a scan for a
+//! backslash; `EscapeFlags` does the scan once for every string of a
+//! document, right after parsing, after which each `RawStr` comes for free.
+//!
+
+use std::borrow::Cow;
+
+use crate::{JsmnTok, JsmnType};
+
+/// Returns the decoded contents of a string token of `js`, or None if the
+/// token is not a string.
+pub fn decode_token<'a>(js: &'a str, tok: &JsmnTok) -> Option<Cow<'a, str>> {
+    let raw = js.get(tok.start as usize..tok.end as usize)?;
+    (tok.typ == JsmnType::JsmnString).then(|| unescape(raw))
+}
+
+/// Decodes the escape sequences of a raw string span (without its quotes).
+/// Spans without a backslash are returned as-is without allocating. Invalid
+/// escapes and unpaired surrogates decode to U+FFFD.
+pub fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+
+    Cow::Owned(decode_escaped(raw))
+}
+
+fn decode_escaped(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    out.extend(Unescape(raw.chars()));
+    out
+}
+
+/// Returns true if a raw string span decodes to `text`. Spans without a
+/// backslash are compared as they are, and the others are decoded on the
+/// fly, so this never allocates.
+pub(crate) fn raw_eq(raw: &str, text: &str) -> bool {
+    if !raw.contains('\\') {
+        return raw == text;
+    }
+    Unescape(raw.chars()).eq(text.chars())
+}
+
+/// The text of a string between its quotes, escapes included, and whether
+/// it holds any.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RawStr<'a> {
+    raw: &'a str,
+    escaped: bool,
+}
+
+impl<'a> RawStr<'a> {
+    /// Wraps a raw string span, scanning it for a backslash.
+    pub fn new(raw: &'a str) -> Self {
+        RawStr {
+            raw,
+            escaped: raw.contains('\\'),
+        }
+    }
+
+    /// Returns the text of a string token of `js`, or None if the token is
+    /// not a string.
+    pub fn from_token(js: &'a str, tok: &JsmnTok) -> Option<Self> {
+        let raw = tok.try_as_str(js)?;
+        (tok.typ == JsmnType::JsmnString).then(|| RawStr::new(raw))
+    }
+
+    /// The text as written, escapes included.
+    pub fn raw(&self) -> &'a str {
+        self.raw
+    }
+
+    /// Returns true if the text holds at least one escape, so that it
+    /// differs from its decoded form.
+    pub fn has_escapes(&self) -> bool {
+        self.escaped
+    }
+
+    /// The decoded text, borrowed from the document unless there are
+    /// escapes to decode.
+    pub fn decoded(&self) -> Cow<'a, str> {
+        if self.escaped {
+            Cow::Owned(decode_escaped(self.raw))
+        } else {
+            Cow::Borrowed(self.raw)
+        }
+    }
+
+    /// Returns true if the decoded text is `text`, without allocating.
+    pub fn eq_decoded(&self, text: &str) -> bool {
+        if self.escaped {
+            Unescape(self.raw.chars()).eq(text.chars())
+        } else {
+            self.raw == text
+        }
+    }
+}
+
+/// Which string tokens of a document hold escapes, worked out once for all
+/// of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EscapeFlags {
+    /// One bit per token, set for strings with escapes.
+    bits: Vec<u64>,
+}
+
+impl EscapeFlags {
+    /// Scans every string token of `js` for escapes.
+    pub fn new(js: &str, tokens: &[JsmnTok]) -> Self {
+        let mut bits = vec![0; tokens.len().div_ceil(64)];
+        for (idx, tok) in tokens.iter().enumerate() {
+            if tok.typ == JsmnType::JsmnString
+                && tok.try_as_str(js).is_some_and(|raw| raw.contains('\\'))
+            {
+                bits[idx / 64] |= 1 << (idx % 64);
+            }
+        }
+        EscapeFlags { bits }
+    }
+
+    /// Returns true if the token at `idx` is a string with escapes.
+    pub fn is_escaped(&self, idx: usize) -> bool {
+        self.bits
+            .get(idx / 64)
+            .is_some_and(|word| word & (1 << (idx % 64)) != 0)
+    }
+
+    /// Returns the string token at `idx` without scanning it again, or None
+    /// if it is not a string. `js` and `tokens` must be the ones the flags
+    /// were computed from.
+    pub fn raw_str<'a>(&self, js: &'a str, tokens: &[JsmnTok], idx: usize) -> Option<RawStr<'a>> {
+        let tok = tokens.get(idx)?;
+        let raw = tok.try_as_str(js)?;
+        (tok.typ == JsmnType::JsmnString).then(|| RawStr {
+            raw,
+            escaped: self.is_escaped(idx),
+        })
+    }
+}
+
+/// Iterator over the decoded characters of a raw string span.
+struct Unescape<'a>(std::str::Chars<'a>);
+
+impl Iterator for Unescape<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let chars = &mut self.0;
+        let c = chars.next()?;
+        if c != '\\' {
+            return Some(c);
+        }
+        Some(match chars.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => unicode_escape(chars),
+            _ => char::REPLACEMENT_CHARACTER,
+        })
+    }
+}
+
+/// Decodes the code point of a `\u` escape whose `\u` was already consumed,
+/// combining it with a following low surrogate escape when needed.
+pub(crate) fn unicode_escape(chars: &mut std::str::Chars) -> char {
+    let Some(high) = hex4(chars) else {
+        return char::REPLACEMENT_CHARACTER;
+    };
+    if !(0xD800..0xDC00).contains(&high) {
+        return char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER);
+    }
+
+    let mut lookahead = chars.clone();
+    if lookahead.next() == Some('\\')
+        && lookahead.next() == Some('u')
+        && let Some(low @ 0xDC00..=0xDFFF) = hex4(&mut lookahead)
+    {
+        *chars = lookahead;
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        return char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+    }
+    char::REPLACEMENT_CHARACTER
+}
+
+/// Reads the four hex digits of a `\u` escape.
+pub(crate) fn hex4(chars: &mut std::str::Chars) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.next()?.to_digit(16)?;
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_sequences() {
+        assert!(matches!(unescape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(unescape(r#"a\"b\\c\/d\n\t"#), "a\"b\\c/d\n\t");
+        assert_eq!(unescape(r"\u00e9\u20AC"), "é€");
+        assert_eq!(unescape(r"\ud83d\ude00!"), "😀!");
+        assert_eq!(unescape(r"\ud83dx"), "\u{FFFD}x");
+    }
+
+    #[test]
+    fn decode_tokens() {
+        let js = r#"["tab\there", "plain", 12]"#;
+        let tokens = crate::parse_to_vec(js).unwrap();
+        assert_eq!(decode_token(js, &tokens[1]).as_deref(), Some("tab\there"));
+        assert!(matches!(
+            decode_token(js, &tokens[2]),
+            Some(Cow::Borrowed("plain"))
+        ));
+        assert_eq!(decode_token(js, &tokens[3]), None);
+        assert_eq!(decode_token(js, &tokens[0]), None);
+    }
+
+    #[test]
+    fn raw_comparison() {
+        assert!(raw_eq("plain", "plain"));
+        assert!(!raw_eq("plain", "plai"));
+        assert!(raw_eq(r"caf\u00e9\n", "café\n"));
+        assert!(raw_eq(r"\ud83d\ude00", "😀"));
+        assert!(!raw_eq(r"a\\", "a"));
+        assert!(!raw_eq(r"a\n", "a\nb"));
+    }
+
+    #[test]
+    fn raw_strings_and_flags() {
+        let js = r#"{"plain": "caf\u00e9", "x": 1}"#;
+        let tokens = crate::parse_to_vec(js).unwrap();
+        let flags = EscapeFlags::new(js, &tokens);
+        assert_eq!(
+            (0..tokens.len())
+                .map(|i| flags.is_escaped(i))
+                .collect::<Vec<_>>(),
+            [false, false, true, false, false]
+        );
+        assert!(!flags.is_escaped(100));
+
+        let key = flags.raw_str(js, &tokens, 1).unwrap();
+        assert_eq!(key, RawStr::from_token(js, &tokens[1]).unwrap());
+        assert!(!key.has_escapes());
+        assert!(matches!(key.decoded(), Cow::Borrowed("plain")));
+
+        let value = flags.raw_str(js, &tokens, 2).unwrap();
+        assert_eq!(value, RawStr::new(r"caf\u00e9"));
+        assert_eq!(value.raw(), r"caf\u00e9");
+        assert_eq!(value.decoded(), "café");
+        assert!(value.eq_decoded("café") && !value.eq_decoded(r"caf\u00e9"));
+        assert_eq!(flags.raw_str(js, &tokens, 4), None);
+        assert_eq!(flags.raw_str(js, &tokens, 0), None);
+    }
+}
@@ -0,0 +1,291 @@
+//!
+//! This module compares two versions of a document and reports the paths
+//! whose values were added, removed or changed, as JSON Pointers, such as
+//! `/servers/0/port`. It is meant for things like reloading a configuration
+//! file and acting only on the settings that changed, without building a
+//! tree of values for either version.
+//!
+//! Unlike the change report of the changes module, which finds moved and
+//! reordered subtrees for editors, this comparison is by path: object
+//! members are matched by their decoded keys, array elements by their
+//! index, and a value whose type changed is reported as changed as a whole.
+//! Strings are compared decoded, so `"\u0041"` and `"A"` are the same, and
+//! other primitives by their text, so `1` and `1.0` differ. When an object
+//! has the same key twice, the last member counts, as with pointers.
+//!
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::decode::unescape;
+use crate::pointer::{PathItem, to_pointer};
+use crate::tree::{children, value_span};
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// What happened to the value at a path.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DiffKind {
+    /// The path only exists in the new document.
+    Added,
+    /// The path only exists in the old document.
+    Removed,
+    /// The path exists in both documents with different scalars, or with
+    /// values of different types.
+    Changed,
+}
+
+/// A path whose value differs between the two documents, with the byte
+/// ranges of its old and new values, quotes of strings included. Added
+/// paths only have a `new` range and removed paths only an `old` range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub kind: DiffKind,
+    pub path: String,
+    pub old: Option<Range<usize>>,
+    pub new: Option<Range<usize>>,
+}
+
+/// Parses both versions of a document and compares them.
+pub fn diff_str(old_js: &str, new_js: &str) -> Result<Vec<DiffEntry>, JsmnErr> {
+    let old_tokens = parse_to_vec(old_js)?;
+    let new_tokens = parse_to_vec(new_js)?;
+    Ok(diff(old_js, &old_tokens, new_js, &new_tokens))
+}
+
+/// Compares the first values of two already parsed documents. Entries come
+/// in the order of the old document, with the members added to an object
+/// after those it had before.
+pub fn diff(
+    old_js: &str,
+    old_tokens: &[JsmnTok],
+    new_js: &str,
+    new_tokens: &[JsmnTok],
+) -> Vec<DiffEntry> {
+    let old = Side {
+        js: old_js,
+        tokens: old_tokens,
+    };
+    let new = Side {
+        js: new_js,
+        tokens: new_tokens,
+    };
+    let mut entries = Vec::new();
+    let mut path = Vec::new();
+    match (old_tokens.is_empty(), new_tokens.is_empty()) {
+        (false, false) => diff_values(&old, 0, &new, 0, &mut path, &mut entries),
+        (false, true) => entries.push(old.entry(DiffKind::Removed, &path, 0)),
+        (true, false) => entries.push(new.entry(DiffKind::Added, &path, 0)),
+        (true, true) => {}
+    }
+    entries
+}
+
+/// One version of the document.
+struct Side<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+}
+
+impl<'a> Side<'a> {
+    fn text(&self, idx: usize) -> &'a str {
+        let tok = &self.tokens[idx];
+        &self.js[tok.start as usize..tok.end as usize]
+    }
+
+    fn span(&self, idx: usize) -> Range<usize> {
+        value_span(self.tokens, idx)
+    }
+
+    /// Returns the members of the object at `idx` as decoded keys with the
+    /// indices of their values, keeping only the last of repeated keys.
+    fn members(&self, idx: usize) -> Vec<(Cow<'a, str>, usize)> {
+        let mut members: Vec<(Cow<'a, str>, usize)> = Vec::new();
+        for key in children(self.tokens, idx) {
+            let name = unescape(self.text(key));
+            members.retain(|(other, _)| *other != name);
+            members.push((name, key + 1));
+        }
+        members
+    }
+
+    fn entry(&self, kind: DiffKind, path: &[PathItem], idx: usize) -> DiffEntry {
+        let span = Some(self.span(idx));
+        let (old, new) = match kind {
+            DiffKind::Added => (None, span),
+            _ => (span, None),
+        };
+        DiffEntry {
+            kind,
+            path: to_pointer(path),
+            old,
+            new,
+        }
+    }
+}
+
+fn diff_values(
+    old: &Side,
+    o: usize,
+    new: &Side,
+    n: usize,
+    path: &mut Vec<PathItem>,
+    entries: &mut Vec<DiffEntry>,
+) {
+    // Identical text is the common case when reloading, and needs no walk.
+    if old.js[old.span(o)] == new.js[new.span(n)] {
+        return;
+    }
+
+    let (old_tok, new_tok) = (&old.tokens[o], &new.tokens[n]);
+    let same = match (old_tok.typ, new_tok.typ) {
+        (JsmnType::JsmnObject, JsmnType::JsmnObject) => {
+            diff_objects(old, o, new, n, path, entries);
+            true
+        }
+        (JsmnType::JsmnArray, JsmnType::JsmnArray) => {
+            diff_arrays(old, o, new, n, path, entries);
+            true
+        }
+        (JsmnType::JsmnString, JsmnType::JsmnString) => {
+            unescape(old.text(o)) == unescape(new.text(n))
+        }
+        (old_typ, new_typ) => old_typ == new_typ && old.text(o) == new.text(n),
+    };
+    if !same {
+        entries.push(DiffEntry {
+            kind: DiffKind::Changed,
+            path: to_pointer(path),
+            old: Some(old.span(o)),
+            new: Some(new.span(n)),
+        });
+    }
+}
+
+fn diff_objects(
+    old: &Side,
+    o: usize,
+    new: &Side,
+    n: usize,
+    path: &mut Vec<PathItem>,
+    entries: &mut Vec<DiffEntry>,
+) {
+    let mut new_members: Vec<Option<(Cow<str>, usize)>> =
+        new.members(n).into_iter().map(Some).collect();
+
+    for (key, old_value) in old.members(o) {
+        let found = new_members
+            .iter_mut()
+            .find(|slot| slot.as_ref().is_some_and(|(other, _)| *other == key))
+            .and_then(Option::take);
+        path.push(PathItem::Key(key.into_owned()));
+        match found {
+            Some((_, new_value)) => diff_values(old, old_value, new, new_value, path, entries),
+            None => entries.push(old.entry(DiffKind::Removed, path, old_value)),
+        }
+        path.pop();
+    }
+
+    for (key, new_value) in new_members.into_iter().flatten() {
+        path.push(PathItem::Key(key.into_owned()));
+        entries.push(new.entry(DiffKind::Added, path, new_value));
+        path.pop();
+    }
+}
+
+fn diff_arrays(
+    old: &Side,
+    o: usize,
+    new: &Side,
+    n: usize,
+    path: &mut Vec<PathItem>,
+    entries: &mut Vec<DiffEntry>,
+) {
+    let mut old_items = children(old.tokens, o);
+    let mut new_items = children(new.tokens, n);
+    for index in 0.. {
+        path.push(PathItem::Index(index));
+        match (old_items.next(), new_items.next()) {
+            (Some(old_item), Some(new_item)) => {
+                diff_values(old, old_item, new, new_item, path, entries)
+            }
+            (Some(old_item), None) => entries.push(old.entry(DiffKind::Removed, path, old_item)),
+            (None, Some(new_item)) => entries.push(new.entry(DiffKind::Added, path, new_item)),
+            (None, None) => {
+                path.pop();
+                break;
+            }
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(entries: &[DiffEntry]) -> Vec<(DiffKind, &str)> {
+        entries
+            .iter()
+            .map(|entry| (entry.kind, entry.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn config_reload() {
+        let old = r#"{"name": "api", "port": 80, "tls": {"cert": "a.pem", "key": "a.key"},
+                      "hosts": ["a", "b", "c"], "debug": true}"#;
+        let new = r#"{"port": 8080, "name": "api", "tls": {"cert": "b.pem", "key": "a.key"},
+                      "hosts": ["a", "x"], "log/level": "info"}"#;
+        let entries = diff_str(old, new).unwrap();
+        assert_eq!(
+            summary(&entries),
+            [
+                (DiffKind::Changed, "/port"),
+                (DiffKind::Changed, "/tls/cert"),
+                (DiffKind::Changed, "/hosts/1"),
+                (DiffKind::Removed, "/hosts/2"),
+                (DiffKind::Removed, "/debug"),
+                (DiffKind::Added, "/log~1level"),
+            ]
+        );
+
+        let port = &entries[0];
+        assert_eq!(&old[port.old.clone().unwrap()], "80");
+        assert_eq!(&new[port.new.clone().unwrap()], "8080");
+        assert_eq!(&old[entries[3].old.clone().unwrap()], r#""c""#);
+        assert_eq!(entries[3].new, None);
+        assert_eq!(&new[entries[5].new.clone().unwrap()], r#""info""#);
+    }
+
+    #[test]
+    fn whitespace_and_repeated_keys() {
+        assert!(
+            diff_str(r#"{"a": [1, {"b": null}]}"#, r#"{"a":[1,{"b":null}]}"#)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            diff_str(r#"{"a": 1, "a": 2}"#, r#"{"a": 2}"#)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            summary(&diff_str("[1]", "[1.0]").unwrap()),
+            [(DiffKind::Changed, "/0")]
+        );
+    }
+
+    #[test]
+    fn type_changes_and_empty_documents() {
+        let entries = diff_str(r#"{"a": {"b": 1}}"#, r#"{"a": [1]}"#).unwrap();
+        assert_eq!(summary(&entries), [(DiffKind::Changed, "/a")]);
+        assert_eq!(entries[0].old, Some(6..14));
+
+        let entries = diff_str("[]", "{}").unwrap();
+        assert_eq!(summary(&entries), [(DiffKind::Changed, "")]);
+        let entries = diff_str("", "[1]").unwrap();
+        assert_eq!(summary(&entries), [(DiffKind::Added, "")]);
+        assert_eq!(entries[0].new, Some(0..3));
+        assert_eq!(diff_str("", "").unwrap(), []);
+    }
+}
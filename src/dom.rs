@@ -0,0 +1,244 @@
+//!
+//! This module provides an owned tree of JSON values, for code that wants to
+//! keep a document around, or to take it apart, after the tokens and the
+//! text have gone. It is behind the "dom" feature, as the rest of the crate
+//! works on tokens in place and never needs it.
+//!
+//! Objects keep their members in document order, duplicates included, and
+//! lookups take the last member with a key like everywhere else in the
+//! crate. Numbers are read as the nearest `f64`, like `Value::as_f64_exact`
+//! in the value module, so integers beyond 2^53 lose precision: read those
+//! through the token cursor instead.
+//!
+//! A `ValueRef` is the same tree borrowing from the text it was parsed
+//! from: numbers are kept as their source text, which loses nothing, and
+//! strings are only copied when they have escapes to decode. Building one
+//! allocates nothing but the vectors of arrays and objects.
+//!
+
+use std::borrow::Cow;
+
+use crate::value::{self, ValueErr, ValueErrKind};
+use crate::{JsmnTok, JsmnType};
+
+/// An owned JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Builds the tree of the first root of a parsed document.
+    ///
+    /// Without the strict feature jsmn accepts primitives that are neither
+    /// literals nor numbers, like `yes`, which are reported as invalid, and
+    /// so is a document without any value.
+    pub fn from_tokens(js: &str, tokens: &[JsmnTok]) -> Result<Value, ValueErr> {
+        match value::Value::new(js, tokens, 0) {
+            Some(root) => Value::from_value(root),
+            None => Err(ValueErr {
+                pointer: String::new(),
+                kind: ValueErrKind::Invalid("empty document".into()),
+            }),
+        }
+    }
+
+    /// Builds the tree of the value a cursor points to.
+    pub fn from_value(value: value::Value) -> Result<Value, ValueErr> {
+        Ok(match value.typ() {
+            JsmnType::JsmnObject => Value::Object(
+                value
+                    .members()
+                    .map(|(key, value)| Ok((key.into_owned(), Value::from_value(value)?)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            JsmnType::JsmnArray => Value::Array(
+                value
+                    .elements()
+                    .map(Value::from_value)
+                    .collect::<Result<_, _>>()?,
+            ),
+            JsmnType::JsmnString => Value::String(value.read_str()?.into_owned()),
+            JsmnType::JsmnPrimitive if value.is_null() => Value::Null,
+            JsmnType::JsmnPrimitive => match value.as_bool() {
+                Some(b) => Value::Bool(b),
+                None if value.number_text().is_some() => Value::Number(value.read_f64()?),
+                None => return Err(not_a_primitive(value)),
+            },
+            typ => return Err(value.error(ValueErrKind::WrongType(typ))),
+        })
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    /// Returns the value of the member named `key` of an object. When the
+    /// key appears several times, the last member wins.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns the element at `index` of an array.
+    pub fn at(&self, index: usize) -> Option<&Value> {
+        match self {
+            Value::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+}
+
+/// A JSON value borrowing its strings and numbers from the parsed text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    Null,
+    Bool(bool),
+    /// A number as it is spelled in the text.
+    Number(&'a str),
+    String(Cow<'a, str>),
+    Array(Vec<ValueRef<'a>>),
+    Object(Vec<(Cow<'a, str>, ValueRef<'a>)>),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Builds the tree of the first root of a parsed document, rejecting
+    /// the same things as [`Value::from_tokens`] except numbers too large
+    /// for an `f64`.
+    pub fn from_tokens(js: &'a str, tokens: &'a [JsmnTok]) -> Result<ValueRef<'a>, ValueErr> {
+        match value::Value::new(js, tokens, 0) {
+            Some(root) => ValueRef::from_value(root),
+            None => Err(ValueErr {
+                pointer: String::new(),
+                kind: ValueErrKind::Invalid("empty document".into()),
+            }),
+        }
+    }
+
+    /// Builds the tree of the value a cursor points to.
+    pub fn from_value(value: value::Value<'a>) -> Result<ValueRef<'a>, ValueErr> {
+        Ok(match value.typ() {
+            JsmnType::JsmnObject => ValueRef::Object(
+                value
+                    .members()
+                    .map(|(key, value)| Ok((key, ValueRef::from_value(value)?)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            JsmnType::JsmnArray => ValueRef::Array(
+                value
+                    .elements()
+                    .map(ValueRef::from_value)
+                    .collect::<Result<_, _>>()?,
+            ),
+            JsmnType::JsmnString => ValueRef::String(value.read_str()?),
+            JsmnType::JsmnPrimitive if value.is_null() => ValueRef::Null,
+            JsmnType::JsmnPrimitive => match (value.as_bool(), value.number_text()) {
+                (Some(b), _) => ValueRef::Bool(b),
+                (None, Some(text)) => ValueRef::Number(text),
+                (None, None) => return Err(not_a_primitive(value)),
+            },
+            typ => return Err(value.error(ValueErrKind::WrongType(typ))),
+        })
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == ValueRef::Null
+    }
+
+    /// Returns the value of the member named `key` of an object. When the
+    /// key appears several times, the last member wins.
+    pub fn get(&self, key: &str) -> Option<&ValueRef<'a>> {
+        match self {
+            ValueRef::Object(members) => {
+                members.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the element at `index` of an array.
+    pub fn at(&self, index: usize) -> Option<&ValueRef<'a>> {
+        match self {
+            ValueRef::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+}
+
+fn not_a_primitive(value: value::Value) -> ValueErr {
+    value.error(ValueErrKind::Invalid("not a literal or a number".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    #[test]
+    fn tree_from_tokens() {
+        let js = r#"{"a": [1, -2.5e1, "x\n"], "b": {"c": null, "d": true}, "a": false}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let tree = Value::from_tokens(js, &tokens).unwrap();
+
+        let first = Value::Array(vec![
+            Value::Number(1.0),
+            Value::Number(-25.0),
+            Value::String("x\n".into()),
+        ]);
+        let Value::Object(members) = &tree else {
+            panic!("not an object: {:?}", tree);
+        };
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[0], ("a".into(), first));
+        assert_eq!(tree.get("a"), Some(&Value::Bool(false)));
+        assert!(tree.get("b").and_then(|b| b.get("c")).unwrap().is_null());
+        assert_eq!(members[0].1.at(2), Some(&Value::String("x\n".into())));
+        assert_eq!(tree.at(0), None);
+    }
+
+    #[test]
+    fn borrowed_tree() {
+        let js = r#"{"n": 12345678901234567890, "s": "plain", "e": "caf\u00e9", "l": [null]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let tree = ValueRef::from_tokens(js, &tokens).unwrap();
+
+        assert_eq!(
+            tree.get("n"),
+            Some(&ValueRef::Number("12345678901234567890"))
+        );
+        let Some(ValueRef::String(Cow::Borrowed(plain))) = tree.get("s") else {
+            panic!("not borrowed: {:?}", tree.get("s"));
+        };
+        let start = js.find("plain").unwrap();
+        assert!(std::ptr::eq(*plain, &js[start..start + 5]));
+        assert_eq!(tree.get("e"), Some(&ValueRef::String("café".into())));
+        assert!(tree.get("l").and_then(|l| l.at(0)).unwrap().is_null());
+        assert_eq!(ValueRef::from_tokens("", &[]).unwrap_err().pointer, "");
+    }
+
+    #[test]
+    fn invalid_values() {
+        let err = Value::from_tokens("", &[]).unwrap_err();
+        assert_eq!(err.pointer, "");
+
+        #[cfg(not(feature = "strict"))]
+        {
+            let js = r#"{"a": [1, yes]}"#;
+            let tokens = parse_to_vec(js).unwrap();
+            let err = Value::from_tokens(js, &tokens).unwrap_err();
+            assert_eq!(err.pointer, "/a/1");
+        }
+        let js = "[1e999]";
+        let tokens = parse_to_vec(js).unwrap();
+        let err = Value::from_tokens(js, &tokens).unwrap_err();
+        assert_eq!(err.kind, ValueErrKind::Invalid("out of range".into()));
+    }
+}
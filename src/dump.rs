@@ -0,0 +1,149 @@
+//!
+//! This module prints tokens as an indented tree, one token per line, for
+//! looking at what jsmn made of a document while debugging. Each line has
+//! the token's index, type and byte span, then the number of children of
+//! an object or array, or the text of a string or primitive, cut short
+//! when it is long:
+//!
+//! ```text
+//! 0: object 0..22, 1 member
+//!   1: string 2..6 "tags"
+//!     2: array 9..21, 2 elements
+//!       3: string 11..12 "a"
+//!       4: primitive 15..20 false
+//! ```
+//!
+//! Tokens are nested by their sizes, as in the tree module, and a token
+//! whose span does not lie in the text, such as an object left open by a
+//! partial parse, is printed without any text.
+//!
+
+use std::fmt;
+
+use crate::tree::TokenTree;
+use crate::{JsmnTok, JsmnType};
+
+/// The longest text printed for a string or primitive, in characters.
+const EXCERPT: usize = 40;
+
+/// Prints `tokens`, parsed from `js`, as an indented tree.
+pub fn dump_tokens(js: &str, tokens: &[JsmnTok]) -> String {
+    TokenDump::new(js, tokens).to_string()
+}
+
+/// Tokens displayed as an indented tree, like [`dump_tokens`] prints them,
+/// as in `println!("{}", TokenDump::new(js, &tokens))`.
+#[derive(Debug, Copy, Clone)]
+pub struct TokenDump<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+}
+
+impl<'a> TokenDump<'a> {
+    pub fn new(js: &'a str, tokens: &'a [JsmnTok]) -> Self {
+        TokenDump { js, tokens }
+    }
+}
+
+impl fmt::Display for TokenDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tree = TokenTree::new(self.tokens);
+        for (idx, tok) in self.tokens.iter().enumerate() {
+            let indent = 2 * tree.depth(idx);
+            write!(f, "{:indent$}{}: ", "", idx, indent = indent)?;
+            let plural = |n: i32| if n == 1 { "" } else { "s" };
+            match tok.typ {
+                JsmnType::JsmnObject => write!(f, "object {}..{}", tok.start, tok.end)?,
+                JsmnType::JsmnArray => write!(f, "array {}..{}", tok.start, tok.end)?,
+                JsmnType::JsmnString => write!(f, "string {}..{}", tok.start, tok.end)?,
+                JsmnType::JsmnPrimitive => write!(f, "primitive {}..{}", tok.start, tok.end)?,
+                JsmnType::JsmnUndefined => write!(f, "undefined {}..{}", tok.start, tok.end)?,
+            }
+            match (tok.typ, tok.try_as_str(self.js)) {
+                (JsmnType::JsmnObject, _) => {
+                    write!(f, ", {} member{}", tok.size, plural(tok.size))?
+                }
+                (JsmnType::JsmnArray, _) => {
+                    write!(f, ", {} element{}", tok.size, plural(tok.size))?
+                }
+                (JsmnType::JsmnString, Some(text)) => write!(f, " \"{}\"", Excerpt(text))?,
+                (JsmnType::JsmnPrimitive, Some(text)) => write!(f, " {}", Excerpt(text))?,
+                _ => {}
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The start of a token's text, with an ellipsis if there is more.
+struct Excerpt<'a>(&'a str);
+
+impl fmt::Display for Excerpt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.char_indices().nth(EXCERPT) {
+            Some((end, _)) => write!(f, "{}...", &self.0[..end]),
+            None => f.write_str(self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsmnParser, parse_to_vec};
+
+    #[test]
+    fn tree_dump() {
+        let js = r#"{"tags": ["a", false]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(
+            dump_tokens(js, &tokens),
+            "0: object 0..22, 1 member\n\
+             \x20 1: string 2..6 \"tags\"\n\
+             \x20   2: array 9..21, 2 elements\n\
+             \x20     3: string 11..12 \"a\"\n\
+             \x20     4: primitive 15..20 false\n"
+        );
+
+        let js = "[1] [{}]";
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(
+            TokenDump::new(js, &tokens).to_string(),
+            "0: array 0..3, 1 element\n\
+             \x20 1: primitive 1..2 1\n\
+             2: array 4..8, 1 element\n\
+             \x20 3: object 5..7, 0 members\n"
+        );
+    }
+
+    #[test]
+    fn long_and_partial_tokens() {
+        let long = "x".repeat(50);
+        let js = format!(r#"["{}", "é{}"]"#, long, long);
+        let tokens = parse_to_vec(&js).unwrap();
+        let dump = dump_tokens(&js, &tokens);
+        let lines: Vec<_> = dump.lines().collect();
+        assert_eq!(
+            lines[1],
+            format!("  1: string 2..52 \"{}...\"", &long[..40])
+        );
+        assert_eq!(
+            lines[2],
+            format!("  2: string 56..108 \"é{}...\"", &long[..39])
+        );
+
+        let js = r#"{"a": [1, "#;
+        let mut tokens = vec![JsmnTok::default(); 8];
+        let mut parser = JsmnParser::new();
+        parser.parse(js, &mut tokens).unwrap_err();
+        tokens.truncate(parser.toknext as usize);
+        assert_eq!(
+            dump_tokens(js, &tokens),
+            "0: object 0..-1, 1 member\n\
+             \x20 1: string 2..3 \"a\"\n\
+             \x20   2: array 6..-1, 1 element\n\
+             \x20     3: primitive 7..8 1\n"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//!
+//! This module walks parsed tokens as a stream of events, pulled one at a
+//! time, in the manner of StAX. It is the pull counterpart of the visitor
+//! module: the consumer drives the loop, and can stop, skip ahead or hand
+//! the iterator down to a function reading one part of the document.
+//!
+//! The events are worked out from the tokens with a small cursor: the
+//! index of the next token, and for each open object or array the number
+//! of values still to come. Strings and keys are raw spans, escapes
+//! included, and primitives other than `true`, `false` and `null` are
+//! reported as numbers, since that is all jsmn's strict mode lets through.
+//! When the tokens end before their objects and arrays do, as after a
+//! partial parse, the events close them.
+//!
+
+use crate::visitor::JsonVisitor;
+use crate::{JsmnTok, JsmnType};
+
+/// One event of a document.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// An object key, without its quotes and with its escapes.
+    Key(&'a str),
+    /// A string value, without its quotes and with its escapes.
+    Str(&'a str),
+    /// A number, as written.
+    Num(&'a str),
+    Bool(bool),
+    Null,
+}
+
+/// An open object or array.
+#[derive(Debug, Clone)]
+struct Frame {
+    array: bool,
+    /// The values of this container still to come.
+    remaining: usize,
+    /// Set between an object key and its value.
+    in_member: bool,
+}
+
+/// Iterator over the events of parsed tokens.
+#[derive(Debug, Clone)]
+pub struct Events<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+    next: usize,
+    stack: Vec<Frame>,
+}
+
+impl<'a> Events<'a> {
+    pub fn new(js: &'a str, tokens: &'a [JsmnTok]) -> Self {
+        Events {
+            js,
+            tokens,
+            next: 0,
+            stack: Vec::new(),
+        }
+    }
+
+    /// The number of objects and arrays open after the last event.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Skips the rest of the innermost open object or array, up to and
+    /// including its end event. Does nothing outside of any.
+    pub fn skip_container(&mut self) {
+        let depth = self.depth();
+        if depth == 0 {
+            return;
+        }
+        while self.depth() >= depth && self.next().is_some() {}
+    }
+
+    /// Hands the remaining events to a visitor.
+    pub fn visit<V: JsonVisitor + ?Sized>(self, visitor: &mut V) {
+        for event in self {
+            match event {
+                Event::StartObject => visitor.start_object(),
+                Event::EndObject => visitor.end_object(),
+                Event::StartArray => visitor.start_array(),
+                Event::EndArray => visitor.end_array(),
+                Event::Key(raw) => visitor.key(raw),
+                Event::Str(raw) => visitor.string(raw),
+                Event::Num(text) => visitor.number(text),
+                Event::Bool(value) => visitor.bool(value),
+                Event::Null => visitor.null(),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        if let Some(frame) = self.stack.last() {
+            let done = frame.remaining == 0 && !frame.in_member;
+            if done || self.next >= self.tokens.len() {
+                let frame = self.stack.pop().unwrap();
+                return Some(if frame.array {
+                    Event::EndArray
+                } else {
+                    Event::EndObject
+                });
+            }
+        }
+
+        let tok = self.tokens.get(self.next)?;
+        self.next += 1;
+        let text = self
+            .js
+            .get(tok.start as usize..tok.end as usize)
+            .unwrap_or_default();
+
+        if let Some(frame) = self.stack.last_mut() {
+            if !frame.array && !frame.in_member {
+                frame.in_member = true;
+                return Some(Event::Key(text));
+            }
+            frame.in_member = false;
+            frame.remaining -= 1;
+        }
+
+        Some(match tok.typ {
+            JsmnType::JsmnObject | JsmnType::JsmnArray => {
+                let array = tok.typ == JsmnType::JsmnArray;
+                self.stack.push(Frame {
+                    array,
+                    remaining: tok.size.max(0) as usize,
+                    in_member: false,
+                });
+                if array {
+                    Event::StartArray
+                } else {
+                    Event::StartObject
+                }
+            }
+            JsmnType::JsmnString => Event::Str(text),
+            _ => match text {
+                "true" => Event::Bool(true),
+                "false" => Event::Bool(false),
+                "null" => Event::Null,
+                _ => Event::Num(text),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsmnParser, parse_to_vec};
+
+    #[test]
+    fn events_from_tokens() {
+        let js = r#"{"id": 7, "tags": ["a\n", {}, []], "ok": true, "no": null} "x""#;
+        let tokens = parse_to_vec(js).unwrap();
+        let events: Vec<_> = Events::new(js, &tokens).collect();
+        assert_eq!(
+            events,
+            [
+                Event::StartObject,
+                Event::Key("id"),
+                Event::Num("7"),
+                Event::Key("tags"),
+                Event::StartArray,
+                Event::Str("a\\n"),
+                Event::StartObject,
+                Event::EndObject,
+                Event::StartArray,
+                Event::EndArray,
+                Event::EndArray,
+                Event::Key("ok"),
+                Event::Bool(true),
+                Event::Key("no"),
+                Event::Null,
+                Event::EndObject,
+                Event::Str("x"),
+            ]
+        );
+        assert_eq!(Events::new("", &[]).next(), None);
+    }
+
+    #[test]
+    fn pull_and_skip() {
+        let js = r#"{"skip": [1, [2, 3], {"a": 4}], "keep": [5]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let mut events = Events::new(js, &tokens);
+        assert_eq!(events.next(), Some(Event::StartObject));
+        assert_eq!(events.next(), Some(Event::Key("skip")));
+        assert_eq!(events.next(), Some(Event::StartArray));
+        assert_eq!(events.depth(), 2);
+        events.skip_container();
+        assert_eq!(events.depth(), 1);
+        let rest: Vec<_> = events.collect();
+        assert_eq!(
+            rest,
+            [
+                Event::Key("keep"),
+                Event::StartArray,
+                Event::Num("5"),
+                Event::EndArray,
+                Event::EndObject
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_tokens_and_visitors() {
+        let js = r#"{"a": [1, "#;
+        let mut tokens = vec![JsmnTok::default(); 8];
+        let mut parser = JsmnParser::new();
+        parser.parse(js, &mut tokens).unwrap_err();
+        tokens.truncate(parser.toknext as usize);
+        let events: Vec<_> = Events::new(js, &tokens).collect();
+        assert_eq!(
+            events,
+            [
+                Event::StartObject,
+                Event::Key("a"),
+                Event::StartArray,
+                Event::Num("1"),
+                Event::EndArray,
+                Event::EndObject
+            ]
+        );
+
+        /// Counts the keys, as the visitor tokenizer sees them.
+        struct Keys(usize);
+
+        impl JsonVisitor for Keys {
+            fn key(&mut self, _raw: &str) {
+                self.0 += 1;
+            }
+        }
+
+        let js = r#"[{"a": {"b": 1}}, {"c": [{"d": null}]}]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let (mut pulled, mut pushed) = (Keys(0), Keys(0));
+        Events::new(js, &tokens).visit(&mut pulled);
+        crate::visitor::parse_events(js, &mut pushed).unwrap();
+        assert_eq!((pulled.0, pushed.0), (4, 4));
+    }
+}
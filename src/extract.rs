@@ -0,0 +1,320 @@
+//!
+//! This module pulls a fixed set of typed fields out of many documents of
+//! the same shape, which is the bread and butter of ETL jobs. The fields are
+//! declared once in an `Extractor`, as JSON Pointers with the type expected
+//! there and a function storing the value into a row struct, and every
+//! document is then read in a single walk over its tokens.
+//!
+//! Missing fields and `null` leave the row as it was, so rows made of
+//! `Option`s come out with None for them. A value of the wrong type does
+//! not stop the walk: it is recorded as an error next to the row, and the
+//! other fields are still filled in.
+//!
+
+use std::collections::HashMap;
+
+use crate::pointer::{PointerErr, parse_pointer};
+use crate::tree::children;
+use crate::value::{Value, ValueErr};
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// The row read from one document, with the errors of the fields that
+/// could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extracted<T> {
+    pub row: T,
+    pub errors: Vec<ValueErr>,
+}
+
+/// A table of typed fields to read from documents into rows of type `T`.
+#[derive(Debug, Clone)]
+pub struct Extractor<T> {
+    root: Node,
+    fields: Vec<Field<T>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    fields: Vec<usize>,
+}
+
+#[derive(Debug)]
+enum Field<T> {
+    Str(fn(&mut T, String)),
+    Bool(fn(&mut T, bool)),
+    I64(fn(&mut T, i64)),
+    F64(fn(&mut T, f64)),
+    Number(fn(&mut T, String)),
+    #[cfg(feature = "decimal")]
+    Decimal(fn(&mut T, rust_decimal::Decimal)),
+}
+
+// Function pointers are Copy whatever `T` is, which derive can't see.
+impl<T> Clone for Field<T> {
+    fn clone(&self) -> Self {
+        match *self {
+            Field::Str(set) => Field::Str(set),
+            Field::Bool(set) => Field::Bool(set),
+            Field::I64(set) => Field::I64(set),
+            Field::F64(set) => Field::F64(set),
+            Field::Number(set) => Field::Number(set),
+            #[cfg(feature = "decimal")]
+            Field::Decimal(set) => Field::Decimal(set),
+        }
+    }
+}
+
+impl<T> Field<T> {
+    fn apply(&self, row: &mut T, value: Value) -> Result<(), ValueErr> {
+        match self {
+            Field::Str(set) => set(row, value.read_str()?.into_owned()),
+            Field::Bool(set) => set(row, value.read_bool()?),
+            Field::I64(set) => set(row, value.read_i64()?),
+            Field::F64(set) => set(row, value.read_f64()?),
+            Field::Number(set) => set(row, value.read_number_str()?.to_string()),
+            #[cfg(feature = "decimal")]
+            Field::Decimal(set) => set(row, value.read_decimal()?),
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for Extractor<T> {
+    fn default() -> Self {
+        Extractor {
+            root: Node::default(),
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl<T: Default> Extractor<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a string field, stored with its escapes decoded.
+    pub fn str(&mut self, pointer: &str, set: fn(&mut T, String)) -> Result<&mut Self, PointerErr> {
+        self.field(pointer, Field::Str(set))
+    }
+
+    /// Declares a boolean field.
+    pub fn bool(&mut self, pointer: &str, set: fn(&mut T, bool)) -> Result<&mut Self, PointerErr> {
+        self.field(pointer, Field::Bool(set))
+    }
+
+    /// Declares an integer field. Numbers with a fraction or out of range
+    /// are errors.
+    pub fn i64(&mut self, pointer: &str, set: fn(&mut T, i64)) -> Result<&mut Self, PointerErr> {
+        self.field(pointer, Field::I64(set))
+    }
+
+    /// Declares a number field.
+    pub fn f64(&mut self, pointer: &str, set: fn(&mut T, f64)) -> Result<&mut Self, PointerErr> {
+        self.field(pointer, Field::F64(set))
+    }
+
+    /// Declares a number field, stored as the text it is written with, so
+    /// that amounts reach their decimal type without going through an
+    /// `f64`.
+    pub fn number(
+        &mut self,
+        pointer: &str,
+        set: fn(&mut T, String),
+    ) -> Result<&mut Self, PointerErr> {
+        self.field(pointer, Field::Number(set))
+    }
+
+    /// Declares an exact decimal field. Numbers with more significant
+    /// digits than a `Decimal` holds are errors.
+    #[cfg(feature = "decimal")]
+    pub fn decimal(
+        &mut self,
+        pointer: &str,
+        set: fn(&mut T, rust_decimal::Decimal),
+    ) -> Result<&mut Self, PointerErr> {
+        self.field(pointer, Field::Decimal(set))
+    }
+
+    fn field(&mut self, pointer: &str, field: Field<T>) -> Result<&mut Self, PointerErr> {
+        let mut node = &mut self.root;
+        for token in parse_pointer(pointer)? {
+            node = node.children.entry(token).or_default();
+        }
+        node.fields.push(self.fields.len());
+        self.fields.push(field);
+        Ok(self)
+    }
+
+    /// Parses `js` and reads a row from it.
+    pub fn extract(&self, js: &str) -> Result<Extracted<T>, JsmnErr> {
+        let tokens = parse_to_vec(js)?;
+        Ok(self.extract_tokens(js, &tokens))
+    }
+
+    /// Reads a row from an already parsed document. Only the first root
+    /// is read.
+    pub fn extract_tokens(&self, js: &str, tokens: &[JsmnTok]) -> Extracted<T> {
+        let mut extracted = Extracted {
+            row: T::default(),
+            errors: Vec::new(),
+        };
+        self.visit(&self.root, js, tokens, 0, &mut extracted);
+        extracted
+    }
+
+    fn visit(&self, node: &Node, js: &str, tokens: &[JsmnTok], idx: usize, out: &mut Extracted<T>) {
+        let Some(value) = Value::new(js, tokens, idx) else {
+            return;
+        };
+        if !value.is_null() {
+            for &field in &node.fields {
+                if let Err(err) = self.fields[field].apply(&mut out.row, value) {
+                    out.errors.push(err);
+                }
+            }
+        }
+        if node.children.is_empty() {
+            return;
+        }
+
+        match value.typ() {
+            JsmnType::JsmnObject => {
+                for key in children(tokens, idx) {
+                    let name = Value::new(js, tokens, key).and_then(|key| key.as_str());
+                    if let Some(child) = name.and_then(|name| node.children.get(name.as_ref())) {
+                        self.visit(child, js, tokens, key + 1, out);
+                    }
+                }
+            }
+            JsmnType::JsmnArray => {
+                for (i, element) in children(tokens, idx).enumerate() {
+                    if let Some(child) = node.children.get(&i.to_string()) {
+                        self.visit(child, js, tokens, element, out);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::value::ValueErrKind;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Row {
+        id: Option<i64>,
+        name: Option<String>,
+        score: Option<f64>,
+        first_tag: Option<String>,
+        active: Option<bool>,
+    }
+
+    fn extractor() -> Extractor<Row> {
+        let mut extractor = Extractor::new();
+        extractor
+            .i64("/id", |row: &mut Row, v| row.id = Some(v))
+            .unwrap()
+            .str("/user/name", |row, v| row.name = Some(v))
+            .unwrap()
+            .f64("/user/score", |row, v| row.score = Some(v))
+            .unwrap()
+            .str("/tags/0", |row, v| row.first_tag = Some(v))
+            .unwrap()
+            .bool("/active", |row, v| row.active = Some(v))
+            .unwrap();
+        extractor
+    }
+
+    #[test]
+    fn rows_from_documents() {
+        let extractor = extractor();
+        let docs = [
+            r#"{"id": 1, "user": {"name": "aé", "score": 2.5}, "tags": ["x", "y"]}"#,
+            r#"{"id": null, "active": false, "extra": {"id": 7}}"#,
+        ];
+        let rows: Vec<_> = docs
+            .iter()
+            .map(|js| extractor.extract(js).unwrap())
+            .collect();
+
+        assert_eq!(
+            rows[0],
+            Extracted {
+                row: Row {
+                    id: Some(1),
+                    name: Some("aé".into()),
+                    score: Some(2.5),
+                    first_tag: Some("x".into()),
+                    active: None,
+                },
+                errors: vec![],
+            }
+        );
+        assert_eq!(
+            rows[1].row,
+            Row {
+                active: Some(false),
+                ..Row::default()
+            }
+        );
+    }
+
+    #[test]
+    fn errors_do_not_stop_the_row() {
+        let js = r#"{"id": 1.5, "user": {"name": 3, "score": 1}}"#;
+        let extracted = extractor().extract(js).unwrap();
+        assert_eq!(extracted.row.score, Some(1.0));
+
+        let errors: Vec<_> = extracted
+            .errors
+            .iter()
+            .map(|e| e.pointer.as_str())
+            .collect();
+        assert_eq!(errors, ["/id", "/user/name"]);
+        assert_eq!(
+            extracted.errors[1].kind,
+            ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+        );
+        assert!(Extractor::<Row>::new().i64("id", |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn exact_numbers() {
+        #[derive(Debug, Default)]
+        struct Payment {
+            amount: Option<String>,
+            #[cfg(feature = "decimal")]
+            fee: Option<rust_decimal::Decimal>,
+        }
+
+        let mut extractor = Extractor::new();
+        extractor
+            .number("/amount", |row: &mut Payment, v| row.amount = Some(v))
+            .unwrap();
+        #[cfg(feature = "decimal")]
+        extractor
+            .decimal("/fee", |row, v| row.fee = Some(v))
+            .unwrap();
+
+        let js = r#"{"amount": 12345678901234567.89, "fee": 0.10}"#;
+        let extracted = extractor.extract(js).unwrap();
+        assert_eq!(
+            extracted.row.amount.as_deref(),
+            Some("12345678901234567.89")
+        );
+        #[cfg(feature = "decimal")]
+        assert_eq!(
+            extracted.row.fee,
+            rust_decimal::Decimal::from_str_exact("0.10").ok()
+        );
+
+        let extracted = extractor.extract(r#"{"amount": "12"}"#).unwrap();
+        assert_eq!(extracted.errors[0].pointer, "/amount");
+    }
+}
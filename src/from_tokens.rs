@@ -0,0 +1,301 @@
+//!
+//! This module maps parsed documents onto Rust types through the
+//! `FromTokens` trait, for code that wants its own structs out of a
+//! document without pulling in serde. The trait is implemented for the
+//! primitive types, `String`, `Option`, `Vec` and tuples, and structs
+//! implement it by hand, reading their members with `field`.
+//!
+//! `null` and missing members read as None into an `Option`, and are an
+//! error for every other type. Integers must not have a fraction or an
+//! exponent and must fit in the target type, and with the "decimal" feature
+//! a `Decimal` is read exactly from the text of a number. Tuples are read
+//! from arrays with exactly as many elements. Errors carry the JSON Pointer
+//! of the value they are about.
+//!
+//! With the "derive" feature, `#[derive(FromTokens)]` from the jsmn-derive
+//! crate writes the implementations for structs.
+//!
+
+use crate::value::{Value, ValueErr, ValueErrKind};
+
+use crate::{JsmnTok, JsmnType};
+#[cfg(feature = "derive")]
+pub use jsmn_derive::FromTokens;
+
+/// A type that can be read from the value at one token of a document.
+pub trait FromTokens: Sized {
+    /// Reads the value at token `idx`.
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr>;
+
+    /// The value of a missing object member, if it is not an error.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Reads the first root of a parsed document.
+pub fn from_root<T: FromTokens>(js: &str, tokens: &[JsmnTok]) -> Result<T, ValueErr> {
+    if tokens.is_empty() {
+        return Err(ValueErr {
+            pointer: String::new(),
+            kind: ValueErrKind::Invalid("empty document".into()),
+        });
+    }
+    T::from_tokens(js, tokens, 0)
+}
+
+/// Reads the member `key` of the object at token `idx`, for use in
+/// implementations of `FromTokens`. When the key appears several times the
+/// last member is read.
+pub fn field<T: FromTokens>(
+    js: &str,
+    tokens: &[JsmnTok],
+    idx: usize,
+    key: &str,
+) -> Result<T, ValueErr> {
+    let object = cursor(js, tokens, idx)?;
+    if object.typ() != JsmnType::JsmnObject {
+        return Err(object.wrong_type());
+    }
+    match object.get(key) {
+        Some(value) => T::from_tokens(js, tokens, value.index()),
+        None => T::missing()
+            .ok_or_else(|| object.error(ValueErrKind::Invalid(format!("missing field {:?}", key)))),
+    }
+}
+
+fn cursor<'a>(js: &'a str, tokens: &'a [JsmnTok], idx: usize) -> Result<Value<'a>, ValueErr> {
+    Value::new(js, tokens, idx).ok_or_else(|| ValueErr {
+        pointer: String::new(),
+        kind: ValueErrKind::Invalid(format!("no token {}", idx)),
+    })
+}
+
+impl FromTokens for bool {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        cursor(js, tokens, idx)?.read_bool()
+    }
+}
+
+impl FromTokens for String {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        Ok(cursor(js, tokens, idx)?.read_str()?.into_owned())
+    }
+}
+
+impl FromTokens for char {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        let value = cursor(js, tokens, idx)?;
+        let text = value.read_str()?;
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(value.error(ValueErrKind::Invalid("not a single character".into()))),
+        }
+    }
+}
+
+macro_rules! from_tokens_int {
+    ($($t:ty),*) => {$(
+        impl FromTokens for $t {
+            fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+                let value = cursor(js, tokens, idx)?;
+                value.number_text().ok_or_else(|| value.wrong_type())?;
+                let text = value.integer_text().ok_or_else(|| {
+                    value.error(ValueErrKind::Invalid("not an integer".into()))
+                })?;
+                text.parse().map_err(|_| {
+                    value.error(ValueErrKind::Invalid(
+                        concat!("out of range for ", stringify!($t)).into(),
+                    ))
+                })
+            }
+        }
+    )*};
+}
+
+from_tokens_int!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+impl FromTokens for f64 {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        cursor(js, tokens, idx)?.read_f64()
+    }
+}
+
+impl FromTokens for f32 {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        let value = cursor(js, tokens, idx)?;
+        Some(value.read_f64()? as f32)
+            .filter(|f| f.is_finite())
+            .ok_or_else(|| value.error(ValueErrKind::Invalid("out of range".into())))
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl FromTokens for rust_decimal::Decimal {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        cursor(js, tokens, idx)?.read_decimal()
+    }
+}
+
+impl<T: FromTokens> FromTokens for Option<T> {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        if cursor(js, tokens, idx)?.is_null() {
+            Ok(None)
+        } else {
+            T::from_tokens(js, tokens, idx).map(Some)
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: FromTokens> FromTokens for Vec<T> {
+    fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+        let array = cursor(js, tokens, idx)?;
+        if array.typ() != JsmnType::JsmnArray {
+            return Err(array.wrong_type());
+        }
+        array
+            .elements()
+            .map(|element| T::from_tokens(js, tokens, element.index()))
+            .collect()
+    }
+}
+
+macro_rules! from_tokens_tuple {
+    ($len:literal: $($t:ident),*) => {
+        impl<$($t: FromTokens),*> FromTokens for ($($t,)*) {
+            fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+                let array = cursor(js, tokens, idx)?;
+                if array.typ() != JsmnType::JsmnArray {
+                    return Err(array.wrong_type());
+                }
+                if array.token().size != $len {
+                    return Err(array.error(ValueErrKind::Invalid(
+                        concat!("expected ", $len, " elements").into(),
+                    )));
+                }
+                let mut elements = array.elements();
+                Ok(($($t::from_tokens(js, tokens, elements.next().unwrap().index())?,)*))
+            }
+        }
+    };
+}
+
+from_tokens_tuple!(1: A);
+from_tokens_tuple!(2: A, B);
+from_tokens_tuple!(3: A, B, C);
+from_tokens_tuple!(4: A, B, C, D);
+from_tokens_tuple!(5: A, B, C, D, E);
+from_tokens_tuple!(6: A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_vec;
+
+    #[derive(Debug, PartialEq)]
+    struct Item {
+        sku: String,
+        qty: u32,
+        tags: Vec<String>,
+        note: Option<String>,
+        at: (f64, f64),
+    }
+
+    impl FromTokens for Item {
+        fn from_tokens(js: &str, tokens: &[JsmnTok], idx: usize) -> Result<Self, ValueErr> {
+            Ok(Item {
+                sku: field(js, tokens, idx, "sku")?,
+                qty: field(js, tokens, idx, "qty")?,
+                tags: field(js, tokens, idx, "tags")?,
+                note: field(js, tokens, idx, "note")?,
+                at: field(js, tokens, idx, "at")?,
+            })
+        }
+    }
+
+    fn read<T: FromTokens>(js: &str) -> Result<T, ValueErr> {
+        from_root(js, &parse_to_vec(js).unwrap())
+    }
+
+    #[test]
+    fn primitives_and_containers() {
+        let js = r#"[true, -128, 18446744073709551615, 2.5e1, "é", "a\nb"]"#;
+        assert_eq!(
+            read::<(bool, i8, u64, f32, char, String)>(js),
+            Ok((true, -128, u64::MAX, 25.0, 'é', "a\nb".to_string()))
+        );
+        assert_eq!(
+            read::<Vec<Option<i32>>>("[1, null, 3]"),
+            Ok(vec![Some(1), None, Some(3)])
+        );
+        assert_eq!(
+            read::<(u8, String, bool)>(r#"[7, "x", false]"#),
+            Ok((7, "x".to_string(), false))
+        );
+        assert_eq!(read::<Vec<Option<Vec<u8>>>>("[null]"), Ok(vec![None]));
+        #[cfg(feature = "decimal")]
+        assert_eq!(
+            read::<Vec<rust_decimal::Decimal>>("[19.99]"),
+            Ok(vec![
+                rust_decimal::Decimal::from_str_exact("19.99").unwrap()
+            ])
+        );
+    }
+
+    #[test]
+    fn hand_written_structs() {
+        let js = r#"[{"sku": "A-1", "qty": 2, "tags": ["x"], "at": [1.5, -2]},
+                     {"sku": "B-2", "qty": 1, "tags": [], "note": "gift", "at": [0, 0]}]"#;
+        let items: Vec<Item> = read(js).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].sku, "A-1");
+        assert_eq!(items[0].at, (1.5, -2.0));
+        assert_eq!(items[0].note, None);
+        assert_eq!(
+            items[1],
+            Item {
+                sku: "B-2".into(),
+                qty: 1,
+                tags: vec![],
+                note: Some("gift".into()),
+                at: (0.0, 0.0),
+            }
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let err = |js| read::<Vec<Item>>(js).unwrap_err();
+        assert_eq!(
+            err(r#"[{"sku": "A", "qty": -1, "tags": [], "at": [0, 0]}]"#),
+            ValueErr {
+                pointer: "/0/qty".into(),
+                kind: ValueErrKind::Invalid("out of range for u32".into()),
+            }
+        );
+        assert_eq!(
+            err(r#"[{"sku": "A", "tags": [], "at": [0, 0]}]"#).kind,
+            ValueErrKind::Invalid(r#"missing field "qty""#.into())
+        );
+        assert_eq!(
+            err(r#"[{"sku": "A", "qty": 1, "tags": [], "at": [0]}]"#).pointer,
+            "/0/at"
+        );
+        assert_eq!(
+            err(r#"[{"sku": "A", "qty": 1.5, "tags": [], "at": [0, 0]}]"#).pointer,
+            "/0/qty"
+        );
+        assert_eq!(read::<u8>("").unwrap_err().pointer, "");
+        assert_eq!(
+            read::<Vec<String>>("[1]").unwrap_err().kind,
+            ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//!
+//! A small implementation of the 64 bit xxHash algorithm (XXH64), used to
+//! fingerprint records as they are read. It is fast, has good distribution,
+//! and its output is stable across platforms and releases, unlike the std
+//! `DefaultHasher`, so hashes can be stored and compared later on.
+//!
+
+use std::hash::Hasher;
+
+const P1: u64 = 0x9E37_79B1_85EB_CA87;
+const P2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const P3: u64 = 0x1656_67B1_9E37_79F9;
+const P4: u64 = 0x85EB_CA77_C2B2_AE63;
+const P5: u64 = 0x27D4_EB2F_1656_67C5;
+
+/// Streaming XXH64 state. Bytes can be fed in pieces of any size.
+#[derive(Debug, Clone)]
+pub(crate) struct Xxh64 {
+    seed: u64,
+    acc: [u64; 4],
+    buf: [u8; 32],
+    buffered: usize,
+    total: u64,
+}
+
+impl Xxh64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Xxh64 {
+            seed,
+            acc: [
+                seed.wrapping_add(P1).wrapping_add(P2),
+                seed.wrapping_add(P2),
+                seed,
+                seed.wrapping_sub(P1),
+            ],
+            buf: [0; 32],
+            buffered: 0,
+            total: 0,
+        }
+    }
+
+    fn consume_stripe(&mut self, stripe: &[u8]) {
+        for (acc, lane) in self.acc.iter_mut().zip(stripe.chunks_exact(8)) {
+            *acc = round(*acc, read_u64(lane));
+        }
+    }
+}
+
+impl Default for Xxh64 {
+    fn default() -> Self {
+        Xxh64::new(0)
+    }
+}
+
+impl Hasher for Xxh64 {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.total += bytes.len() as u64;
+
+        if self.buffered > 0 {
+            let take = bytes.len().min(32 - self.buffered);
+            self.buf[self.buffered..self.buffered + take].copy_from_slice(&bytes[..take]);
+            self.buffered += take;
+            bytes = &bytes[take..];
+            if self.buffered < 32 {
+                return;
+            }
+            let stripe = self.buf;
+            self.consume_stripe(&stripe);
+            self.buffered = 0;
+        }
+
+        let mut stripes = bytes.chunks_exact(32);
+        for stripe in &mut stripes {
+            self.consume_stripe(stripe);
+        }
+        let rest = stripes.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut h = if self.total >= 32 {
+            let [v1, v2, v3, v4] = self.acc;
+            let mut h = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            for v in self.acc {
+                h = (h ^ round(0, v)).wrapping_mul(P1).wrapping_add(P4);
+            }
+            h
+        } else {
+            self.seed.wrapping_add(P5)
+        };
+        h = h.wrapping_add(self.total);
+
+        let mut rest = &self.buf[..self.buffered];
+        while rest.len() >= 8 {
+            h ^= round(0, read_u64(rest));
+            h = h.rotate_left(27).wrapping_mul(P1).wrapping_add(P4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let word = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+            h ^= word.wrapping_mul(P1);
+            h = h.rotate_left(23).wrapping_mul(P2).wrapping_add(P3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            h ^= (byte as u64).wrapping_mul(P5);
+            h = h.rotate_left(11).wrapping_mul(P1);
+        }
+
+        h ^= h >> 33;
+        h = h.wrapping_mul(P2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(P3);
+        h ^ (h >> 32)
+    }
+}
+
+fn round(acc: u64, lane: u64) -> u64 {
+    acc.wrapping_add(lane.wrapping_mul(P2))
+        .rotate_left(31)
+        .wrapping_mul(P1)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+/// Hashes `bytes` in one go.
+pub(crate) fn xxh64(bytes: &[u8], seed: u64) -> u64 {
+    let mut hasher = Xxh64::new(seed);
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        assert_eq!(xxh64(b"", 0), 0xEF46_DB37_51D8_E999);
+        assert_eq!(xxh64(b"abc", 0), 0x44BC_2CF5_AD77_0999);
+        assert_eq!(
+            xxh64(b"Nobody inspects the spammish repetition", 0),
+            0xFBCE_A83C_8A37_8BF1
+        );
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let data: Vec<u8> = (0..200u32).map(|i| (i * 7 + 3) as u8).collect();
+        for split in [0, 1, 5, 31, 32, 33, 64, 150, 200] {
+            let mut hasher = Xxh64::new(42);
+            hasher.write(&data[..split]);
+            for chunk in data[split..].chunks(3) {
+                hasher.write(chunk);
+            }
+            assert_eq!(hasher.finish(), xxh64(&data, 42));
+        }
+        assert_ne!(xxh64(&data, 0), xxh64(&data, 1));
+    }
+}
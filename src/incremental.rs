@@ -0,0 +1,345 @@
+//!
+//! This module parses documents that arrive in pieces, such as a response
+//! read from a socket. jsmn can resume a parse that ran out of input: when
+//! it returns `JsmErrorPart`, calling it again with the same parser, the
+//! same tokens and a longer buffer picks up where it stopped. The
+//! `StreamingParser` keeps all three around so that each new piece of input
+//! is only scanned once.
+//!
+//! Without the strict feature, jsmn ends a primitive at the end of the input
+//! as if a delimiter followed, so `12` fed before `34` would become two
+//! numbers. A primitive ending right at the end of an incomplete document is
+//! therefore taken back and parsed again with the next piece. At the top
+//! level there is no telling, and a document made of a lone primitive is
+//! complete as soon as it has been fed.
+//!
+//! `read_from` runs the same loop against an `io::Read`, such as a file or a
+//! socket, reading until the buffer holds complete documents. As it knows
+//! where the input ends, it keeps reading after a trailing top-level
+//! primitive instead. With the "futures" feature, `read_from_async` does the
+//! same with a `futures_io::AsyncRead`.
+//!
+
+use std::io::{self, Read};
+
+use crate::stream::StreamError;
+use crate::{JsmnErr, JsmnParser, JsmnTok, JsmnType, jsmn_parse_vec_bytes};
+
+/// A parser fed its input a piece at a time.
+#[derive(Debug, Clone, Default)]
+pub struct StreamingParser {
+    parser: JsmnParser,
+    buf: Vec<u8>,
+    tokens: Vec<JsmnTok>,
+    complete: bool,
+}
+
+impl StreamingParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `bytes` to the input and parses as far as it goes. Returns
+    /// true once the document is complete, and false while objects, arrays
+    /// or strings are still open. Pieces may split UTF-8 sequences, since
+    /// jsmn only looks at ASCII bytes.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<bool, JsmnErr> {
+        self.buf.extend_from_slice(bytes);
+        match jsmn_parse_vec_bytes(&mut self.parser, &self.buf, &mut self.tokens) {
+            Ok(_) => self.complete = true,
+            Err(JsmnErr::JsmErrorPart) => {
+                self.take_back_primitive();
+                self.complete = false;
+            }
+            Err(err) => return Err(err),
+        }
+        Ok(self.complete)
+    }
+
+    /// Undoes the last token if it is a primitive that the end of the input
+    /// may have cut short.
+    fn take_back_primitive(&mut self) {
+        if !self.ends_in_primitive() {
+            return;
+        }
+        let Some(last) = self.tokens.pop() else {
+            return;
+        };
+        self.parser.pos = last.start as u32;
+        self.parser.toknext -= 1;
+        if let Ok(parent) = usize::try_from(self.parser.toksuper) {
+            self.tokens[parent].size -= 1;
+        }
+    }
+
+    /// Feeds the bytes a read loop just read, or handles the end of the
+    /// input for None, and returns true when the loop should stop.
+    fn read_step(&mut self, read: Option<&[u8]>) -> Result<bool, StreamError> {
+        let Some(bytes) = read else {
+            if self.complete {
+                return Ok(true);
+            }
+            return Err(self.error(JsmnErr::JsmErrorPart));
+        };
+        self.take_back_primitive();
+        let complete = self.feed(bytes).map_err(|err| self.error(err))?;
+        // Input that is empty so far, or ends in a number, might go on.
+        Ok(complete && !self.tokens.is_empty() && !self.ends_in_primitive())
+    }
+
+    fn error(&self, err: JsmnErr) -> StreamError {
+        StreamError::Parse {
+            err,
+            pos: self.parser.pos as usize,
+        }
+    }
+
+    fn ends_in_primitive(&self) -> bool {
+        self.tokens.last().is_some_and(|last| {
+            last.typ == JsmnType::JsmnPrimitive && last.end as usize == self.buf.len()
+        })
+    }
+
+    /// Returns true if the input fed so far holds a complete document.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The input fed so far.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// The tokens parsed so far. Objects and arrays that are still open
+    /// have an `end` of -1.
+    pub fn tokens(&self) -> &[JsmnTok] {
+        &self.tokens
+    }
+
+    /// Returns the input and its tokens.
+    pub fn into_parts(self) -> (Vec<u8>, Vec<JsmnTok>) {
+        (self.buf, self.tokens)
+    }
+}
+
+/// Reads from `reader` into `buf`, after what it already holds, until the
+/// buffer holds one or more complete documents, and parses them into
+/// `tokens`. Returns the number of tokens, which is 0 if the reader holds
+/// nothing but whitespace.
+///
+/// Every byte read is parsed, so the reader should hold whole documents, as
+/// a file or a request body does. On a connection carrying one document
+/// after another, reading stops at the first read ending between two
+/// documents, with all those before it in the buffer.
+pub fn read_from<R: Read>(
+    mut reader: R,
+    buf: &mut Vec<u8>,
+    tokens: &mut Vec<JsmnTok>,
+) -> Result<usize, StreamError> {
+    let mut stream = StreamingParser::new();
+    let mut done = stream.read_step(Some(&std::mem::take(buf)))?;
+    let mut chunk = [0; 8192];
+    while !done {
+        let len = match reader.read(&mut chunk) {
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        done = stream.read_step((len > 0).then(|| &chunk[..len]))?;
+    }
+    (*buf, *tokens) = stream.into_parts();
+    Ok(tokens.len())
+}
+
+/// Like [`read_from`], but reading from an asynchronous reader, so that a
+/// task waiting for the rest of a document does not block its thread.
+#[cfg(feature = "futures")]
+pub async fn read_from_async<R: futures_io::AsyncRead + Unpin>(
+    mut reader: R,
+    buf: &mut Vec<u8>,
+    tokens: &mut Vec<JsmnTok>,
+) -> Result<usize, StreamError> {
+    use std::pin::Pin;
+
+    let mut stream = StreamingParser::new();
+    let mut done = stream.read_step(Some(&std::mem::take(buf)))?;
+    let mut chunk = [0; 8192];
+    while !done {
+        let read = std::future::poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut chunk));
+        let len = match read.await {
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        done = stream.read_step((len > 0).then(|| &chunk[..len]))?;
+    }
+    (*buf, *tokens) = stream.into_parts();
+    Ok(tokens.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    #[test]
+    fn byte_by_byte() {
+        let js = r#"{"name": "café é", "n": [12, -3.5e2, true, null], "o": {}}"#;
+        let expected = parse_to_vec(js).unwrap();
+
+        let mut parser = StreamingParser::new();
+        let bytes = js.as_bytes();
+        for (i, byte) in bytes.iter().enumerate() {
+            let complete = parser.feed(std::slice::from_ref(byte)).unwrap();
+            assert_eq!(complete, i + 1 == bytes.len(), "after {} bytes", i + 1);
+        }
+        assert_eq!(parser.tokens(), &expected[..]);
+        assert_eq!(parser.into_parts(), (bytes.to_vec(), expected));
+    }
+
+    #[test]
+    fn pieces_and_errors() {
+        let mut parser = StreamingParser::new();
+        assert_eq!(parser.feed(b"[1"), Ok(false));
+        assert!(parser.tokens()[0].end == -1 && parser.tokens()[0].size == 0);
+        assert_eq!(parser.feed(b"23, \"a"), Ok(false));
+        assert_eq!(parser.tokens().len(), 2);
+        assert_eq!(parser.feed(b"b\"]"), Ok(true));
+        assert!(parser.is_complete());
+        assert_eq!(parser.tokens()[0].size, 2);
+        assert_eq!(parser.tokens()[1].end, 4);
+
+        let mut parser = StreamingParser::new();
+        assert_eq!(parser.feed(b"[1, "), Ok(false));
+        assert_eq!(parser.feed(b"}"), Err(JsmnErr::JsmErrorInval));
+    }
+
+    /// A reader returning at most `step` bytes at a time.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let len = self.step.min(self.data.len()).min(out.len());
+            out[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn reading_in_pieces() {
+        let read = |data: &[u8], step| {
+            let (mut buf, mut tokens) = (Vec::new(), Vec::new());
+            let reader = Trickle { data, step };
+            read_from(reader, &mut buf, &mut tokens).map(|count| (buf, tokens, count))
+        };
+
+        let js = r#"{"a": [1, 22, "x y"], "b": {"c": 333}}"#;
+        let (buf, tokens, count) = read(js.as_bytes(), 3).unwrap();
+        assert_eq!(
+            (buf, tokens),
+            (js.as_bytes().to_vec(), parse_to_vec(js).unwrap())
+        );
+        assert_eq!(count, 10);
+
+        let (buf, tokens, _) = read(b"12345\n", 2).unwrap();
+        assert_eq!(buf, b"12345\n");
+        assert_eq!((tokens.len(), tokens[0].end), (1, 5));
+        // Strict jsmn wants a delimiter after a primitive, even at the end.
+        #[cfg(not(feature = "strict"))]
+        assert_eq!(read(b"678", 2).unwrap().1[0].end, 3);
+        assert_eq!(read(b" \n", 1).unwrap().2, 0);
+
+        match read(br#"{"a": [1, "#, 4) {
+            Err(StreamError::Parse { err, pos }) => {
+                assert_eq!((err, pos), (JsmnErr::JsmErrorPart, 10))
+            }
+            other => panic!("unexpected {:?}", other.map(|(_, tokens, _)| tokens)),
+        }
+        assert!(matches!(
+            read(b"[1, }", 8),
+            Err(StreamError::Parse {
+                err: JsmnErr::JsmErrorInval,
+                pos: 4
+            })
+        ));
+
+        let mut buf = b"[tr".to_vec();
+        let mut tokens = Vec::new();
+        let reader = Trickle {
+            data: b"ue]",
+            step: 1,
+        };
+        assert_eq!(read_from(reader, &mut buf, &mut tokens).unwrap(), 2);
+        assert_eq!((tokens[1].start, tokens[1].end), (1, 5));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn reading_asynchronously() {
+        use std::pin::{Pin, pin};
+        use std::task::{Context, Poll, Waker};
+
+        /// Gives `step` bytes every other poll, and is pending in between.
+        struct Slow<'a> {
+            data: &'a [u8],
+            step: usize,
+            ready: bool,
+        }
+
+        impl futures_io::AsyncRead for Slow<'_> {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                out: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                self.ready = !self.ready;
+                if !self.ready {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                let step = self.step;
+                Poll::Ready(
+                    Trickle {
+                        data: self.data,
+                        step,
+                    }
+                    .read(out)
+                    .inspect(|&len| {
+                        self.data = &self.data[len..];
+                    }),
+                )
+            }
+        }
+
+        let js = br#"[{"a": 1}, "b", 23]"#;
+        let (mut buf, mut tokens) = (Vec::new(), Vec::new());
+        let reader = Slow {
+            data: js,
+            step: 4,
+            ready: false,
+        };
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut polls = 0;
+        let count = {
+            let mut read = pin!(read_from_async(reader, &mut buf, &mut tokens));
+            loop {
+                polls += 1;
+                if let Poll::Ready(result) = read.as_mut().poll(&mut cx) {
+                    break result.unwrap();
+                }
+            }
+        };
+        assert_eq!(count, 6);
+        assert!(polls > 1);
+        assert_eq!(buf, js);
+        assert_eq!(
+            tokens,
+            parse_to_vec(std::str::from_utf8(js).unwrap()).unwrap()
+        );
+    }
+}
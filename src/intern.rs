@@ -0,0 +1,194 @@
+//!
+//! This module gives object keys small integer ids, for workloads reading
+//! many objects with the same keys, such as arrays of telemetry records.
+//! A `KeyTable` interns every distinct key once, and annotating a parsed
+//! document with it gives each key token the id of its key, so that code
+//! matching members compares integers instead of bytes, and never decodes
+//! a key twice.
+//!
+//! The table outlives the documents: the ids of the keys a program looks
+//! for can be taken once, up front, and stay valid for every document
+//! annotated afterwards. Keys are interned decoded, so `"\u0069d"` and
+//! `"id"` get the same id. For input that is not trusted, where every new
+//! key would grow the table, `KeyTable::annotate_known` only looks keys up
+//! and leaves the others without an id.
+//!
+
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use crate::decode::unescape;
+use crate::tree::children;
+use crate::{JsmnTok, JsmnType};
+
+/// The id of an interned key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyId(NonZeroU32);
+
+impl KeyId {
+    /// The position of the key in its table, from 0 in the order the keys
+    /// were interned.
+    pub fn index(self) -> usize {
+        self.0.get() as usize - 1
+    }
+}
+
+/// A table of interned keys.
+#[derive(Debug, Clone, Default)]
+pub struct KeyTable {
+    names: Vec<Box<str>>,
+    ids: HashMap<Box<str>, KeyId>,
+}
+
+impl KeyTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns the id of `key`, interning it if needed.
+    pub fn intern(&mut self, key: &str) -> KeyId {
+        if let Some(&id) = self.ids.get(key) {
+            return id;
+        }
+        let id = u32::try_from(self.names.len() + 1)
+            .ok()
+            .and_then(NonZeroU32::new)
+            .expect("too many keys");
+        let id = KeyId(id);
+        self.names.push(key.into());
+        self.ids.insert(key.into(), id);
+        id
+    }
+
+    /// Returns the id of `key` if it was interned.
+    pub fn id(&self, key: &str) -> Option<KeyId> {
+        self.ids.get(key).copied()
+    }
+
+    /// Returns the key with the id `id`.
+    pub fn name(&self, id: KeyId) -> Option<&str> {
+        self.names.get(id.index()).map(|name| &**name)
+    }
+
+    /// Gives every key of a parsed document its id, interning the keys not
+    /// seen before.
+    pub fn annotate(&mut self, js: &str, tokens: &[JsmnTok]) -> KeyIds {
+        KeyIds::build(js, tokens, |key| Some(self.intern(key)))
+    }
+
+    /// Gives the keys of a parsed document that are already in the table
+    /// their id, and leaves the others without one.
+    pub fn annotate_known(&self, js: &str, tokens: &[JsmnTok]) -> KeyIds {
+        KeyIds::build(js, tokens, |key| self.id(key))
+    }
+}
+
+/// The key ids of the tokens of one document, from `KeyTable::annotate`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyIds {
+    /// One entry per token, set for keys.
+    ids: Vec<Option<KeyId>>,
+}
+
+impl KeyIds {
+    fn build(js: &str, tokens: &[JsmnTok], mut id: impl FnMut(&str) -> Option<KeyId>) -> Self {
+        let ids = tokens
+            .iter()
+            .map(|tok| {
+                if tok.typ != JsmnType::JsmnString || tok.size <= 0 {
+                    return None;
+                }
+                id(&unescape(tok.try_as_str(js)?))
+            })
+            .collect();
+        KeyIds { ids }
+    }
+
+    /// Returns the id of the key at token `idx`, or None if it is not a key
+    /// or has no id.
+    pub fn get(&self, idx: usize) -> Option<KeyId> {
+        self.ids.get(idx).copied().flatten()
+    }
+
+    /// Returns the index of the value of the member with the key `id` in
+    /// the object at token `object`. When the key appears several times
+    /// the last member wins.
+    pub fn find(&self, tokens: &[JsmnTok], object: usize, id: KeyId) -> Option<usize> {
+        if tokens.get(object)?.typ != JsmnType::JsmnObject {
+            return None;
+        }
+        let key = children(tokens, object)
+            .filter(|&key| self.get(key) == Some(id))
+            .last()?;
+        (key + 1 < tokens.len()).then_some(key + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_vec;
+    use crate::value::Value;
+
+    #[test]
+    fn intern_keys() {
+        let mut table = KeyTable::new();
+        let ts = table.intern("ts");
+        assert_eq!(table.intern("ts"), ts);
+        let value = table.intern("value");
+        assert_ne!(ts, value);
+        assert_eq!((ts.index(), value.index()), (0, 1));
+        assert_eq!(table.id("value"), Some(value));
+        assert_eq!(table.id("nope"), None);
+        assert_eq!(table.name(value), Some("value"));
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn annotate_documents() {
+        let mut table = KeyTable::new();
+        let ts = table.intern("ts");
+        let value = table.intern("value");
+
+        let js = r#"[{"ts": 1, "value": 2.5, "tags": {"ts": 0}},
+                     {"ts": 2, "value": 3, "value": 4}]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let ids = table.annotate(js, &tokens);
+        let root = Value::new(js, &tokens, 0).unwrap();
+        let records: Vec<_> = (0..2).map(|i| root.at(i).unwrap().index()).collect();
+
+        let read = |object, id| {
+            let idx = ids.find(&tokens, object, id)?;
+            Value::new(js, &tokens, idx)?.as_f64_exact()
+        };
+        assert_eq!(read(records[0], ts), Some(1.0));
+        assert_eq!(read(records[0], value), Some(2.5));
+        assert_eq!(read(records[1], ts), Some(2.0));
+        assert_eq!(read(records[1], value), Some(4.0));
+        assert_eq!(ids.find(&tokens, 0, ts), None);
+
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.id("tags").and_then(|id| table.name(id)), Some("tags"));
+        assert_eq!(ids.get(0), None);
+        assert_eq!(ids.get(3), None);
+    }
+
+    #[test]
+    fn annotate_known_keys_only() {
+        let mut table = KeyTable::new();
+        let ts = table.intern("ts");
+        let js = r#"{"\u0074s": 1, "junk": 2}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let ids = table.annotate_known(js, &tokens);
+        assert_eq!((ids.get(1), ids.get(3)), (Some(ts), None));
+        assert_eq!(table.len(), 1);
+    }
+}
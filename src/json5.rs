@@ -0,0 +1,460 @@
+//!
+//! This module tokenizes JSON5, the superset of JSON meant for files that
+//! people write by hand. On top of JSON it accepts:
+//!
+//! - keys written as identifiers, like `{name: 1}`,
+//! - strings in single quotes, and escapes JSON lacks, like `\x41`, `\'`
+//!   or a backslash before a line break,
+//! - hexadecimal numbers, numbers with a leading `+` or a leading or
+//!   trailing decimal point, `Infinity` and `NaN`,
+//! - `//` and `/* */` comments, and a trailing comma in objects and arrays.
+//!
+//! jsmn knows none of this, so the tokenizer is written in Rust, but it
+//! fills in the same tokens as jsmn would for the equivalent JSON: strings
+//! span the text between their quotes, keys are strings whose size is 1,
+//! and everything else is a primitive spanning its text. Identifier keys
+//! are strings spanning the identifier. Unlike jsmn, it checks the whole
+//! grammar, so `[1 2]` or `{a}` are rejected like they are in strict mode.
+//!
+//! The tokens can be read with the rest of the crate, except for strings
+//! with JSON5 escapes, which need [`unescape`] rather than the decode
+//! module, and numbers only JSON5 has, which the value accessors reject.
+//!
+//! This module is behind the "json5" feature.
+//!
+
+use std::borrow::Cow;
+
+use crate::decode::unicode_escape;
+use crate::{JsmnErr, JsmnTok, JsmnType, ParseError};
+
+/// Tokenizes a JSON5 document into `tokens`, returning the number of tokens
+/// like [`crate::jsmn_parse`]. Several documents may follow one another,
+/// as with jsmn. Errors are reported with the offset where tokenizing
+/// stopped: the start of an invalid or unterminated string, primitive or
+/// comment, the offending character otherwise, or the end of the input
+/// for an unfinished document.
+pub fn parse(js: &str, tokens: &mut [JsmnTok]) -> Result<usize, ParseError> {
+    let mut tokenizer = Tokenizer {
+        js: js.as_bytes(),
+        tokens,
+        next: 0,
+        pos: 0,
+        open: Vec::new(),
+        key: None,
+        expect: Expect::Value,
+        close_ok: false,
+    };
+    tokenizer.run().map_err(|kind| tokenizer.error(kind))?;
+    Ok(tokenizer.next)
+}
+
+/// Like [`parse`], but with a vector of tokens that is grown as needed.
+pub fn parse_vec(js: &str, tokens: &mut Vec<JsmnTok>) -> Result<usize, ParseError> {
+    tokens.clear();
+    tokens.resize(js.len() / 8 + 8, JsmnTok::default());
+    loop {
+        match parse(js, tokens) {
+            Ok(count) => {
+                tokens.truncate(count);
+                return Ok(count);
+            }
+            Err(err) if err.kind == JsmnErr::JsmErrorNoMem => {
+                let len = tokens.len() * 2;
+                tokens.resize(len, JsmnTok::default());
+            }
+            Err(err) => {
+                tokens.truncate(err.tokens);
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// What may come next.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Expect {
+    Value,
+    Key,
+    Colon,
+    /// A comma or the end of the innermost object or array.
+    Next,
+}
+
+struct Tokenizer<'a> {
+    js: &'a [u8],
+    tokens: &'a mut [JsmnTok],
+    next: usize,
+    pos: usize,
+    /// The objects and arrays still open, innermost last.
+    open: Vec<usize>,
+    /// The key whose value comes next.
+    key: Option<usize>,
+    expect: Expect,
+    /// Whether the innermost object or array may end here, as it may
+    /// right after its opening bracket or a comma.
+    close_ok: bool,
+}
+
+impl Tokenizer<'_> {
+    fn run(&mut self) -> Result<(), JsmnErr> {
+        while self.skip_blanks()? {
+            let c = self.js[self.pos];
+            match (c, self.expect) {
+                (b'{' | b'[', Expect::Value) => {
+                    let typ = match c {
+                        b'{' => JsmnType::JsmnObject,
+                        _ => JsmnType::JsmnArray,
+                    };
+                    let idx = self.push(typ, self.pos, -1)?;
+                    self.open.push(idx);
+                    self.pos += 1;
+                    self.expect = match typ {
+                        JsmnType::JsmnObject => Expect::Key,
+                        _ => Expect::Value,
+                    };
+                    self.close_ok = true;
+                }
+                (b'}' | b']', _) if self.expect == Expect::Next || self.close_ok => {
+                    let typ = match c {
+                        b'}' => JsmnType::JsmnObject,
+                        _ => JsmnType::JsmnArray,
+                    };
+                    let Some(&idx) = self.open.last() else {
+                        return Err(JsmnErr::JsmErrorInval);
+                    };
+                    if self.tokens[idx].typ != typ {
+                        return Err(JsmnErr::JsmErrorInval);
+                    }
+                    self.open.pop();
+                    self.pos += 1;
+                    self.tokens[idx].end = self.pos as i32;
+                    self.value_done();
+                }
+                (b',', Expect::Next) if !self.open.is_empty() => {
+                    self.pos += 1;
+                    self.expect = match self.tokens[self.open[self.open.len() - 1]].typ {
+                        JsmnType::JsmnObject => Expect::Key,
+                        _ => Expect::Value,
+                    };
+                    self.close_ok = true;
+                }
+                (b':', Expect::Colon) => {
+                    self.pos += 1;
+                    self.expect = Expect::Value;
+                }
+                (b'"' | b'\'', Expect::Value | Expect::Key) => {
+                    let (start, end) = self.string(c)?;
+                    self.push(JsmnType::JsmnString, start, end as i32)?;
+                    self.pos = end + 1;
+                    self.after_scalar();
+                }
+                (_, Expect::Key) if is_identifier_start(c) => {
+                    let start = self.pos;
+                    let end = self.identifier();
+                    self.push(JsmnType::JsmnString, start, end as i32)?;
+                    self.pos = end;
+                    self.after_scalar();
+                }
+                (_, Expect::Value) => {
+                    let start = self.pos;
+                    let end = start
+                        + self.js[start..]
+                            .iter()
+                            .position(|&c| is_delimiter(c))
+                            .unwrap_or(self.js.len() - start);
+                    if !is_primitive(&self.js[start..end]) {
+                        return Err(JsmnErr::JsmErrorInval);
+                    }
+                    self.push(JsmnType::JsmnPrimitive, start, end as i32)?;
+                    self.pos = end;
+                    self.value_done();
+                }
+                _ => return Err(JsmnErr::JsmErrorInval),
+            }
+        }
+        if !self.open.is_empty() || self.key.is_some() {
+            return Err(JsmnErr::JsmErrorPart);
+        }
+        Ok(())
+    }
+
+    /// Skips whitespace and comments, and returns false at the end of the
+    /// input.
+    fn skip_blanks(&mut self) -> Result<bool, JsmnErr> {
+        loop {
+            match self.js.get(self.pos..).unwrap_or_default() {
+                [] => return Ok(false),
+                [b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c', ..] => self.pos += 1,
+                // No-break space, line and paragraph separators, and BOM.
+                [0xc2, 0xa0, ..] => self.pos += 2,
+                [0xe2, 0x80, 0xa8 | 0xa9, ..] | [0xef, 0xbb, 0xbf, ..] => self.pos += 3,
+                [b'/', b'/', rest @ ..] => {
+                    let len = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+                    self.pos += 2 + len;
+                }
+                [b'/', b'*', rest @ ..] => {
+                    let Some(len) = rest.windows(2).position(|w| w == b"*/") else {
+                        return Err(JsmnErr::JsmErrorPart);
+                    };
+                    self.pos += 2 + len + 2;
+                }
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    /// Finds the end of the string starting with the quote at `pos`, and
+    /// returns the span between its quotes.
+    fn string(&mut self, quote: u8) -> Result<(usize, usize), JsmnErr> {
+        let start = self.pos + 1;
+        let mut pos = start;
+        loop {
+            match self.js.get(pos..).unwrap_or_default() {
+                [] => return Err(JsmnErr::JsmErrorPart),
+                [c, ..] if *c == quote => return Ok((start, pos)),
+                [b'\n' | b'\r', ..] => return Err(JsmnErr::JsmErrorInval),
+                [b'\\', b'u', rest @ ..] => match rest.get(..4) {
+                    Some(hex) if hex.iter().all(u8::is_ascii_hexdigit) => pos += 6,
+                    Some(_) => return Err(JsmnErr::JsmErrorInval),
+                    None => return Err(JsmnErr::JsmErrorPart),
+                },
+                [b'\\', b'x', rest @ ..] => match rest.get(..2) {
+                    Some(hex) if hex.iter().all(u8::is_ascii_hexdigit) => pos += 4,
+                    Some(_) => return Err(JsmnErr::JsmErrorInval),
+                    None => return Err(JsmnErr::JsmErrorPart),
+                },
+                [b'\\', b'1'..=b'9', ..] => return Err(JsmnErr::JsmErrorInval),
+                [b'\\', b'\r', b'\n', ..] => pos += 3,
+                [b'\\', _, ..] => pos += 2,
+                [b'\\'] => return Err(JsmnErr::JsmErrorPart),
+                _ => pos += 1,
+            }
+        }
+    }
+
+    /// Returns the end of the identifier starting at `pos`.
+    fn identifier(&self) -> usize {
+        let len = self.js[self.pos..]
+            .iter()
+            .position(|&c| !is_identifier_start(c) && !c.is_ascii_digit())
+            .unwrap_or(self.js.len() - self.pos);
+        self.pos + len
+    }
+
+    /// Adds a token, counting it in the size of its parent.
+    fn push(&mut self, typ: JsmnType, start: usize, end: i32) -> Result<usize, JsmnErr> {
+        if self.next == self.tokens.len() {
+            return Err(JsmnErr::JsmErrorNoMem);
+        }
+        let parent = match self.expect {
+            Expect::Key => self.open.last().copied(),
+            _ => self.key.take().or(self.open.last().copied()),
+        };
+        if let Some(parent) = parent {
+            self.tokens[parent].size += 1;
+        }
+        let idx = self.next;
+        self.tokens[idx] = JsmnTok {
+            typ,
+            start: start as i32,
+            end,
+            size: 0,
+            parent: parent.map_or(-1, |parent| parent as i32),
+        };
+        self.next += 1;
+        Ok(idx)
+    }
+
+    /// Moves on after a string, which is either a key or a value.
+    fn after_scalar(&mut self) {
+        if self.expect == Expect::Key {
+            self.key = Some(self.next - 1);
+            self.expect = Expect::Colon;
+            self.close_ok = false;
+        } else {
+            self.value_done();
+        }
+    }
+
+    fn value_done(&mut self) {
+        self.expect = match self.open.is_empty() {
+            true => Expect::Value,
+            false => Expect::Next,
+        };
+        self.close_ok = false;
+    }
+
+    fn error(&self, kind: JsmnErr) -> ParseError {
+        ParseError {
+            kind,
+            pos: self.pos,
+            tokens: self.next,
+        }
+    }
+}
+
+fn is_identifier_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, b'_' | b'$') || c >= 0x80
+}
+
+fn is_delimiter(c: u8) -> bool {
+    matches!(
+        c,
+        b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c' | b',' | b':' | b']' | b'}' | b'/'
+    )
+}
+
+/// Returns true if `text` is a literal or a JSON5 number.
+fn is_primitive(text: &[u8]) -> bool {
+    let unsigned = match text {
+        b"true" | b"false" | b"null" => return true,
+        [b'+' | b'-', rest @ ..] => rest,
+        _ => text,
+    };
+    match unsigned {
+        b"Infinity" | b"NaN" => true,
+        [b'0', b'x' | b'X', hex @ ..] => !hex.is_empty() && hex.iter().all(u8::is_ascii_hexdigit),
+        _ => is_decimal(unsigned),
+    }
+}
+
+/// Returns true for digits with an optional decimal point, which may come
+/// first or last but not alone, and an optional exponent.
+fn is_decimal(text: &[u8]) -> bool {
+    let digits = |text: &[u8]| text.iter().take_while(|c| c.is_ascii_digit()).count();
+    let int = digits(text);
+    let mut rest = &text[int..];
+    let mut frac = 0;
+    if let [b'.', after @ ..] = rest {
+        frac = digits(after);
+        rest = &after[frac..];
+    }
+    if int == 0 && frac == 0 {
+        return false;
+    }
+    if int > 1 && text[0] == b'0' {
+        return false;
+    }
+    match rest {
+        [] => true,
+        [b'e' | b'E', exp @ ..] => {
+            let exp = match exp {
+                [b'+' | b'-', exp @ ..] => exp,
+                _ => exp,
+            };
+            !exp.is_empty() && digits(exp) == exp.len()
+        }
+        _ => false,
+    }
+}
+
+/// Decodes the escape sequences of a raw JSON5 string span, as found
+/// between the quotes. On top of the escapes of JSON this decodes `\'`,
+/// `\v`, `\0` and `\x` escapes, drops escaped line breaks, and takes any
+/// other escaped character as itself. Spans without a backslash are
+/// returned as they are.
+pub fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('v') => out.push('\u{b}'),
+            Some('0') => out.push('\0'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let code = u32::from_str_radix(&hex, 16).ok();
+                out.push(
+                    code.and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER),
+                );
+            }
+            Some('u') => out.push(unicode_escape(&mut chars)),
+            // Line continuations.
+            Some('\r') => {
+                let mut lookahead = chars.clone();
+                if lookahead.next() == Some('\n') {
+                    chars = lookahead;
+                }
+            }
+            Some('\n' | '\u{2028}' | '\u{2029}') => {}
+            Some(c) => out.push(c),
+            None => out.push(char::REPLACEMENT_CHARACTER),
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    #[test]
+    fn same_tokens_as_json() {
+        let js5 = r#"{ a :'x', "b": [+1, 0x1F, NaN],  c :null,}"#;
+        let json = r#"{"a":"x", "b": [-1, 1234, 123], "c":null }"#;
+        let mut tokens = Vec::new();
+        assert_eq!(parse_vec(js5, &mut tokens), Ok(10));
+        assert_eq!(tokens, parse_to_vec(json).unwrap());
+
+        let js5 = "/* list */ [1, // one\n .5, 5., -Infinity, 'it\\'s',]\n{}";
+        assert_eq!(parse_vec(js5, &mut tokens), Ok(7));
+        let texts: Vec<_> = tokens[1..6].iter().map(|tok| tok.as_str(js5)).collect();
+        assert_eq!(texts, ["1", ".5", "5.", "-Infinity", "it\\'s"]);
+        assert_eq!((tokens[0].size, tokens[6].start), (5, js5.len() as i32 - 2));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |js| {
+            let err = parse_vec(js, &mut Vec::new()).unwrap_err();
+            (err.kind, err.pos)
+        };
+        for (js, pos) in [
+            ("[1 2]", 3),
+            ("{a}", 2),
+            ("[1,,2]", 3),
+            ("[01]", 1),
+            ("[0x]", 1),
+            ("{a: 1,,}", 6),
+            ("[1}", 2),
+            ("['a\nb']", 1),
+            (r"['\1']", 1),
+        ] {
+            assert_eq!(error(js), (JsmnErr::JsmErrorInval, pos), "{}", js);
+        }
+        for (js, pos) in [("{'a': 1", 7), ("['ab", 1), ("[1] /* x", 4), ("{a:", 3)] {
+            assert_eq!(error(js), (JsmnErr::JsmErrorPart, pos), "{}", js);
+        }
+
+        let mut tokens = [JsmnTok::default(); 2];
+        let err = parse("[1, 'a']", &mut tokens).unwrap_err();
+        assert_eq!(
+            (err.kind, err.pos, err.tokens),
+            (JsmnErr::JsmErrorNoMem, 4, 2)
+        );
+    }
+
+    #[test]
+    fn json5_escapes() {
+        assert!(matches!(unescape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(unescape(r"it\'s \x41\u00e9\v\0"), "it's A\u{e9}\u{b}\0");
+        assert_eq!(unescape("one \\\ntwo \\\r\nthree"), "one two three");
+        assert_eq!(unescape(r#"\"\n\q"#), "\"\nq");
+    }
+}
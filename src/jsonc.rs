@@ -0,0 +1,138 @@
+//!
+//! This module reads JSON with comments, as found in the configuration
+//! files of VS Code and other editors. Both `//` line comments and `/* */`
+//! block comments are accepted, along with a trailing comma before the
+//! closing bracket of an object or array, which those files allow too.
+//!
+//! jsmn knows nothing of comments, so they are blanked out before parsing:
+//! every byte of a comment or trailing comma becomes a space, except for
+//! the line breaks inside block comments. The text keeps its length and
+//! its line numbers, and the tokens parsed from it can be used with the
+//! original text as well.
+//!
+
+use std::borrow::Cow;
+
+use crate::{JsmnErr, JsmnParser, JsmnTok, ParseError, jsmn_parse_vec};
+
+/// Returns `js` with comments and trailing commas replaced by spaces, or
+/// `js` itself if it has none. An unterminated block comment is reported
+/// as `JsmErrorPart` at its start.
+pub fn strip_comments(js: &str) -> Result<Cow<'_, str>, ParseError> {
+    let bytes = js.as_bytes();
+    let mut out: Option<Vec<u8>> = None;
+    let mut blank = |range: std::ops::Range<usize>| {
+        let out = out.get_or_insert_with(|| bytes.to_vec());
+        for byte in &mut out[range] {
+            if !matches!(byte, b'\r' | b'\n') {
+                *byte = b' ';
+            }
+        }
+    };
+
+    let mut last_comma = None;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match (bytes[pos], bytes.get(pos + 1)) {
+            (b'"', _) => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'"' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+                last_comma = None;
+            }
+            (b'/', Some(b'/')) => {
+                let end = bytes[pos..]
+                    .iter()
+                    .position(|&c| c == b'\n')
+                    .map_or(bytes.len(), |len| pos + len);
+                blank(pos..end);
+                pos = end;
+                continue;
+            }
+            (b'/', Some(b'*')) => {
+                let Some(len) = js[pos + 2..].find("*/") else {
+                    return Err(ParseError {
+                        kind: JsmnErr::JsmErrorPart,
+                        pos,
+                        tokens: 0,
+                    });
+                };
+                let end = pos + 2 + len + 2;
+                blank(pos..end);
+                pos = end;
+                continue;
+            }
+            (b',', _) => last_comma = Some(pos),
+            (b']' | b'}', _) => {
+                if let Some(comma) = last_comma.take() {
+                    blank(comma..comma + 1);
+                }
+            }
+            (b' ' | b'\t' | b'\r' | b'\n', _) => {}
+            _ => last_comma = None,
+        }
+        pos += 1;
+    }
+
+    Ok(match out {
+        // Only whole characters were blanked, so this is still UTF-8.
+        Some(out) => Cow::Owned(String::from_utf8(out).expect("blanked UTF-8")),
+        None => Cow::Borrowed(js),
+    })
+}
+
+/// Parses a document with comments into `tokens`, growing the vector as
+/// needed like [`crate::jsmn_parse_vec`]. The tokens refer to `js` itself.
+pub fn parse_vec(js: &str, tokens: &mut Vec<JsmnTok>) -> Result<usize, ParseError> {
+    let js = strip_comments(js)?;
+    let mut parser = JsmnParser::new();
+    tokens.clear();
+    jsmn_parse_vec(&mut parser, &js, tokens).map_err(|err| parser.error(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stripping() {
+        let js = "{\n  // Font size, in points.\n  \"size\": 12, /* not \"px\" */\n  \"path\": \"a//b/*c\",\n}\n";
+        let stripped = strip_comments(js).unwrap();
+        let spaces = |n| " ".repeat(n);
+        assert_eq!(
+            stripped,
+            format!(
+                "{{\n  {}\n  \"size\": 12, {}\n  \"path\": \"a//b/*c\" \n}}\n",
+                spaces(24),
+                spaces(14)
+            )
+        );
+        assert_eq!(stripped.len(), js.len());
+
+        let plain = r#"{"a": [1, "/*", "//"]}"#;
+        assert!(matches!(strip_comments(plain), Ok(Cow::Borrowed(_))));
+        assert_eq!(
+            strip_comments("[1, 2, /* , */ ]").unwrap(),
+            format!("[1, 2{}]", spaces(10))
+        );
+        assert_eq!(
+            strip_comments("/* é\n */ [\"\\\"\" /*é*/]").unwrap(),
+            format!("{}\n{}[\"\\\"\"{}]", spaces(5), spaces(4), spaces(7))
+        );
+    }
+
+    #[test]
+    fn parsing() {
+        let js = "[\n  1, // one\n  /* two\n  */ 2,\n  {\"a\": \"x\",}, // trailing\n]";
+        let mut tokens = Vec::new();
+        assert_eq!(parse_vec(js, &mut tokens), Ok(6));
+        let texts: Vec<_> = tokens[1..].iter().map(|tok| tok.as_str(js)).collect();
+        assert_eq!(texts, ["1", "2", r#"{"a": "x",}"#, "a", "x"]);
+        assert_eq!(tokens[3].size, 1);
+
+        let js = "[1, 2] /* unterminated";
+        let err = parse_vec(js, &mut tokens).unwrap_err();
+        assert_eq!((err.kind, err.pos), (JsmnErr::JsmErrorPart, 7));
+    }
+}
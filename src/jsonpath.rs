@@ -0,0 +1,751 @@
+//!
+//! This module evaluates JSONPath queries, as described in RFC 9535,
+//! directly against the tokens of a document, without building a tree of
+//! values first.
+//!
+//! The supported syntax covers member names (`$.store.book`, or quoted as
+//! `$['store']`), wildcards (`.*` and `[*]`), array indices counting from
+//! the end when negative (`[0]`, `[-1]`), slices (`[1:5:2]`), unions of
+//! several selectors (`[0,'title']`), descendants (`..author`), and
+//! filters such as `[?@.price < 10 && @.isbn]`. Filters compare the value
+//! at a path made of names and indices, from the current value `@` or the
+//! root `$`, with a literal or another such path, or test that such a path
+//! exists, and may be grouped with parentheses and negated with `!`. The
+//! function extensions of the RFC, like `length()`, are not supported.
+//!
+//! Results are the indices of the selected value tokens, in the order the
+//! query selects them, so a value can show up more than once.
+//!
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use crate::decode::{raw_eq, unescape};
+use crate::patch::values_equal;
+use crate::tree::{children, subtree_end, value_span};
+use crate::value::is_number;
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Error type for JSONPath queries.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JsonPathErr {
+    /// The document is not valid JSON.
+    Parse(JsmnErr),
+    /// The query is malformed at this byte offset.
+    Syntax { pos: usize },
+}
+
+impl From<JsmnErr> for JsonPathErr {
+    fn from(err: JsmnErr) -> Self {
+        JsonPathErr::Parse(err)
+    }
+}
+
+impl fmt::Display for JsonPathErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonPathErr::Parse(err) => write!(f, "{:?}", err),
+            JsonPathErr::Syntax { pos } => write!(f, "invalid query at byte {}", pos),
+        }
+    }
+}
+
+impl Error for JsonPathErr {}
+
+/// A compiled JSONPath query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    descendants: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, i64),
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Operand),
+    Compare(Operand, CompareOp, Operand),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// A path of names and indices from the current value, or from the
+    /// root when `root` is set.
+    Path {
+        root: bool,
+        steps: Vec<Step>,
+    },
+    Literal(Literal),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Name(String),
+    Index(i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Null,
+}
+
+impl JsonPath {
+    /// Compiles a query, which must start with `$`.
+    pub fn parse(query: &str) -> Result<Self, JsonPathErr> {
+        let mut parser = Parser { query, pos: 0 };
+        parser.expect("$")?;
+        let mut segments = Vec::new();
+        while parser.pos < query.len() {
+            segments.push(parser.segment()?);
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// Returns the indices of the tokens of the values the query selects
+    /// in an already parsed document, starting from its first root.
+    pub fn select(&self, js: &str, tokens: &[JsmnTok]) -> Vec<usize> {
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+        let doc = Doc { js, tokens };
+        let mut nodes = vec![0];
+        for segment in &self.segments {
+            let mut selected = Vec::new();
+            for node in nodes {
+                if segment.descendants {
+                    for inner in (node..subtree_end(tokens, node)).filter(|&i| !doc.is_key(i)) {
+                        doc.apply(&segment.selectors, inner, &mut selected);
+                    }
+                } else {
+                    doc.apply(&segment.selectors, node, &mut selected);
+                }
+            }
+            nodes = selected;
+        }
+        nodes
+    }
+}
+
+/// Parses `js`, runs `query` against it, and returns the byte spans of the
+/// selected values, quotes of strings included.
+pub fn query(js: &str, query: &str) -> Result<Vec<Range<usize>>, JsonPathErr> {
+    let path = JsonPath::parse(query)?;
+    let tokens = parse_to_vec(js)?;
+    Ok(path
+        .select(js, &tokens)
+        .into_iter()
+        .map(|idx| value_span(&tokens, idx))
+        .collect())
+}
+
+struct Doc<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+}
+
+impl<'a> Doc<'a> {
+    fn text(&self, idx: usize) -> &'a str {
+        let tok = &self.tokens[idx];
+        &self.js[tok.start as usize..tok.end as usize]
+    }
+
+    fn is_key(&self, idx: usize) -> bool {
+        let tok = &self.tokens[idx];
+        tok.typ == JsmnType::JsmnString && tok.size > 0
+    }
+
+    /// Returns the values held by the object or array at `idx`.
+    fn values(&self, idx: usize) -> Vec<usize> {
+        let object = self.tokens[idx].typ == JsmnType::JsmnObject;
+        children(self.tokens, idx)
+            .map(|child| if object { child + 1 } else { child })
+            .filter(|&child| child < self.tokens.len())
+            .collect()
+    }
+
+    /// Returns the value of the member `name` of the object at `idx`, the
+    /// last one when the name is repeated.
+    fn member(&self, idx: usize, name: &str) -> Option<usize> {
+        if self.tokens[idx].typ != JsmnType::JsmnObject {
+            return None;
+        }
+        children(self.tokens, idx)
+            .filter(|&key| key + 1 < self.tokens.len() && raw_eq(self.text(key), name))
+            .last()
+            .map(|key| key + 1)
+    }
+
+    fn element(&self, idx: usize, index: i64) -> Option<usize> {
+        if self.tokens[idx].typ != JsmnType::JsmnArray {
+            return None;
+        }
+        let elements = self.values(idx);
+        let index = if index < 0 {
+            elements.len() as i64 + index
+        } else {
+            index
+        };
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| elements.get(index).copied())
+    }
+
+    fn apply(&self, selectors: &[Selector], idx: usize, out: &mut Vec<usize>) {
+        for selector in selectors {
+            match selector {
+                Selector::Name(name) => out.extend(self.member(idx, name)),
+                Selector::Wildcard => out.extend(self.values(idx)),
+                Selector::Index(index) => out.extend(self.element(idx, *index)),
+                Selector::Slice(start, end, step) => {
+                    if self.tokens[idx].typ == JsmnType::JsmnArray {
+                        let elements = self.values(idx);
+                        let picked = slice(elements.len() as i64, *start, *end, *step);
+                        out.extend(picked.map(|i| elements[i]));
+                    }
+                }
+                Selector::Filter(expr) => out.extend(
+                    self.values(idx)
+                        .into_iter()
+                        .filter(|&value| self.test(expr, value)),
+                ),
+            }
+        }
+    }
+
+    fn test(&self, expr: &Expr, current: usize) -> bool {
+        match expr {
+            Expr::Or(a, b) => self.test(a, current) || self.test(b, current),
+            Expr::And(a, b) => self.test(a, current) && self.test(b, current),
+            Expr::Not(a) => !self.test(a, current),
+            Expr::Exists(operand) => self.resolve(operand, current).is_some(),
+            Expr::Compare(a, op, b) => {
+                let (a, b) = (self.resolve(a, current), self.resolve(b, current));
+                match op {
+                    CompareOp::Eq => self.equal(a, b),
+                    CompareOp::Ne => !self.equal(a, b),
+                    CompareOp::Lt => self.order(a, b) == Some(Ordering::Less),
+                    CompareOp::Gt => self.order(b, a) == Some(Ordering::Less),
+                    CompareOp::Le => self.order(a, b) == Some(Ordering::Less) || self.equal(a, b),
+                    CompareOp::Ge => self.order(b, a) == Some(Ordering::Less) || self.equal(a, b),
+                }
+            }
+        }
+    }
+
+    fn resolve<'e>(&self, operand: &'e Operand, current: usize) -> Option<Resolved<'e>> {
+        match operand {
+            Operand::Literal(literal) => Some(Resolved::Literal(literal)),
+            Operand::Path { root, steps } => {
+                let mut idx = if *root { 0 } else { current };
+                for step in steps {
+                    idx = match step {
+                        Step::Name(name) => self.member(idx, name)?,
+                        Step::Index(index) => self.element(idx, *index)?,
+                    };
+                }
+                Some(Resolved::Token(idx))
+            }
+        }
+    }
+
+    /// Reads a scalar, or None for an object or an array.
+    fn scalar<'e>(&self, value: Resolved<'e>) -> Option<Scalar<'e>>
+    where
+        'a: 'e,
+    {
+        match value {
+            Resolved::Literal(Literal::Number(n)) => Some(Scalar::Number(*n)),
+            Resolved::Literal(Literal::Str(s)) => Some(Scalar::Str(Cow::Borrowed(s))),
+            Resolved::Literal(Literal::Bool(b)) => Some(Scalar::Bool(*b)),
+            Resolved::Literal(Literal::Null) => Some(Scalar::Null),
+            Resolved::Token(idx) => {
+                let text = self.text(idx);
+                match self.tokens[idx].typ {
+                    JsmnType::JsmnString => Some(Scalar::Str(unescape(text))),
+                    JsmnType::JsmnPrimitive => match text {
+                        "true" => Some(Scalar::Bool(true)),
+                        "false" => Some(Scalar::Bool(false)),
+                        "null" => Some(Scalar::Null),
+                        _ if is_number(text) => text.parse().ok().map(Scalar::Number),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn equal(&self, a: Option<Resolved>, b: Option<Resolved>) -> bool {
+        match (a, b) {
+            (None, None) => true,
+            (Some(Resolved::Token(i)), Some(Resolved::Token(j))) => {
+                values_equal(self.js, self.tokens, i, self.js, self.tokens, j)
+            }
+            (Some(a), Some(b)) => match (self.scalar(a), self.scalar(b)) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn order(&self, a: Option<Resolved>, b: Option<Resolved>) -> Option<Ordering> {
+        match (self.scalar(a?)?, self.scalar(b?)?) {
+            (Scalar::Number(a), Scalar::Number(b)) => a.partial_cmp(&b),
+            (Scalar::Str(a), Scalar::Str(b)) => Some(a.cmp(&b)),
+            _ => None,
+        }
+    }
+}
+
+/// A filter operand once evaluated against the current value.
+#[derive(Debug, Copy, Clone)]
+enum Resolved<'e> {
+    Token(usize),
+    Literal(&'e Literal),
+}
+
+#[derive(Debug, PartialEq)]
+enum Scalar<'e> {
+    Number(f64),
+    Str(Cow<'e, str>),
+    Bool(bool),
+    Null,
+}
+
+/// Returns the indices a slice selects from an array of `len` elements.
+fn slice(
+    len: i64,
+    start: Option<i64>,
+    end: Option<i64>,
+    step: i64,
+) -> Box<dyn Iterator<Item = usize>> {
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    if step > 0 {
+        let lower = start.map_or(0, normalize).clamp(0, len);
+        let upper = end.map_or(len, normalize).clamp(0, len);
+        Box::new((lower..upper).step_by(step as usize).map(|i| i as usize))
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        Box::new(
+            ((lower + 1)..=upper)
+                .rev()
+                .step_by(step.unsigned_abs() as usize)
+                .map(|i| i as usize),
+        )
+    } else {
+        Box::new(std::iter::empty())
+    }
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> JsonPathErr {
+        JsonPathErr::Syntax { pos: self.pos }
+    }
+
+    fn rest(&self) -> &str {
+        &self.query[self.pos..]
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.query.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let found = self.rest().starts_with(text);
+        if found {
+            self.pos += text.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), JsonPathErr> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn segment(&mut self) -> Result<Segment, JsonPathErr> {
+        let descendants = self.eat("..");
+        let selectors = if self.eat("[") {
+            self.bracket()?
+        } else if descendants || self.eat(".") {
+            if self.eat("*") {
+                vec![Selector::Wildcard]
+            } else {
+                vec![Selector::Name(self.name()?)]
+            }
+        } else {
+            return Err(self.error());
+        };
+        Ok(Segment {
+            descendants,
+            selectors,
+        })
+    }
+
+    fn name(&mut self) -> Result<String, JsonPathErr> {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || !c.is_ascii()))
+            .unwrap_or(self.rest().len());
+        if len == 0 || self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error());
+        }
+        let name = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(name)
+    }
+
+    /// Parses the selectors of a bracket, after the `[`.
+    fn bracket(&mut self) -> Result<Vec<Selector>, JsonPathErr> {
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_space();
+            selectors.push(self.selector()?);
+            self.skip_space();
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, JsonPathErr> {
+        match self.peek() {
+            Some(b'\'' | b'"') => Ok(Selector::Name(self.string()?)),
+            Some(b'*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some(b'?') => {
+                self.pos += 1;
+                Ok(Selector::Filter(self.or()?))
+            }
+            _ => {
+                let start = self.int()?;
+                self.skip_space();
+                if !self.eat(":") {
+                    return start.map(Selector::Index).ok_or(self.error());
+                }
+                self.skip_space();
+                let end = self.int()?;
+                self.skip_space();
+                let step = if self.eat(":") {
+                    self.skip_space();
+                    self.int()?.unwrap_or(1)
+                } else {
+                    1
+                };
+                Ok(Selector::Slice(start, end, step))
+            }
+        }
+    }
+
+    /// Parses an optional integer, without leading zeros.
+    fn int(&mut self) -> Result<Option<i64>, JsonPathErr> {
+        let start = self.pos;
+        self.eat("-");
+        let digits = self.rest().find(|c: char| !c.is_ascii_digit());
+        let digits = digits.unwrap_or(self.rest().len());
+        let text = &self.query[start..self.pos + digits];
+        if digits == 0 {
+            return if self.pos == start {
+                Ok(None)
+            } else {
+                Err(self.error())
+            };
+        }
+        if text.trim_start_matches('-').starts_with('0') && digits > 1 || text == "-0" {
+            return Err(self.error());
+        }
+        self.pos += digits;
+        text.parse()
+            .map(Some)
+            .map_err(|_| JsonPathErr::Syntax { pos: start })
+    }
+
+    /// Parses a quoted string, in either kind of quotes, with JSON escapes.
+    fn string(&mut self) -> Result<String, JsonPathErr> {
+        let start = self.pos;
+        let quote = self.query.as_bytes()[start] as char;
+        let mut escaped = false;
+        for (i, c) in self.query[start + 1..].char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                c if c == quote => {
+                    let raw = &self.query[start + 1..start + 1 + i];
+                    self.pos = start + i + 2;
+                    return Ok(unescape(&raw.replace("\\'", "'")).into_owned());
+                }
+                _ => {}
+            }
+        }
+        Err(JsonPathErr::Syntax { pos: start })
+    }
+
+    fn or(&mut self) -> Result<Expr, JsonPathErr> {
+        let mut expr = self.and()?;
+        while {
+            self.skip_space();
+            self.eat("||")
+        } {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, JsonPathErr> {
+        let mut expr = self.unary()?;
+        while {
+            self.skip_space();
+            self.eat("&&")
+        } {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, JsonPathErr> {
+        self.skip_space();
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            self.skip_space();
+            self.expect(")")?;
+            return Ok(expr);
+        }
+
+        let start = self.pos;
+        let left = self.operand()?;
+        self.skip_space();
+        let ops = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+        match ops.into_iter().find(|(text, _)| self.eat(text)) {
+            Some((_, op)) => {
+                self.skip_space();
+                Ok(Expr::Compare(left, op, self.operand()?))
+            }
+            None if matches!(left, Operand::Path { .. }) => Ok(Expr::Exists(left)),
+            None => Err(JsonPathErr::Syntax { pos: start }),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, JsonPathErr> {
+        let root = match self.peek() {
+            Some(b'@') => false,
+            Some(b'$') => true,
+            Some(b'\'' | b'"') => return Ok(Operand::Literal(Literal::Str(self.string()?))),
+            _ => return self.literal().map(Operand::Literal),
+        };
+        self.pos += 1;
+
+        let mut steps = Vec::new();
+        loop {
+            if self.rest().starts_with("..") {
+                return Err(self.error());
+            } else if self.eat(".") {
+                steps.push(Step::Name(self.name()?));
+            } else if self.eat("[") {
+                self.skip_space();
+                steps.push(match self.peek() {
+                    Some(b'\'' | b'"') => Step::Name(self.string()?),
+                    _ => Step::Index(self.int()?.ok_or(self.error())?),
+                });
+                self.skip_space();
+                self.expect("]")?;
+            } else {
+                return Ok(Operand::Path { root, steps });
+            }
+        }
+    }
+
+    fn literal(&mut self) -> Result<Literal, JsonPathErr> {
+        for (text, literal) in [
+            ("true", Literal::Bool(true)),
+            ("false", Literal::Bool(false)),
+            ("null", Literal::Null),
+        ] {
+            if self.eat(text) {
+                return Ok(literal);
+            }
+        }
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+            .unwrap_or(self.rest().len());
+        let text = &self.rest()[..len];
+        if !is_number(text) {
+            return Err(self.error());
+        }
+        let number = text.parse().map_err(|_| self.error())?;
+        self.pos += len;
+        Ok(Literal::Number(number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORE: &str = r#"{ "store": {
+    "book": [
+      { "category": "reference", "author": "Nigel Rees",
+        "title": "Sayings of the Century", "price": 8.95 },
+      { "category": "fiction", "author": "Evelyn Waugh",
+        "title": "Sword of Honour", "price": 12.99 },
+      { "category": "fiction", "author": "Herman Melville",
+        "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+      { "category": "fiction", "author": "J. R. R. Tolkien",
+        "title": "The Lord of the Rings", "isbn": "0-395-19395-8",
+        "price": 22.99 }
+    ],
+    "bicycle": { "color": "red", "price": 399 }
+  }
+}"#;
+
+    fn texts(query: &str) -> Vec<&'static str> {
+        super::query(STORE, query)
+            .unwrap()
+            .into_iter()
+            .map(|span| &STORE[span])
+            .collect()
+    }
+
+    #[test]
+    fn selectors() {
+        assert_eq!(
+            texts("$.store.book[*].author"),
+            [
+                r#""Nigel Rees""#,
+                r#""Evelyn Waugh""#,
+                r#""Herman Melville""#,
+                r#""J. R. R. Tolkien""#
+            ]
+        );
+        assert_eq!(texts("$..author").len(), 4);
+        assert_eq!(texts("$.store.*").len(), 2);
+        assert_eq!(
+            texts("$.store..price"),
+            ["8.95", "12.99", "8.99", "22.99", "399"]
+        );
+        assert_eq!(texts("$..book[2].title"), [r#""Moby Dick""#]);
+        assert_eq!(
+            texts("$..book[-1]['title']"),
+            [r#""The Lord of the Rings""#]
+        );
+        assert_eq!(texts("$..book[:2].price"), ["8.95", "12.99"]);
+        assert_eq!(texts("$..book[::-2].price"), ["22.99", "12.99"]);
+        assert_eq!(texts("$..book[0, 2].price"), ["8.95", "8.99"]);
+        assert_eq!(
+            texts("$.store.bicycle[\"color\", 'price']"),
+            [r#""red""#, "399"]
+        );
+        assert_eq!(texts("$").len(), 1);
+        assert!(texts("$.store.book.title").is_empty());
+        assert!(texts("$..book[4]").is_empty());
+
+        // Repeated keys resolve to the last one, however they are escaped.
+        let js = r#"{"a": 1, "\u0061": 2, "b": {"a": 3}}"#;
+        let spans = query(js, "$.a").unwrap();
+        assert_eq!(spans.into_iter().map(|s| &js[s]).collect::<Vec<_>>(), ["2"]);
+        let spans = query(js, "$[?@.a == 3].a").unwrap();
+        assert_eq!(spans.into_iter().map(|s| &js[s]).collect::<Vec<_>>(), ["3"]);
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(
+            texts("$.store.book[?(@.price<10)].title"),
+            [r#""Sayings of the Century""#, r#""Moby Dick""#]
+        );
+        assert_eq!(texts("$..book[?@.isbn].price"), ["8.99", "22.99"]);
+        assert_eq!(texts("$..book[?!@.isbn].price"), ["8.95", "12.99"]);
+        assert_eq!(
+            texts("$..book[?@.category == 'fiction' && @.price >= 12.99].price"),
+            ["12.99", "22.99"]
+        );
+        assert_eq!(
+            texts("$..book[?(@.price > 20 || @.author == \"Nigel Rees\")].price"),
+            ["8.95", "22.99"]
+        );
+        assert_eq!(
+            texts("$..book[?@.price < $.store.bicycle.price && @.price != 8.99].price"),
+            ["8.95", "12.99", "22.99"]
+        );
+        assert_eq!(texts("$.store[?@.color == 'red'].price"), ["399"]);
+        assert_eq!(texts("$..book[?@.missing == @.other].price").len(), 4);
+        assert!(texts("$..book[?@.price == '8.95']").is_empty());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let err = |query| JsonPath::parse(query).unwrap_err();
+        assert_eq!(err("store"), JsonPathErr::Syntax { pos: 0 });
+        assert_eq!(err("$.store."), JsonPathErr::Syntax { pos: 8 });
+        assert_eq!(err("$[01]"), JsonPathErr::Syntax { pos: 2 });
+        assert_eq!(err("$['a'"), JsonPathErr::Syntax { pos: 5 });
+        assert_eq!(err("$['a"), JsonPathErr::Syntax { pos: 2 });
+        assert_eq!(err("$[?@.a == ]"), JsonPathErr::Syntax { pos: 10 });
+        assert_eq!(err("$[?1]"), JsonPathErr::Syntax { pos: 3 });
+        assert_eq!(
+            query("[1, 2", "$[0]"),
+            Err(JsonPathErr::Parse(JsmnErr::JsmErrorPart))
+        );
+        assert_eq!(err("$.a b").to_string(), "invalid query at byte 3");
+    }
+}
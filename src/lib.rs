@@ -14,9 +14,15 @@
 //!
 //!
 //! To use this library, simply create a parser using JsmnParser::new()
-//! and pass the parser, a JSON string, and a slice of JsmnToks to jsmn_parse.
+//! and pass the parser, a JSON string, and a slice of JsmnToks to jsmn_parse,
+//! or call the parser's parse method with the string and the tokens.
 //! The result will be that the slice will be filled out with tokens defining the
-//! starting and ending offset of each JSON token in the given string.
+//! starting and ending offset of each JSON token in the given string. If you
+//! would rather not guess how many tokens a document needs, jsmn_parse_vec
+//! takes a Vec instead and grows it until everything fits. Both have a
+//! variant taking bytes, for input that has not been checked to be UTF-8.
+//! A `buf::TokenBuf` keeps a fixed array together with how many of its
+//! tokens were filled in, and returns only those.
 //!
 //!
 //! Thats all there is to it! This crate is just intended to make jsmn easy to use
@@ -29,9 +35,67 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
-use std::mem::transmute;
+use std::error::Error;
+use std::fmt;
+#[cfg(not(feature = "pure-rust"))]
+use std::mem::MaybeUninit;
 
+#[cfg(feature = "arrow")]
+pub mod batch;
+pub mod buf;
+pub mod canonical;
+pub mod changes;
+pub mod columns;
+pub mod csv;
+pub mod decode;
+pub mod diff;
+#[cfg(feature = "dom")]
+pub mod dom;
+pub mod dump;
+pub mod events;
+pub mod extract;
+pub mod from_tokens;
+pub mod incremental;
+pub mod intern;
+#[cfg(feature = "json5")]
+pub mod json5;
+pub mod jsonc;
+pub mod jsonpath;
+pub mod limits;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod ndjson;
+pub mod parallel;
+pub mod patch;
+pub mod pointer;
+pub mod progress;
+pub mod project;
+#[cfg(test)]
+mod proptests;
+#[cfg_attr(not(feature = "pure-rust"), allow(dead_code))]
+mod pure;
 pub mod raw;
+pub mod rewrite;
+pub mod scan;
+pub mod schema;
+pub mod ser;
+#[cfg(feature = "serde_json")]
+pub mod serde_value;
+pub mod session;
+pub mod shared;
+pub mod source_map;
+pub mod storage;
+pub mod stream;
+#[cfg(feature = "transcode")]
+pub mod transcode;
+pub mod tree;
+pub mod validate;
+pub mod value;
+pub mod view;
+pub mod visitor;
+pub mod write;
+
+mod hash;
 
 /// The JSON object type. These enum values are identical to the jsmn library
 /// enum jsmntype_t, but renamed to match Rust's conventions.
@@ -56,12 +120,38 @@ pub enum JsmnErr {
     JsmErrorPart = raw::jsmnerr_JSMN_ERROR_PART,
 }
 
+/// A parse error with the state of the parser when it occurred, as built
+/// by [`JsmnParser::error`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: JsmnErr,
+    /// The byte offset where parsing stopped. For a string cut short, and
+    /// for a string with an invalid escape, this is the start of the
+    /// string, and for a document missing its closing brackets it is the
+    /// end of the input.
+    pub pos: usize,
+    /// The number of tokens produced before the error.
+    pub tokens: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} at byte {}, after {} tokens",
+            self.kind, self.pos, self.tokens
+        )
+    }
+}
+
+impl Error for ParseError {}
+
 /// A JSON token structure, defining which type of JSON object it is, the starting
 /// character, ending character, and size in bytes. All offsets are from the start
 /// of the parsed string.
 ///
-/// Note that if the parent-links feature is used, then this struct will
-/// have the "parent" field, and otherwise it will not.
+/// `parent` is the index of the token holding this one, or -1 for a root.
+/// A value in an object is held by its key, and the key by the object.
 #[repr(C)]
 #[derive(Debug, Copy, PartialEq)]
 pub struct JsmnTok {
@@ -69,7 +159,6 @@ pub struct JsmnTok {
     pub start: i32,
     pub end: i32,
     pub size: i32,
-    #[cfg(feature = "parent-links")]
     pub parent: i32,
 }
 
@@ -80,10 +169,28 @@ impl JsmnTok {
             start: 0,
             end: 0,
             size: 0,
-            #[cfg(feature = "parent-links")]
             parent: 0,
         }
     }
+
+    /// The text of the token in `js`, the string it was parsed from. For
+    /// strings this is the text between the quotes, with escapes left as
+    /// they are.
+    ///
+    /// Panics if the token does not lie in `js` on character boundaries,
+    /// which cannot happen with the string the token was parsed from.
+    pub fn as_str<'a>(&self, js: &'a str) -> &'a str {
+        &js[self.start as usize..self.end as usize]
+    }
+
+    /// Like [`JsmnTok::as_str`], but returns None if the token does not lie
+    /// in `js` on character boundaries, as with a token from another
+    /// document or one that was never filled in.
+    pub fn try_as_str<'a>(&self, js: &'a str) -> Option<&'a str> {
+        let start = usize::try_from(self.start).ok()?;
+        let end = usize::try_from(self.end).ok()?;
+        js.get(start..end)
+    }
 }
 
 impl Clone for JsmnTok {
@@ -99,33 +206,150 @@ impl Default for JsmnTok {
             start: Default::default(),
             end: Default::default(),
             size: Default::default(),
-            #[cfg(feature = "parent-links")]
             parent: -1,
         }
     }
 }
 
-/// A JsmnParser is the parser state for the jsmn library.
+/// A JsmnParser is the parser state for the jsmn library. Its fields have
+/// the same C types as those of jsmn_parser, so that jsmn can write to it.
 #[repr(C)]
-#[derive(Debug, Copy, Default)]
+#[derive(Debug, Copy)]
 pub struct JsmnParser {
-    pub pos: usize,
-    pub toknext: usize,
-    pub toksuper: isize,
+    pub pos: u32,
+    pub toknext: u32,
+    pub toksuper: i32,
 }
 
 impl JsmnParser {
+    /// A parser ready for a new document, as set up by jsmn_init.
+    #[cfg(feature = "pure-rust")]
     pub fn new() -> Self {
-        let parser = JsmnParser {
+        JsmnParser {
             pos: 0,
             toknext: 0,
-            toksuper: 0,
-        };
+            toksuper: -1,
+        }
+    }
+
+    /// A parser ready for a new document, as set up by jsmn_init.
+    #[cfg(not(feature = "pure-rust"))]
+    pub fn new() -> Self {
+        let mut parser = MaybeUninit::<JsmnParser>::uninit();
+        // jsmn_init writes every field, and the layouts are checked to
+        // match in the raw module.
+        unsafe {
+            raw::jsmn_init(parser.as_mut_ptr().cast());
+            parser.assume_init()
+        }
+    }
+
+    /// Parses `js` into `tokens`, see [`jsmn_parse`].
+    pub fn parse(&mut self, js: &str, tokens: &mut [JsmnTok]) -> Result<usize, JsmnErr> {
+        self.parse_bytes(js.as_bytes(), tokens)
+    }
+
+    /// Parses input that is not known to be UTF-8, see [`jsmn_parse_bytes`].
+    pub fn parse_bytes(&mut self, js: &[u8], tokens: &mut [JsmnTok]) -> Result<usize, JsmnErr> {
+        self.parse_mode(js, tokens, cfg!(feature = "strict"))
+    }
+
+    /// Parses `js` in strict mode if `strict` is true, or in the lenient
+    /// mode otherwise, whatever the strict feature says.
+    pub(crate) fn parse_mode(
+        &mut self,
+        js: &[u8],
+        tokens: &mut [JsmnTok],
+        strict: bool,
+    ) -> Result<usize, JsmnErr> {
+        #[cfg(feature = "pure-rust")]
+        {
+            pure::parse(self, js, Some(tokens), strict)
+        }
+        #[cfg(not(feature = "pure-rust"))]
+        match strict {
+            true => self.parse_in(js, tokens, raw::jsmn_parse_strict),
+            false => self.parse_in(js, tokens, raw::jsmn_parse_lenient),
+        }
+    }
+
+    /// Parses with `parse`, one of the builds of jsmn_parse.
+    #[cfg(not(feature = "pure-rust"))]
+    fn parse_in(
+        &mut self,
+        js: &[u8],
+        tokens: &mut [JsmnTok],
+        parse: unsafe extern "C" fn(
+            *mut raw::jsmn_parser,
+            *const std::os::raw::c_char,
+            usize,
+            *mut raw::jsmntok_t,
+            std::os::raw::c_uint,
+        ) -> std::os::raw::c_int,
+    ) -> Result<usize, JsmnErr> {
+        unsafe fn cast_slice_mut<T, U>(src: &mut [T]) -> &mut [U] {
+            assert_eq!(size_of::<T>(), size_of::<U>(), "Size mismatch");
+            assert_eq!(align_of::<T>(), align_of::<U>(), "Alignment mismatch");
+
+            let len = src.len();
+            let ptr = src.as_mut_ptr() as *mut U;
+            unsafe { std::slice::from_raw_parts_mut(ptr, len) }
+        }
+
+        let result: i32;
         unsafe {
-            raw::jsmn_init(transmute(&parser));
+            let raw_tokens: &mut [raw::jsmntok_t] = cast_slice_mut(tokens);
+
+            result = parse(
+                self as *mut _ as *mut raw::jsmn_parser,
+                js.as_ptr() as *const _,
+                js.len(),
+                raw_tokens.as_mut_ptr(),
+                raw_tokens.len() as u32,
+            );
+        }
+
+        parse_result(result)
+    }
+
+    /// Describes an error that parsing with this parser just returned,
+    /// with where it happened, as in
+    /// `parser.parse(js, &mut tokens).map_err(|err| parser.error(err))`.
+    pub fn error(&self, kind: JsmnErr) -> ParseError {
+        ParseError {
+            kind,
+            pos: self.pos as usize,
+            tokens: self.toknext as usize,
         }
+    }
+
+    /// The number of bytes of the input read so far.
+    pub fn bytes_consumed(&self) -> usize {
+        self.pos as usize
+    }
+
+    /// The number of tokens filled in so far.
+    pub fn tokens_emitted(&self) -> usize {
+        self.toknext as usize
+    }
+
+    /// The index of the token that the next token will belong to, that is
+    /// the innermost object or array still open, or the key whose value
+    /// comes next. None at the top level.
+    pub fn open_token(&self) -> Option<usize> {
+        usize::try_from(self.toksuper).ok()
+    }
+
+    /// Puts the parser back in its initial state, so that it can be used
+    /// for another document.
+    pub fn reset(&mut self) {
+        *self = JsmnParser::new();
+    }
+}
 
-        parser
+impl Default for JsmnParser {
+    fn default() -> Self {
+        JsmnParser::new()
     }
 }
 
@@ -147,43 +371,119 @@ impl Clone for JsmnParser {
 /// If the function succeeds, it will return a usize giving how many
 /// tokens were parsed, and on error it will return an JsmnErr describing the
 /// problem encountered while parsing.
+///
+/// This is the same as calling [`JsmnParser::parse`].
 pub fn jsmn_parse(
     parser: &mut JsmnParser,
     js: &str,
     tokens: &mut [JsmnTok],
 ) -> Result<usize, JsmnErr> {
-    unsafe fn cast_slice_mut<T, U>(src: &mut [T]) -> &mut [U] {
-        assert_eq!(size_of::<T>(), size_of::<U>(), "Size mismatch");
-        assert_eq!(align_of::<T>(), align_of::<U>(), "Alignment mismatch");
+    parser.parse(js, tokens)
+}
 
-        let len = src.len();
-        let ptr = src.as_mut_ptr() as *mut U;
-        unsafe { std::slice::from_raw_parts_mut(ptr, len) }
-    }
+/// Like [`jsmn_parse`], but for input that is not known to be UTF-8, such
+/// as a buffer read from a socket. jsmn only looks at ASCII bytes, so it
+/// does not need to be, and invalid sequences inside strings are left for
+/// the caller to deal with when decoding them. Token offsets are byte
+/// offsets into `js` either way.
+pub fn jsmn_parse_bytes(
+    parser: &mut JsmnParser,
+    js: &[u8],
+    tokens: &mut [JsmnTok],
+) -> Result<usize, JsmnErr> {
+    parser.parse_bytes(js, tokens)
+}
 
-    let result: i32;
-    unsafe {
-        let raw_tokens: &mut [raw::jsmntok_t] = cast_slice_mut(tokens);
+/// Counts the tokens a document needs without storing any, by running jsmn
+/// without a token array. This allows allocating exactly the right number
+/// of tokens before parsing.
+///
+/// Counting checks less than parsing does: in particular, unclosed objects
+/// and arrays are only reported by a real parse.
+pub fn count_tokens(js: &str) -> Result<usize, JsmnErr> {
+    let mut parser = JsmnParser::new();
+    #[cfg(feature = "pure-rust")]
+    {
+        pure::parse(&mut parser, js.as_bytes(), None, cfg!(feature = "strict"))
+    }
+    #[cfg(not(feature = "pure-rust"))]
+    {
+        let result = unsafe {
+            raw::jsmn_parse(
+                &mut parser as *mut _ as *mut raw::jsmn_parser,
+                js.as_ptr() as *const _,
+                js.len(),
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        parse_result(result)
+    }
+}
 
-        result = raw::jsmn_parse(
-            parser as *mut _ as *mut raw::jsmn_parser,
-            js.as_ptr() as *const _,
-            js.len(),
-            raw_tokens.as_mut_ptr(),
-            raw_tokens.len() as u32,
-        );
+#[cfg(not(feature = "pure-rust"))]
+fn parse_result(result: i32) -> Result<usize, JsmnErr> {
+    match result {
+        -1 => Err(JsmnErr::JsmErrorNoMem),
+        -2 => Err(JsmnErr::JsmErrorInval),
+        -3 => Err(JsmnErr::JsmErrorPart),
+        _ if result < 0 => unreachable!(),
+        _ => Ok(result as usize),
     }
+}
 
-    if result < 0 {
-        return match result {
-            -1 => Err(JsmnErr::JsmErrorNoMem),
-            -2 => Err(JsmnErr::JsmErrorInval),
-            -3 => Err(JsmnErr::JsmErrorPart),
-            _ => unreachable!(),
-        };
+/// Like [`jsmn_parse`], but with a vector of tokens that is grown as needed.
+/// Whenever jsmn runs out of tokens, the vector is doubled in size and
+/// parsing resumes where it stopped, with the same parser. On success the
+/// vector is truncated to the tokens parsed so far, and their count is
+/// returned. It is truncated the same way on `JsmErrorPart`.
+///
+/// The tokens already in the vector are kept, so a parser resuming after
+/// `JsmErrorPart` can be handed the tokens it produced before.
+pub fn jsmn_parse_vec(
+    parser: &mut JsmnParser,
+    js: &str,
+    tokens: &mut Vec<JsmnTok>,
+) -> Result<usize, JsmnErr> {
+    jsmn_parse_vec_bytes(parser, js.as_bytes(), tokens)
+}
+
+/// Like [`jsmn_parse_vec`], but for input that is not known to be UTF-8,
+/// see [`jsmn_parse_bytes`].
+pub fn jsmn_parse_vec_bytes(
+    parser: &mut JsmnParser,
+    js: &[u8],
+    tokens: &mut Vec<JsmnTok>,
+) -> Result<usize, JsmnErr> {
+    if tokens.len() <= parser.toknext as usize {
+        let len = parser.toknext as usize + js.len() / 8 + 8;
+        tokens.resize(len, JsmnTok::default());
+    }
+    loop {
+        match jsmn_parse_bytes(parser, js, tokens) {
+            Ok(count) => {
+                tokens.truncate(count);
+                return Ok(count);
+            }
+            Err(JsmnErr::JsmErrorNoMem) => {
+                let len = tokens.len() * 2;
+                tokens.resize(len, JsmnTok::default());
+            }
+            Err(err) => {
+                if err == JsmnErr::JsmErrorPart {
+                    tokens.truncate(parser.toknext as usize);
+                }
+                return Err(err);
+            }
+        }
     }
+}
 
-    Ok(result as usize)
+/// Parses a whole document into a freshly allocated token vector.
+pub(crate) fn parse_to_vec(js: &str) -> Result<Vec<JsmnTok>, JsmnErr> {
+    let mut tokens = Vec::new();
+    jsmn_parse_vec(&mut JsmnParser::new(), js, &mut tokens)?;
+    Ok(tokens)
 }
 
 #[cfg(test)]
@@ -243,18 +543,14 @@ mod tests {
                     start: 1,
                     end: 3,
                     size: 0,
-                    #[cfg(feature = "parent-links")]
                     parent: 0,
-                    ..Default::default()
                 },
                 JsmnTok {
                     typ: JsmnType::JsmnObject,
                     start: 4,
                     end: 6,
                     size: 0,
-                    #[cfg(feature = "parent-links")]
                     parent: 0,
-                    ..Default::default()
                 }
             ],
             &tokens
@@ -278,18 +574,14 @@ mod tests {
                     start: 2,
                     end: 3,
                     size: 1,
-                    #[cfg(feature = "parent-links")]
                     parent: 0,
-                    ..Default::default()
                 },
                 JsmnTok {
                     typ: JsmnType::JsmnPrimitive,
                     start: 5,
                     end: 6,
                     size: 0,
-                    #[cfg(feature = "parent-links")]
                     parent: 1,
-                    ..Default::default()
                 }
             ],
             &tokens
@@ -310,18 +602,14 @@ mod tests {
                     start: 2,
                     end: 3,
                     size: 1,
-                    #[cfg(feature = "parent-links")]
                     parent: 0,
-                    ..Default::default()
                 },
                 JsmnTok {
                     typ: JsmnType::JsmnArray,
                     start: 5,
                     end: 7,
                     size: 0,
-                    #[cfg(feature = "parent-links")]
                     parent: 1,
-                    ..Default::default()
                 }
             ],
             &tokens
@@ -345,9 +633,7 @@ mod tests {
                     start: 1,
                     end: 3,
                     size: 0,
-                    #[cfg(feature = "parent-links")]
                     parent: 0,
-                    ..Default::default()
                 }
             ],
             &tokens
@@ -362,7 +648,6 @@ mod tests {
                 typ: JsmnType::JsmnPrimitive,
                 start: 12,
                 end: 16,
-                #[cfg(feature = "parent-links")]
                 parent: 1,
                 ..Default::default()
             },
@@ -373,7 +658,6 @@ mod tests {
                 typ: JsmnType::JsmnPrimitive,
                 start: 12,
                 end: 17,
-                #[cfg(feature = "parent-links")]
                 parent: 1,
                 ..Default::default()
             },
@@ -384,7 +668,6 @@ mod tests {
                 typ: JsmnType::JsmnPrimitive,
                 start: 12,
                 end: 16,
-                #[cfg(feature = "parent-links")]
                 parent: 1,
                 ..Default::default()
             },
@@ -395,7 +678,6 @@ mod tests {
                 typ: JsmnType::JsmnPrimitive,
                 start: 11,
                 end: 13,
-                #[cfg(feature = "parent-links")]
                 parent: 1,
                 ..Default::default()
             },
@@ -406,7 +688,6 @@ mod tests {
                 typ: JsmnType::JsmnPrimitive,
                 start: 13,
                 end: 19,
-                #[cfg(feature = "parent-links")]
                 parent: 1,
                 ..Default::default()
             },
@@ -428,4 +709,124 @@ mod tests {
             JsmnErr::JsmErrorInval
         );
     }
+
+    #[test]
+    fn parse_into_growing_vec() {
+        let js = format!("[{}]", vec!["[1, {\"a\": 2}]"; 100].join(", "));
+        let mut tokens = Vec::new();
+        let mut parser = JsmnParser::new();
+        assert_eq!(jsmn_parse_vec(&mut parser, &js, &mut tokens), Ok(501));
+        assert_eq!(tokens.len(), 501);
+        assert_eq!(tokens[0].size, 100);
+        assert_eq!(tokens[500].typ, JsmnType::JsmnPrimitive);
+
+        let mut tokens = vec![JsmnTok::default(); 1];
+        let mut parser = JsmnParser::new();
+        assert_eq!(jsmn_parse_vec(&mut parser, &js, &mut tokens), Ok(501));
+        assert_eq!(tokens, parse_to_vec(&js).unwrap());
+
+        let mut parser = JsmnParser::new();
+        assert_eq!(
+            jsmn_parse_vec(&mut parser, "[1, 2", &mut Vec::new()),
+            Err(JsmnErr::JsmErrorPart)
+        );
+    }
+
+    #[test]
+    fn count_then_parse() {
+        let js = r#"{"a": [1, 2, {"b": null}], "c": "d"}"#;
+        let count = count_tokens(js).unwrap();
+        assert_eq!(count, 10);
+
+        let mut tokens = vec![JsmnTok::default(); count];
+        assert_eq!(
+            jsmn_parse(&mut JsmnParser::new(), js, &mut tokens),
+            Ok(count)
+        );
+        assert_eq!(count_tokens(""), Ok(0));
+        assert_eq!(count_tokens(r#"{"a": "b"#), Err(JsmnErr::JsmErrorPart));
+    }
+
+    #[test]
+    fn parser_reuse() {
+        let mut parser = JsmnParser::default();
+        assert_eq!((parser.pos, parser.toknext, parser.toksuper), (0, 0, -1));
+
+        let mut tokens = [JsmnTok::default(); 4];
+        assert_eq!(jsmn_parse(&mut parser, "[1, 2]", &mut tokens), Ok(3));
+        assert_eq!(parser.parse("[3]", &mut tokens), Ok(3));
+        parser.reset();
+        assert_eq!(parser.parse("[3]", &mut tokens), Ok(2));
+        assert_eq!((tokens[1].start, tokens[1].end), (1, 2));
+    }
+
+    #[test]
+    fn error_positions() {
+        let error = |js: &str, len: usize| {
+            let mut parser = JsmnParser::new();
+            let mut tokens = vec![JsmnTok::default(); len];
+            let err = parser.parse(js, &mut tokens).unwrap_err();
+            parser.error(err)
+        };
+
+        let err = error(r#"{"a": 1, "b": [true, "x"}]"#, 16);
+        assert_eq!(
+            (err.kind, err.pos, err.tokens),
+            (JsmnErr::JsmErrorInval, 24, 7)
+        );
+        let err = error(r#"[1, 2, "unterminated"#, 16);
+        assert_eq!(
+            (err.kind, err.pos, err.tokens),
+            (JsmnErr::JsmErrorPart, 7, 3)
+        );
+        let err = error("[[1, 2]", 16);
+        assert_eq!((err.kind, err.pos), (JsmnErr::JsmErrorPart, 7));
+        let err = error("[1, 2, 3]", 3);
+        assert_eq!((err.kind, err.tokens), (JsmnErr::JsmErrorNoMem, 3));
+        assert_eq!(err.to_string(), "JsmErrorNoMem at byte 7, after 3 tokens");
+    }
+
+    #[test]
+    fn token_text() {
+        let js = r#"{"clé": [1.5, "a\"b"]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let texts: Vec<_> = tokens.iter().map(|tok| tok.as_str(js)).collect();
+        assert_eq!(texts, [js, "clé", r#"[1.5, "a\"b"]"#, "1.5", r#"a\"b"#]);
+
+        assert_eq!(tokens[1].try_as_str(js), Some("clé"));
+        let inside_e = JsmnTok {
+            end: 5,
+            ..tokens[1]
+        };
+        assert_eq!(inside_e.try_as_str(js), None);
+        assert_eq!(tokens[0].try_as_str("{}"), None);
+        assert_eq!(JsmnTok::default().try_as_str(js), Some(""));
+        let open = JsmnTok {
+            end: -1,
+            ..tokens[0]
+        };
+        assert_eq!(open.try_as_str(js), None);
+    }
+
+    #[test]
+    fn parse_unchecked_bytes() {
+        let js = b"{\"a\xff\": [1, \"\xc3\"]}";
+        let mut tokens = [JsmnTok::default(); 5];
+        assert_eq!(
+            jsmn_parse_bytes(&mut JsmnParser::new(), js, &mut tokens),
+            Ok(5)
+        );
+        assert_eq!(
+            &js[tokens[1].start as usize..tokens[1].end as usize],
+            b"a\xff"
+        );
+        assert_eq!((tokens[4].start, tokens[4].end), (12, 13));
+
+        let mut tokens = Vec::new();
+        assert_eq!(
+            jsmn_parse_vec_bytes(&mut JsmnParser::new(), js, &mut tokens),
+            Ok(5)
+        );
+        assert_eq!(tokens[2].size, 2);
+    }
 }
@@ -0,0 +1,329 @@
+//!
+//! This module parses documents that may come from someone hostile. jsmn
+//! itself copes with anything, as it neither recurses nor allocates, but
+//! the code walking the tokens afterwards often recurses into objects and
+//! arrays, and a request body of `[[[[...` a few megabytes long is enough
+//! to overflow its stack. `ParseOptions` rejects such documents up front,
+//! along with documents too long or with too many tokens to be worth the
+//! time or the memory.
+//!
+//! The options also choose between jsmn's strict and lenient modes for
+//! each parse, which the strict feature can only do for the whole build.
+//! A library can thus be strict with its own input without imposing it on
+//! the rest of the program.
+//!
+//! The depth is checked with a quick scan of the input before jsmn runs,
+//! following jsmn's reading of strings and primitives, since jsmn slows
+//! down on deep nesting too: closing an object or array looks back through
+//! the tokens for the one it closes, which makes `[[[[...` quadratic.
+//!
+
+use std::error::Error;
+use std::fmt;
+
+use crate::{JsmnErr, JsmnParser, JsmnTok, ParseError};
+
+/// The limits of [`ParseOptions`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Limit {
+    Depth,
+    Tokens,
+    Length,
+}
+
+/// Error type for parsing with limits.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LimitErr {
+    /// The document is not valid JSON.
+    Parse(ParseError),
+    /// The document goes over a limit. `pos` is the byte offset of the
+    /// object or array nested too deeply, of the first token over the
+    /// limit, or the length limit itself.
+    LimitExceeded { limit: Limit, pos: usize },
+}
+
+impl From<ParseError> for LimitErr {
+    fn from(err: ParseError) -> Self {
+        LimitErr::Parse(err)
+    }
+}
+
+impl fmt::Display for LimitErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitErr::Parse(err) => err.fmt(f),
+            LimitErr::LimitExceeded { limit, pos } => {
+                let what = match limit {
+                    Limit::Depth => "nested too deeply",
+                    Limit::Tokens => "too many tokens",
+                    Limit::Length => "too long",
+                };
+                write!(f, "{} at byte {}", what, pos)
+            }
+        }
+    }
+}
+
+impl Error for LimitErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LimitErr::Parse(err) => Some(err),
+            LimitErr::LimitExceeded { .. } => None,
+        }
+    }
+}
+
+/// Limits on the documents to accept.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ParseOptions {
+    /// The most objects and arrays that may be open at once, one inside
+    /// the other, so that `[[1]]` needs 2. With 0 only scalars are
+    /// accepted.
+    pub max_depth: usize,
+    /// The most tokens a document may have.
+    pub max_tokens: usize,
+    /// The longest a document may be, in bytes.
+    pub max_len: usize,
+    /// Whether to parse in jsmn's strict mode, which rejects primitives
+    /// other than numbers and literals, and keys that are not strings.
+    pub strict: bool,
+}
+
+impl Default for ParseOptions {
+    /// Limits the depth to 128, and nothing else, and parses in the mode
+    /// the strict feature chooses.
+    fn default() -> Self {
+        ParseOptions {
+            max_depth: 128,
+            max_tokens: usize::MAX,
+            max_len: usize::MAX,
+            strict: cfg!(feature = "strict"),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Checks `js` against the limits, then parses it into `tokens` like
+    /// [`crate::jsmn_parse`], with a parser of its own. Only the first
+    /// `max_tokens` of `tokens` are used, and running out of them is
+    /// reported as `JsmErrorNoMem` if there are fewer.
+    pub fn parse(&self, js: &str, tokens: &mut [JsmnTok]) -> Result<usize, LimitErr> {
+        self.check(js)?;
+        let len = self.max_tokens.min(tokens.len());
+        let tokens = &mut tokens[..len];
+        let mut parser = JsmnParser::new();
+        parser
+            .parse_mode(js.as_bytes(), tokens, self.strict)
+            .map_err(|err| self.error(&parser, err, tokens.len()))
+    }
+
+    /// Like [`ParseOptions::parse`], but growing `tokens` as needed like
+    /// [`crate::jsmn_parse_vec`], up to `max_tokens`.
+    pub fn parse_vec(&self, js: &str, tokens: &mut Vec<JsmnTok>) -> Result<usize, LimitErr> {
+        self.check(js)?;
+        let mut parser = JsmnParser::new();
+        let mut len = js.len() / 8 + 8;
+        tokens.clear();
+        loop {
+            tokens.resize(len.min(self.max_tokens), JsmnTok::default());
+            match parser.parse_mode(js.as_bytes(), tokens, self.strict) {
+                Ok(count) => {
+                    tokens.truncate(count);
+                    return Ok(count);
+                }
+                Err(JsmnErr::JsmErrorNoMem) if tokens.len() < self.max_tokens => {
+                    len = tokens.len() * 2;
+                }
+                Err(err) => {
+                    tokens.truncate(parser.toknext as usize);
+                    return Err(self.error(&parser, err, tokens.len()));
+                }
+            }
+        }
+    }
+
+    fn check(&self, js: &str) -> Result<(), LimitErr> {
+        let exceeded = |limit, pos| Err(LimitErr::LimitExceeded { limit, pos });
+        if js.len() > self.max_len {
+            return exceeded(Limit::Length, self.max_len);
+        }
+        match too_deep(js.as_bytes(), self.max_depth) {
+            Some(pos) => exceeded(Limit::Depth, pos),
+            None => Ok(()),
+        }
+    }
+
+    /// Describes an error from parsing with `available` tokens.
+    fn error(&self, parser: &JsmnParser, err: JsmnErr, available: usize) -> LimitErr {
+        match err {
+            JsmnErr::JsmErrorNoMem if available >= self.max_tokens => LimitErr::LimitExceeded {
+                limit: Limit::Tokens,
+                pos: parser.pos as usize,
+            },
+            _ => LimitErr::Parse(parser.error(err)),
+        }
+    }
+}
+
+/// Returns the offset of the first object or array nested deeper than
+/// `max_depth`. Brackets inside strings and primitives do not count, as
+/// jsmn takes `a[` for a primitive without the strict feature.
+fn too_deep(js: &[u8], max_depth: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut pos = 0;
+    while pos < js.len() {
+        match js[pos] {
+            b'{' | b'[' if depth == max_depth => return Some(pos),
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            b'"' => {
+                pos += 1;
+                while pos < js.len() && js[pos] != b'"' {
+                    pos += if js[pos] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'\t' | b'\r' | b'\n' | b' ' | b',' | b':' => {}
+            _ => {
+                while pos + 1 < js.len()
+                    && !matches!(
+                        js[pos + 1],
+                        b'\t' | b'\r' | b'\n' | b' ' | b',' | b':' | b']' | b'}'
+                    )
+                {
+                    pos += 1;
+                }
+            }
+        }
+        pos += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nesting_depth() {
+        let options = ParseOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let mut tokens = Vec::new();
+        for js in [
+            "[[1], []]",
+            r#"{"a": {"b": 1, "c": "[[{"}, "d": [2]}"#,
+            "\"s\"",
+            "[]",
+        ] {
+            assert!(options.parse_vec(js, &mut tokens).is_ok(), "{}", js);
+        }
+        for (js, pos) in [
+            ("[[[]]]", 2),
+            (r#"{"a": [1, {"b": 2}]}"#, 10),
+            (r#"[{"a": 1}, [2, [3]]]"#, 15),
+            (r#"{"a\"": {"b": []}}"#, 14),
+        ] {
+            assert_eq!(
+                options.parse_vec(js, &mut tokens),
+                Err(LimitErr::LimitExceeded {
+                    limit: Limit::Depth,
+                    pos
+                }),
+                "{}",
+                js
+            );
+        }
+
+        let options = ParseOptions {
+            max_depth: 0,
+            ..Default::default()
+        };
+        assert!(options.parse_vec("\"s\"", &mut tokens).is_ok());
+        assert_eq!(
+            options.parse_vec("[]", &mut tokens),
+            Err(LimitErr::LimitExceeded {
+                limit: Limit::Depth,
+                pos: 0
+            })
+        );
+    }
+
+    #[test]
+    fn hostile_input() {
+        let js = "[".repeat(1_000_000) + &"]".repeat(1_000_000);
+        let mut tokens = vec![JsmnTok::default(); 1_000_000];
+        let err = ParseOptions::default().parse(&js, &mut tokens).unwrap_err();
+        assert_eq!(
+            err,
+            LimitErr::LimitExceeded {
+                limit: Limit::Depth,
+                pos: 128
+            }
+        );
+        assert_eq!(err.to_string(), "nested too deeply at byte 128");
+
+        let mut tokens = [JsmnTok::default(); 2];
+        match ParseOptions::default().parse("[1, 2]", &mut tokens) {
+            Err(LimitErr::Parse(err)) => assert_eq!(err.kind, JsmnErr::JsmErrorNoMem),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn token_and_length_limits() {
+        let options = ParseOptions {
+            max_tokens: 4,
+            max_len: 20,
+            ..Default::default()
+        };
+        let exceeded = |limit, pos| Err(LimitErr::LimitExceeded { limit, pos });
+
+        let mut tokens = Vec::new();
+        assert_eq!(options.parse_vec(r#"{"a": [1]}"#, &mut tokens), Ok(4));
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(
+            options.parse_vec(r#"{"a": [1, 2]}"#, &mut tokens),
+            exceeded(Limit::Tokens, 10)
+        );
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(
+            options.parse_vec(r#"{"a": [1, 2, 3, 4, 5]}"#, &mut tokens),
+            exceeded(Limit::Length, 20)
+        );
+
+        let mut pool = [JsmnTok::default(); 8];
+        assert_eq!(
+            options.parse(r#"[1, 2, 3, 4]"#, &mut pool),
+            exceeded(Limit::Tokens, 10)
+        );
+        match options.parse("[1, 2]", &mut pool[..2]) {
+            Err(LimitErr::Parse(err)) => assert_eq!(err.kind, JsmnErr::JsmErrorNoMem),
+            other => panic!("unexpected {:?}", other),
+        }
+        let err = options.parse("[1, 2, 3, 4]", &mut pool).unwrap_err();
+        assert_eq!(err.to_string(), "too many tokens at byte 10");
+    }
+
+    #[test]
+    fn strictness() {
+        let lenient = ParseOptions {
+            strict: false,
+            ..Default::default()
+        };
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let mut tokens = Vec::new();
+        assert_eq!(lenient.parse_vec("{a: yes}", &mut tokens), Ok(3));
+        match strict.parse_vec("{a: yes}", &mut tokens) {
+            Err(LimitErr::Parse(err)) => {
+                assert_eq!((err.kind, err.pos), (JsmnErr::JsmErrorInval, 1))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(strict.parse_vec(r#"{"a": true}"#, &mut tokens), Ok(3));
+        assert_eq!(ParseOptions::default().strict, cfg!(feature = "strict"));
+    }
+}
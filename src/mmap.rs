@@ -0,0 +1,188 @@
+//!
+//! This module parses files without reading them into a buffer first,
+//! behind the "mmap" feature. `parse_file` maps the file into memory and
+//! tokenizes it in place, and the `ParsedFile` it returns owns both the
+//! mapping and the tokens, so that values borrowed from it can never
+//! outlive the text they point into. Tools going through many large log
+//! or dump files get each one with a single call and no buffers to size.
+//!
+//! The text must be UTF-8, which is checked once when the file is parsed.
+//! As with any memory map, the file should not be changed while it is
+//! mapped: another process truncating or rewriting it can make reads fail
+//! or see the new contents, which no check done up front can prevent.
+//!
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::pointer::PointerErr;
+use crate::value::Value;
+use crate::{JsmnParser, JsmnTok, ParseError, jsmn_parse_vec};
+
+/// Error type for parsing files.
+#[derive(Debug)]
+pub enum FileErr {
+    /// Opening or mapping the file failed.
+    Io(io::Error),
+    /// The file is not UTF-8, from byte `pos` on.
+    NotUtf8 { pos: usize },
+    /// The file is not valid JSON.
+    Parse(ParseError),
+}
+
+impl From<io::Error> for FileErr {
+    fn from(err: io::Error) -> Self {
+        FileErr::Io(err)
+    }
+}
+
+impl From<ParseError> for FileErr {
+    fn from(err: ParseError) -> Self {
+        FileErr::Parse(err)
+    }
+}
+
+impl fmt::Display for FileErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileErr::Io(err) => write!(f, "I/O error: {}", err),
+            FileErr::NotUtf8 { pos } => write!(f, "invalid UTF-8 at byte {}", pos),
+            FileErr::Parse(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for FileErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FileErr::Io(err) => Some(err),
+            FileErr::Parse(err) => Some(err),
+            FileErr::NotUtf8 { .. } => None,
+        }
+    }
+}
+
+/// A file mapped into memory along with its tokens.
+#[derive(Debug)]
+pub struct ParsedFile {
+    map: Option<Mmap>,
+    tokens: Vec<JsmnTok>,
+}
+
+/// Maps the file at `path` and parses it.
+pub fn parse_file(path: impl AsRef<Path>) -> Result<ParsedFile, FileErr> {
+    let file = File::open(path)?;
+    // Empty files cannot be mapped on every platform, and need no mapping.
+    let map = match file.metadata()?.len() {
+        0 => None,
+        // Safety: the mapping is only ever read, and the module docs tell
+        // callers not to change the file while it is mapped.
+        _ => Some(unsafe { Mmap::map(&file)? }),
+    };
+
+    let bytes = map.as_deref().unwrap_or_default();
+    let js = std::str::from_utf8(bytes).map_err(|err| FileErr::NotUtf8 {
+        pos: err.valid_up_to(),
+    })?;
+    let mut parser = JsmnParser::new();
+    let mut tokens = Vec::new();
+    jsmn_parse_vec(&mut parser, js, &mut tokens).map_err(|err| parser.error(err))?;
+    Ok(ParsedFile { map, tokens })
+}
+
+impl ParsedFile {
+    /// The text of the file.
+    pub fn text(&self) -> &str {
+        let bytes = self.map.as_deref().unwrap_or_default();
+        // Checked in parse_file.
+        unsafe { std::str::from_utf8_unchecked(bytes) }
+    }
+
+    pub fn tokens(&self) -> &[JsmnTok] {
+        &self.tokens
+    }
+
+    /// Returns the value of the token at `idx`.
+    pub fn value(&self, idx: usize) -> Option<Value<'_>> {
+        Value::new(self.text(), &self.tokens, idx)
+    }
+
+    /// Returns the first root of the file, or None if it is empty.
+    pub fn root(&self) -> Option<Value<'_>> {
+        self.value(0)
+    }
+
+    /// Returns the value at a JSON Pointer from the first root.
+    pub fn pointer(&self, pointer: &str) -> Result<Option<Value<'_>>, PointerErr> {
+        match self.root() {
+            Some(root) => root.pointer(pointer),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsmnErr;
+    use std::path::PathBuf;
+
+    /// A file in the temporary directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("jsmn-{}-{}", std::process::id(), name));
+            std::fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn parse_files() {
+        let file = TempFile::new("ok.json", r#"{"level": "info", "ids": [1, 2]}"#.as_bytes());
+        let parsed = parse_file(&file.0).unwrap();
+        assert_eq!(parsed.tokens().len(), 7);
+        assert_eq!(
+            parsed.pointer("/level").unwrap().and_then(|v| v.as_str()),
+            Some("info".into())
+        );
+        assert_eq!(parsed.value(6).and_then(|v| v.as_i64()), Some(2));
+
+        let file = TempFile::new("empty.json", b"");
+        let parsed = parse_file(&file.0).unwrap();
+        assert_eq!((parsed.text(), parsed.root()), ("", None));
+    }
+
+    #[test]
+    fn errors() {
+        let file = TempFile::new("part.json", b"[1, 2");
+        assert!(matches!(
+            parse_file(&file.0),
+            Err(FileErr::Parse(ParseError {
+                kind: JsmnErr::JsmErrorPart,
+                ..
+            }))
+        ));
+
+        let file = TempFile::new("latin1.json", b"[\"caf\xe9\"]");
+        assert!(matches!(
+            parse_file(&file.0),
+            Err(FileErr::NotUtf8 { pos: 5 })
+        ));
+
+        let missing = std::env::temp_dir().join("jsmn-missing-file.json");
+        assert!(matches!(parse_file(missing), Err(FileErr::Io(_))));
+    }
+}
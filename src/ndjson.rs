@@ -0,0 +1,501 @@
+//!
+//! This module reads newline-delimited JSON (NDJSON), where every line of
+//! the input holds one complete document, and tokenizes the records one at
+//! a time so the whole stream never has to be held in memory.
+//!
+//! Blank lines are skipped, and both `\n` and `\r\n` line endings are
+//! accepted. A line that is not a single valid document is reported as an
+//! error, and reading goes on with the next line.
+//!
+//! When the whole input is already in memory, `NdjsonLines` goes over it
+//! without allocating for every record: one parser and one token vector
+//! serve all the lines, and each line is handed out with a borrow of the
+//! tokens that lasts until the next one is parsed. Only a line holding a
+//! lone string, number or literal is copied, to be parsed inside an array.
+//!
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hasher;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::decode::unescape;
+use crate::hash::{Xxh64, xxh64};
+use crate::pointer::{KeyMatch, PointerErr, parse_pointer, resolve};
+use crate::scan::{ScanErr, skip_whitespace, tokenize_value, value_end};
+use crate::stream::StreamError;
+use crate::{JsmnErr, JsmnParser, JsmnTok, JsmnType, jsmn_parse_vec};
+
+/// Which bytes of a record its hash is computed over.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HashMode {
+    /// The line exactly as it was read, without its line ending.
+    Raw,
+    /// The line with all insignificant whitespace left out, so records that
+    /// only differ in formatting get the same hash. Key order and number
+    /// spelling still matter.
+    Compact,
+}
+
+/// One record of an NDJSON stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The byte offset of the start of the line in the input.
+    pub offset: usize,
+    /// The line, without its line ending.
+    pub text: String,
+    /// The tokens of the document on this line, with offsets into `text`.
+    pub tokens: Vec<JsmnTok>,
+    /// The XXH64 hash of the record, if hashing was requested.
+    pub hash: Option<u64>,
+}
+
+/// An iterator over the records of an NDJSON stream.
+///
+/// A line that fails to parse is reported and skipped, and the iterator
+/// goes on with the next line, as [`NdjsonLines`] does. It stops after an
+/// error reading the input.
+pub struct NdjsonReader<R> {
+    input: R,
+    hash: Option<HashMode>,
+    offset: usize,
+    parser: JsmnParser,
+    done: bool,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    pub fn new(input: R) -> Self {
+        NdjsonReader {
+            input,
+            hash: None,
+            offset: 0,
+            parser: JsmnParser::new(),
+            done: false,
+        }
+    }
+
+    /// Computes a hash of every record while it is read, to help spot
+    /// duplicates without going over the data a second time.
+    pub fn with_hash(mut self, mode: HashMode) -> Self {
+        self.hash = Some(mode);
+        self
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, StreamError> {
+        let mut text = String::new();
+        loop {
+            text.clear();
+            let offset = self.offset;
+            let read = self.input.read_line(&mut text)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.offset += read;
+
+            let len = text.trim_end_matches(['\n', '\r']).len();
+            text.truncate(len);
+            let mut tokens = Vec::new();
+            if !parse_line(&mut self.parser, &text, offset, &mut tokens)? {
+                continue;
+            }
+            let hash = self.hash.map(|mode| hash_record(&text, mode));
+            return Ok(Some(Record {
+                offset,
+                text,
+                tokens,
+                hash,
+            }));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<Record, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record().transpose();
+        self.done = matches!(record, None | Some(Err(StreamError::Io(_))));
+        record
+    }
+}
+
+/// One record of an NDJSON buffer, see [`NdjsonLines`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Line<'a, 't> {
+    /// The byte offset of the start of the line in the buffer.
+    pub offset: usize,
+    /// The line, without its line ending.
+    pub text: &'a str,
+    /// The tokens of the document on this line, with offsets into `text`.
+    pub tokens: &'t [JsmnTok],
+}
+
+/// Goes over the records of an NDJSON buffer, reusing the same parser and
+/// token vector for every line.
+///
+/// As the tokens of a line are overwritten by the next one, this is not an
+/// `Iterator`: call `next_line` in a `while let` loop instead. A line that
+/// fails to parse is reported and skipped, and the following call goes on
+/// with the next line, as [`NdjsonReader`] does.
+#[derive(Debug, Clone)]
+pub struct NdjsonLines<'a> {
+    buf: &'a str,
+    pos: usize,
+    parser: JsmnParser,
+    tokens: Vec<JsmnTok>,
+}
+
+impl<'a> NdjsonLines<'a> {
+    pub fn new(buf: &'a str) -> Self {
+        NdjsonLines {
+            buf,
+            pos: 0,
+            parser: JsmnParser::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    /// Parses the next record, or returns None at the end of the buffer.
+    pub fn next_line(&mut self) -> Option<Result<Line<'a, '_>, StreamError>> {
+        loop {
+            let offset = self.pos;
+            if offset >= self.buf.len() {
+                return None;
+            }
+            let rest = &self.buf[offset..];
+            let with_ending = rest.find('\n').map_or(rest.len(), |i| i + 1);
+            self.pos += with_ending;
+
+            let text = rest[..with_ending].trim_end_matches(['\n', '\r']);
+            match parse_line(&mut self.parser, text, offset, &mut self.tokens) {
+                Ok(false) => continue,
+                Ok(true) => {}
+                Err(err) => return Some(Err(err)),
+            }
+            return Some(Ok(Line {
+                offset,
+                text,
+                tokens: &self.tokens,
+            }));
+        }
+    }
+}
+
+/// Error type for [`collect_column`] and [`collect_column_as`].
+#[derive(Debug)]
+pub enum ColumnError {
+    /// The pointer is malformed.
+    Pointer(PointerErr),
+    /// Reading or parsing the stream failed.
+    Stream(StreamError),
+    /// The value in the record starting at byte `offset` of the input could
+    /// not be converted.
+    Convert { offset: usize },
+}
+
+impl From<PointerErr> for ColumnError {
+    fn from(err: PointerErr) -> Self {
+        ColumnError::Pointer(err)
+    }
+}
+
+impl From<StreamError> for ColumnError {
+    fn from(err: StreamError) -> Self {
+        ColumnError::Stream(err)
+    }
+}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnError::Pointer(err) => write!(f, "invalid pointer: {:?}", err),
+            ColumnError::Stream(err) => write!(f, "{}", err),
+            ColumnError::Convert { offset } => {
+                write!(
+                    f,
+                    "cannot convert the value of the record at byte {}",
+                    offset
+                )
+            }
+        }
+    }
+}
+
+impl Error for ColumnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ColumnError::Stream(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Gathers the numbers at `pointer` in every record of an NDJSON stream.
+/// See [`collect_column_as`] for the details.
+pub fn collect_column<R: BufRead>(input: R, pointer: &str) -> Result<Vec<f64>, ColumnError> {
+    collect_column_as(input, pointer)
+}
+
+/// Gathers the values at `pointer` in every record of an NDJSON stream,
+/// converted with `FromStr`. Primitives are converted from their text and
+/// strings from their decoded contents, so numbers written as strings are
+/// fine too. Records without a value at the pointer, or where it is `null`,
+/// are skipped, and objects and arrays cannot be converted.
+pub fn collect_column_as<T: FromStr, R: BufRead>(
+    input: R,
+    pointer: &str,
+) -> Result<Vec<T>, ColumnError> {
+    let pointer = parse_pointer(pointer)?;
+    let mut values = Vec::new();
+    for record in NdjsonReader::new(input) {
+        let record = record?;
+        let Some(idx) = resolve(&record.text, &record.tokens, 0, &pointer, KeyMatch::Exact) else {
+            continue;
+        };
+
+        let tok = &record.tokens[idx];
+        let text = &record.text[tok.start as usize..tok.end as usize];
+        let value = match tok.typ {
+            JsmnType::JsmnPrimitive if text == "null" => continue,
+            JsmnType::JsmnPrimitive => text.parse().ok(),
+            JsmnType::JsmnString => unescape(text).parse().ok(),
+            _ => None,
+        };
+        match value {
+            Some(value) => values.push(value),
+            None => {
+                return Err(ColumnError::Convert {
+                    offset: record.offset,
+                });
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Tokenizes the single document of a line, without its line ending, into
+/// `tokens`, which are replaced. Returns false for a blank line. Errors are
+/// placed in the input by `offset`, the start of the line.
+///
+/// Both readers go through here, so they accept and reject the same lines.
+/// jsmn wants a delimiter after a top-level primitive in strict mode, so
+/// scalars go through the scan module, which parses them inside an array.
+fn parse_line(
+    parser: &mut JsmnParser,
+    text: &str,
+    offset: usize,
+    tokens: &mut Vec<JsmnTok>,
+) -> Result<bool, StreamError> {
+    let error = |err, pos| StreamError::Parse {
+        err,
+        pos: offset + pos,
+    };
+    let bytes = text.as_bytes();
+    let start = skip_whitespace(bytes, 0);
+    if start == bytes.len() {
+        return Ok(false);
+    }
+    let end = value_end(bytes, start).map_err(|err| match err {
+        ScanErr::Parse { err, pos } => error(err, pos),
+        other => unreachable!("unexpected scan error {:?}", other),
+    })?;
+    let rest = skip_whitespace(bytes, end);
+    if rest != bytes.len() {
+        return Err(error(JsmnErr::JsmErrorInval, rest));
+    }
+
+    tokens.clear();
+    let scanned = match bytes[start] {
+        b'{' | b'[' => {
+            parser.reset();
+            return match jsmn_parse_vec(parser, text, tokens) {
+                Ok(_) => Ok(true),
+                Err(err) => Err(error(err, parser.pos as usize)),
+            };
+        }
+        _ => tokenize_value(text, start..end, -1, tokens),
+    };
+    match scanned {
+        Ok(()) => Ok(true),
+        Err(ScanErr::Parse { err, pos }) => Err(error(err, pos)),
+        Err(other) => unreachable!("unexpected scan error {:?}", other),
+    }
+}
+
+fn hash_record(text: &str, mode: HashMode) -> u64 {
+    match mode {
+        HashMode::Raw => xxh64(text.as_bytes(), 0),
+        HashMode::Compact => {
+            let mut hasher = Xxh64::default();
+            let bytes = text.as_bytes();
+            let mut in_string = false;
+            let mut escaped = false;
+            let mut run = 0;
+            for (i, &b) in bytes.iter().enumerate() {
+                let skip = !in_string && matches!(b, b' ' | b'\t' | b'\n' | b'\r');
+                if in_string {
+                    match (escaped, b) {
+                        (true, _) => escaped = false,
+                        (false, b'\\') => escaped = true,
+                        (false, b'"') => in_string = false,
+                        _ => {}
+                    }
+                } else if b == b'"' {
+                    in_string = true;
+                }
+                if skip {
+                    hasher.write(&bytes[run..i]);
+                    run = i + 1;
+                }
+            }
+            hasher.write(&bytes[run..]);
+            hasher.finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::JsmnType;
+
+    #[test]
+    fn read_records() {
+        let input = "{\"a\": 1}\r\n\n  [1, 2]  \n\"s\"\n3";
+        let records: Vec<_> = NdjsonReader::new(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].text, "{\"a\": 1}");
+        assert_eq!(records[0].tokens.len(), 3);
+        assert_eq!((records[1].offset, records[1].tokens[0].start), (11, 2));
+        assert_eq!(records[2].tokens[0].typ, JsmnType::JsmnString);
+        assert_eq!(records[3].tokens[0].typ, JsmnType::JsmnPrimitive);
+        assert!(records.iter().all(|r| r.hash.is_none()));
+    }
+
+    #[test]
+    fn hash_records() {
+        let input = "{\"a\": \"x y\"}\n{\"a\":\"x y\"}\n{\"a\":\"xy\"}\n";
+        let raw: Vec<_> = NdjsonReader::new(input.as_bytes())
+            .with_hash(HashMode::Raw)
+            .map(|r| r.unwrap().hash.unwrap())
+            .collect();
+        assert_eq!(raw[0], xxh64(b"{\"a\": \"x y\"}", 0));
+        assert_ne!(raw[0], raw[1]);
+
+        let compact: Vec<_> = NdjsonReader::new(input.as_bytes())
+            .with_hash(HashMode::Compact)
+            .map(|r| r.unwrap().hash.unwrap())
+            .collect();
+        assert_eq!(compact[0], raw[1]);
+        assert_eq!(compact[0], compact[1]);
+        assert_ne!(compact[1], compact[2]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut reader = NdjsonReader::new("[1]\n{\"a\": 1]\n[2]\n".as_bytes());
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next() {
+            Some(Err(StreamError::Parse { err, pos })) => {
+                assert_eq!((err, pos), (JsmnErr::JsmErrorInval, 11));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(reader.next().unwrap().unwrap().text, "[2]");
+        assert!(reader.next().is_none());
+
+        let mut reader = NdjsonReader::new("[1] [2]\n".as_bytes());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(StreamError::Parse { pos: 4, .. }))
+        ));
+    }
+
+    #[test]
+    fn lines_of_a_buffer() {
+        let input = "{\"a\": [1, 2]}\r\n\n  \"s\"  \n{\"a\": 1]\n[1] [2]\n3\n";
+        let mut lines = NdjsonLines::new(input);
+        let error = |lines: &mut NdjsonLines| match lines.next_line() {
+            Some(Err(StreamError::Parse { err, pos })) => (err, pos),
+            other => panic!("unexpected {:?}", other),
+        };
+
+        let line = lines.next_line().unwrap().unwrap();
+        assert_eq!(line.text, "{\"a\": [1, 2]}");
+        assert_eq!((line.offset, line.tokens.len()), (0, 5));
+        let line = lines.next_line().unwrap().unwrap();
+        let tok = line.tokens[0];
+        assert_eq!(
+            (line.offset, tok.typ, tok.start),
+            (16, JsmnType::JsmnString, 3)
+        );
+
+        assert_eq!(error(&mut lines), (JsmnErr::JsmErrorInval, 31));
+        assert_eq!(error(&mut lines), (JsmnErr::JsmErrorInval, 37));
+        let line = lines.next_line().unwrap().unwrap();
+        assert_eq!((line.text, line.tokens[0].end), ("3", 1));
+        assert!(lines.next_line().is_none());
+    }
+
+    #[test]
+    fn readers_agree() {
+        let input = "[1]\n{\"a\": 1]\n  \"s\"\n{\"a\": [}\n[1] [2]\n\n-2.5\n \"\\q\"";
+        let records: Vec<_> = NdjsonReader::new(input.as_bytes())
+            .map(|record| record.map(|r| (r.offset, r.text, r.tokens)))
+            .collect();
+
+        let mut lines = NdjsonLines::new(input);
+        let mut expected = Vec::new();
+        while let Some(line) = lines.next_line() {
+            expected.push(line.map(|l| (l.offset, l.text.to_string(), l.tokens.to_vec())));
+        }
+        assert_eq!(records.len(), 7);
+        assert_eq!(format!("{:?}", records), format!("{:?}", expected));
+        let failed: Vec<_> = (0..7).filter(|&i| records[i].is_err()).collect();
+        assert_eq!(failed, [1, 3, 4, 6]);
+        assert!(matches!(
+            records[6],
+            Err(StreamError::Parse {
+                err: JsmnErr::JsmErrorInval,
+                pos: 43
+            })
+        ));
+    }
+
+    #[test]
+    fn collect_columns() {
+        let input = concat!(
+            "{\"metrics\": {\"cpu\": 0.5, \"host\": \"a\"}}\n",
+            "{\"metrics\": {\"cpu\": null}}\n",
+            "{\"other\": 1}\n",
+            "{\"metrics\": {\"cpu\": \"2\", \"host\": \"b\\u00e9\"}}\n",
+        );
+        assert_eq!(
+            collect_column(input.as_bytes(), "/metrics/cpu").unwrap(),
+            [0.5, 2.0]
+        );
+        assert_eq!(
+            collect_column_as::<String, _>(input.as_bytes(), "/metrics/host").unwrap(),
+            ["a", "b\u{e9}"]
+        );
+        assert!(matches!(
+            collect_column_as::<u32, _>(input.as_bytes(), "/metrics/cpu"),
+            Err(ColumnError::Convert { offset: 0 })
+        ));
+        assert!(matches!(
+            collect_column(input.as_bytes(), "/metrics"),
+            Err(ColumnError::Convert { offset: 0 })
+        ));
+        assert!(matches!(
+            collect_column(input.as_bytes(), "metrics"),
+            Err(ColumnError::Pointer(PointerErr::MissingSlash))
+        ));
+    }
+}
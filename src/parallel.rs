@@ -0,0 +1,67 @@
+//!
+//! This module parses many independent documents at once, as an ingest
+//! pipeline does with the records it receives. Each document gets a parser
+//! and a token vector of its own, so nothing is shared between them and
+//! they can be parsed in any order.
+//!
+//! With the "rayon" feature the documents are spread over rayon's thread
+//! pool, and without it they are parsed one after the other on the calling
+//! thread. The results are the same either way, in the order of the
+//! documents.
+//!
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{JsmnParser, JsmnTok, ParseError, jsmn_parse_vec};
+
+/// Parses every document of `docs` into a vector of tokens, or the error
+/// that stopped it, in the same order as `docs`.
+pub fn parse_batch(docs: &[&str]) -> Vec<Result<Vec<JsmnTok>, ParseError>> {
+    #[cfg(feature = "rayon")]
+    let docs = docs.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let docs = docs.iter();
+    docs.map(|js| parse_one(js)).collect()
+}
+
+fn parse_one(js: &str) -> Result<Vec<JsmnTok>, ParseError> {
+    let mut parser = JsmnParser::new();
+    let mut tokens = Vec::new();
+    jsmn_parse_vec(&mut parser, js, &mut tokens).map_err(|err| parser.error(err))?;
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsmnErr, parse_to_vec};
+
+    #[test]
+    fn results_in_order() {
+        let docs: Vec<String> = (0..100)
+            .map(|i| format!(r#"{{"id": {}, "tags": [{}]}}"#, i, "1, ".repeat(i) + "0"))
+            .collect();
+        let docs: Vec<&str> = docs.iter().map(String::as_str).collect();
+        let results = parse_batch(&docs);
+        assert_eq!(results.len(), 100);
+        for (i, (js, result)) in docs.iter().zip(&results).enumerate() {
+            let tokens = result.as_ref().unwrap();
+            assert_eq!(tokens.len(), i + 6);
+            assert_eq!(tokens, &parse_to_vec(js).unwrap());
+        }
+        assert!(parse_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn errors_stay_with_their_document() {
+        let results = parse_batch(&["[1]", "[1, 2", r#"{"a": 1]"#, "[]"]);
+        assert_eq!(results[0].as_ref().map(Vec::len), Ok(2));
+        let kinds: Vec<_> = results[1..3]
+            .iter()
+            .map(|result| result.as_ref().unwrap_err().kind)
+            .collect();
+        assert_eq!(kinds, [JsmnErr::JsmErrorPart, JsmnErr::JsmErrorInval]);
+        assert_eq!(results[3].as_ref().map(Vec::len), Ok(1));
+    }
+}
@@ -0,0 +1,676 @@
+//!
+//! This module applies patches to documents held in memory, working from
+//! the tokens of both the document and the patch.
+//!
+//! A JSON Merge Patch, RFC 7386, is a document shaped like the target: its
+//! members replace the target's members with the same keys, objects in it
+//! are merged into the target's objects recursively, and `null` removes a
+//! member. Any other patch replaces the whole target. Values taken from
+//! either input are copied as they were written, while the objects being
+//! merged are written out again without whitespace.
+//!
+//! A JSON Patch, RFC 6902, is an array of operations, each of which adds,
+//! removes, replaces, moves, copies or tests the value at a JSON Pointer.
+//! They are applied one after the other by splicing the text of the
+//! document, so everything they do not touch keeps its formatting, and a
+//! patch that fails part way leaves nothing applied. A patch can also be
+//! generated from the differences the diff module finds between two
+//! versions of a document.
+//!
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use std::ops::Range;
+
+use crate::decode::unescape;
+use crate::diff::{DiffKind, diff};
+use crate::pointer::{KeyMatch, PathItem, parse_pointer, resolve, token_matches};
+use crate::ser::write_escaped;
+use crate::tree::{children, subtree_end, value_span};
+use crate::value::is_number;
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Error type for applying patches.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PatchErr {
+    /// The document or the patch is not valid JSON.
+    Parse(JsmnErr),
+    /// The document or the patch does not have exactly one value.
+    NotOneRoot,
+    /// The JSON Patch is not an array.
+    NotAnArray,
+    /// The JSON Patch operation at this index is not an object with a
+    /// known `op` and the members it needs, has a malformed pointer, or
+    /// moves a value into itself.
+    InvalidOperation { index: usize },
+    /// The JSON Patch operation at this index refers to a value, or to the
+    /// parent of a value to add, that the document does not have.
+    NotFound { index: usize },
+    /// The `test` operation at this index found a different value.
+    TestFailed { index: usize },
+}
+
+impl From<JsmnErr> for PatchErr {
+    fn from(err: JsmnErr) -> Self {
+        PatchErr::Parse(err)
+    }
+}
+
+impl fmt::Display for PatchErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchErr::Parse(err) => write!(f, "{:?}", err),
+            PatchErr::NotOneRoot => write!(f, "document does not have exactly one value"),
+            PatchErr::NotAnArray => write!(f, "patch is not an array of operations"),
+            PatchErr::InvalidOperation { index } => write!(f, "invalid operation {}", index),
+            PatchErr::NotFound { index } => write!(f, "operation {} has no target", index),
+            PatchErr::TestFailed { index } => write!(f, "test operation {} failed", index),
+        }
+    }
+}
+
+impl Error for PatchErr {}
+
+/// Parses a document and a merge patch and returns the patched document.
+pub fn merge_patch(target_js: &str, patch_js: &str) -> Result<String, PatchErr> {
+    let target_tokens = parse_to_vec(target_js)?;
+    let patch_tokens = parse_to_vec(patch_js)?;
+    merge_patch_tokens(target_js, &target_tokens, patch_js, &patch_tokens)
+}
+
+/// Applies an already parsed merge patch to an already parsed document.
+pub fn merge_patch_tokens(
+    target_js: &str,
+    target_tokens: &[JsmnTok],
+    patch_js: &str,
+    patch_tokens: &[JsmnTok],
+) -> Result<String, PatchErr> {
+    let target = Doc::new(target_js, target_tokens)?;
+    let patch = Doc::new(patch_js, patch_tokens)?;
+    let mut out = String::with_capacity(target_js.len() + patch_js.len());
+    merge(Some((&target, 0)), &patch, 0, &mut out);
+    Ok(out)
+}
+
+/// A parsed document with exactly one value.
+struct Doc<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+}
+
+impl<'a> Doc<'a> {
+    fn new(js: &'a str, tokens: &'a [JsmnTok]) -> Result<Self, PatchErr> {
+        if tokens.is_empty() || subtree_end(tokens, 0) != tokens.len() {
+            return Err(PatchErr::NotOneRoot);
+        }
+        Ok(Doc { js, tokens })
+    }
+
+    fn is(&self, idx: usize, typ: JsmnType) -> bool {
+        self.tokens[idx].typ == typ
+    }
+
+    fn is_null(&self, idx: usize) -> bool {
+        self.is(idx, JsmnType::JsmnPrimitive) && self.text(idx) == "null"
+    }
+
+    fn text(&self, idx: usize) -> &'a str {
+        let tok = &self.tokens[idx];
+        &self.js[tok.start as usize..tok.end as usize]
+    }
+
+    /// Returns the text of the value at `idx`, with the quotes of a string.
+    fn raw(&self, idx: usize) -> &'a str {
+        &self.js[value_span(self.tokens, idx)]
+    }
+
+    fn quoted_key(&self, key: usize) -> &'a str {
+        let tok = &self.tokens[key];
+        &self.js[tok.start as usize - 1..tok.end as usize + 1]
+    }
+
+    /// Returns the keys of the object at `idx` whose member counts, the
+    /// last one when a key is repeated, by their decoded names.
+    fn keys(&self, idx: usize) -> HashMap<Cow<'a, str>, usize> {
+        children(self.tokens, idx)
+            .map(|key| (unescape(self.text(key)), key))
+            .collect()
+    }
+
+    /// Like `keys`, in the order of the object.
+    fn ordered_keys(&self, idx: usize) -> (Vec<usize>, HashMap<Cow<'a, str>, usize>) {
+        let names = self.keys(idx);
+        let mut keys: Vec<usize> = names.values().copied().collect();
+        keys.sort_unstable();
+        (keys, names)
+    }
+
+    fn find(&self, idx: usize, name: &str) -> Option<usize> {
+        children(self.tokens, idx)
+            .filter(|&key| unescape(self.text(key)) == name)
+            .last()
+    }
+}
+
+/// Writes the result of merging the patch value at `p` into the target
+/// value, or into nothing when the member is new.
+fn merge(target: Option<(&Doc, usize)>, patch: &Doc, p: usize, out: &mut String) {
+    if !patch.is(p, JsmnType::JsmnObject) {
+        out.push_str(patch.raw(p));
+        return;
+    }
+
+    let target = target.filter(|(doc, t)| doc.is(*t, JsmnType::JsmnObject));
+    let target_keys = target.map(|(doc, t)| doc.ordered_keys(t));
+    let (patch_keys, patch_names) = patch.ordered_keys(p);
+    let mut first = true;
+    let mut separate = |out: &mut String| {
+        if !std::mem::take(&mut first) {
+            out.push(',');
+        }
+    };
+
+    out.push('{');
+    if let (Some((doc, _)), Some((keys, _))) = (target, &target_keys) {
+        for &key in keys {
+            match patch_names.get(&unescape(doc.text(key))).copied() {
+                Some(patched) if patch.is_null(patched + 1) => {}
+                Some(patched) => {
+                    separate(out);
+                    out.push_str(doc.quoted_key(key));
+                    out.push(':');
+                    merge(Some((doc, key + 1)), patch, patched + 1, out);
+                }
+                None => {
+                    separate(out);
+                    out.push_str(doc.quoted_key(key));
+                    out.push(':');
+                    out.push_str(doc.raw(key + 1));
+                }
+            }
+        }
+    }
+    for key in patch_keys {
+        let name = unescape(patch.text(key));
+        let existing = target_keys
+            .as_ref()
+            .is_some_and(|(_, names)| names.contains_key(&name));
+        if !existing && !patch.is_null(key + 1) {
+            separate(out);
+            out.push_str(patch.quoted_key(key));
+            out.push(':');
+            merge(None, patch, key + 1, out);
+        }
+    }
+    out.push('}');
+}
+
+/// Parses a document and a JSON Patch and returns the patched document.
+pub fn apply_patch(js: &str, patch_js: &str) -> Result<String, PatchErr> {
+    let patch_tokens = parse_to_vec(patch_js)?;
+    let patch = Doc::new(patch_js, &patch_tokens)?;
+    if !patch.is(0, JsmnType::JsmnArray) {
+        return Err(PatchErr::NotAnArray);
+    }
+
+    let mut doc = js.to_string();
+    for (index, op) in children(patch.tokens, 0).enumerate() {
+        doc = apply_operation(&doc, &patch, op).map_err(|err| match err {
+            OpErr::Parse(err) => err,
+            OpErr::Invalid => PatchErr::InvalidOperation { index },
+            OpErr::NotFound => PatchErr::NotFound { index },
+            OpErr::TestFailed => PatchErr::TestFailed { index },
+        })?;
+    }
+    Ok(doc)
+}
+
+/// Parses two versions of a document and returns a JSON Patch turning the
+/// old one into the new one.
+pub fn generate_patch(old_js: &str, new_js: &str) -> Result<String, PatchErr> {
+    let old_tokens = parse_to_vec(old_js)?;
+    let new_tokens = parse_to_vec(new_js)?;
+    Ok(generate_patch_tokens(
+        old_js,
+        &old_tokens,
+        new_js,
+        &new_tokens,
+    ))
+}
+
+/// Returns a JSON Patch turning an already parsed document into another,
+/// with an `add`, `remove` or `replace` operation for each entry of their
+/// diff. Values are copied from the new document as they were written.
+pub fn generate_patch_tokens(
+    old_js: &str,
+    old_tokens: &[JsmnTok],
+    new_js: &str,
+    new_tokens: &[JsmnTok],
+) -> String {
+    let mut entries = diff(old_js, old_tokens, new_js, new_tokens);
+    // Removals come in document order, and are applied last first so that
+    // removing an array element does not shift the ones still to remove.
+    let mut i = 0;
+    while i < entries.len() {
+        let run = entries[i..]
+            .iter()
+            .take_while(|entry| entry.kind == DiffKind::Removed)
+            .count();
+        entries[i..i + run].reverse();
+        i += run.max(1);
+    }
+
+    let mut out = String::from("[");
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let op = match entry.kind {
+            DiffKind::Added => "add",
+            DiffKind::Removed => "remove",
+            DiffKind::Changed => "replace",
+        };
+        out.push_str(r#"{"op":""#);
+        out.push_str(op);
+        out.push_str(r#"","path":"#);
+        write_escaped(&mut out, &entry.path).unwrap();
+        if let Some(new) = &entry.new {
+            out.push_str(r#","value":"#);
+            out.push_str(&new_js[new.clone()]);
+        }
+        out.push('}');
+    }
+    out.push(']');
+    out
+}
+
+/// Why a single JSON Patch operation failed, before its index is known.
+enum OpErr {
+    Parse(PatchErr),
+    Invalid,
+    NotFound,
+    TestFailed,
+}
+
+impl From<PatchErr> for OpErr {
+    fn from(err: PatchErr) -> Self {
+        OpErr::Parse(err)
+    }
+}
+
+impl From<JsmnErr> for OpErr {
+    fn from(err: JsmnErr) -> Self {
+        OpErr::Parse(PatchErr::Parse(err))
+    }
+}
+
+fn apply_operation(js: &str, patch: &Doc, op: usize) -> Result<String, OpErr> {
+    if !patch.is(op, JsmnType::JsmnObject) {
+        return Err(OpErr::Invalid);
+    }
+    let member = |name: &str| patch.find(op, name).map(|key| key + 1);
+    let pointer = |name: &str| {
+        let value = member(name).ok_or(OpErr::Invalid)?;
+        if !patch.is(value, JsmnType::JsmnString) {
+            return Err(OpErr::Invalid);
+        }
+        parse_pointer(&unescape(patch.text(value))).map_err(|_| OpErr::Invalid)
+    };
+    let value = || {
+        member("value")
+            .map(|value| patch.raw(value))
+            .ok_or(OpErr::Invalid)
+    };
+    let name = member("op")
+        .filter(|&name| patch.is(name, JsmnType::JsmnString))
+        .map(|name| unescape(patch.text(name)))
+        .ok_or(OpErr::Invalid)?;
+
+    let path = pointer("path")?;
+    match &*name {
+        "add" => add(js, &path, value()?),
+        "remove" => remove(js, &path),
+        "replace" => replace(js, &path, value()?),
+        "move" => {
+            let from = pointer("from")?;
+            if path.len() > from.len() && path.starts_with(&from) {
+                return Err(OpErr::Invalid);
+            }
+            let moved = get(js, &from)?;
+            add(&remove(js, &from)?, &path, &moved)
+        }
+        "copy" => add(js, &path, &get(js, &pointer("from")?)?),
+        "test" => {
+            let tokens = parse_to_vec(js)?;
+            let doc = Doc::new(js, &tokens)?;
+            let idx = resolve(js, &tokens, 0, &path, KeyMatch::Exact).ok_or(OpErr::NotFound)?;
+            let expected = member("value").ok_or(OpErr::Invalid)?;
+            if !json_eq(&doc, idx, patch, expected) {
+                return Err(OpErr::TestFailed);
+            }
+            Ok(js.to_string())
+        }
+        _ => Err(OpErr::Invalid),
+    }
+}
+
+/// Returns the text of the value at `pointer`.
+fn get(js: &str, pointer: &[String]) -> Result<String, OpErr> {
+    let tokens = parse_to_vec(js)?;
+    Doc::new(js, &tokens)?;
+    let idx = resolve(js, &tokens, 0, pointer, KeyMatch::Exact).ok_or(OpErr::NotFound)?;
+    Ok(js[value_span(&tokens, idx)].to_string())
+}
+
+fn add(js: &str, pointer: &[String], value: &str) -> Result<String, OpErr> {
+    let tokens = parse_to_vec(js)?;
+    Doc::new(js, &tokens)?;
+    let Some((last, parent_pointer)) = pointer.split_last() else {
+        return Ok(value.to_string());
+    };
+    let parent = resolve(js, &tokens, 0, parent_pointer, KeyMatch::Exact).ok_or(OpErr::NotFound)?;
+    let items: Vec<usize> = children(&tokens, parent).collect();
+    let after = |i: usize| value_span(&tokens, items[i]).end;
+    let open = tokens[parent].start as usize + 1;
+
+    match tokens[parent].typ {
+        JsmnType::JsmnObject => {
+            if let Some(idx) = resolve(js, &tokens, 0, pointer, KeyMatch::Exact) {
+                return Ok(splice(js, value_span(&tokens, idx), value));
+            }
+            let mut member = String::new();
+            write_escaped(&mut member, last).unwrap();
+            member.push(':');
+            member.push_str(value);
+            Ok(match items.len() {
+                0 => splice(js, open..open, &member),
+                n => splice(js, after(n - 1)..after(n - 1), &format!(",{}", member)),
+            })
+        }
+        JsmnType::JsmnArray => {
+            let index = match last.as_str() {
+                "-" => items.len(),
+                _ => (0..=items.len())
+                    .find(|&i| token_matches(last, &PathItem::Index(i)))
+                    .ok_or(OpErr::NotFound)?,
+            };
+            Ok(if index < items.len() {
+                let start = value_span(&tokens, items[index]).start;
+                splice(js, start..start, &format!("{},", value))
+            } else if index > 0 {
+                splice(
+                    js,
+                    after(index - 1)..after(index - 1),
+                    &format!(",{}", value),
+                )
+            } else {
+                splice(js, open..open, value)
+            })
+        }
+        _ => Err(OpErr::NotFound),
+    }
+}
+
+fn remove(js: &str, pointer: &[String]) -> Result<String, OpErr> {
+    let tokens = parse_to_vec(js)?;
+    Doc::new(js, &tokens)?;
+    let Some((_, parent_pointer)) = pointer.split_last() else {
+        return Err(OpErr::Invalid);
+    };
+    let idx = resolve(js, &tokens, 0, pointer, KeyMatch::Exact).ok_or(OpErr::NotFound)?;
+    let parent = resolve(js, &tokens, 0, parent_pointer, KeyMatch::Exact).ok_or(OpErr::NotFound)?;
+    let object = tokens[parent].typ == JsmnType::JsmnObject;
+    let child = if object { idx - 1 } else { idx };
+    let spans: Vec<_> = children(&tokens, parent)
+        .map(|child| value_span(&tokens, child))
+        .collect();
+    let i = children(&tokens, parent)
+        .position(|c| c == child)
+        .ok_or(OpErr::NotFound)?;
+
+    // Take the comma before the value along if there is one, and the comma
+    // after it if not.
+    let cut = if i > 0 {
+        spans[i - 1].end..spans[i].end
+    } else if i + 1 < spans.len() {
+        spans[i].start..spans[i + 1].start
+    } else {
+        spans[i].clone()
+    };
+    Ok(splice(js, cut, ""))
+}
+
+fn replace(js: &str, pointer: &[String], value: &str) -> Result<String, OpErr> {
+    let tokens = parse_to_vec(js)?;
+    Doc::new(js, &tokens)?;
+    let idx = resolve(js, &tokens, 0, pointer, KeyMatch::Exact).ok_or(OpErr::NotFound)?;
+    Ok(splice(js, value_span(&tokens, idx), value))
+}
+
+fn splice(js: &str, cut: Range<usize>, text: &str) -> String {
+    let mut out = String::with_capacity(js.len() + text.len());
+    out.push_str(&js[..cut.start]);
+    out.push_str(text);
+    out.push_str(&js[cut.end..]);
+    out
+}
+
+/// Returns true if two values are equal as the `test` operation compares
+/// them: strings decoded, numbers by value, and objects whatever the order
+/// of their members.
+pub(crate) fn values_equal(
+    a_js: &str,
+    a_tokens: &[JsmnTok],
+    i: usize,
+    b_js: &str,
+    b_tokens: &[JsmnTok],
+    j: usize,
+) -> bool {
+    let a = Doc {
+        js: a_js,
+        tokens: a_tokens,
+    };
+    let b = Doc {
+        js: b_js,
+        tokens: b_tokens,
+    };
+    json_eq(&a, i, &b, j)
+}
+
+fn json_eq(a: &Doc, i: usize, b: &Doc, j: usize) -> bool {
+    let (a_tok, b_tok) = (&a.tokens[i], &b.tokens[j]);
+    if a_tok.typ != b_tok.typ {
+        return false;
+    }
+    match a_tok.typ {
+        JsmnType::JsmnObject => {
+            let (a_keys, b_keys) = (a.keys(i), b.keys(j));
+            a_keys.len() == b_keys.len()
+                && a_keys.iter().all(|(name, &key)| {
+                    b_keys
+                        .get(name)
+                        .is_some_and(|&other| json_eq(a, key + 1, b, other + 1))
+                })
+        }
+        JsmnType::JsmnArray => {
+            a_tok.size == b_tok.size
+                && children(a.tokens, i)
+                    .zip(children(b.tokens, j))
+                    .all(|(x, y)| json_eq(a, x, b, y))
+        }
+        JsmnType::JsmnString => unescape(a.text(i)) == unescape(b.text(j)),
+        _ => {
+            let (x, y) = (a.text(i), b.text(j));
+            match (is_number(x), is_number(y)) {
+                (true, true) => x.parse::<f64>().ok() == y.parse::<f64>().ok(),
+                _ => x == y,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff_str;
+
+    #[test]
+    fn rfc_examples() {
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, "null ", "null"),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+        ];
+        for (target, patch, result) in cases {
+            assert_eq!(merge_patch(target, patch).unwrap(), result, "{}", patch);
+        }
+    }
+
+    #[test]
+    fn copies_values_as_written() {
+        let target = r#"{
+  "name": "api",
+  "limits": {"cpu": 2, "mem": "1G"},
+  "hosts": [ "a", "b" ],
+  "debug": true
+}"#;
+        let patch = r#"{"limits": {"mem": null, "disk": 1e2}, "debug": null, "name": "web"}"#;
+        assert_eq!(
+            merge_patch(target, patch).unwrap(),
+            r#"{"name":"web","limits":{"cpu":2,"disk":1e2},"hosts":[ "a", "b" ]}"#
+        );
+    }
+
+    #[test]
+    fn repeated_keys_and_errors() {
+        assert_eq!(
+            merge_patch(r#"{"a": 1, "b": 2, "a": 3}"#, r#"{"b": 4}"#).unwrap(),
+            r#"{"b":4,"a":3}"#
+        );
+        assert_eq!(
+            merge_patch(r#"{"a": 1}"#, r#"{"a": null, "a": 2}"#).unwrap(),
+            r#"{"a":2}"#
+        );
+        assert_eq!(merge_patch("[1] [2]", "{}"), Err(PatchErr::NotOneRoot));
+        assert_eq!(merge_patch("{}", ""), Err(PatchErr::NotOneRoot));
+        assert_eq!(
+            merge_patch("{}", "[1"),
+            Err(PatchErr::Parse(JsmnErr::JsmErrorPart))
+        );
+    }
+
+    #[test]
+    fn apply_operations() {
+        let doc = r#"{"foo": ["bar", "baz"], "n": {"x": 1}}"#;
+        let cases = [
+            (
+                r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#,
+                r#"{"foo": ["bar", "qux","baz"], "n": {"x": 1}}"#,
+            ),
+            (
+                r#"[{"op": "add", "path": "/foo/-", "value": 1}, {"op": "add", "path": "/a~1b", "value": []}]"#,
+                r#"{"foo": ["bar", "baz",1], "n": {"x": 1},"a/b":[]}"#,
+            ),
+            (
+                r#"[{"op": "remove", "path": "/foo/0"}, {"op": "remove", "path": "/n"}]"#,
+                r#"{"foo": ["baz"]}"#,
+            ),
+            (
+                r#"[{"op": "replace", "path": "/n/x", "value": {"y": 2}}]"#,
+                r#"{"foo": ["bar", "baz"], "n": {"x": {"y": 2}}}"#,
+            ),
+            (
+                r#"[{"op": "move", "from": "/foo/0", "path": "/n/y"}]"#,
+                r#"{"foo": ["baz"], "n": {"x": 1,"y":"bar"}}"#,
+            ),
+            (
+                r#"[{"op": "copy", "from": "/n", "path": "/foo/0"}]"#,
+                r#"{"foo": [{"x": 1},"bar", "baz"], "n": {"x": 1}}"#,
+            ),
+            (
+                r#"[{"op": "test", "path": "/n", "value": {"x": 1.0}}, {"op": "add", "path": "", "value": 0}]"#,
+                "0",
+            ),
+            ("[]", doc),
+        ];
+        for (patch, result) in cases {
+            assert_eq!(apply_patch(doc, patch).unwrap(), result, "{}", patch);
+        }
+    }
+
+    #[test]
+    fn patch_errors() {
+        let doc = r#"{"a": [1, 2], "b": "x"}"#;
+        let err = |patch| apply_patch(doc, patch).unwrap_err();
+        assert_eq!(err(r#"{"op": "remove"}"#), PatchErr::NotAnArray);
+        assert_eq!(
+            err(r#"[{"op": "test", "path": "/b", "value": "x"}, {"op": "jump", "path": ""}]"#),
+            PatchErr::InvalidOperation { index: 1 }
+        );
+        assert_eq!(
+            err(r#"[{"op": "add", "path": "/a/3", "value": 0}]"#),
+            PatchErr::NotFound { index: 0 }
+        );
+        assert_eq!(
+            err(r#"[{"op": "add", "path": "/a/01", "value": 0}]"#),
+            PatchErr::NotFound { index: 0 }
+        );
+        assert_eq!(
+            err(r#"[{"op": "replace", "path": "/c", "value": 0}]"#),
+            PatchErr::NotFound { index: 0 }
+        );
+        assert_eq!(
+            err(r#"[{"op": "move", "from": "/a", "path": "/a/0"}]"#),
+            PatchErr::InvalidOperation { index: 0 }
+        );
+        assert_eq!(
+            err(r#"[{"op": "add", "path": "a", "value": 0}]"#),
+            PatchErr::InvalidOperation { index: 0 }
+        );
+        assert_eq!(
+            err(r#"[{"op": "remove", "path": "/b"}, {"op": "test", "path": "/a", "value": [1]}]"#),
+            PatchErr::TestFailed { index: 1 }
+        );
+        assert_eq!(
+            err(r#"[{"op": "test", "path": "/a"}]"#).to_string(),
+            "invalid operation 0"
+        );
+    }
+
+    #[test]
+    fn generated_patch_round_trips() {
+        let old = r#"{"name": "api", "port": 80, "hosts": ["a", "b", "c", "d"], "debug": true}"#;
+        let new = r#"{"name": "api", "port": 8080, "hosts": ["a", "x"], "tls": {"on": true}}"#;
+        let patch = generate_patch(old, new).unwrap();
+        assert_eq!(
+            patch,
+            r#"[{"op":"replace","path":"/port","value":8080},{"op":"replace","path":"/hosts/1","value":"x"},{"op":"remove","path":"/debug"},{"op":"remove","path":"/hosts/3"},{"op":"remove","path":"/hosts/2"},{"op":"add","path":"/tls","value":{"on": true}}]"#
+        );
+        let patched = apply_patch(old, &patch).unwrap();
+        assert!(diff_str(&patched, new).unwrap().is_empty());
+        assert_eq!(generate_patch(old, old).unwrap(), "[]");
+    }
+}
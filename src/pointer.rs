@@ -0,0 +1,308 @@
+//!
+//! This module handles JSON Pointers (RFC 6901) and the path patterns used
+//! to select values by their location in a document.
+//!
+//! A pointer like `/config/servers/0/port` is a list of reference tokens,
+//! where `~1` stands for `/` and `~0` for `~`. A path pattern is either such
+//! a pointer, in which a `*` token matches any single key or index, or a bare
+//! key that matches members with that name at any depth.
+//!
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
+
+use crate::decode::raw_eq;
+#[cfg(feature = "unicode-normalization")]
+use crate::decode::unescape;
+use crate::tree::children;
+use crate::{JsmnTok, JsmnType};
+
+/// One step of a concrete path into a document: an object member's key or
+/// an array element's index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathItem {
+    Key(String),
+    Index(usize),
+}
+
+/// Error type for malformed JSON Pointers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PointerErr {
+    /// A non-empty pointer must start with `/`.
+    MissingSlash,
+    /// A `~` was not followed by `0` or `1`.
+    InvalidEscape,
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens. The empty
+/// pointer refers to the whole document and has no tokens.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, PointerErr> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PointerErr::MissingSlash);
+    };
+    rest.split('/').map(unescape_token).collect()
+}
+
+fn unescape_token(token: &str) -> Result<String, PointerErr> {
+    let mut out = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => out.push('~'),
+            Some('1') => out.push('/'),
+            _ => return Err(PointerErr::InvalidEscape),
+        }
+    }
+    Ok(out)
+}
+
+/// Formats a concrete path as a JSON Pointer, escaping `~` and `/` in keys.
+pub fn to_pointer(path: &[PathItem]) -> String {
+    let mut out = String::new();
+    for item in path {
+        out.push('/');
+        match item {
+            PathItem::Key(key) => out.push_str(&key.replace('~', "~0").replace('/', "~1")),
+            PathItem::Index(index) => write!(out, "{}", index).unwrap(),
+        }
+    }
+    out
+}
+
+/// Returns true if a pointer reference token designates the given path step.
+/// Indices only match their canonical decimal spelling, so `01` is a key.
+pub(crate) fn token_matches(token: &str, item: &PathItem) -> bool {
+    match item {
+        PathItem::Key(key) => key == token,
+        PathItem::Index(index) => {
+            (token == "0" || !token.starts_with('0')) && token.parse::<usize>() == Ok(*index)
+        }
+    }
+}
+
+/// How the keys found in a document are compared with the keys being
+/// looked up.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum KeyMatch {
+    /// Keys must be made of the same code points.
+    #[default]
+    Exact,
+    /// Keys are normalized to Unicode NFC before being compared, so that
+    /// precomposed and combining spellings of the same text match.
+    #[cfg(feature = "unicode-normalization")]
+    Nfc,
+}
+
+impl KeyMatch {
+    /// Returns the form of `key` that is compared under this option.
+    pub fn normalize(self, key: Cow<'_, str>) -> Cow<'_, str> {
+        match self {
+            KeyMatch::Exact => key,
+            #[cfg(feature = "unicode-normalization")]
+            KeyMatch::Nfc if unicode_normalization::is_nfc(&key) => key,
+            #[cfg(feature = "unicode-normalization")]
+            KeyMatch::Nfc => Cow::Owned(key.nfc().collect()),
+        }
+    }
+
+    /// Returns true if both keys are the same under this option.
+    pub fn keys_equal(self, a: &str, b: &str) -> bool {
+        a == b || self.normalize(Cow::Borrowed(a)) == self.normalize(Cow::Borrowed(b))
+    }
+
+    /// Returns true if the raw span of a key, escapes included, matches a
+    /// key already normalized under this option. Exact matching never
+    /// allocates.
+    pub(crate) fn raw_matches(self, raw: &str, key: &str) -> bool {
+        match self {
+            KeyMatch::Exact => raw_eq(raw, key),
+            #[cfg(feature = "unicode-normalization")]
+            KeyMatch::Nfc => self.normalize(unescape(raw)) == key,
+        }
+    }
+}
+
+/// Follows the reference tokens of a pointer down from the token at `idx`
+/// and returns the index of the token it designates. When an object has
+/// several members with the same key, the last one wins.
+pub(crate) fn resolve(
+    js: &str,
+    tokens: &[JsmnTok],
+    mut idx: usize,
+    pointer: &[String],
+    keys: KeyMatch,
+) -> Option<usize> {
+    for token in pointer {
+        idx = match tokens.get(idx)?.typ {
+            JsmnType::JsmnObject => {
+                let token = keys.normalize(Cow::Borrowed(token));
+                children(tokens, idx)
+                    .filter(|&key| {
+                        let key = &tokens[key];
+                        keys.raw_matches(&js[key.start as usize..key.end as usize], &token)
+                    })
+                    .last()?
+                    + 1
+            }
+            JsmnType::JsmnArray => {
+                children(tokens, idx)
+                    .enumerate()
+                    .find(|&(index, _)| token_matches(token, &PathItem::Index(index)))?
+                    .1
+            }
+            _ => return None,
+        };
+    }
+    Some(idx)
+}
+
+/// Returns the token a JSON Pointer designates, starting from the first
+/// token of the slice, or None if the document has no such value. Keys are
+/// compared exactly, and when an object has several members with the same
+/// key, the last one wins. Nothing is allocated besides the unescaped
+/// reference tokens of the pointer.
+pub fn get<'t>(
+    js: &str,
+    tokens: &'t [JsmnTok],
+    pointer: &str,
+) -> Result<Option<&'t JsmnTok>, PointerErr> {
+    let pointer = parse_pointer(pointer)?;
+    Ok(resolve(js, tokens, 0, &pointer, KeyMatch::Exact).and_then(|idx| tokens.get(idx)))
+}
+
+/// A pattern selecting values by their path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathPattern {
+    /// Matches every object member with this key, at any depth.
+    Key(String),
+    /// Matches the value at exactly this pointer, where a `*` token matches
+    /// any key or index at that level.
+    Pointer(Vec<String>),
+}
+
+impl PathPattern {
+    pub fn key(key: &str) -> Self {
+        PathPattern::Key(key.to_string())
+    }
+
+    pub fn pointer(pointer: &str) -> Result<Self, PointerErr> {
+        parse_pointer(pointer).map(PathPattern::Pointer)
+    }
+
+    /// Returns true if the value at `path` is selected by this pattern.
+    pub fn matches(&self, path: &[PathItem]) -> bool {
+        match self {
+            PathPattern::Key(key) => matches!(path.last(), Some(PathItem::Key(k)) if k == key),
+            PathPattern::Pointer(tokens) => {
+                tokens.len() == path.len()
+                    && tokens
+                        .iter()
+                        .zip(path)
+                        .all(|(token, item)| token == "*" || token_matches(token, item))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(k: &str) -> PathItem {
+        PathItem::Key(k.to_string())
+    }
+
+    #[test]
+    fn parse_and_format_pointers() {
+        assert_eq!(parse_pointer(""), Ok(vec![]));
+        assert_eq!(
+            parse_pointer("/a~1b/~0c/0"),
+            Ok(vec!["a/b".into(), "~c".into(), "0".into()])
+        );
+        assert_eq!(parse_pointer("/"), Ok(vec!["".into()]));
+        assert_eq!(parse_pointer("a"), Err(PointerErr::MissingSlash));
+        assert_eq!(parse_pointer("/a~2"), Err(PointerErr::InvalidEscape));
+
+        assert_eq!(
+            to_pointer(&[key("a/b"), PathItem::Index(3), key("~")]),
+            "/a~1b/3/~0"
+        );
+    }
+
+    #[test]
+    fn pattern_matching() {
+        let path = [key("items"), PathItem::Index(10), key("id")];
+        assert!(PathPattern::key("id").matches(&path));
+        assert!(!PathPattern::key("items").matches(&path));
+        assert!(PathPattern::pointer("/items/10/id").unwrap().matches(&path));
+        assert!(PathPattern::pointer("/items/*/id").unwrap().matches(&path));
+        assert!(
+            !PathPattern::pointer("/items/010/id")
+                .unwrap()
+                .matches(&path)
+        );
+        assert!(!PathPattern::pointer("/items/*").unwrap().matches(&path));
+    }
+
+    #[test]
+    fn resolve_tokens() {
+        let js = r#"{"a": [10], "a": [20, {"b~/": true}], "c": 1}"#;
+        let tokens = crate::parse_to_vec(js).unwrap();
+        let text = |idx: usize| &js[tokens[idx].start as usize..tokens[idx].end as usize];
+        let resolve = |pointer: &str| {
+            resolve(
+                js,
+                &tokens,
+                0,
+                &parse_pointer(pointer).unwrap(),
+                KeyMatch::Exact,
+            )
+        };
+
+        assert_eq!(resolve(""), Some(0));
+        assert_eq!(resolve("/a/0").map(text), Some("20"));
+        assert_eq!(resolve("/c").map(text), Some("1"));
+        assert_eq!(resolve("/a/1/b~0~1").map(text), Some("true"));
+        assert_eq!(resolve("/a/2"), None);
+        assert_eq!(resolve("/c/0"), None);
+        assert_eq!(resolve("/x"), None);
+    }
+
+    #[test]
+    fn get_tokens() {
+        let js = r#"{"config": {"servers": [{"port": 80}, {"port": 8080}]}}"#;
+        let tokens = crate::parse_to_vec(js).unwrap();
+        let text = |tok: &JsmnTok| &js[tok.start as usize..tok.end as usize];
+
+        let port = get(js, &tokens, "/config/servers/1/port").unwrap();
+        assert_eq!(port.map(text), Some("8080"));
+        assert_eq!(get(js, &tokens, ""), Ok(tokens.first()));
+        assert_eq!(get(js, &tokens, "/config/servers/2"), Ok(None));
+        assert_eq!(get(js, &[], "/config"), Ok(None));
+        assert_eq!(get(js, &tokens, "config"), Err(PointerErr::MissingSlash));
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn nfc_key_matching() {
+        let (precomposed, combining) = ("caf\u{e9}", "cafe\u{301}");
+        assert!(!KeyMatch::Exact.keys_equal(precomposed, combining));
+        assert!(KeyMatch::Nfc.keys_equal(precomposed, combining));
+
+        let js = r#"{"cafe\u0301": 1}"#;
+        let tokens = crate::parse_to_vec(js).unwrap();
+        let pointer = [precomposed.to_string()];
+        assert_eq!(resolve(js, &tokens, 0, &pointer, KeyMatch::Exact), None);
+        assert_eq!(resolve(js, &tokens, 0, &pointer, KeyMatch::Nfc), Some(2));
+    }
+}
@@ -0,0 +1,326 @@
+//!
+//! This module parses large documents while reporting how far along the
+//! parse is, and lets the caller stop it halfway, as for a progress bar
+//! over a multi-megabyte file or a request that is no longer wanted.
+//!
+//! jsmn runs to completion once called, so the parse is split up instead:
+//! jsmn is given room for a few more tokens at a time, and every time it
+//! runs out and stops, the callback is told where it got to and decides
+//! whether to go on. Giving jsmn more room then resumes the parse where it
+//! stopped, as [`crate::jsmn_parse_vec`] does, so the only cost is one call
+//! into jsmn per step.
+//!
+//! A cancelled parse leaves the parser and the tokens as they were, so it
+//! can be resumed later by calling again with both.
+//!
+//! The same steps bound how long a parse may take, for soft real-time code
+//! that would rather drop a document than miss its next deadline: the
+//! `*_with_deadline` functions look at the clock every few hundred tokens
+//! and give up once the deadline has passed. The clock is only read
+//! between tokens, so a single very long string is read to its end first.
+//!
+
+use std::error::Error;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::time::Instant;
+
+use crate::{JsmnErr, JsmnParser, JsmnTok, ParseError};
+
+/// How far a parse has got.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Progress {
+    /// The bytes of the input read so far.
+    pub pos: usize,
+    /// The length of the input.
+    pub len: usize,
+    /// The tokens filled in so far.
+    pub tokens: usize,
+}
+
+impl Progress {
+    fn of(parser: &JsmnParser, js: &[u8]) -> Self {
+        Progress {
+            pos: parser.bytes_consumed(),
+            len: js.len(),
+            tokens: parser.tokens_emitted(),
+        }
+    }
+}
+
+/// Error type for parsing with progress reports.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProgressErr {
+    /// The document is not valid JSON.
+    Parse(ParseError),
+    /// The callback stopped the parse at this point.
+    Cancelled(Progress),
+    /// The deadline passed with the parse at this point.
+    Timeout(Progress),
+}
+
+impl From<ParseError> for ProgressErr {
+    fn from(err: ParseError) -> Self {
+        ProgressErr::Parse(err)
+    }
+}
+
+impl fmt::Display for ProgressErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgressErr::Parse(err) => err.fmt(f),
+            ProgressErr::Cancelled(progress) => write!(
+                f,
+                "cancelled at byte {} of {}, after {} tokens",
+                progress.pos, progress.len, progress.tokens
+            ),
+            ProgressErr::Timeout(progress) => write!(
+                f,
+                "timed out at byte {} of {}, after {} tokens",
+                progress.pos, progress.len, progress.tokens
+            ),
+        }
+    }
+}
+
+impl Error for ProgressErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProgressErr::Parse(err) => Some(err),
+            ProgressErr::Cancelled(_) | ProgressErr::Timeout(_) => None,
+        }
+    }
+}
+
+/// How many tokens the deadline functions parse between two looks at the
+/// clock.
+const DEADLINE_STEP: usize = 256;
+
+/// Parses `js` into `tokens` like [`crate::jsmn_parse`], calling
+/// `on_progress` every `every` tokens. Returning `ControlFlow::Break` from
+/// it stops the parse with `ProgressErr::Cancelled`.
+pub fn parse_with_progress(
+    parser: &mut JsmnParser,
+    js: &str,
+    tokens: &mut [JsmnTok],
+    every: usize,
+    mut on_progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Result<usize, ProgressErr> {
+    let js = js.as_bytes();
+    let every = every.max(1);
+    loop {
+        let room = (parser.tokens_emitted() + every).min(tokens.len());
+        match parser.parse_bytes(js, &mut tokens[..room]) {
+            Ok(count) => return Ok(count),
+            Err(JsmnErr::JsmErrorNoMem) if room < tokens.len() => {
+                let progress = Progress::of(parser, js);
+                if on_progress(progress).is_break() {
+                    return Err(ProgressErr::Cancelled(progress));
+                }
+            }
+            Err(err) => return Err(parser.error(err).into()),
+        }
+    }
+}
+
+/// Parses `js` into `tokens` like [`crate::jsmn_parse_vec`], calling
+/// `on_progress` every `every` tokens. Returning `ControlFlow::Break` from
+/// it stops the parse with `ProgressErr::Cancelled`.
+pub fn parse_vec_with_progress(
+    parser: &mut JsmnParser,
+    js: &str,
+    tokens: &mut Vec<JsmnTok>,
+    every: usize,
+    on_progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Result<usize, ProgressErr> {
+    parse_vec_bytes_with_progress(parser, js.as_bytes(), tokens, every, on_progress)
+}
+
+/// Like [`parse_vec_with_progress`], for input that is not known to be
+/// UTF-8, see [`crate::jsmn_parse_bytes`].
+pub fn parse_vec_bytes_with_progress(
+    parser: &mut JsmnParser,
+    js: &[u8],
+    tokens: &mut Vec<JsmnTok>,
+    every: usize,
+    mut on_progress: impl FnMut(Progress) -> ControlFlow<()>,
+) -> Result<usize, ProgressErr> {
+    let every = every.max(1);
+    loop {
+        let room = parser.tokens_emitted() + every;
+        if tokens.len() < room {
+            tokens.resize(room, JsmnTok::default());
+        }
+        match parser.parse_bytes(js, &mut tokens[..room]) {
+            Ok(count) => {
+                tokens.truncate(count);
+                return Ok(count);
+            }
+            Err(JsmnErr::JsmErrorNoMem) => {
+                let progress = Progress::of(parser, js);
+                if on_progress(progress).is_break() {
+                    tokens.truncate(progress.tokens);
+                    return Err(ProgressErr::Cancelled(progress));
+                }
+            }
+            Err(err) => {
+                if err == JsmnErr::JsmErrorPart {
+                    tokens.truncate(parser.tokens_emitted());
+                }
+                return Err(parser.error(err).into());
+            }
+        }
+    }
+}
+
+/// Parses `js` into `tokens` like [`crate::jsmn_parse`], but stops with
+/// `ProgressErr::Timeout` once `deadline` has passed.
+pub fn parse_with_deadline(
+    parser: &mut JsmnParser,
+    js: &str,
+    tokens: &mut [JsmnTok],
+    deadline: Instant,
+) -> Result<usize, ProgressErr> {
+    parse_with_progress(parser, js, tokens, DEADLINE_STEP, |_| until(deadline)).map_err(timeout)
+}
+
+/// Parses `js` into `tokens` like [`crate::jsmn_parse_vec`], but stops
+/// with `ProgressErr::Timeout` once `deadline` has passed.
+pub fn parse_vec_with_deadline(
+    parser: &mut JsmnParser,
+    js: &str,
+    tokens: &mut Vec<JsmnTok>,
+    deadline: Instant,
+) -> Result<usize, ProgressErr> {
+    parse_vec_with_progress(parser, js, tokens, DEADLINE_STEP, |_| until(deadline)).map_err(timeout)
+}
+
+fn until(deadline: Instant) -> ControlFlow<()> {
+    if Instant::now() < deadline {
+        ControlFlow::Continue(())
+    } else {
+        ControlFlow::Break(())
+    }
+}
+
+fn timeout(err: ProgressErr) -> ProgressErr {
+    match err {
+        ProgressErr::Cancelled(progress) => ProgressErr::Timeout(progress),
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_vec;
+
+    fn document(items: usize) -> String {
+        let items: Vec<_> = (0..items)
+            .map(|i| format!(r#"{{"id": {}, "tags": ["a", "b"]}}"#, i))
+            .collect();
+        format!("[{}]", items.join(", "))
+    }
+
+    #[test]
+    fn reports_progress() {
+        let js = document(50);
+        let mut reports = Vec::new();
+        let mut tokens = Vec::new();
+        let count = parse_vec_with_progress(&mut JsmnParser::new(), &js, &mut tokens, 64, |p| {
+            reports.push(p);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        assert_eq!(tokens, parse_to_vec(&js).unwrap());
+        assert_eq!(count, 351);
+        assert_eq!(reports.len(), 5);
+        for (i, report) in reports.iter().enumerate() {
+            assert_eq!(report.tokens, 64 * (i + 1));
+            assert_eq!(report.len, js.len());
+        }
+        assert!(reports.windows(2).all(|w| w[0].pos < w[1].pos));
+    }
+
+    #[test]
+    fn cancel_and_resume() {
+        let js = document(20);
+        let mut parser = JsmnParser::new();
+        let mut tokens = Vec::new();
+        let err = parse_vec_with_progress(&mut parser, &js, &mut tokens, 10, |p| {
+            if p.tokens >= 30 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap_err();
+
+        let ProgressErr::Cancelled(progress) = err else {
+            panic!("{:?}", err);
+        };
+        assert_eq!(progress.tokens, 30);
+        assert_eq!(tokens.len(), 30);
+        assert_eq!(parser.bytes_consumed(), progress.pos);
+        assert_eq!(parser.tokens_emitted(), 30);
+        // The last token is the fifth object, just opened.
+        assert_eq!(parser.open_token(), Some(29));
+
+        parse_vec_with_progress(&mut parser, &js, &mut tokens, 10, |_| {
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(tokens, parse_to_vec(&js).unwrap());
+        assert_eq!(parser.open_token(), None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let js = document(10);
+        let js = &js[..js.len() - 1];
+        let mut tokens = Vec::new();
+        let err = parse_vec_with_progress(&mut JsmnParser::new(), js, &mut tokens, 8, |_| {
+            ControlFlow::Continue(())
+        });
+        assert_eq!(
+            err,
+            Err(ProgressErr::Parse(ParseError {
+                kind: JsmnErr::JsmErrorPart,
+                pos: js.len(),
+                tokens: 71,
+            }))
+        );
+        assert_eq!(tokens.len(), 71);
+    }
+
+    #[test]
+    fn deadlines() {
+        use std::time::Duration;
+
+        let js = document(100);
+        let expected = parse_to_vec(&js).unwrap();
+        let mut parser = JsmnParser::new();
+        let mut tokens = vec![JsmnTok::default(); expected.len()];
+        let err = parse_with_deadline(&mut parser, &js, &mut tokens, Instant::now());
+        assert!(matches!(err, Err(ProgressErr::Timeout(p)) if p.tokens == DEADLINE_STEP));
+
+        let later = Instant::now() + Duration::from_secs(60);
+        assert_eq!(
+            parse_with_deadline(&mut parser, &js, &mut tokens, later),
+            Ok(expected.len())
+        );
+        assert_eq!(tokens, expected);
+
+        let mut tokens = vec![JsmnTok::default(); 10];
+        let err = parse_with_deadline(&mut JsmnParser::new(), &js, &mut tokens, later);
+        assert!(matches!(err, Err(ProgressErr::Parse(e)) if e.kind == JsmnErr::JsmErrorNoMem));
+
+        let mut tokens = Vec::new();
+        let mut parser = JsmnParser::new();
+        let err = parse_vec_with_deadline(&mut parser, &js, &mut tokens, Instant::now());
+        assert!(matches!(err, Err(ProgressErr::Timeout(_))));
+        parse_vec_with_deadline(&mut parser, &js, &mut tokens, later).unwrap();
+        assert_eq!(tokens, expected);
+    }
+}
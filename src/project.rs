@@ -0,0 +1,283 @@
+//!
+//! This module picks a few fields out of large documents, for services
+//! that only need `user.id` and `items[*].sku` from a payload and have no
+//! use for the rest of it. The fields are given as dotted paths, compiled
+//! once into a `Projector`, and every document is then read in one walk
+//! that only enters the objects and arrays some field goes through.
+//!
+//! A path is a list of steps: `.name` or `["name"]` selects an object
+//! member, `[3]` an array element, and `*` or `[*]` any member or element.
+//! The leading dot may be left out, and names with dots or brackets in them
+//! go in quotes, as in `headers["content.type"]`. What each field selects
+//! comes back as the byte spans of the values, quotes of strings included,
+//! in document order.
+//!
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use crate::decode::unescape;
+use crate::tree::{children, value_span};
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Error type for projections.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProjectErr {
+    /// The document is not valid JSON.
+    Parse(JsmnErr),
+    /// The path of the field at index `field` is malformed at byte `pos`.
+    InvalidPath { field: usize, pos: usize },
+}
+
+impl From<JsmnErr> for ProjectErr {
+    fn from(err: JsmnErr) -> Self {
+        ProjectErr::Parse(err)
+    }
+}
+
+impl fmt::Display for ProjectErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProjectErr::Parse(err) => write!(f, "{:?}", err),
+            ProjectErr::InvalidPath { field, pos } => {
+                write!(f, "invalid path for field {} at byte {}", field, pos)
+            }
+        }
+    }
+}
+
+impl Error for ProjectErr {}
+
+/// One step of a field path.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Any,
+}
+
+/// A set of field paths compiled into a tree of the steps they share.
+#[derive(Debug, Clone, Default)]
+pub struct Projector {
+    root: Node,
+    fields: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    keys: HashMap<String, Node>,
+    indices: HashMap<usize, Node>,
+    any: Option<Box<Node>>,
+    fields: Vec<usize>,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.keys.is_empty() && self.indices.is_empty() && self.any.is_none()
+    }
+}
+
+impl Projector {
+    /// Compiles the paths of the fields to select.
+    pub fn new(paths: &[&str]) -> Result<Self, ProjectErr> {
+        let mut root = Node::default();
+        for (field, path) in paths.iter().enumerate() {
+            let steps = parse_path(path).map_err(|pos| ProjectErr::InvalidPath { field, pos })?;
+            let mut node = &mut root;
+            for step in steps {
+                node = match step {
+                    Step::Key(key) => node.keys.entry(key).or_default(),
+                    Step::Index(index) => node.indices.entry(index).or_default(),
+                    Step::Any => node.any.get_or_insert_with(Default::default),
+                };
+            }
+            node.fields.push(field);
+        }
+        Ok(Projector {
+            root,
+            fields: paths.len(),
+        })
+    }
+
+    /// Parses `js` and returns the spans selected by each field, in the
+    /// order the fields were given.
+    pub fn project(&self, js: &str) -> Result<Vec<Vec<Range<usize>>>, JsmnErr> {
+        let tokens = parse_to_vec(js)?;
+        Ok(self.project_tokens(js, &tokens))
+    }
+
+    /// Returns the spans selected by each field in an already parsed
+    /// document. Only the first root is read.
+    pub fn project_tokens(&self, js: &str, tokens: &[JsmnTok]) -> Vec<Vec<Range<usize>>> {
+        let mut spans = vec![Vec::new(); self.fields];
+        if !tokens.is_empty() {
+            visit(&self.root, js, tokens, 0, &mut spans);
+        }
+        spans
+    }
+}
+
+/// Compiles `paths` and applies them to `js`, for a single document.
+pub fn project(js: &str, paths: &[&str]) -> Result<Vec<Vec<Range<usize>>>, ProjectErr> {
+    Ok(Projector::new(paths)?.project(js)?)
+}
+
+fn visit(node: &Node, js: &str, tokens: &[JsmnTok], idx: usize, spans: &mut [Vec<Range<usize>>]) {
+    for &field in &node.fields {
+        spans[field].push(value_span(tokens, idx));
+    }
+    if node.is_leaf() {
+        return;
+    }
+
+    match tokens[idx].typ {
+        JsmnType::JsmnObject => {
+            for key in children(tokens, idx) {
+                if key + 1 >= tokens.len() {
+                    break;
+                }
+                let tok = &tokens[key];
+                let name = unescape(&js[tok.start as usize..tok.end as usize]);
+                if let Some(child) = node.keys.get(name.as_ref()) {
+                    visit(child, js, tokens, key + 1, spans);
+                }
+                if let Some(child) = &node.any {
+                    visit(child, js, tokens, key + 1, spans);
+                }
+            }
+        }
+        JsmnType::JsmnArray => {
+            for (i, element) in children(tokens, idx).enumerate() {
+                if let Some(child) = node.indices.get(&i) {
+                    visit(child, js, tokens, element, spans);
+                }
+                if let Some(child) = &node.any {
+                    visit(child, js, tokens, element, spans);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Splits a field path into its steps, or returns the byte offset where
+/// it stops making sense.
+fn parse_path(path: &str) -> Result<Vec<Step>, usize> {
+    let mut steps = Vec::new();
+    let mut pos = 0;
+    // The first name needs no dot before it.
+    let mut name_next = !path.starts_with('[');
+    loop {
+        if name_next {
+            let len = path[pos..].find(['.', '[']).unwrap_or(path.len() - pos);
+            steps.push(match &path[pos..pos + len] {
+                "" => return Err(pos),
+                "*" => Step::Any,
+                name => Step::Key(name.to_string()),
+            });
+            pos += len;
+        }
+
+        match path.as_bytes().get(pos) {
+            None => return Ok(steps),
+            Some(b'.') => {
+                pos += 1;
+                name_next = true;
+            }
+            Some(b'[') => {
+                let end = path[pos..].find(']').ok_or(path.len())? + pos;
+                let inner = &path[pos + 1..end];
+                steps.push(match inner.as_bytes() {
+                    b"*" => Step::Any,
+                    [quote @ (b'"' | b'\''), .., last] if last == quote => {
+                        Step::Key(inner[1..inner.len() - 1].to_string())
+                    }
+                    [b'0'] | [b'1'..=b'9', ..] => Step::Index(inner.parse().map_err(|_| pos + 1)?),
+                    _ => return Err(pos + 1),
+                });
+                pos = end + 1;
+                name_next = false;
+            }
+            Some(_) => return Err(pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_paths() {
+        let key = |k: &str| Step::Key(k.to_string());
+        assert_eq!(parse_path("user.id"), Ok(vec![key("user"), key("id")]));
+        assert_eq!(
+            parse_path("items[*].sku"),
+            Ok(vec![key("items"), Step::Any, key("sku")])
+        );
+        assert_eq!(
+            parse_path(r#"[2]["a.b"].*['c']"#),
+            Ok(vec![Step::Index(2), key("a.b"), Step::Any, key("c")])
+        );
+        assert_eq!(parse_path(""), Err(0));
+        assert_eq!(parse_path("a..b"), Err(2));
+        assert_eq!(parse_path("a[01]"), Err(2));
+        assert_eq!(parse_path("a[1"), Err(3));
+        assert_eq!(parse_path("a[1]b"), Err(4));
+    }
+
+    #[test]
+    fn selected_spans() {
+        let js = r#"{"user": {"id": 7, "name": "Zoë", "tags": ["a"]},
+                     "items": [{"sku": "A-1", "qty": 2}, {"qty": 1}, {"sku": "B-2"}],
+                     "user.id": true}"#;
+        let spans = project(
+            js,
+            &[
+                "user.id",
+                "user.name",
+                "items[*].sku",
+                "items[1]",
+                r#"["user.id"]"#,
+                "nope.x",
+            ],
+        )
+        .unwrap();
+        let texts: Vec<Vec<&str>> = spans
+            .iter()
+            .map(|field| field.iter().map(|span| &js[span.clone()]).collect())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                vec!["7"],
+                vec![r#""Zoë""#],
+                vec![r#""A-1""#, r#""B-2""#],
+                vec![r#"{"qty": 1}"#],
+                vec!["true"],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn projector_reuse_and_errors() {
+        let projector = Projector::new(&["*.id"]).unwrap();
+        let spans = projector
+            .project(r#"{"a": {"id": 1}, "b": [{"id": 2}], "c": {"id": 3}}"#)
+            .unwrap();
+        assert_eq!(spans, [vec![13..14, 47..48]]);
+        assert_eq!(
+            projector.project("[]").unwrap(),
+            [Vec::<Range<usize>>::new()]
+        );
+        assert_eq!(projector.project("").unwrap(), [Vec::<Range<usize>>::new()]);
+        assert_eq!(projector.project("[1, 2"), Err(JsmnErr::JsmErrorPart));
+        assert_eq!(
+            project("{}", &["a", "b["]),
+            Err(ProjectErr::InvalidPath { field: 1, pos: 2 })
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//!
+//! Property tests: arbitrary JSON values are written out, parsed back, and
+//! the tokens checked against the values they came from. Every token must
+//! have the type, size and parent its value calls for, lie inside the
+//! span of its parent after its previous sibling, and read back as the
+//! same string or number. This catches mistakes in the wrapper, such as an
+//! off-by-one in a span or a cast, as well as in the tree helpers.
+//!
+
+use proptest::prelude::*;
+
+use crate::decode::unescape;
+use crate::ser::JsonWriter;
+use crate::tree::TokenTree;
+use crate::value::{token_as_f64, token_as_i64};
+use crate::{JsmnErr, JsmnParser, JsmnTok, JsmnType, count_tokens, parse_to_vec};
+
+#[derive(Debug, Clone)]
+enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+fn json() -> impl Strategy<Value = Json> {
+    let leaf = prop_oneof![
+        Just(Json::Null),
+        any::<bool>().prop_map(Json::Bool),
+        any::<i64>().prop_map(Json::Int),
+        any::<f64>()
+            .prop_filter("JSON numbers are finite", |n| n.is_finite())
+            .prop_map(Json::Float),
+        any::<String>().prop_map(Json::Str),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Json::Array),
+            prop::collection::vec((any::<String>(), inner), 0..8).prop_map(Json::Object),
+        ]
+    })
+}
+
+fn write(out: &mut JsonWriter<String>, value: &Json) {
+    match value {
+        Json::Null => out.null(),
+        Json::Bool(b) => out.bool(*b),
+        Json::Int(n) => out.i64(*n),
+        Json::Float(n) => out.f64(*n),
+        Json::Str(s) => out.str(s),
+        Json::Array(items) => {
+            out.begin_array().unwrap();
+            items.iter().for_each(|item| write(out, item));
+            out.end_array()
+        }
+        Json::Object(members) => {
+            out.begin_object().unwrap();
+            for (key, value) in members {
+                out.key(key).unwrap();
+                write(out, value);
+            }
+            out.end_object()
+        }
+    }
+    .unwrap();
+}
+
+/// Checks the token at `*idx`, and those of its children, against `value`,
+/// and returns where its text ends, closing quote included.
+fn check(
+    js: &str,
+    tokens: &[JsmnTok],
+    idx: &mut usize,
+    value: &Json,
+    parent: i32,
+) -> Result<usize, TestCaseError> {
+    let tok = tokens[*idx];
+    let this = *idx as i32;
+    *idx += 1;
+    prop_assert_eq!(tok.parent, parent);
+    prop_assert!(0 <= tok.start && tok.start <= tok.end && tok.end as usize <= js.len());
+    let (start, end) = (tok.start as usize, tok.end as usize);
+
+    let (typ, size) = match value {
+        Json::Null | Json::Bool(_) | Json::Int(_) | Json::Float(_) => (JsmnType::JsmnPrimitive, 0),
+        Json::Str(_) => (JsmnType::JsmnString, 0),
+        Json::Array(items) => (JsmnType::JsmnArray, items.len()),
+        Json::Object(members) => (JsmnType::JsmnObject, members.len()),
+    };
+    prop_assert_eq!(tok.typ, typ);
+    prop_assert_eq!(tok.size as usize, size);
+
+    match value {
+        Json::Null => prop_assert_eq!(tok.as_str(js), "null"),
+        Json::Bool(b) => prop_assert_eq!(tok.as_str(js), b.to_string()),
+        Json::Int(n) => prop_assert_eq!(token_as_i64(js, &tok), Ok(*n)),
+        Json::Float(n) => prop_assert_eq!(token_as_f64(js, &tok), Ok(*n)),
+        Json::Str(s) => {
+            prop_assert_eq!(&js[start - 1..start], "\"");
+            prop_assert_eq!(&js[end..end + 1], "\"");
+            prop_assert_eq!(unescape(tok.as_str(js)), s.as_str());
+            return Ok(end + 1);
+        }
+        Json::Array(items) => {
+            let mut last = start + 1;
+            for item in items {
+                prop_assert!(tokens[*idx].start as usize >= last);
+                last = check(js, tokens, idx, item, this)?;
+            }
+            prop_assert!(last < end);
+        }
+        Json::Object(members) => {
+            let mut last = start + 1;
+            for (key, value) in members {
+                let key_tok = tokens[*idx];
+                prop_assert!(key_tok.start as usize > last);
+                prop_assert_eq!(
+                    (key_tok.typ, key_tok.size, key_tok.parent),
+                    (JsmnType::JsmnString, 1, this)
+                );
+                prop_assert_eq!(unescape(key_tok.as_str(js)), key.as_str());
+                let key_idx = *idx as i32;
+                *idx += 1;
+                prop_assert!(tokens[*idx].start >= key_tok.end);
+                last = check(js, tokens, idx, value, key_idx)?;
+            }
+            prop_assert!(last < end);
+        }
+    }
+    Ok(end)
+}
+
+proptest! {
+    #[test]
+    fn tokens_match_values(value in json()) {
+        let mut out = JsonWriter::new(String::new());
+        write(&mut out, &value);
+        // A trailing delimiter, for a lone primitive in strict mode.
+        let js = out.into_inner() + "\n";
+
+        let tokens = parse_to_vec(&js).unwrap();
+        let mut idx = 0;
+        let end = check(&js, &tokens, &mut idx, &value, -1)?;
+        prop_assert_eq!(end, js.len() - 1);
+        prop_assert_eq!(idx, tokens.len());
+        prop_assert_eq!(count_tokens(&js), Ok(tokens.len()));
+
+        let tree = TokenTree::new(&tokens);
+        for (i, tok) in tokens.iter().enumerate() {
+            prop_assert_eq!(tree.parent(i), usize::try_from(tok.parent).ok());
+            prop_assert_eq!(tree.children(i).count(), tok.size as usize);
+        }
+
+        let mut short = vec![JsmnTok::default(); tokens.len() - 1];
+        let mut parser = JsmnParser::new();
+        prop_assert_eq!(parser.parse(&js, &mut short), Err(JsmnErr::JsmErrorNoMem));
+    }
+}
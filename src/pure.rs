@@ -0,0 +1,390 @@
+//!
+//! This module is a port of jsmn to safe Rust, for builds without a C
+//! toolchain, as is often the case on Windows or when cross-compiling.
+//! With the "pure-rust" feature, the parser uses it instead of the C
+//! library, and jsmn is neither compiled nor bound with bindgen. Builds
+//! for WebAssembly always use it, with or without the feature.
+//!
+//! The port follows jsmn line by line, as built with parent links, so it
+//! fills in the same tokens and leaves the parser in the same state,
+//! errors included, down to jsmn's quirks: parsing stops at a NUL byte,
+//! and the lenient mode takes anything between delimiters for a
+//! primitive. The tests below check this against the C library on a
+//! corpus of documents and on random input.
+//!
+
+use crate::{JsmnErr, JsmnParser, JsmnTok, JsmnType};
+
+/// Parses `js` like jsmn_parse, in strict mode if `strict` is true. Without
+/// tokens it only counts them, like jsmn_parse given a null array.
+pub(crate) fn parse(
+    parser: &mut JsmnParser,
+    js: &[u8],
+    mut tokens: Option<&mut [JsmnTok]>,
+    strict: bool,
+) -> Result<usize, JsmnErr> {
+    let mut count = parser.toknext as usize;
+
+    while let Some(c) = current(parser, js) {
+        match c {
+            b'{' | b'[' => {
+                count += 1;
+                if let Some(tokens) = tokens.as_deref_mut() {
+                    let idx = alloc_token(parser, tokens).ok_or(JsmnErr::JsmErrorNoMem)?;
+                    if parser.toksuper != -1 {
+                        tokens[parser.toksuper as usize].size += 1;
+                        tokens[idx].parent = parser.toksuper;
+                    }
+                    tokens[idx].typ = match c {
+                        b'{' => JsmnType::JsmnObject,
+                        _ => JsmnType::JsmnArray,
+                    };
+                    tokens[idx].start = parser.pos as i32;
+                    parser.toksuper = parser.toknext as i32 - 1;
+                }
+            }
+            b'}' | b']' => {
+                if let Some(tokens) = tokens.as_deref_mut() {
+                    let typ = match c {
+                        b'}' => JsmnType::JsmnObject,
+                        _ => JsmnType::JsmnArray,
+                    };
+                    close(parser, tokens, typ)?;
+                }
+            }
+            b'"' => {
+                parse_string(parser, js, tokens.as_deref_mut())?;
+                count += 1;
+                if let Some(tokens) = tokens.as_deref_mut()
+                    && parser.toksuper != -1
+                {
+                    tokens[parser.toksuper as usize].size += 1;
+                }
+            }
+            b'\t' | b'\r' | b'\n' | b' ' => {}
+            b':' => parser.toksuper = parser.toknext as i32 - 1,
+            b',' => {
+                if let Some(tokens) = tokens.as_deref_mut()
+                    && parser.toksuper != -1
+                {
+                    let sup = &tokens[parser.toksuper as usize];
+                    if !matches!(sup.typ, JsmnType::JsmnArray | JsmnType::JsmnObject) {
+                        parser.toksuper = sup.parent;
+                    }
+                }
+            }
+            _ => {
+                if strict {
+                    if !matches!(c, b'-' | b'0'..=b'9' | b't' | b'f' | b'n') {
+                        return Err(JsmnErr::JsmErrorInval);
+                    }
+                    // A primitive cannot be a key, nor follow a key's value.
+                    if let Some(tokens) = tokens.as_deref()
+                        && parser.toksuper != -1
+                    {
+                        let sup = &tokens[parser.toksuper as usize];
+                        if sup.typ == JsmnType::JsmnObject
+                            || (sup.typ == JsmnType::JsmnString && sup.size != 0)
+                        {
+                            return Err(JsmnErr::JsmErrorInval);
+                        }
+                    }
+                }
+                parse_primitive(parser, js, tokens.as_deref_mut(), strict)?;
+                count += 1;
+                if let Some(tokens) = tokens.as_deref_mut()
+                    && parser.toksuper != -1
+                {
+                    tokens[parser.toksuper as usize].size += 1;
+                }
+            }
+        }
+        parser.pos += 1;
+    }
+
+    if let Some(tokens) = tokens {
+        let open = tokens[..parser.toknext as usize]
+            .iter()
+            .any(|tok| tok.start != -1 && tok.end == -1);
+        if open {
+            return Err(JsmnErr::JsmErrorPart);
+        }
+    }
+    Ok(count)
+}
+
+/// The byte at the parser's position, or None at the end of the input,
+/// which a NUL byte also marks.
+fn current(parser: &JsmnParser, js: &[u8]) -> Option<u8> {
+    js.get(parser.pos as usize).copied().filter(|&c| c != 0)
+}
+
+/// Takes the next token, reset, and returns its index, or None if all of
+/// them are used.
+fn alloc_token(parser: &mut JsmnParser, tokens: &mut [JsmnTok]) -> Option<usize> {
+    let idx = parser.toknext as usize;
+    let tok = tokens.get_mut(idx)?;
+    parser.toknext += 1;
+    *tok = JsmnTok {
+        start: -1,
+        end: -1,
+        ..JsmnTok::default()
+    };
+    Some(idx)
+}
+
+/// Closes the innermost open object or array, which must be of type `typ`,
+/// walking up the parent links from the last token.
+fn close(parser: &mut JsmnParser, tokens: &mut [JsmnTok], typ: JsmnType) -> Result<(), JsmnErr> {
+    if parser.toknext < 1 {
+        return Err(JsmnErr::JsmErrorInval);
+    }
+    let mut idx = parser.toknext as usize - 1;
+    loop {
+        let tok = &mut tokens[idx];
+        if tok.start != -1 && tok.end == -1 {
+            if tok.typ != typ {
+                return Err(JsmnErr::JsmErrorInval);
+            }
+            tok.end = parser.pos as i32 + 1;
+            parser.toksuper = tok.parent;
+            return Ok(());
+        }
+        if tok.parent == -1 {
+            if tok.typ != typ || parser.toksuper == -1 {
+                return Err(JsmnErr::JsmErrorInval);
+            }
+            return Ok(());
+        }
+        idx = tok.parent as usize;
+    }
+}
+
+fn parse_primitive(
+    parser: &mut JsmnParser,
+    js: &[u8],
+    tokens: Option<&mut [JsmnTok]>,
+    strict: bool,
+) -> Result<(), JsmnErr> {
+    let start = parser.pos;
+
+    let mut found = false;
+    while let Some(c) = current(parser, js) {
+        if matches!(c, b'\t' | b'\r' | b'\n' | b' ' | b',' | b']' | b'}') || (c == b':' && !strict)
+        {
+            found = true;
+            break;
+        }
+        if !(32..127).contains(&c) {
+            parser.pos = start;
+            return Err(JsmnErr::JsmErrorInval);
+        }
+        parser.pos += 1;
+    }
+    if strict && !found {
+        parser.pos = start;
+        return Err(JsmnErr::JsmErrorPart);
+    }
+
+    if let Some(tokens) = tokens {
+        let Some(idx) = alloc_token(parser, tokens) else {
+            parser.pos = start;
+            return Err(JsmnErr::JsmErrorNoMem);
+        };
+        tokens[idx] = JsmnTok {
+            typ: JsmnType::JsmnPrimitive,
+            start: start as i32,
+            end: parser.pos as i32,
+            size: 0,
+            parent: parser.toksuper,
+        };
+    }
+    parser.pos -= 1;
+    Ok(())
+}
+
+fn parse_string(
+    parser: &mut JsmnParser,
+    js: &[u8],
+    tokens: Option<&mut [JsmnTok]>,
+) -> Result<(), JsmnErr> {
+    let start = parser.pos;
+    parser.pos += 1;
+
+    while let Some(c) = current(parser, js) {
+        if c == b'"' {
+            let Some(tokens) = tokens else {
+                return Ok(());
+            };
+            let Some(idx) = alloc_token(parser, tokens) else {
+                parser.pos = start;
+                return Err(JsmnErr::JsmErrorNoMem);
+            };
+            tokens[idx] = JsmnTok {
+                typ: JsmnType::JsmnString,
+                start: start as i32 + 1,
+                end: parser.pos as i32,
+                size: 0,
+                parent: parser.toksuper,
+            };
+            return Ok(());
+        }
+
+        if c == b'\\' && (parser.pos as usize) + 1 < js.len() {
+            parser.pos += 1;
+            match js[parser.pos as usize] {
+                b'"' | b'/' | b'\\' | b'b' | b'f' | b'r' | b'n' | b't' => {}
+                b'u' => {
+                    parser.pos += 1;
+                    let mut digits = 0;
+                    while digits < 4
+                        && let Some(c) = current(parser, js)
+                    {
+                        if !c.is_ascii_hexdigit() {
+                            parser.pos = start;
+                            return Err(JsmnErr::JsmErrorInval);
+                        }
+                        parser.pos += 1;
+                        digits += 1;
+                    }
+                    parser.pos -= 1;
+                }
+                _ => {
+                    parser.pos = start;
+                    return Err(JsmnErr::JsmErrorInval);
+                }
+            }
+        }
+        parser.pos += 1;
+    }
+    parser.pos = start;
+    Err(JsmnErr::JsmErrorPart)
+}
+
+#[cfg(all(test, not(feature = "pure-rust")))]
+mod tests {
+    use super::*;
+
+    use crate::raw;
+
+    /// Runs both parsers from the same state, and checks that they agree on
+    /// the result, the tokens and the state they leave behind.
+    fn compare(js: &[u8], tokens: &[JsmnTok], parser: JsmnParser, strict: bool) {
+        let mut c_parser = parser;
+        let mut c_tokens = tokens.to_vec();
+        let c_result = c_parser.parse_mode(js, &mut c_tokens, strict);
+
+        let mut rust_parser = parser;
+        let mut rust_tokens = tokens.to_vec();
+        let rust_result = parse(&mut rust_parser, js, Some(&mut rust_tokens), strict);
+
+        let input = String::from_utf8_lossy(js);
+        assert_eq!(rust_result, c_result, "{:?} strict={}", input, strict);
+        assert_eq!(rust_tokens, c_tokens, "{:?} strict={}", input, strict);
+        assert_eq!(
+            (rust_parser.pos, rust_parser.toknext, rust_parser.toksuper),
+            (c_parser.pos, c_parser.toknext, c_parser.toksuper),
+            "{:?} strict={}",
+            input,
+            strict
+        );
+
+        let mut c_parser = parser;
+        let c_count = unsafe {
+            let parse = match strict {
+                true => raw::jsmn_parse_strict,
+                false => raw::jsmn_parse_lenient,
+            };
+            parse(
+                &mut c_parser as *mut _ as *mut raw::jsmn_parser,
+                js.as_ptr().cast(),
+                js.len(),
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        let mut rust_parser = parser;
+        let rust_count = parse(&mut rust_parser, js, None, strict);
+        let rust_count = rust_count.map_or_else(|err| err as i32, |count| count as i32);
+        assert_eq!(
+            rust_count, c_count,
+            "counting {:?} strict={}",
+            input, strict
+        );
+    }
+
+    /// Compares the parsers on `js` and all its prefixes, with enough
+    /// tokens and with too few, in both modes, and resuming from where
+    /// they stopped on the prefix.
+    fn compare_all(js: &[u8]) {
+        for strict in [false, true] {
+            for len in 0..=js.len() {
+                for count in [0, 1, 3, 64] {
+                    compare(
+                        &js[..len],
+                        &vec![JsmnTok::default(); count],
+                        JsmnParser::new(),
+                        strict,
+                    );
+                }
+                let mut parser = JsmnParser::new();
+                let mut tokens = vec![JsmnTok::default(); 64];
+                let _ = parse(&mut parser, &js[..len], Some(&mut tokens), strict);
+                compare(js, &tokens, parser, strict);
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_jsmn() {
+        let corpus: &[&[u8]] = &[
+            br#"{"a": [1, -2.5e3, true, null], "b": {"c": "d\n\u00e9"}}"#,
+            br#"[[], {}, [{}], {"x": []}, "s", 12]"#,
+            br#"{"a" 1, "b": 2 3, 4: 5}"#,
+            br#"{"a": [1}, ]"#,
+            br#"[1, 2]]"#,
+            br#"{"key": value, other: "x"}"#,
+            b"[\"bad \\q escape\", \"\\u12G4\", \"\\u12\"]",
+            b"[1, \"a\x00b\", 2]",
+            b"1 2 \"three\" [4] {}",
+            b"[\x01, \x7f, \xc3\xa9]",
+            b"{\"a\":{\"b\":{\"c\":[1,[2,[3]]]}},\"d\":4}\n",
+            b"[tru, nul, -, 0x1F]",
+            b"}",
+            b"\"",
+            b"\"\\",
+        ];
+        for js in corpus {
+            compare_all(js);
+        }
+    }
+
+    #[test]
+    fn same_as_jsmn_on_random_input() {
+        let alphabet = b"{}[]\":,\\ \n1-etrunl0xa\x00";
+        // A small xorshift generator, so that failures can be replayed.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..2000 {
+            let len = next() as usize % 24;
+            let js: Vec<u8> = (0..len)
+                .map(|_| alphabet[next() as usize % alphabet.len()])
+                .collect();
+            for strict in [false, true] {
+                for count in [2, 64] {
+                    compare(
+                        &js,
+                        &vec![JsmnTok::default(); count],
+                        JsmnParser::new(),
+                        strict,
+                    );
+                }
+            }
+        }
+    }
+}
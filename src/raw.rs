@@ -1,10 +1,135 @@
 //!
 //! This module provides raw bindings to the jsmn library,
-//! generated by bindgen. It is not needed for most use cases,
-//! as the more Rustic interface is provided.
+//! generated by bindgen ahead of time. It is not needed for most use cases,
+//! as the more Rustic interface is provided. With the pure-rust feature
+//! only the types are left, as there is no C library to call.
+//!
+//! The Rustic types double as a stable ABI for exchanging token buffers
+//! with C code, plugins and other languages. `JsmnTok` and `JsmnParser` are
+//! `#[repr(C)]` mirrors of `jsmntok_t` and `jsmn_parser`, `JsmnType` is a
+//! `#[repr(u32)]` mirror of `jsmntype_t` and `JsmnErr` is a `#[repr(i32)]`
+//! mirror of `jsmnerr`. Their sizes, alignments and field offsets are
+//! checked against the generated bindings at compile time, under every
+//! combination of features, so a slice of tokens can be handed to C as a
+//! `jsmntok_t *` and back without any conversion.
+//!
+//! jsmn is always built with parent links, so tokens always have their
+//! trailing `parent` field and are 20 bytes long. Before version 2 of the
+//! ABI that depended on the parent-links feature, and tokens without it
+//! were 16 bytes. Code on the other side of the boundary should compare
+//! `ABI` with what it was built for before trusting a buffer.
 //!
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
-include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+use std::mem::{align_of, offset_of, size_of};
+
+use crate::{JsmnErr, JsmnParser, JsmnTok, JsmnType};
+
+// The bindings are generated once and checked in, as they are the same for
+// every combination of features, so that building the crate needs neither
+// bindgen nor libclang. The regenerate-bindings feature runs bindgen again,
+// and its output in OUT_DIR can be copied over src/bindings.rs when jsmn
+// changes.
+#[cfg_attr(feature = "pure-rust", allow(dead_code))]
+mod bindings {
+    #[cfg(not(feature = "regenerate-bindings"))]
+    include!("bindings.rs");
+    #[cfg(feature = "regenerate-bindings")]
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+#[cfg(not(feature = "pure-rust"))]
+pub use bindings::*;
+
+// With the pure-rust feature there is no C library to call, so only the
+// types of jsmn.h are exported.
+#[cfg(feature = "pure-rust")]
+pub use bindings::{
+    jsmn_parser, jsmnerr, jsmnerr_JSMN_ERROR_INVAL, jsmnerr_JSMN_ERROR_NOMEM,
+    jsmnerr_JSMN_ERROR_PART, jsmntok_t, jsmntype_t, jsmntype_t_JSMN_ARRAY, jsmntype_t_JSMN_OBJECT,
+    jsmntype_t_JSMN_PRIMITIVE, jsmntype_t_JSMN_STRING, jsmntype_t_JSMN_UNDEFINED,
+};
+
+// jsmn_parse is also built in both modes, whatever the strict feature says,
+// under names of its own.
+#[cfg(not(feature = "pure-rust"))]
+unsafe extern "C" {
+    pub fn jsmn_parse_strict(
+        parser: *mut jsmn_parser,
+        js: *const ::std::os::raw::c_char,
+        len: usize,
+        tokens: *mut jsmntok_t,
+        num_tokens: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_int;
+
+    pub fn jsmn_parse_lenient(
+        parser: *mut jsmn_parser,
+        js: *const ::std::os::raw::c_char,
+        len: usize,
+        tokens: *mut jsmntok_t,
+        num_tokens: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_int;
+}
+
+/// The version of the layouts described by `AbiInfo`. It changes whenever
+/// one of the mirrored types changes in a way foreign code could notice.
+pub const ABI_VERSION: u32 = 2;
+
+/// A description of the ABI this build of the crate uses.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AbiInfo {
+    pub version: u32,
+    /// The size of a token in bytes.
+    pub token_size: u32,
+    /// The size of the parser state in bytes.
+    pub parser_size: u32,
+    /// Whether tokens have the `parent` field, which is always the case
+    /// since version 2.
+    pub parent_links: bool,
+    /// Whether `jsmn_parse` was built in strict mode. Both modes are
+    /// available either way, see `limits::ParseOptions`.
+    pub strict: bool,
+}
+
+/// The ABI of this build.
+pub const ABI: AbiInfo = AbiInfo {
+    version: ABI_VERSION,
+    token_size: size_of::<JsmnTok>() as u32,
+    parser_size: size_of::<JsmnParser>() as u32,
+    parent_links: true,
+    strict: cfg!(feature = "strict"),
+};
+
+const _: () = {
+    assert!(size_of::<JsmnType>() == size_of::<jsmntype_t>());
+    assert!(size_of::<JsmnErr>() == size_of::<jsmnerr>());
+
+    assert!(size_of::<JsmnTok>() == size_of::<jsmntok_t>());
+    assert!(align_of::<JsmnTok>() == align_of::<jsmntok_t>());
+    assert!(offset_of!(JsmnTok, typ) == offset_of!(jsmntok_t, type_));
+    assert!(offset_of!(JsmnTok, start) == offset_of!(jsmntok_t, start));
+    assert!(offset_of!(JsmnTok, end) == offset_of!(jsmntok_t, end));
+    assert!(offset_of!(JsmnTok, size) == offset_of!(jsmntok_t, size));
+    assert!(offset_of!(JsmnTok, parent) == offset_of!(jsmntok_t, parent));
+
+    assert!(size_of::<JsmnParser>() == size_of::<jsmn_parser>());
+    assert!(align_of::<JsmnParser>() == align_of::<jsmn_parser>());
+    assert!(offset_of!(JsmnParser, pos) == offset_of!(jsmn_parser, pos));
+    assert!(offset_of!(JsmnParser, toknext) == offset_of!(jsmn_parser, toknext));
+    assert!(offset_of!(JsmnParser, toksuper) == offset_of!(jsmn_parser, toksuper));
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abi_description() {
+        assert_eq!(ABI.token_size, 20);
+        assert_eq!(ABI.parser_size, 12);
+        assert_eq!(ABI.version, ABI_VERSION);
+    }
+}
@@ -0,0 +1,614 @@
+//!
+//! This module rewrites whole documents held in memory. The rewrites work
+//! on the raw text spans found by the tokens, so everything they do not
+//! touch is copied through exactly as it was, formatting included.
+//!
+//! A `CowDocument` records edits against a shared, parsed document instead
+//! of applying them, and only writes out the edited text when asked. Its
+//! clones share the original text and tokens, which makes it cheap to
+//! derive many variants of a template payload.
+//!
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::decode::unescape;
+use crate::pointer::{KeyMatch, PathItem, PointerErr, parse_pointer, resolve, to_pointer};
+use crate::shared::SharedDocument;
+use crate::stream::Edit;
+use crate::tree::{children, roots, subtree_end, value_span};
+use crate::value::{NumberKind, classify_number};
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Error type for the rewrites that target a value by pointer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RewriteErr {
+    /// The document is not valid JSON.
+    Parse(JsmnErr),
+    /// A pointer is malformed.
+    Pointer(PointerErr),
+    /// There is no value at the target pointer.
+    NotFound,
+    /// The value at the target pointer does not have the expected type.
+    WrongType(JsmnType),
+}
+
+impl From<JsmnErr> for RewriteErr {
+    fn from(err: JsmnErr) -> Self {
+        RewriteErr::Parse(err)
+    }
+}
+
+impl From<PointerErr> for RewriteErr {
+    fn from(err: PointerErr) -> Self {
+        RewriteErr::Pointer(err)
+    }
+}
+
+/// Which of several members with the same key [`dedup_keys`] keeps.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KeepPolicy {
+    First,
+    Last,
+}
+
+/// An object member dropped by [`dedup_keys`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroppedMember {
+    /// The JSON Pointer of the member.
+    pub pointer: String,
+    /// The bytes of the member in the input, from the opening quote of its
+    /// key to the end of its value.
+    pub span: Range<usize>,
+}
+
+/// The result of [`dedup_keys`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deduped {
+    /// The document without the dropped members.
+    pub json: String,
+    /// The dropped members, in document order.
+    pub dropped: Vec<DroppedMember>,
+}
+
+/// Removes the members of every object that repeat the key of another
+/// member of the same object, keeping only the first or the last of them.
+/// Keys are compared after decoding their escapes, and the commas around
+/// dropped members are fixed up so the output stays valid JSON.
+pub fn dedup_keys(js: &str, policy: KeepPolicy) -> Result<Deduped, JsmnErr> {
+    dedup_keys_with(js, policy, KeyMatch::Exact)
+}
+
+/// Like [`dedup_keys`], with keys compared according to `keys`.
+pub fn dedup_keys_with(js: &str, policy: KeepPolicy, keys: KeyMatch) -> Result<Deduped, JsmnErr> {
+    let tokens = parse_to_vec(js)?;
+    let mut dropped = Vec::new();
+    let mut cuts = Vec::new();
+
+    let mut work: Vec<_> = roots(&tokens)
+        .into_iter()
+        .rev()
+        .map(|root| (root, Vec::new()))
+        .collect();
+    while let Some((idx, path)) = work.pop() {
+        match tokens[idx].typ {
+            JsmnType::JsmnObject => {
+                let members: Vec<_> = children(&tokens, idx).collect();
+                let names: Vec<_> = members
+                    .iter()
+                    .map(|&key| key_name(js, &tokens, key))
+                    .collect();
+                let compared: Vec<_> = names
+                    .iter()
+                    .map(|name| keys.normalize(Cow::Borrowed(name)))
+                    .collect();
+                let mut kept = HashMap::new();
+                for (i, name) in compared.iter().enumerate() {
+                    match policy {
+                        KeepPolicy::First => {
+                            kept.entry(name).or_insert(i);
+                        }
+                        KeepPolicy::Last => {
+                            kept.insert(name, i);
+                        }
+                    }
+                }
+
+                let spans: Vec<_> = members
+                    .iter()
+                    .map(|&key| value_span(&tokens, key))
+                    .collect();
+                let first_kept = (0..members.len()).find(|&i| kept[&compared[i]] == i);
+                for (i, name) in names.iter().enumerate().rev() {
+                    let mut path = path.clone();
+                    path.push(PathItem::Key(name.clone()));
+                    if kept[&compared[i]] == i {
+                        work.push((members[i] + 1, path));
+                        continue;
+                    }
+
+                    // Take the comma after the member along if it comes
+                    // before every kept one, and the comma before it if not.
+                    cuts.push(match first_kept {
+                        Some(first) if first < i => spans[i - 1].end..spans[i].end,
+                        _ if i + 1 < spans.len() => spans[i].start..spans[i + 1].start,
+                        _ => spans[i].clone(),
+                    });
+                    dropped.push(DroppedMember {
+                        pointer: to_pointer(&path),
+                        span: spans[i].clone(),
+                    });
+                }
+            }
+            JsmnType::JsmnArray => {
+                let elements: Vec<_> = children(&tokens, idx).collect();
+                for (i, &element) in elements.iter().enumerate().rev() {
+                    let mut path = path.clone();
+                    path.push(PathItem::Index(i));
+                    work.push((element, path));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    dropped.sort_by_key(|member| member.span.start);
+    Ok(Deduped {
+        json: splice(js, cuts.into_iter().map(|cut| (cut, "")).collect()),
+        dropped,
+    })
+}
+
+/// The direction of a sort.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Reorders the elements of the array at `array` by the value at `key`
+/// within each element, and returns the rewritten document. Elements are
+/// moved as raw text, so their formatting is kept, while the whitespace and
+/// commas between the positions of the array stay where they were.
+///
+/// Sort keys compare as `null`, then booleans, numbers, strings and finally
+/// objects and arrays, which compare by their raw text. Elements without a
+/// value at `key` always go last, and the sort is stable.
+pub fn sort_array_by(
+    js: &str,
+    array: &str,
+    key: &str,
+    order: SortOrder,
+) -> Result<String, RewriteErr> {
+    let tokens = parse_to_vec(js)?;
+    let array = parse_pointer(array)?;
+    let key = parse_pointer(key)?;
+
+    let idx = match roots(&tokens).first() {
+        Some(&root) => {
+            resolve(js, &tokens, root, &array, KeyMatch::Exact).ok_or(RewriteErr::NotFound)?
+        }
+        None => return Err(RewriteErr::NotFound),
+    };
+    if tokens[idx].typ != JsmnType::JsmnArray {
+        return Err(RewriteErr::WrongType(tokens[idx].typ));
+    }
+
+    let elements: Vec<_> = children(&tokens, idx).collect();
+    let spans: Vec<_> = elements
+        .iter()
+        .map(|&element| value_span(&tokens, element))
+        .collect();
+    let keys: Vec<_> = elements
+        .iter()
+        .map(|&element| {
+            resolve(js, &tokens, element, &key, KeyMatch::Exact)
+                .map(|k| SortKey::new(js, &tokens, k))
+        })
+        .collect();
+
+    let mut sorted: Vec<_> = (0..elements.len()).collect();
+    sorted.sort_by(|&a, &b| match (&keys[a], &keys[b]) {
+        (Some(a), Some(b)) => match order {
+            SortOrder::Ascending => a.order(b),
+            SortOrder::Descending => b.order(a),
+        },
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+
+    let Some(first) = spans.first() else {
+        return Ok(js.to_string());
+    };
+    let mut out = String::with_capacity(js.len());
+    out.push_str(&js[..first.start]);
+    for (position, &element) in sorted.iter().enumerate() {
+        out.push_str(&js[spans[element].clone()]);
+        match spans.get(position + 1) {
+            Some(next) => out.push_str(&js[spans[position].end..next.start]),
+            None => out.push_str(&js[spans[position].end..]),
+        }
+    }
+    Ok(out)
+}
+
+/// A sort key taken from a token, ordered as described on [`sort_array_by`].
+#[derive(Debug)]
+enum SortKey<'a> {
+    Null,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    String(String),
+    Raw(&'a str),
+}
+
+impl<'a> SortKey<'a> {
+    fn new(js: &'a str, tokens: &[JsmnTok], idx: usize) -> Self {
+        let tok = &tokens[idx];
+        let text = &js[tok.start as usize..tok.end as usize];
+        match tok.typ {
+            JsmnType::JsmnString => SortKey::String(unescape(text).into_owned()),
+            JsmnType::JsmnPrimitive => match (text, classify_number(text)) {
+                ("null", _) => SortKey::Null,
+                ("true", _) => SortKey::Bool(true),
+                ("false", _) => SortKey::Bool(false),
+                (_, None) => SortKey::Raw(text),
+                (_, Some(NumberKind::Float)) => {
+                    text.parse().map_or(SortKey::Raw(text), SortKey::Float)
+                }
+                // Integers past `i128` are ordered by their nearest `f64`.
+                (_, Some(_)) => match text.parse() {
+                    Ok(int) => SortKey::Int(int),
+                    Err(_) => text.parse().map_or(SortKey::Raw(text), SortKey::Float),
+                },
+            },
+            _ => SortKey::Raw(&js[value_span(tokens, idx)]),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortKey::Null => 0,
+            SortKey::Bool(_) => 1,
+            SortKey::Int(_) | SortKey::Float(_) => 2,
+            SortKey::String(_) => 3,
+            SortKey::Raw(_) => 4,
+        }
+    }
+
+    /// A total order, so integers compare exactly and no two keys are
+    /// unordered.
+    fn order(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortKey::Bool(a), SortKey::Bool(b)) => a.cmp(b),
+            (SortKey::Int(a), SortKey::Int(b)) => a.cmp(b),
+            (SortKey::Float(a), SortKey::Float(b)) => a.total_cmp(b),
+            (SortKey::Int(a), SortKey::Float(b)) => int_float_order(*a, *b),
+            (SortKey::Float(a), SortKey::Int(b)) => int_float_order(*b, *a).reverse(),
+            (SortKey::String(a), SortKey::String(b)) => a.cmp(b),
+            (SortKey::Raw(a), SortKey::Raw(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+/// Compares an integer with a float, exactly where the float is a whole
+/// number an `i128` can hold.
+fn int_float_order(int: i128, float: f64) -> Ordering {
+    if float.fract() == 0.0 && float.abs() < 2f64.powi(127) {
+        int.cmp(&(float as i128))
+    } else {
+        (int as f64).total_cmp(&float)
+    }
+}
+
+/// A shared document with a set of pending edits. Pointers given to the
+/// edits always refer to the original document, and edits inside a value
+/// that is replaced or removed as a whole have no effect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CowDocument {
+    base: SharedDocument,
+    /// The edits by the index of the value they apply to, along with the
+    /// index of the object or array holding it.
+    edits: BTreeMap<usize, (Option<usize>, Edit)>,
+}
+
+impl CowDocument {
+    pub fn new(base: SharedDocument) -> Self {
+        CowDocument {
+            base,
+            edits: BTreeMap::new(),
+        }
+    }
+
+    /// Parses `text` into a document without edits.
+    pub fn parse(text: impl Into<Arc<str>>) -> Result<Self, RewriteErr> {
+        Ok(Self::new(SharedDocument::parse(text)?))
+    }
+
+    /// The original document.
+    pub fn base(&self) -> &SharedDocument {
+        &self.base
+    }
+
+    /// Returns true if there are edits to apply.
+    pub fn is_edited(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    /// Records an edit of the value at a JSON Pointer of the first root,
+    /// replacing any earlier edit of that value or of values inside it.
+    /// `Edit::Keep` reverts the value to the original.
+    pub fn edit(&mut self, pointer: &str, edit: Edit) -> Result<&mut Self, RewriteErr> {
+        let mut pointer = parse_pointer(pointer)?;
+        let (js, tokens) = (self.base.text(), self.base.tokens());
+        let root = *roots(tokens).first().ok_or(RewriteErr::NotFound)?;
+        let idx =
+            resolve(js, tokens, root, &pointer, KeyMatch::Exact).ok_or(RewriteErr::NotFound)?;
+        let parent = pointer
+            .pop()
+            .and_then(|_| resolve(js, tokens, root, &pointer, KeyMatch::Exact));
+
+        let end = subtree_end(tokens, idx);
+        self.edits
+            .retain(|&edited, _| !(idx..end).contains(&edited));
+        if edit != Edit::Keep {
+            self.edits.insert(idx, (parent, edit));
+        }
+        Ok(self)
+    }
+
+    /// Replaces the value at a JSON Pointer with JSON text, which is copied
+    /// as-is.
+    pub fn set(&mut self, pointer: &str, json: impl Into<String>) -> Result<&mut Self, RewriteErr> {
+        self.edit(pointer, Edit::Replace(json.into()))
+    }
+
+    /// Removes the value at a JSON Pointer, along with its key if it is an
+    /// object member.
+    pub fn remove(&mut self, pointer: &str) -> Result<&mut Self, RewriteErr> {
+        self.edit(pointer, Edit::Drop)
+    }
+
+    /// Writes out the document with the edits applied. Everything they do
+    /// not touch is copied exactly.
+    pub fn materialize(&self) -> String {
+        let (js, tokens) = (self.base.text(), self.base.tokens());
+        let mut cuts = Vec::new();
+        let mut covered = 0;
+        for (&idx, (parent, edit)) in &self.edits {
+            if idx < covered {
+                continue;
+            }
+            covered = subtree_end(tokens, idx);
+            match edit {
+                Edit::Replace(text) => cuts.push((value_span(tokens, idx), text.as_str())),
+                Edit::Drop => cuts.push((self.removal(idx, *parent), "")),
+                Edit::Keep => {}
+            }
+        }
+        splice(js, cuts)
+    }
+
+    /// The bytes to cut to remove the value at `idx`, with a comma next to
+    /// it when it has siblings.
+    fn removal(&self, idx: usize, parent: Option<usize>) -> Range<usize> {
+        let tokens = self.base.tokens();
+        let Some(parent) = parent else {
+            return value_span(tokens, idx);
+        };
+        let object = tokens[parent].typ == JsmnType::JsmnObject;
+        let siblings: Vec<_> = children(tokens, parent).collect();
+        let dropped = |child: usize| {
+            let value = if object { child + 1 } else { child };
+            matches!(self.edits.get(&value), Some((_, Edit::Drop)))
+        };
+        let spans: Vec<_> = siblings
+            .iter()
+            .map(|&child| value_span(tokens, child))
+            .collect();
+        let child = if object { idx - 1 } else { idx };
+        let i = siblings.iter().position(|&c| c == child).unwrap_or(0);
+
+        // As in dedup_keys, take the comma before the value if a kept one
+        // comes before it, and the comma after it if not.
+        if siblings[..i].iter().any(|&child| !dropped(child)) {
+            spans[i - 1].end..spans[i].end
+        } else if i + 1 < spans.len() {
+            spans[i].start..spans[i + 1].start
+        } else {
+            spans[i].clone()
+        }
+    }
+}
+
+fn key_name(js: &str, tokens: &[JsmnTok], key: usize) -> String {
+    let tok = &tokens[key];
+    unescape(&js[tok.start as usize..tok.end as usize]).into_owned()
+}
+
+/// Copies `js` with the byte ranges in `cuts` replaced by their text. Cuts
+/// may only overlap when both have no text.
+fn splice(js: &str, mut cuts: Vec<(Range<usize>, &str)>) -> String {
+    cuts.sort_by_key(|(cut, _)| cut.start);
+    let mut out = String::with_capacity(js.len());
+    let mut pos = 0;
+    for (cut, text) in cuts {
+        if cut.start > pos {
+            out.push_str(&js[pos..cut.start]);
+        }
+        out.push_str(text);
+        pos = pos.max(cut.end);
+    }
+    out.push_str(&js[pos..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_keep_first_and_last() {
+        let js = r#"{"a": 1, "b": {"x": 1, "x": 2}, "a": 3, "a": 4}"#;
+
+        let first = dedup_keys(js, KeepPolicy::First).unwrap();
+        assert_eq!(first.json, r#"{"a": 1, "b": {"x": 1}}"#);
+        let pointers: Vec<_> = first.dropped.iter().map(|d| d.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/b/x", "/a", "/a"]);
+        assert_eq!(&js[first.dropped[1].span.clone()], r#""a": 3"#);
+
+        let last = dedup_keys(js, KeepPolicy::Last).unwrap();
+        assert_eq!(last.json, r#"{"b": {"x": 2}, "a": 4}"#);
+        assert_eq!(last.dropped.len(), 3);
+    }
+
+    #[test]
+    fn dedup_nested_and_untouched() {
+        let js = "[\n  {\"k\": [{\"k\": 1, \"k\": 2}], \"k\": 0}\n]";
+        let first = dedup_keys(js, KeepPolicy::First).unwrap();
+        assert_eq!(first.json, "[\n  {\"k\": [{\"k\": 1}]}\n]");
+
+        // The dropped member's own duplicates are not reported.
+        let last = dedup_keys(js, KeepPolicy::Last).unwrap();
+        assert_eq!(last.json, "[\n  {\"k\": 0}\n]");
+        assert_eq!(last.dropped.len(), 1);
+        assert_eq!(last.dropped[0].pointer, "/0/k");
+
+        let js = "{ \"a\" : [1,2] ,\"b\":null }";
+        let same = dedup_keys(js, KeepPolicy::First).unwrap();
+        assert_eq!(same.json, js);
+        assert!(same.dropped.is_empty());
+    }
+
+    #[test]
+    fn sort_by_member_key() {
+        let js = "{\"items\": [\n  {\"price\": 3, \"n\": \"c\"},\n  {\"n\": \"x\"},\n  {\"price\": 1.5},\n  {\"price\": 3, \"n\": \"d\"}\n]}";
+        let sorted = sort_array_by(js, "/items", "/price", SortOrder::Ascending).unwrap();
+        assert_eq!(
+            sorted,
+            "{\"items\": [\n  {\"price\": 1.5},\n  {\"price\": 3, \"n\": \"c\"},\n  {\"price\": 3, \"n\": \"d\"},\n  {\"n\": \"x\"}\n]}"
+        );
+
+        let sorted = sort_array_by(js, "/items", "/n", SortOrder::Descending).unwrap();
+        assert_eq!(
+            sorted,
+            "{\"items\": [\n  {\"n\": \"x\"},\n  {\"price\": 3, \"n\": \"d\"},\n  {\"price\": 3, \"n\": \"c\"},\n  {\"price\": 1.5}\n]}"
+        );
+
+        let mixed = r#"["b", 2, null, [0], true, 10]"#;
+        assert_eq!(
+            sort_array_by(mixed, "", "", SortOrder::Ascending).unwrap(),
+            r#"[null, true, 2, 10, "b", [0]]"#
+        );
+    }
+
+    #[test]
+    fn sort_numbers_exactly() {
+        let js = "[9007199254740993, 9007199254740992, 9007199254740992.5, -1e999, 1e999, 2]";
+        assert_eq!(
+            sort_array_by(js, "", "", SortOrder::Ascending).unwrap(),
+            "[-1e999, 2, 9007199254740992, 9007199254740992.5, 9007199254740993, 1e999]"
+        );
+    }
+
+    #[cfg(not(feature = "strict"))]
+    #[test]
+    fn sort_non_numbers_last() {
+        let js = r#"[{"k": NaN}, {"k": 2}, {"k": inf}, {"k": NaN}, {"k": 1}, {"k": "s"}]"#;
+        assert_eq!(
+            sort_array_by(js, "", "/k", SortOrder::Ascending).unwrap(),
+            r#"[{"k": 1}, {"k": 2}, {"k": "s"}, {"k": NaN}, {"k": NaN}, {"k": inf}]"#
+        );
+    }
+
+    #[test]
+    fn sort_errors() {
+        let js = r#"{"items": {}, "list": []}"#;
+        assert_eq!(
+            sort_array_by(js, "/items", "/k", SortOrder::Ascending),
+            Err(RewriteErr::WrongType(JsmnType::JsmnObject))
+        );
+        assert_eq!(
+            sort_array_by(js, "/none", "/k", SortOrder::Ascending),
+            Err(RewriteErr::NotFound)
+        );
+        assert_eq!(
+            sort_array_by(js, "/list", "/k", SortOrder::Ascending).as_deref(),
+            Ok(js)
+        );
+        assert_eq!(
+            sort_array_by(js, "/list", "k", SortOrder::Ascending),
+            Err(RewriteErr::Pointer(PointerErr::MissingSlash))
+        );
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn dedup_normalized_keys() {
+        let js = r#"{"caf\u00e9": 1, "cafe\u0301": 2}"#;
+        assert!(
+            dedup_keys(js, KeepPolicy::First)
+                .unwrap()
+                .dropped
+                .is_empty()
+        );
+
+        let deduped = dedup_keys_with(js, KeepPolicy::First, KeyMatch::Nfc).unwrap();
+        assert_eq!(deduped.json, r#"{"caf\u00e9": 1}"#);
+        assert_eq!(deduped.dropped[0].pointer, "/cafe\u{301}");
+    }
+
+    #[test]
+    fn cow_document_variants() {
+        let template = CowDocument::parse(
+            r#"{"user": {"name": "x", "admin": false}, "tags": ["a", "b", "c"], "n": 1}"#,
+        )
+        .unwrap();
+
+        let mut admin = template.clone();
+        admin
+            .set("/user/name", r#""root""#)
+            .unwrap()
+            .set("/user/admin", "true")
+            .unwrap()
+            .remove("/tags/1")
+            .unwrap();
+        assert_eq!(
+            admin.materialize(),
+            r#"{"user": {"name": "root", "admin": true}, "tags": ["a", "c"], "n": 1}"#
+        );
+
+        let mut bare = template.clone();
+        bare.remove("/user").unwrap().remove("/tags/0").unwrap();
+        bare.remove("/tags/1").unwrap().remove("/n").unwrap();
+        assert_eq!(bare.materialize(), r#"{"tags": ["c"]}"#);
+
+        assert!(!template.is_edited());
+        assert!(std::ptr::eq(admin.base().text(), template.base().text()));
+        assert_eq!(template.materialize(), template.base().text());
+    }
+
+    #[test]
+    fn cow_document_nested_edits() {
+        let mut doc = CowDocument::parse(r#"[{"a": 1, "b": 2}, 3]"#).unwrap();
+        doc.set("/0/a", "10").unwrap().set("/0", "{}").unwrap();
+        assert_eq!(doc.materialize(), "[{}, 3]");
+
+        doc.edit("/0", Edit::Keep).unwrap().remove("/0/b").unwrap();
+        assert_eq!(doc.materialize(), r#"[{"a": 1}, 3]"#);
+        doc.remove("/0").unwrap().remove("/1").unwrap();
+        assert_eq!(doc.materialize(), "[]");
+
+        assert_eq!(doc.set("/2", "0").unwrap_err(), RewriteErr::NotFound);
+        assert_eq!(
+            doc.remove("0").unwrap_err(),
+            RewriteErr::Pointer(PointerErr::MissingSlash)
+        );
+        doc.remove("").unwrap();
+        assert_eq!(doc.materialize(), "");
+    }
+}
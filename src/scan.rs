@@ -0,0 +1,808 @@
+//!
+//! This module works directly on the raw text of a document, finding where
+//! values start and end without tokenizing them. Skipping over a value this
+//! way only looks at brackets and string quotes, so it is much cheaper than
+//! a full parse, and it lets the crate stop early or tokenize only the
+//! parts of a large document that are actually needed.
+//!
+//! Skipped values are only checked for balanced brackets and terminated
+//! strings, anything that ends up tokenized is validated by jsmn as usual.
+//!
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::decode::unescape;
+use crate::pointer::{KeyMatch, PathItem, PathPattern, PointerErr, parse_pointer, token_matches};
+use crate::{JsmnErr, JsmnParser, JsmnTok, JsmnType, jsmn_parse_vec};
+
+/// Error type for the scanning functions.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScanErr {
+    /// The pointer is malformed.
+    Pointer(PointerErr),
+    /// The document is not valid JSON, `pos` is the byte offset where the
+    /// problem was found.
+    Parse { err: JsmnErr, pos: usize },
+    /// There is no value at the pointer.
+    NotFound,
+    /// The value at the pointer does not have the expected type.
+    WrongType(JsmnType),
+}
+
+impl From<PointerErr> for ScanErr {
+    fn from(err: PointerErr) -> Self {
+        ScanErr::Pointer(err)
+    }
+}
+
+fn inval(pos: usize) -> ScanErr {
+    ScanErr::Parse {
+        err: JsmnErr::JsmErrorInval,
+        pos,
+    }
+}
+
+fn part(pos: usize) -> ScanErr {
+    ScanErr::Parse {
+        err: JsmnErr::JsmErrorPart,
+        pos,
+    }
+}
+
+/// The result of [`sample_array`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Tokens for the array followed by its sampled elements. The array
+    /// token's size is the number of elements sampled, and its end is
+    /// `stopped_at`.
+    pub tokens: Vec<JsmnTok>,
+    /// The byte offset where parsing stopped: just past the last sampled
+    /// element, or past the closing bracket if the whole array was read.
+    pub stopped_at: usize,
+    /// Whether the array has no elements beyond the sampled ones.
+    pub complete: bool,
+}
+
+/// Tokenizes only the first `count` elements of the array at `pointer`
+/// (use `""` for a top-level array) and stops there, without looking at
+/// the rest of the document. Token offsets are relative to the start of `js`.
+pub fn sample_array(js: &str, pointer: &str, count: usize) -> Result<Sample, ScanErr> {
+    let bytes = js.as_bytes();
+    let start = locate(bytes, &parse_pointer(pointer)?, KeyMatch::Exact)?;
+    if bytes[start] != b'[' {
+        return Err(ScanErr::WrongType(type_at(bytes, start)));
+    }
+
+    let mut tokens = vec![JsmnTok {
+        typ: JsmnType::JsmnArray,
+        start: start as i32,
+        ..JsmnTok::default()
+    }];
+    let mut elements = Elements::new(bytes, start);
+    let mut stopped_at = start + 1;
+    while (tokens[0].size as usize) < count {
+        let Some(element) = elements.next().transpose()? else {
+            break;
+        };
+        stopped_at = elements.end_of_current()?;
+        tokenize_value(js, element..stopped_at, 0, &mut tokens)?;
+        tokens[0].size += 1;
+    }
+
+    let complete = match elements.next().transpose()? {
+        Some(_) => false,
+        None => {
+            stopped_at = elements.pos;
+            true
+        }
+    };
+    tokens[0].end = stopped_at as i32;
+
+    Ok(Sample {
+        tokens,
+        stopped_at,
+        complete,
+    })
+}
+
+/// Tokenizes the value at `pointer` and nothing else. The document is only
+/// scanned up to the end of that value, so anything after it is never looked
+/// at, not even to check that it is well-formed. The first of the returned
+/// tokens is the value itself, and offsets are relative to the start of `js`.
+pub fn find_pointer_streaming(js: &str, pointer: &str) -> Result<Vec<JsmnTok>, ScanErr> {
+    find_pointer_streaming_with(js, pointer, KeyMatch::Exact)
+}
+
+/// Like [`find_pointer_streaming`], with keys compared according to `keys`.
+pub fn find_pointer_streaming_with(
+    js: &str,
+    pointer: &str,
+    keys: KeyMatch,
+) -> Result<Vec<JsmnTok>, ScanErr> {
+    let bytes = js.as_bytes();
+    let start = locate(bytes, &parse_pointer(pointer)?, keys)?;
+    let end = value_end(bytes, start)?;
+
+    let mut tokens = Vec::new();
+    tokenize_value(js, start..end, -1, &mut tokens)?;
+    Ok(tokens)
+}
+
+/// The result of [`parse_skipping`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skimmed {
+    /// Tokens for the document, where each skipped subtree is a single token
+    /// of its own type spanning the whole value, with a size of 0.
+    pub tokens: Vec<JsmnTok>,
+    /// Indices of the tokens standing in for skipped subtrees.
+    pub skipped: Vec<usize>,
+}
+
+/// Tokenizes a document, except for the objects and arrays selected by any
+/// of the `skip` patterns, which are recorded as one opaque token each. This
+/// keeps the token count down when large parts of a document are of no
+/// interest. Scalars are single tokens already, so they are never skipped.
+///
+/// Skipped subtrees are only checked for balanced brackets and terminated
+/// strings. Patterns are only looked for in the first root of the document.
+pub fn parse_skipping(js: &str, skip: &[PathPattern]) -> Result<Skimmed, ScanErr> {
+    let ranges = find_skipped(js, skip)?;
+
+    // Blank the skipped subtrees out as strings of the same length, so jsmn
+    // only emits one token for each and all the offsets stay the same.
+    let mut blanked = js.as_bytes().to_vec();
+    for range in &ranges {
+        blanked[range.clone()].fill(b'_');
+        blanked[range.start] = b'"';
+        blanked[range.end - 1] = b'"';
+    }
+    let blanked = String::from_utf8(blanked).expect("only whole values are blanked");
+    let mut parser = JsmnParser::new();
+    let mut tokens = Vec::new();
+    jsmn_parse_vec(&mut parser, &blanked, &mut tokens).map_err(|err| ScanErr::Parse {
+        err,
+        pos: parser.pos as usize,
+    })?;
+
+    let mut skipped = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        let index = tokens.partition_point(|tok| (tok.start as usize) <= range.start);
+        let tok = &mut tokens[index];
+        tok.typ = type_at(js.as_bytes(), range.start);
+        tok.start = range.start as i32;
+        tok.end = range.end as i32;
+        skipped.push(index);
+    }
+
+    Ok(Skimmed { tokens, skipped })
+}
+
+enum Container<'a> {
+    Object(Members<'a>),
+    Array(Elements<'a>, usize),
+}
+
+/// Walks the containers of the first root of `js` and returns the byte
+/// ranges of the objects and arrays selected by `skip`, in document order.
+fn find_skipped(js: &str, skip: &[PathPattern]) -> Result<Vec<Range<usize>>, ScanErr> {
+    let bytes = js.as_bytes();
+    let mut ranges = Vec::new();
+    let mut stack = Vec::new();
+    let mut path = Vec::new();
+
+    let mut next = Some(skip_whitespace(bytes, 0));
+    loop {
+        if let Some(pos) = next {
+            match bytes.get(pos) {
+                None => return Err(part(pos)),
+                Some(b'{' | b'[') if skip.iter().any(|pattern| pattern.matches(&path)) => {
+                    let end = value_end(bytes, pos)?;
+                    ranges.push(pos..end);
+                    match stack.last_mut() {
+                        Some(Container::Object(members)) => members.resume_at(end),
+                        Some(Container::Array(elements, _)) => elements.resume_at(end),
+                        None => break,
+                    }
+                }
+                Some(b'{') => stack.push(Container::Object(Members::new(bytes, pos))),
+                Some(b'[') => stack.push(Container::Array(Elements::new(bytes, pos), 0)),
+                Some(_) if stack.is_empty() => break,
+                Some(_) => {}
+            }
+        }
+
+        path.truncate(stack.len().saturating_sub(1));
+        next = match stack.last_mut() {
+            Some(Container::Object(members)) => match members.next().transpose()? {
+                Some((key, value)) => {
+                    let key = unescape(&js[key.start + 1..key.end - 1]);
+                    path.push(PathItem::Key(key.into_owned()));
+                    Some(value)
+                }
+                None => None,
+            },
+            Some(Container::Array(elements, index)) => match elements.next().transpose()? {
+                Some(element) => {
+                    path.push(PathItem::Index(*index));
+                    *index += 1;
+                    Some(element)
+                }
+                None => None,
+            },
+            None => break,
+        };
+
+        if next.is_none() {
+            let end = match stack.pop() {
+                Some(Container::Object(members)) => members.pos,
+                Some(Container::Array(elements, _)) => elements.pos,
+                None => unreachable!(),
+            };
+            match stack.last_mut() {
+                Some(Container::Object(members)) => members.resume_at(end),
+                Some(Container::Array(elements, _)) => elements.resume_at(end),
+                None => break,
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+/// Iterator over the raw byte ranges of the elements of a top-level array,
+/// created by [`split_array`].
+pub struct Chunks<'a> {
+    elements: Elements<'a>,
+}
+
+/// Splits a document holding a top-level array into the byte ranges of its
+/// elements, without tokenizing them. Each range is a complete JSON value
+/// that can be parsed on its own, for instance to spread the elements of a
+/// huge array over several worker threads. The elements are only checked
+/// for balanced brackets and terminated strings.
+pub fn split_array(js: &str) -> Result<Chunks<'_>, ScanErr> {
+    let bytes = js.as_bytes();
+    let start = locate(bytes, &[], KeyMatch::Exact)?;
+    if bytes[start] != b'[' {
+        return Err(ScanErr::WrongType(type_at(bytes, start)));
+    }
+    Ok(Chunks {
+        elements: Elements::new(bytes, start),
+    })
+}
+
+impl Iterator for Chunks<'_> {
+    type Item = Result<Range<usize>, ScanErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = match self.elements.next()? {
+            Ok(start) => start,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(self.elements.end_of_current().map(|end| start..end))
+    }
+}
+
+/// Tokenizes the single value at `range` and appends its tokens, with
+/// offsets relative to the whole of `js`. The root of the value gets
+/// `parent` as its parent.
+pub(crate) fn tokenize_value(
+    js: &str,
+    range: Range<usize>,
+    parent: i32,
+    out: &mut Vec<JsmnTok>,
+) -> Result<(), ScanErr> {
+    let base = out.len() as i32;
+    let mut parser = JsmnParser::new();
+    let mut tokens = Vec::new();
+
+    // jsmn wants a delimiter after a top-level primitive in strict mode, so
+    // scalars are parsed as the only element of an array, which is then
+    // dropped, and everything is shifted back by the opening bracket.
+    let (offset, skip) = match js.as_bytes()[range.start] {
+        b'{' | b'[' => {
+            jsmn_parse_vec(&mut parser, &js[range.clone()], &mut tokens).map_err(|err| {
+                ScanErr::Parse {
+                    err,
+                    pos: range.start + parser.pos as usize,
+                }
+            })?;
+            (range.start as i32, 0)
+        }
+        _ => {
+            let wrapped = format!("[{}]", &js[range.clone()]);
+            jsmn_parse_vec(&mut parser, &wrapped, &mut tokens).map_err(|err| ScanErr::Parse {
+                err,
+                pos: range.start + (parser.pos as usize).saturating_sub(1).min(range.len()),
+            })?;
+            (range.start as i32 - 1, 1)
+        }
+    };
+
+    out.extend(tokens.into_iter().skip(skip).map(|mut tok| {
+        tok.start += offset;
+        tok.end += offset;
+        tok.parent = match tok.parent - skip as i32 {
+            -1 => parent,
+            p => p + base,
+        };
+        tok
+    }));
+    Ok(())
+}
+
+fn type_at(bytes: &[u8], pos: usize) -> JsmnType {
+    match bytes[pos] {
+        b'{' => JsmnType::JsmnObject,
+        b'[' => JsmnType::JsmnArray,
+        b'"' => JsmnType::JsmnString,
+        _ => JsmnType::JsmnPrimitive,
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+pub(crate) fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && is_whitespace(bytes[pos]) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Returns the offset just past the string whose opening quote is at `pos`.
+fn string_end(bytes: &[u8], pos: usize) -> Result<usize, ScanErr> {
+    let mut i = pos + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => return Ok(i + 1),
+            b'\\' => i += 2,
+            _ => i += 1,
+        }
+    }
+    Err(part(pos))
+}
+
+/// Returns the offset just past the value starting at `pos`.
+pub(crate) fn value_end(bytes: &[u8], pos: usize) -> Result<usize, ScanErr> {
+    match bytes.get(pos) {
+        None => Err(part(pos)),
+        Some(b'"') => string_end(bytes, pos),
+        Some(b'{' | b'[') => {
+            let mut open = Vec::new();
+            let mut i = pos;
+            loop {
+                match bytes.get(i) {
+                    None => return Err(part(pos)),
+                    Some(b'"') => {
+                        i = string_end(bytes, i)?;
+                        continue;
+                    }
+                    Some(&c @ (b'{' | b'[')) => open.push(c),
+                    Some(&c @ (b'}' | b']')) => {
+                        let opener = if c == b'}' { b'{' } else { b'[' };
+                        if open.pop() != Some(opener) {
+                            return Err(inval(i));
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+                if open.is_empty() {
+                    return Ok(i);
+                }
+            }
+        }
+        Some(b',' | b':' | b'}' | b']') => Err(inval(pos)),
+        Some(_) => {
+            let mut i = pos;
+            while i < bytes.len() && !is_whitespace(bytes[i]) && !b",:]}".contains(&bytes[i]) {
+                if !(32..127).contains(&bytes[i]) {
+                    return Err(inval(i));
+                }
+                i += 1;
+            }
+            Ok(i)
+        }
+    }
+}
+
+/// Follows the reference tokens of a pointer from the first root of the
+/// document and returns the offset where the designated value starts.
+pub(crate) fn locate(bytes: &[u8], tokens: &[String], keys: KeyMatch) -> Result<usize, ScanErr> {
+    let mut pos = skip_whitespace(bytes, 0);
+    if pos == bytes.len() {
+        return Err(part(pos));
+    }
+
+    for token in tokens {
+        pos = match bytes[pos] {
+            b'{' => {
+                let token = keys.normalize(Cow::Borrowed(token));
+                let mut members = Members::new(bytes, pos);
+                loop {
+                    match members.next().transpose()? {
+                        Some((key, value)) if key_is(bytes, &key, &token, keys) => break value,
+                        Some(_) => continue,
+                        None => return Err(ScanErr::NotFound),
+                    }
+                }
+            }
+            b'[' => {
+                let mut elements = Elements::new(bytes, pos);
+                let mut index = 0;
+                loop {
+                    match elements.next().transpose()? {
+                        Some(element) if token_matches(token, &PathItem::Index(index)) => {
+                            break element;
+                        }
+                        Some(_) => index += 1,
+                        None => return Err(ScanErr::NotFound),
+                    }
+                }
+            }
+            _ => return Err(ScanErr::NotFound),
+        };
+    }
+    Ok(pos)
+}
+
+/// Compares the raw key at `range` (including its quotes) to `key`, which
+/// is already normalized according to `keys`.
+fn key_is(bytes: &[u8], range: &Range<usize>, key: &str, keys: KeyMatch) -> bool {
+    match std::str::from_utf8(&bytes[range.start + 1..range.end - 1]) {
+        Ok(raw) => keys.raw_matches(raw, key),
+        Err(_) => false,
+    }
+}
+
+/// Iterator over the start offsets of the elements of the array whose
+/// opening bracket is at the given offset. An element is only scanned to
+/// its end when the iterator moves past it, or when asked through
+/// `end_of_current`, so the elements before a truncated one can be used.
+/// Once the iterator is exhausted, `pos` is just past the closing bracket.
+pub(crate) struct Elements<'a> {
+    bytes: &'a [u8],
+    pub(crate) pos: usize,
+    first: bool,
+    done: bool,
+    current: Option<usize>,
+}
+
+impl<'a> Elements<'a> {
+    pub(crate) fn new(bytes: &'a [u8], open: usize) -> Self {
+        Elements {
+            bytes,
+            pos: open + 1,
+            first: true,
+            done: false,
+            current: None,
+        }
+    }
+
+    /// Returns the end offset of the element last returned.
+    pub(crate) fn end_of_current(&mut self) -> Result<usize, ScanErr> {
+        if let Some(start) = self.current.take() {
+            match value_end(self.bytes, start) {
+                Ok(end) => self.pos = end,
+                Err(err) => {
+                    self.done = true;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(self.pos)
+    }
+
+    /// Records where the current item ends, for callers that already know.
+    pub(crate) fn resume_at(&mut self, end: usize) {
+        self.current = None;
+        self.pos = end;
+    }
+
+    fn step(&mut self) -> Result<Option<usize>, ScanErr> {
+        self.end_of_current()?;
+        let Some(pos) = separator(self.bytes, self.pos, self.first, b']')? else {
+            self.pos = skip_whitespace(self.bytes, self.pos) + 1;
+            return Ok(None);
+        };
+
+        self.first = false;
+        self.current = Some(pos);
+        Ok(Some(pos))
+    }
+}
+
+impl Iterator for Elements<'_> {
+    type Item = Result<usize, ScanErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.step().transpose();
+        self.done = !matches!(item, Some(Ok(_)));
+        item
+    }
+}
+
+/// The byte range of an object member's key, including its quotes, and
+/// the offset where its value starts.
+pub(crate) type Member = (Range<usize>, usize);
+
+/// Iterator over the members of the object whose opening brace is at the
+/// given offset. Values are scanned lazily, like with [`Elements`], and
+/// `pos` is just past the closing brace once the iterator is exhausted.
+pub(crate) struct Members<'a> {
+    bytes: &'a [u8],
+    pub(crate) pos: usize,
+    first: bool,
+    done: bool,
+    current: Option<usize>,
+}
+
+impl<'a> Members<'a> {
+    pub(crate) fn new(bytes: &'a [u8], open: usize) -> Self {
+        Members {
+            bytes,
+            pos: open + 1,
+            first: true,
+            done: false,
+            current: None,
+        }
+    }
+
+    /// Returns the end offset of the value of the member last returned.
+    pub(crate) fn end_of_current(&mut self) -> Result<usize, ScanErr> {
+        if let Some(start) = self.current.take() {
+            match value_end(self.bytes, start) {
+                Ok(end) => self.pos = end,
+                Err(err) => {
+                    self.done = true;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(self.pos)
+    }
+
+    /// Records where the current item ends, for callers that already know.
+    pub(crate) fn resume_at(&mut self, end: usize) {
+        self.current = None;
+        self.pos = end;
+    }
+
+    fn step(&mut self) -> Result<Option<Member>, ScanErr> {
+        self.end_of_current()?;
+        let bytes = self.bytes;
+        let Some(mut pos) = separator(bytes, self.pos, self.first, b'}')? else {
+            self.pos = skip_whitespace(bytes, self.pos) + 1;
+            return Ok(None);
+        };
+
+        if bytes[pos] != b'"' {
+            return Err(inval(pos));
+        }
+        let key = pos..string_end(bytes, pos)?;
+        pos = skip_whitespace(bytes, key.end);
+        match bytes.get(pos) {
+            Some(b':') => pos = skip_whitespace(bytes, pos + 1),
+            Some(_) => return Err(inval(pos)),
+            None => return Err(part(pos)),
+        }
+        if pos == bytes.len() {
+            return Err(part(pos));
+        }
+
+        self.first = false;
+        self.current = Some(pos);
+        Ok(Some((key, pos)))
+    }
+}
+
+impl Iterator for Members<'_> {
+    type Item = Result<Member, ScanErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.step().transpose();
+        self.done = !matches!(item, Some(Ok(_)));
+        item
+    }
+}
+
+/// Moves past the comma in front of the next item of a container, and
+/// returns where that item starts, or None if the container closes first.
+fn separator(bytes: &[u8], pos: usize, first: bool, close: u8) -> Result<Option<usize>, ScanErr> {
+    let pos = skip_whitespace(bytes, pos);
+    match bytes.get(pos) {
+        None => Err(part(pos)),
+        Some(&c) if c == close => Ok(None),
+        Some(b',') if !first => {
+            let pos = skip_whitespace(bytes, pos + 1);
+            match pos < bytes.len() {
+                true => Ok(Some(pos)),
+                false => Err(part(pos)),
+            }
+        }
+        Some(_) if first => Ok(Some(pos)),
+        Some(_) => Err(inval(pos)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_boundaries() {
+        let js = br#"{"a": "}\"", "b": [1, {"c": null}]} tail"#;
+        assert_eq!(value_end(js, 0), Ok(35));
+        assert_eq!(value_end(js, 6), Ok(11));
+        assert_eq!(value_end(b"true,", 0), Ok(4));
+        assert_eq!(value_end(b"[1, 2", 0), Err(part(0)));
+        assert_eq!(value_end(b"[1}", 0), Err(inval(2)));
+    }
+
+    #[test]
+    fn sample_top_level_array() {
+        let js = r#"[{"id": 1}, "two", 3, 4]"#;
+        let sample = sample_array(js, "", 2).unwrap();
+        assert_eq!(sample.stopped_at, 17);
+        assert!(!sample.complete);
+
+        let types: Vec<_> = sample.tokens.iter().map(|t| t.typ).collect();
+        assert_eq!(
+            types,
+            [
+                JsmnType::JsmnArray,
+                JsmnType::JsmnObject,
+                JsmnType::JsmnString,
+                JsmnType::JsmnPrimitive,
+                JsmnType::JsmnString,
+            ]
+        );
+        assert_eq!((sample.tokens[0].size, sample.tokens[0].end), (2, 17));
+        assert_eq!(
+            &js[sample.tokens[3].start as usize..sample.tokens[3].end as usize],
+            "1"
+        );
+        assert_eq!(
+            &js[sample.tokens[4].start as usize..sample.tokens[4].end as usize],
+            "two"
+        );
+
+        let parents: Vec<_> = sample.tokens.iter().map(|t| t.parent).collect();
+        assert_eq!(parents, [-1, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn sample_pointed_to_array() {
+        let js = r#"{"meta": {"n": 3}, "data": {"items": [10, 20, 30]}}"#;
+        let sample = sample_array(js, "/data/items", 5).unwrap();
+        assert!(sample.complete);
+        assert_eq!(sample.stopped_at, 49);
+        assert_eq!(sample.tokens.len(), 4);
+        assert_eq!(sample.tokens[0].size, 3);
+
+        let empty = sample_array(js, "/data/items", 0).unwrap();
+        assert_eq!(
+            (empty.tokens.len(), empty.stopped_at, empty.complete),
+            (1, 38, false)
+        );
+    }
+
+    #[test]
+    fn sample_errors() {
+        let js = r#"{"a": {"b": 1}, "c": [1, 2]}"#;
+        assert_eq!(sample_array(js, "/x", 1), Err(ScanErr::NotFound));
+        assert_eq!(sample_array(js, "/c/2", 1), Err(ScanErr::NotFound));
+        assert_eq!(
+            sample_array(js, "/a/b", 1),
+            Err(ScanErr::WrongType(JsmnType::JsmnPrimitive))
+        );
+        assert_eq!(
+            sample_array(js, "a", 1),
+            Err(ScanErr::Pointer(PointerErr::MissingSlash))
+        );
+
+        // Truncated input is fine as long as the sample stops before the end.
+        let js = &js[..26];
+        assert_eq!(sample_array(js, "/c", 1).unwrap().tokens.len(), 2);
+        assert_eq!(sample_array(js, "/c", 3), Err(part(26)));
+        assert_eq!(sample_array(js, "/x", 1), Err(part(21)));
+    }
+
+    #[test]
+    fn find_pointer_stops_early() {
+        // Everything past the target is garbage and never gets looked at.
+        let js = r#"{"data": {"items": [{"id": "a\"b"}, {"id": 2}]}, "rest": [}"#;
+        let tokens = find_pointer_streaming(js, "/data/items/0/id").unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].typ, JsmnType::JsmnString);
+        assert_eq!(
+            &js[tokens[0].start as usize..tokens[0].end as usize],
+            r#"a\"b"#
+        );
+
+        let tokens = find_pointer_streaming(js, "/data/items/1").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!((tokens[0].typ, tokens[0].size), (JsmnType::JsmnObject, 1));
+        assert_eq!(
+            tokens.iter().map(|t| t.parent).collect::<Vec<_>>(),
+            [-1, 0, 1]
+        );
+
+        assert_eq!(
+            find_pointer_streaming(js, "/data/items/2"),
+            Err(ScanErr::NotFound)
+        );
+        assert_eq!(find_pointer_streaming(js, "/rest"), Err(inval(58)));
+
+        // The value itself is still checked by jsmn, scalars included.
+        let js = r#"{"a": "\q", "b":       [1, "\q"], "c": x}"#;
+        assert_eq!(find_pointer_streaming(js, "/a"), Err(inval(6)));
+        assert_eq!(find_pointer_streaming(js, "/b"), Err(inval(27)));
+        assert_eq!(sample_array(js, "/b", 2), Err(inval(27)));
+        if cfg!(feature = "strict") {
+            assert_eq!(find_pointer_streaming(js, "/c"), Err(inval(39)));
+        }
+    }
+
+    #[test]
+    fn skip_subtrees() {
+        let js = r#"{"id": 7, "payload": {"blob": [1, 2, 3]}, "list": [{"payload": [1]}, [2]]}"#;
+        let skimmed = parse_skipping(js, &[PathPattern::key("payload")]).unwrap();
+        assert_eq!(skimmed.tokens.len(), 12);
+        assert_eq!(skimmed.skipped, [4, 9]);
+
+        let blob = skimmed.tokens[4];
+        assert_eq!((blob.typ, blob.size), (JsmnType::JsmnObject, 0));
+        assert_eq!(
+            &js[blob.start as usize..blob.end as usize],
+            r#"{"blob": [1, 2, 3]}"#
+        );
+        assert_eq!(skimmed.tokens[9].typ, JsmnType::JsmnArray);
+        assert_eq!((blob.parent, skimmed.tokens[9].parent), (3, 8));
+
+        let skimmed = parse_skipping(js, &[PathPattern::pointer("/list/*").unwrap()]).unwrap();
+        assert_eq!(skimmed.tokens.len(), 14);
+        assert_eq!(skimmed.skipped, [12, 13]);
+        assert_eq!(skimmed.tokens[11].size, 2);
+
+        let skimmed = parse_skipping(js, &[PathPattern::pointer("").unwrap()]).unwrap();
+        assert_eq!((skimmed.tokens.len(), skimmed.skipped.len()), (1, 1));
+
+        assert_eq!(
+            parse_skipping(r#"{"a": [1}"#, &[PathPattern::key("a")]),
+            Err(inval(8))
+        );
+        assert_eq!(
+            parse_skipping(r#"{"a": [1], "b": 2]"#, &[PathPattern::key("a")]),
+            Err(inval(17))
+        );
+    }
+
+    #[test]
+    fn split_top_level_array() {
+        let js = r#" [ {"a": [1, 2]}, "x,y" ,3,[] ] "#;
+        let chunks: Vec<_> = split_array(js)
+            .unwrap()
+            .map(|range| &js[range.unwrap()])
+            .collect();
+        assert_eq!(chunks, [r#"{"a": [1, 2]}"#, r#""x,y""#, "3", "[]"]);
+        assert_eq!(split_array("[]").unwrap().count(), 0);
+
+        assert_eq!(
+            split_array("{}").err(),
+            Some(ScanErr::WrongType(JsmnType::JsmnObject))
+        );
+        let mut chunks = split_array("[1, {]").unwrap();
+        assert_eq!(chunks.next(), Some(Ok(1..2)));
+        assert_eq!(chunks.next(), Some(Err(inval(5))));
+        assert_eq!(chunks.next(), None);
+    }
+}
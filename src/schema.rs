@@ -0,0 +1,445 @@
+//!
+//! This module validates documents against a JSON Schema. The schema is a
+//! JSON document itself, parsed with jsmn and compiled once into matchers
+//! that are then run over the tokens of every instance, without building a
+//! tree of either.
+//!
+//! Only a subset of draft 2020-12 is understood: `type`, `enum`, `const`,
+//! `properties`, `required`, `additionalProperties`, `items`, `minItems`,
+//! `maxItems`, `minLength`, `maxLength`, `minimum`, `maximum`,
+//! `exclusiveMinimum` and `exclusiveMaximum`, along with the `true` and
+//! `false` schemas. Other keywords are ignored, as the specification says
+//! of unknown ones, so a schema using `$ref` or `oneOf` is checked less
+//! strictly than it asks for rather than rejected.
+//!
+//! Numbers are compared as `f64`, and `1.0` is an integer, as it is for
+//! JSON Schema. String lengths count Unicode code points.
+//!
+
+use std::error::Error;
+use std::fmt;
+
+use crate::value::{Value, ValueErr, ValueErrKind};
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Error type for compiling schemas and for validating unparsed documents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaErr {
+    /// The document is not valid JSON.
+    Parse(JsmnErr),
+    /// The schema uses a keyword wrongly, or the instance does not match
+    /// the schema. The pointer says where, in the one or the other.
+    Invalid(ValueErr),
+}
+
+impl From<JsmnErr> for SchemaErr {
+    fn from(err: JsmnErr) -> Self {
+        SchemaErr::Parse(err)
+    }
+}
+
+impl From<ValueErr> for SchemaErr {
+    fn from(err: ValueErr) -> Self {
+        SchemaErr::Invalid(err)
+    }
+}
+
+impl fmt::Display for SchemaErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaErr::Parse(err) => write!(f, "invalid JSON: {:?}", err),
+            SchemaErr::Invalid(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for SchemaErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SchemaErr::Parse(_) => None,
+            SchemaErr::Invalid(err) => Some(err),
+        }
+    }
+}
+
+/// A compiled JSON Schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// The `true` schema, or `false`, which nothing matches.
+    Bool(bool),
+    Rules(Box<Rules>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Rules {
+    /// The accepted types, as a mask of `TYPES` bits, or None for all.
+    types: Option<u8>,
+    enumeration: Option<Vec<Literal>>,
+    properties: Vec<(String, Node)>,
+    required: Vec<String>,
+    additional_properties: Option<Node>,
+    items: Option<Node>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+}
+
+/// The type names of JSON Schema, with their bits in a type mask.
+const TYPES: [(&str, u8); 7] = [
+    ("null", 1),
+    ("boolean", 2),
+    ("object", 4),
+    ("array", 8),
+    ("number", 16),
+    ("integer", 32),
+    ("string", 64),
+];
+
+/// A value of `enum` or `const`, compared by value: object members are
+/// sorted by key, the last of duplicates kept.
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Literal>),
+    Object(Vec<(String, Literal)>),
+}
+
+impl Literal {
+    fn new(value: Value) -> Option<Literal> {
+        Some(match value.typ() {
+            JsmnType::JsmnObject => {
+                let mut members = Vec::new();
+                for (key, value) in value.members() {
+                    members.retain(|(k, _)| *k != key);
+                    members.push((key.into_owned(), Literal::new(value)?));
+                }
+                members.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Literal::Object(members)
+            }
+            JsmnType::JsmnArray => {
+                Literal::Array(value.elements().map(Literal::new).collect::<Option<_>>()?)
+            }
+            JsmnType::JsmnString => Literal::String(value.as_str()?.into_owned()),
+            _ if value.is_null() => Literal::Null,
+            _ => match value.as_bool() {
+                Some(b) => Literal::Bool(b),
+                None => Literal::Number(value.as_f64_exact()?),
+            },
+        })
+    }
+}
+
+impl Schema {
+    /// Parses and compiles a schema.
+    pub fn compile(js: &str) -> Result<Schema, SchemaErr> {
+        let tokens = parse_to_vec(js)?;
+        let root = Value::new(js, &tokens, 0).ok_or(JsmnErr::JsmErrorPart)?;
+        Ok(Schema {
+            root: compile(root)?,
+        })
+    }
+
+    /// Checks the first root of a parsed document, and reports the first
+    /// place where it does not match, with its pointer.
+    pub fn validate(&self, js: &str, tokens: &[JsmnTok]) -> Result<(), ValueErr> {
+        match Value::new(js, tokens, 0) {
+            Some(root) => check(&self.root, root),
+            None => Err(ValueErr {
+                pointer: String::new(),
+                kind: ValueErrKind::Invalid("empty document".into()),
+            }),
+        }
+    }
+
+    /// Parses `js` and checks it.
+    pub fn validate_str(&self, js: &str) -> Result<(), SchemaErr> {
+        let tokens = parse_to_vec(js)?;
+        Ok(self.validate(js, &tokens)?)
+    }
+}
+
+fn invalid(value: Value, reason: impl Into<String>) -> ValueErr {
+    value.error(ValueErrKind::Invalid(reason.into()))
+}
+
+fn compile(schema: Value) -> Result<Node, ValueErr> {
+    if let Some(b) = schema.as_bool() {
+        return Ok(Node::Bool(b));
+    }
+    if schema.typ() != JsmnType::JsmnObject {
+        return Err(schema.wrong_type());
+    }
+
+    let mut rules = Rules::default();
+    for (key, value) in schema.members() {
+        let count = || {
+            value
+                .as_i64()
+                .and_then(|n| usize::try_from(n).ok())
+                .ok_or_else(|| invalid(value, "expected a non-negative integer"))
+        };
+        match key.as_ref() {
+            "type" => {
+                let names: Vec<_> = match value.typ() {
+                    JsmnType::JsmnArray => value.elements().collect(),
+                    _ => vec![value],
+                };
+                let mut mask = 0;
+                for name in names {
+                    let text = name.read_str()?;
+                    let (_, bit) = TYPES
+                        .iter()
+                        .find(|(type_name, _)| *type_name == text)
+                        .ok_or_else(|| invalid(name, format!("unknown type {:?}", text)))?;
+                    mask |= bit;
+                }
+                rules.types = Some(mask);
+            }
+            "enum" | "const" => {
+                let values: Vec<_> = match key.as_ref() {
+                    "enum" if value.typ() == JsmnType::JsmnArray => value.elements().collect(),
+                    "enum" => return Err(value.wrong_type()),
+                    _ => vec![value],
+                };
+                let literals = values
+                    .into_iter()
+                    .map(|value| Literal::new(value).ok_or_else(|| invalid(value, "invalid value")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                rules.enumeration = Some(literals);
+            }
+            "properties" => {
+                if value.typ() != JsmnType::JsmnObject {
+                    return Err(value.wrong_type());
+                }
+                for (name, property) in value.members() {
+                    rules
+                        .properties
+                        .push((name.into_owned(), compile(property)?));
+                }
+            }
+            "required" => {
+                if value.typ() != JsmnType::JsmnArray {
+                    return Err(value.wrong_type());
+                }
+                for name in value.elements() {
+                    rules.required.push(name.read_str()?.into_owned());
+                }
+            }
+            "additionalProperties" => rules.additional_properties = Some(compile(value)?),
+            "items" => rules.items = Some(compile(value)?),
+            "minItems" => rules.min_items = Some(count()?),
+            "maxItems" => rules.max_items = Some(count()?),
+            "minLength" => rules.min_length = Some(count()?),
+            "maxLength" => rules.max_length = Some(count()?),
+            "minimum" => rules.minimum = Some(value.read_f64()?),
+            "maximum" => rules.maximum = Some(value.read_f64()?),
+            "exclusiveMinimum" => rules.exclusive_minimum = Some(value.read_f64()?),
+            "exclusiveMaximum" => rules.exclusive_maximum = Some(value.read_f64()?),
+            _ => {}
+        }
+    }
+    Ok(Node::Rules(Box::new(rules)))
+}
+
+/// Returns the type bits of a value: an integer is a number too.
+fn type_bits(value: Value) -> u8 {
+    match value.typ() {
+        JsmnType::JsmnObject => 4,
+        JsmnType::JsmnArray => 8,
+        JsmnType::JsmnString => 64,
+        _ if value.is_null() => 1,
+        _ if value.as_bool().is_some() => 2,
+        _ if value.number_text().is_some() => {
+            let integer = value.as_f64_exact().is_some_and(|n| n.fract() == 0.0);
+            16 | if integer { 32 } else { 0 }
+        }
+        _ => 0,
+    }
+}
+
+fn check(node: &Node, value: Value) -> Result<(), ValueErr> {
+    let rules = match node {
+        Node::Bool(true) => return Ok(()),
+        Node::Bool(false) => return Err(invalid(value, "no value is allowed here")),
+        Node::Rules(rules) => rules,
+    };
+
+    if let Some(mask) = rules.types
+        && type_bits(value) & mask == 0
+    {
+        let names: Vec<_> = TYPES
+            .iter()
+            .filter(|(_, bit)| mask & bit != 0)
+            .map(|(name, _)| *name)
+            .collect();
+        return Err(invalid(value, format!("expected {}", names.join(" or "))));
+    }
+    if let Some(literals) = &rules.enumeration
+        && !Literal::new(value).is_some_and(|literal| literals.contains(&literal))
+    {
+        return Err(invalid(value, "not one of the allowed values"));
+    }
+
+    match value.typ() {
+        JsmnType::JsmnObject => check_object(rules, value),
+        JsmnType::JsmnArray => {
+            let len = value.elements().count();
+            if rules.min_items.is_some_and(|min| len < min) {
+                return Err(invalid(value, "too few items"));
+            }
+            if rules.max_items.is_some_and(|max| len > max) {
+                return Err(invalid(value, "too many items"));
+            }
+            if let Some(items) = &rules.items {
+                for item in value.elements() {
+                    check(items, item)?;
+                }
+            }
+            Ok(())
+        }
+        JsmnType::JsmnString => {
+            let len = value.as_str().map_or(0, |s| s.chars().count());
+            if rules.min_length.is_some_and(|min| len < min) {
+                return Err(invalid(value, "too short"));
+            }
+            if rules.max_length.is_some_and(|max| len > max) {
+                return Err(invalid(value, "too long"));
+            }
+            Ok(())
+        }
+        _ => {
+            let Some(n) = value.as_f64_exact() else {
+                return Ok(());
+            };
+            if rules.minimum.is_some_and(|min| n < min) {
+                return Err(invalid(value, "less than the minimum"));
+            }
+            if rules.maximum.is_some_and(|max| n > max) {
+                return Err(invalid(value, "more than the maximum"));
+            }
+            if rules.exclusive_minimum.is_some_and(|min| n <= min) {
+                return Err(invalid(value, "not more than the exclusive minimum"));
+            }
+            if rules.exclusive_maximum.is_some_and(|max| n >= max) {
+                return Err(invalid(value, "not less than the exclusive maximum"));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_object(rules: &Rules, object: Value) -> Result<(), ValueErr> {
+    for name in &rules.required {
+        if object.get(name).is_none() {
+            return Err(invalid(object, format!("missing key {:?}", name)));
+        }
+    }
+    for (key, value) in object.members() {
+        let property = rules.properties.iter().find(|(name, _)| *name == key);
+        match (property, &rules.additional_properties) {
+            (Some((_, node)), _) | (None, Some(node)) => check(node, value)?,
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERSON: &str = r#"{
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": {
+            "name": {"type": "string", "minLength": 1, "maxLength": 5},
+            "age": {"type": "integer", "minimum": 0, "exclusiveMaximum": 150},
+            "role": {"enum": ["admin", "user", {"custom": [1, 2]}]},
+            "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2},
+            "nick": {"type": ["string", "null"]}
+        },
+        "required": ["name"],
+        "additionalProperties": false
+    }"#;
+
+    fn pointer(schema: &Schema, js: &str) -> Option<String> {
+        match schema.validate_str(js) {
+            Ok(()) => None,
+            Err(SchemaErr::Invalid(err)) => Some(err.pointer),
+            Err(err) => panic!("{}: {}", js, err),
+        }
+    }
+
+    #[test]
+    fn matching_documents() {
+        let schema = Schema::compile(PERSON).unwrap();
+        for js in [
+            r#"{"name": "ana"}"#,
+            r#"{"name": "café", "age": 3.0, "nick": null}"#,
+            r#"{"name": "bo", "role": "user", "tags": ["a", "b"]}"#,
+            r#"{"name": "x", "role": {"custom": [1.0, 2e0]}}"#,
+            r#"{"name": "x", "role": {"custom": [3], "custom": [1, 2]}}"#,
+        ] {
+            assert_eq!(pointer(&schema, js), None, "{}", js);
+        }
+        let any = Schema::compile("true\n").unwrap();
+        assert_eq!(any.validate_str("[1, {}]\n"), Ok(()));
+    }
+
+    #[test]
+    fn violations() {
+        let schema = Schema::compile(PERSON).unwrap();
+        for (js, at) in [
+            ("[]", ""),
+            (r#"{"age": 3}"#, ""),
+            (r#"{"name": ""}"#, "/name"),
+            (r#"{"name": "abcdef"}"#, "/name"),
+            (r#"{"name": "a", "age": 1.5}"#, "/age"),
+            (r#"{"name": "a", "age": -1}"#, "/age"),
+            (r#"{"name": "a", "age": 150}"#, "/age"),
+            (r#"{"name": "a", "role": "root"}"#, "/role"),
+            (r#"{"name": "a", "tags": ["b", 2]}"#, "/tags/1"),
+            (r#"{"name": "a", "tags": ["b", "c", "d"]}"#, "/tags"),
+            (r#"{"name": "a", "nick": 1}"#, "/nick"),
+            (r#"{"name": "a", "extra": 1}"#, "/extra"),
+        ] {
+            assert_eq!(pointer(&schema, js).as_deref(), Some(at), "{}", js);
+        }
+
+        let err = schema.validate_str(r#"{"age": 3}"#).unwrap_err();
+        assert_eq!(err.to_string(), r#": missing key "name""#);
+        assert_eq!(
+            schema.validate_str("[1, 2"),
+            Err(SchemaErr::Parse(JsmnErr::JsmErrorPart))
+        );
+    }
+
+    #[test]
+    fn invalid_schemas() {
+        let compile = |js| match Schema::compile(js) {
+            Err(SchemaErr::Invalid(err)) => err.pointer,
+            other => panic!("{}: {:?}", js, other),
+        };
+        assert_eq!(compile("[]"), "");
+        assert_eq!(compile(r#"{"type": "float"}"#), "/type");
+        assert_eq!(
+            compile(r#"{"items": {"type": ["string", 1]}}"#),
+            "/items/type/1"
+        );
+        assert_eq!(compile(r#"{"minItems": -1}"#), "/minItems");
+        assert_eq!(compile(r#"{"required": "name"}"#), "/required");
+    }
+}
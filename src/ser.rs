@@ -0,0 +1,215 @@
+//!
+//! This module produces JSON from scratch, as the other half of a parser
+//! that only tokenizes. A `JsonWriter` is handed values, keys and the
+//! opening and closing of containers in document order, and writes them
+//! compactly into a `String` or anything else implementing `fmt::Write`,
+//! taking care of commas, colons and string escapes.
+//!
+//! Numbers are written like the writer module writes normalized numbers,
+//! in their shortest round-trip form. JSON has no spelling for infinities
+//! and NaN, which are written as `null`, as ECMAScript does.
+//!
+//! The writer does not check that containers are balanced or that keys
+//! and values alternate in objects: calls out of order produce invalid
+//! JSON rather than an error.
+//!
+
+use std::fmt::{self, Write};
+
+use crate::write::{format_float, format_int};
+
+/// Writes JSON values into `W`.
+#[derive(Debug, Clone, Default)]
+pub struct JsonWriter<W> {
+    out: W,
+    // For each open container, whether anything was written in it yet.
+    open: Vec<bool>,
+    after_key: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(out: W) -> Self {
+        JsonWriter {
+            out,
+            open: Vec::new(),
+            after_key: false,
+        }
+    }
+
+    /// The output written so far.
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes the comma separating a value from the previous one, if any.
+    fn separate(&mut self) -> fmt::Result {
+        if std::mem::take(&mut self.after_key) {
+            return Ok(());
+        }
+        if let Some(written) = self.open.last_mut()
+            && std::mem::replace(written, true)
+        {
+            self.out.write_char(',')?;
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, text: &str) -> Result<&mut Self, fmt::Error> {
+        self.separate()?;
+        self.out.write_str(text)?;
+        Ok(self)
+    }
+
+    pub fn null(&mut self) -> Result<&mut Self, fmt::Error> {
+        self.value("null")
+    }
+
+    pub fn bool(&mut self, b: bool) -> Result<&mut Self, fmt::Error> {
+        self.value(if b { "true" } else { "false" })
+    }
+
+    pub fn i64(&mut self, n: i64) -> Result<&mut Self, fmt::Error> {
+        self.value(&format_int(n))
+    }
+
+    pub fn u64(&mut self, n: u64) -> Result<&mut Self, fmt::Error> {
+        self.value(&n.to_string())
+    }
+
+    /// Writes a number in its shortest round-trip form, or `null` if it is
+    /// not finite.
+    pub fn f64(&mut self, n: f64) -> Result<&mut Self, fmt::Error> {
+        if n.is_finite() {
+            self.value(&format_float(n))
+        } else {
+            self.null()
+        }
+    }
+
+    /// Writes a string, escaping quotes, backslashes and control
+    /// characters.
+    pub fn str(&mut self, s: &str) -> Result<&mut Self, fmt::Error> {
+        self.separate()?;
+        write_escaped(&mut self.out, s)?;
+        Ok(self)
+    }
+
+    /// Writes the key of the next member of an object.
+    pub fn key(&mut self, key: &str) -> Result<&mut Self, fmt::Error> {
+        self.str(key)?;
+        self.out.write_char(':')?;
+        self.after_key = true;
+        Ok(self)
+    }
+
+    pub fn begin_object(&mut self) -> Result<&mut Self, fmt::Error> {
+        self.value("{")?;
+        self.open.push(false);
+        Ok(self)
+    }
+
+    pub fn end_object(&mut self) -> Result<&mut Self, fmt::Error> {
+        self.open.pop();
+        self.out.write_char('}')?;
+        Ok(self)
+    }
+
+    pub fn begin_array(&mut self) -> Result<&mut Self, fmt::Error> {
+        self.value("[")?;
+        self.open.push(false);
+        Ok(self)
+    }
+
+    pub fn end_array(&mut self) -> Result<&mut Self, fmt::Error> {
+        self.open.pop();
+        self.out.write_char(']')?;
+        Ok(self)
+    }
+}
+
+/// Writes `s` as a quoted JSON string.
+pub(crate) fn write_escaped(out: &mut impl Write, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    let mut rest = s;
+    while let Some(i) = rest.find(|c: char| c == '"' || c == '\\' || c < ' ') {
+        out.write_str(&rest[..i])?;
+        match rest.as_bytes()[i] {
+            b'"' => out.write_str("\\\"")?,
+            b'\\' => out.write_str("\\\\")?,
+            b'\n' => out.write_str("\\n")?,
+            b'\r' => out.write_str("\\r")?,
+            b'\t' => out.write_str("\\t")?,
+            0x08 => out.write_str("\\b")?,
+            0x0c => out.write_str("\\f")?,
+            byte => write!(out, "\\u{:04x}", byte)?,
+        }
+        rest = &rest[i + 1..];
+    }
+    out.write_str(rest)?;
+    out.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+    use crate::value::Value;
+
+    #[test]
+    fn nested_containers() -> fmt::Result {
+        let mut writer = JsonWriter::new(String::new());
+        writer
+            .begin_object()?
+            .key("id")?
+            .i64(-7)?
+            .key("tags")?
+            .begin_array()?
+            .str("a")?
+            .begin_object()?
+            .end_object()?
+            .begin_array()?
+            .end_array()?
+            .null()?
+            .end_array()?
+            .key("ok")?
+            .bool(true)?
+            .end_object()?;
+        assert_eq!(
+            writer.get_ref(),
+            r#"{"id":-7,"tags":["a",{},[],null],"ok":true}"#
+        );
+
+        let mut writer = JsonWriter::new(String::new());
+        writer.u64(u64::MAX)?;
+        assert_eq!(writer.into_inner(), "18446744073709551615");
+        Ok(())
+    }
+
+    #[test]
+    fn strings_and_numbers_read_back() -> fmt::Result {
+        let text = "quote \" slash \\ / tab \t nul \0 bell \u{7} é 😀";
+        let mut writer = JsonWriter::new(String::new());
+        writer
+            .begin_array()?
+            .str(text)?
+            .f64(0.1)?
+            .f64(1e21)?
+            .f64(f64::NAN)?
+            .end_array()?;
+        let js = writer.into_inner();
+        assert!(js.contains(r#"\u0000 bell \u0007"#), "{}", js);
+
+        let tokens = parse_to_vec(&js).unwrap();
+        let array = Value::new(&js, &tokens, 0).unwrap();
+        assert_eq!(array.at(0).and_then(|s| s.as_str()).as_deref(), Some(text));
+        assert_eq!(array.at(1).and_then(|n| n.as_f64_exact()), Some(0.1));
+        assert_eq!(array.at(2).map(|n| n.text()), Some("1e+21"));
+        assert!(array.at(3).unwrap().is_null());
+        Ok(())
+    }
+}
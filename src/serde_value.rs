@@ -0,0 +1,109 @@
+//!
+//! This module converts parsed documents into `serde_json::Value` trees,
+//! behind the "serde_json" feature. It lets jsmn do the quick work, such as
+//! looking at a few fields to decide what to do with a document, and hands
+//! the documents that need it over to code built around serde_json.
+//!
+//! Integers are kept exact when they fit in an `i64` or a `u64`, and other
+//! numbers become the nearest `f64`. Objects are serde_json maps, so when a
+//! key appears several times the last member wins, as everywhere else in
+//! the crate. Without the strict feature jsmn accepts primitives that are
+//! neither literals nor numbers, which are reported as invalid.
+//!
+
+use serde_json::{Map, Number};
+
+use crate::value::{Value, ValueErr, ValueErrKind};
+use crate::{JsmnTok, JsmnType};
+
+/// Converts the first root of a parsed document.
+pub fn to_serde_value(js: &str, tokens: &[JsmnTok]) -> Result<serde_json::Value, ValueErr> {
+    match Value::new(js, tokens, 0) {
+        Some(root) => value_to_serde(root),
+        None => Err(ValueErr {
+            pointer: String::new(),
+            kind: ValueErrKind::Invalid("empty document".into()),
+        }),
+    }
+}
+
+/// Converts the value a cursor points to.
+pub fn value_to_serde(value: Value) -> Result<serde_json::Value, ValueErr> {
+    Ok(match value.typ() {
+        JsmnType::JsmnObject => {
+            let mut map = Map::new();
+            for (key, value) in value.members() {
+                map.insert(key.into_owned(), value_to_serde(value)?);
+            }
+            serde_json::Value::Object(map)
+        }
+        JsmnType::JsmnArray => serde_json::Value::Array(
+            value
+                .elements()
+                .map(value_to_serde)
+                .collect::<Result<_, _>>()?,
+        ),
+        JsmnType::JsmnString => serde_json::Value::String(value.read_str()?.into_owned()),
+        JsmnType::JsmnPrimitive if value.is_null() => serde_json::Value::Null,
+        JsmnType::JsmnPrimitive => match (value.as_bool(), value.number_text()) {
+            (Some(b), _) => serde_json::Value::Bool(b),
+            (None, Some(text)) => serde_json::Value::Number(number(value, text)?),
+            (None, None) => {
+                return Err(value.error(ValueErrKind::Invalid("not a literal or a number".into())));
+            }
+        },
+        typ => return Err(value.error(ValueErrKind::WrongType(typ))),
+    })
+}
+
+fn number(value: Value, text: &str) -> Result<Number, ValueErr> {
+    if let Ok(n) = text.parse::<i64>() {
+        return Ok(n.into());
+    }
+    if let Ok(n) = text.parse::<u64>() {
+        return Ok(n.into());
+    }
+    Number::from_f64(value.read_f64()?)
+        .ok_or_else(|| value.error(ValueErrKind::Invalid("out of range".into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_vec;
+
+    #[test]
+    fn documents_convert() {
+        let js = r#"{"id": -7, "big": 18446744073709551615, "pi": 3.25, "e": 1e2,
+                     "name": "café", "tags": ["a", null, true], "id": 8}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let value = to_serde_value(js, &tokens).unwrap();
+
+        assert_eq!(value["id"].as_i64(), Some(8));
+        assert_eq!(value["big"].as_u64(), Some(u64::MAX));
+        assert_eq!(value["pi"].as_f64(), Some(3.25));
+        assert_eq!(value["e"].as_f64(), Some(100.0));
+        assert_eq!(value["name"].as_str(), Some("café"));
+        assert_eq!(value["tags"][0].as_str(), Some("a"));
+        assert!(value["tags"][1].is_null());
+        assert_eq!(value["tags"][2].as_bool(), Some(true));
+        assert_eq!(value.as_object().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn invalid_documents() {
+        assert_eq!(to_serde_value("", &[]).unwrap_err().pointer, "");
+
+        let js = r#"{"a": [1, 1e999]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(to_serde_value(js, &tokens).unwrap_err().pointer, "/a/1");
+
+        #[cfg(not(feature = "strict"))]
+        {
+            let js = r#"{"a": yes}"#;
+            let tokens = parse_to_vec(js).unwrap();
+            let err = to_serde_value(js, &tokens).unwrap_err();
+            assert_eq!(err.pointer, "/a");
+        }
+    }
+}
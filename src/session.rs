@@ -0,0 +1,133 @@
+//!
+//! This module provides `Session`, a parser and a token buffer kept
+//! together for parsing one document after another, as in a loop handling
+//! requests. Each parse starts from a fresh parser state, so there is
+//! nothing to reset by hand, and the buffer only ever grows: once it has
+//! reached the size of the largest document, parsing allocates nothing,
+//! and the tokens left over from before are overwritten rather than
+//! cleared.
+//!
+
+use crate::value::Value;
+use crate::{JsmnErr, JsmnParser, JsmnTok, ParseError};
+
+/// A parser with tokens to reuse across documents.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    parser: JsmnParser,
+    tokens: Vec<JsmnTok>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// A session with room for `tokens` tokens before it needs to grow.
+    pub fn with_capacity(tokens: usize) -> Self {
+        Session {
+            parser: JsmnParser::new(),
+            tokens: vec![JsmnTok::default(); tokens],
+        }
+    }
+
+    /// The number of tokens a document can have without the buffer growing.
+    pub fn capacity(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Parses `js` and returns its tokens, which stay borrowed from the
+    /// session until the next parse. The buffer is doubled whenever jsmn
+    /// runs out of tokens, as with [`crate::jsmn_parse_vec`].
+    pub fn parse<'a>(&'a mut self, js: &'a str) -> Result<Tokens<'a>, ParseError> {
+        self.parser.reset();
+        if self.tokens.is_empty() {
+            self.tokens.resize(js.len() / 8 + 8, JsmnTok::default());
+        }
+        loop {
+            match self.parser.parse(js, &mut self.tokens) {
+                Ok(count) => {
+                    return Ok(Tokens {
+                        js,
+                        tokens: &self.tokens[..count],
+                    });
+                }
+                Err(JsmnErr::JsmErrorNoMem) => {
+                    let len = self.tokens.len() * 2;
+                    self.tokens.resize(len, JsmnTok::default());
+                }
+                Err(err) => return Err(self.parser.error(err)),
+            }
+        }
+    }
+}
+
+/// The tokens of a document parsed by a [`Session`], with its text.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tokens<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+}
+
+impl<'a> Tokens<'a> {
+    pub fn js(&self) -> &'a str {
+        self.js
+    }
+
+    pub fn tokens(&self) -> &'a [JsmnTok] {
+        self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Returns the value of the token at `idx`.
+    pub fn value(&self, idx: usize) -> Option<Value<'a>> {
+        Value::new(self.js, self.tokens, idx)
+    }
+
+    /// Returns the first root of the document, or None if it is empty.
+    pub fn root(&self) -> Option<Value<'a>> {
+        self.value(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_in_a_row() {
+        let mut session = Session::new();
+        let js = r#"{"id": 1, "tags": ["a", "b"]}"#;
+        let tokens = session.parse(js).unwrap();
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(tokens.js(), js);
+        assert_eq!(tokens.root().unwrap().get("id").unwrap().as_i64(), Some(1));
+
+        let tokens = session.parse("[true]").unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens.tokens()[0].end, 6);
+        assert_eq!(tokens.value(1).unwrap().as_bool(), Some(true));
+        assert!(session.parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn growth_and_errors() {
+        let mut session = Session::with_capacity(2);
+        let js = format!("[{}]", vec!["{\"a\": 1}"; 50].join(", "));
+        assert_eq!(session.parse(&js).unwrap().len(), 151);
+        let capacity = session.capacity();
+        assert!(capacity >= 151);
+
+        let err = session.parse(r#"{"a": [1, "#).unwrap_err();
+        assert_eq!((err.kind, err.tokens), (JsmnErr::JsmErrorPart, 4));
+        assert_eq!(session.parse("[1, 2]").unwrap().len(), 3);
+        assert_eq!(session.capacity(), capacity);
+    }
+}
@@ -0,0 +1,176 @@
+//!
+//! This module provides `SharedDocument`, a parsed document that owns its
+//! text and tokens behind reference counts. Cloning one only bumps the
+//! counts, and since neither the text nor the tokens ever change, the
+//! clones can be handed to other threads and queried there at the same
+//! time. It suits reference data parsed once and read everywhere, such as
+//! feature flags or schemas served across a thread pool.
+//!
+//! A `SubDoc` narrows a shared document down to one of its values, such as
+//! an element of a large array, and keeps the whole document alive for as
+//! long as it exists. Elements can so be dispatched to separate workers
+//! without copying either the text or the token table.
+//!
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::pointer::PointerErr;
+use crate::tree::{children, subtree_end, value_span};
+use crate::value::Value;
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// A parsed document that is cheap to clone and to share across threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedDocument {
+    text: Arc<str>,
+    tokens: Arc<[JsmnTok]>,
+}
+
+impl SharedDocument {
+    /// Parses `text`, taking it over without copying when it is already
+    /// an `Arc<str>`.
+    pub fn parse(text: impl Into<Arc<str>>) -> Result<Self, JsmnErr> {
+        let text = text.into();
+        let tokens = parse_to_vec(&text)?.into();
+        Ok(SharedDocument { text, tokens })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn tokens(&self) -> &[JsmnTok] {
+        &self.tokens
+    }
+
+    /// Returns the value of the token at `idx`.
+    pub fn value(&self, idx: usize) -> Option<Value<'_>> {
+        Value::new(&self.text, &self.tokens, idx)
+    }
+
+    /// Returns the first root of the document, or None if it is empty.
+    pub fn root(&self) -> Option<Value<'_>> {
+        self.value(0)
+    }
+
+    /// Returns the value at a JSON Pointer from the first root.
+    pub fn pointer(&self, pointer: &str) -> Result<Option<Value<'_>>, PointerErr> {
+        match self.root() {
+            Some(root) => root.pointer(pointer),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns a handle on the value of the token at `idx`.
+    pub fn subdoc(&self, idx: usize) -> Option<SubDoc> {
+        (idx < self.tokens.len()).then(|| SubDoc {
+            doc: self.clone(),
+            root: idx,
+        })
+    }
+
+    /// Returns a handle on every element of the array at a JSON Pointer.
+    /// Anything other than an array has no elements.
+    pub fn elements(&self, pointer: &str) -> Result<Vec<SubDoc>, PointerErr> {
+        let Some(array) = self.pointer(pointer)? else {
+            return Ok(Vec::new());
+        };
+        if array.typ() != JsmnType::JsmnArray {
+            return Ok(Vec::new());
+        }
+        Ok(children(&self.tokens, array.index())
+            .filter_map(|idx| self.subdoc(idx))
+            .collect())
+    }
+}
+
+/// A value of a shared document, which it keeps alive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubDoc {
+    doc: SharedDocument,
+    root: usize,
+}
+
+impl SubDoc {
+    /// The whole document this value belongs to.
+    pub fn document(&self) -> &SharedDocument {
+        &self.doc
+    }
+
+    /// The range of the tokens of the value in the document's token table.
+    pub fn token_range(&self) -> Range<usize> {
+        self.root..subtree_end(&self.doc.tokens, self.root)
+    }
+
+    /// The tokens of the value. Their offsets, like their parent links, are
+    /// still relative to the whole document.
+    pub fn tokens(&self) -> &[JsmnTok] {
+        &self.doc.tokens[self.token_range()]
+    }
+
+    /// The JSON text of the value, with the quotes of a string.
+    pub fn text(&self) -> &str {
+        &self.doc.text[value_span(&self.doc.tokens, self.root)]
+    }
+
+    /// The value itself. Its path, and that of the errors about it, starts
+    /// from the root of the whole document.
+    pub fn value(&self) -> Value<'_> {
+        Value::new(&self.doc.text, &self.doc.tokens, self.root)
+            .expect("subdoc root is a valid token")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedDocument>();
+
+        let doc = SharedDocument::parse(r#"{"flags": {"beta": true, "limit": 20}}"#).unwrap();
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let doc = doc.clone();
+                thread::spawn(move || {
+                    let limit = doc.pointer("/flags/limit").unwrap().unwrap();
+                    limit.as_i64().unwrap() + i
+                })
+            })
+            .collect();
+        let limits: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(limits, [20, 21, 22, 23]);
+
+        let clone = doc.clone();
+        assert!(std::ptr::eq(clone.text(), doc.text()));
+        let beta = clone.pointer("/flags/beta").unwrap();
+        assert_eq!(beta.and_then(|beta| beta.as_bool()), Some(true));
+        assert_eq!(SharedDocument::parse("").unwrap().pointer("/a"), Ok(None));
+        assert!(SharedDocument::parse("[1, ").is_err());
+    }
+
+    #[test]
+    fn subdocs_outlive_the_document() {
+        let doc = SharedDocument::parse(r#"{"items": [{"id": 1}, "two", [3]]}"#).unwrap();
+        let items = doc.elements("/items").unwrap();
+        drop(doc);
+
+        let texts: Vec<_> = items.iter().map(SubDoc::text).collect();
+        assert_eq!(texts, [r#"{"id": 1}"#, r#""two""#, "[3]"]);
+        assert_eq!(items[0].token_range(), 3..6);
+        assert_eq!(items[0].tokens().len(), 3);
+
+        let worker = thread::spawn({
+            let item = items[0].clone();
+            move || item.value().get("id").and_then(|id| id.as_i64())
+        });
+        assert_eq!(worker.join().unwrap(), Some(1));
+        assert_eq!(items[1].value().wrong_type().pointer, "/items/1");
+        assert!(items[0].document().elements("/items/0").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+//!
+//! This module maps byte offsets between a text and a rewritten version of
+//! it. Layers that prepare input for the parser, by transcoding it to UTF-8
+//! or by stripping comments out of it, change where everything is, so the
+//! token offsets and error positions they lead to refer to the rewritten
+//! text rather than to the bytes the user wrote.
+//!
+//! A `SourceMap` is recorded by the rewriting layer as it goes, as a series
+//! of runs that are either copied verbatim or replaced by something of a
+//! different length, and can then translate offsets both ways. Offsets in
+//! copied runs map exactly. Offsets inside a replaced run map to its edges:
+//! the start of a span goes to the start of the run and the end of a span
+//! to its end, so a mapped span always covers what produced it.
+//!
+
+use std::ops::Range;
+
+/// Maps offsets between an original text and a rewritten one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Run {
+    original: Range<usize>,
+    transformed: Range<usize>,
+    verbatim: bool,
+}
+
+impl Run {
+    fn side(&self, to_original: bool) -> (&Range<usize>, &Range<usize>) {
+        if to_original {
+            (&self.transformed, &self.original)
+        } else {
+            (&self.original, &self.transformed)
+        }
+    }
+}
+
+impl SourceMap {
+    /// An empty map, to be filled in order by `copy` and `replace`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the next `len` bytes of the original were copied as
+    /// they are.
+    pub fn copy(&mut self, len: usize) -> &mut Self {
+        if let Some(last) = self.runs.last_mut().filter(|run| run.verbatim) {
+            last.original.end += len;
+            last.transformed.end += len;
+        } else if len > 0 {
+            self.push(len, len, true);
+        }
+        self
+    }
+
+    /// Records that the next `original_len` bytes of the original were
+    /// replaced by `transformed_len` bytes, which may be none.
+    pub fn replace(&mut self, original_len: usize, transformed_len: usize) -> &mut Self {
+        self.push(original_len, transformed_len, false);
+        self
+    }
+
+    fn push(&mut self, original_len: usize, transformed_len: usize, verbatim: bool) {
+        let (original, transformed) = (self.original_len(), self.transformed_len());
+        self.runs.push(Run {
+            original: original..original + original_len,
+            transformed: transformed..transformed + transformed_len,
+            verbatim,
+        });
+    }
+
+    /// The length of the original text recorded so far.
+    pub fn original_len(&self) -> usize {
+        self.runs.last().map_or(0, |run| run.original.end)
+    }
+
+    /// The length of the rewritten text recorded so far.
+    pub fn transformed_len(&self) -> usize {
+        self.runs.last().map_or(0, |run| run.transformed.end)
+    }
+
+    /// Maps an offset in the rewritten text, such as the start of a token
+    /// or the position of a parse error, to the original text.
+    pub fn to_original(&self, pos: usize) -> usize {
+        self.map_start(pos, true)
+    }
+
+    /// Maps an offset in the original text to the rewritten text.
+    pub fn to_transformed(&self, pos: usize) -> usize {
+        self.map_start(pos, false)
+    }
+
+    /// Maps a span of the rewritten text, such as a token's, to the span
+    /// of the original text it came from.
+    pub fn span_to_original(&self, span: Range<usize>) -> Range<usize> {
+        self.map_start(span.start, true)..self.map_end(span.end, true)
+    }
+
+    /// Maps a span of the original text to the rewritten text.
+    pub fn span_to_transformed(&self, span: Range<usize>) -> Range<usize> {
+        self.map_start(span.start, false)..self.map_end(span.end, false)
+    }
+
+    /// Maps an offset that starts something, from the run containing it.
+    fn map_start(&self, pos: usize, to_original: bool) -> usize {
+        let i = self
+            .runs
+            .partition_point(|run| run.side(to_original).0.end <= pos);
+        let Some(run) = self.runs.get(i) else {
+            return self.end(pos, to_original);
+        };
+        let (from, to) = run.side(to_original);
+        if run.verbatim {
+            to.start + (pos - from.start)
+        } else {
+            to.start
+        }
+    }
+
+    /// Maps an offset that ends something, from the first run reaching it.
+    fn map_end(&self, pos: usize, to_original: bool) -> usize {
+        let i = self
+            .runs
+            .partition_point(|run| run.side(to_original).0.end < pos);
+        let Some(run) = self.runs.get(i) else {
+            return self.end(pos, to_original);
+        };
+        let (from, to) = run.side(to_original);
+        if run.verbatim {
+            to.start + (pos - from.start)
+        } else if pos == from.start {
+            to.start
+        } else {
+            to.end
+        }
+    }
+
+    /// Offsets past the recorded runs are taken to be in an unchanged tail.
+    fn end(&self, pos: usize, to_original: bool) -> usize {
+        let (from, to) = if to_original {
+            (self.transformed_len(), self.original_len())
+        } else {
+            (self.original_len(), self.transformed_len())
+        };
+        to + pos.saturating_sub(from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    /// Removes `/* */` comments, recording them in a map.
+    fn strip_comments(text: &str) -> (String, SourceMap) {
+        let mut out = String::new();
+        let mut map = SourceMap::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("/*") {
+            let end = rest[start..]
+                .find("*/")
+                .map_or(rest.len(), |end| start + end + 2);
+            out.push_str(&rest[..start]);
+            map.copy(start).replace(end - start, 0);
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        map.copy(rest.len());
+        (out, map)
+    }
+
+    #[test]
+    fn comment_stripping() {
+        let original = r#"{/* id */"id": 1, "tags": /* none */ []}"#;
+        let (stripped, map) = strip_comments(original);
+        assert_eq!(stripped, r#"{"id": 1, "tags":  []}"#);
+        assert_eq!((map.original_len(), map.transformed_len()), (40, 22));
+
+        let tokens = parse_to_vec(&stripped).unwrap();
+        let span = |i: usize| tokens[i].start as usize..tokens[i].end as usize;
+        let spans: Vec<_> = (1..tokens.len())
+            .map(|i| &original[map.span_to_original(span(i))])
+            .collect();
+        assert_eq!(spans, ["id", "1", "tags", "[]"]);
+        assert_eq!(&original[map.span_to_original(span(0))], original);
+
+        assert_eq!(map.to_transformed(original.find("[]").unwrap()), 19);
+        assert_eq!(map.to_transformed(3), 1);
+        assert_eq!(map.span_to_transformed(1..9), 1..1);
+    }
+
+    #[test]
+    fn replaced_runs() {
+        // A six byte escape like `\u00e9` rewritten as the two bytes of "é".
+        let mut map = SourceMap::new();
+        map.copy(2).replace(6, 2).copy(3);
+
+        assert_eq!(map.to_original(2), 2);
+        assert_eq!(map.to_original(3), 2);
+        assert_eq!(map.to_original(4), 8);
+        assert_eq!(map.span_to_original(1..4), 1..8);
+        assert_eq!(map.span_to_original(3..3), 2..8);
+        assert_eq!(map.to_transformed(5), 2);
+        assert_eq!(map.span_to_transformed(4..9), 2..5);
+        assert_eq!(map.to_original(9), 13);
+    }
+}
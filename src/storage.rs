@@ -0,0 +1,170 @@
+//!
+//! This module abstracts over where tokens are kept while parsing, with the
+//! `TokenStorage` trait. It is implemented for slices, which never grow, for
+//! vectors, which grow as needed like [`crate::jsmn_parse_vec`] does, and
+//! for [`crate::buf::TokenBuf`].
+//!
+//! With the "bumpalo" feature it is also implemented for vectors allocated
+//! in a `bumpalo::Bump` arena. A service parsing a great many small
+//! documents can then take the tokens of each request from an arena and
+//! reset it once the request is handled, which costs next to nothing,
+//! instead of allocating a vector each time or zeroing a large array.
+//!
+
+use crate::{JsmnErr, JsmnParser, JsmnTok, ParseError};
+
+/// Somewhere to put tokens while parsing.
+pub trait TokenStorage {
+    /// All the tokens the parser may fill in.
+    fn tokens_mut(&mut self) -> &mut [JsmnTok];
+
+    /// Makes room for `len` tokens, if there is not already, and returns
+    /// whether there is now.
+    fn grow(&mut self, len: usize) -> bool;
+
+    /// Keeps only the first `len` tokens, those filled in by the parser.
+    fn truncate(&mut self, len: usize);
+}
+
+/// A slice cannot shrink, so only the count returned by [`parse_into`]
+/// tells which of its tokens were filled in.
+impl TokenStorage for [JsmnTok] {
+    fn tokens_mut(&mut self) -> &mut [JsmnTok] {
+        self
+    }
+
+    fn grow(&mut self, len: usize) -> bool {
+        len <= self.len()
+    }
+
+    fn truncate(&mut self, _len: usize) {}
+}
+
+impl TokenStorage for Vec<JsmnTok> {
+    fn tokens_mut(&mut self) -> &mut [JsmnTok] {
+        self
+    }
+
+    fn grow(&mut self, len: usize) -> bool {
+        if len > self.len() {
+            self.resize(len, JsmnTok::default());
+        }
+        true
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len);
+    }
+}
+
+#[cfg(feature = "bumpalo")]
+impl TokenStorage for bumpalo::collections::Vec<'_, JsmnTok> {
+    fn tokens_mut(&mut self) -> &mut [JsmnTok] {
+        self
+    }
+
+    fn grow(&mut self, len: usize) -> bool {
+        if len > self.len() {
+            self.resize(len, JsmnTok::default());
+        }
+        true
+    }
+
+    fn truncate(&mut self, len: usize) {
+        bumpalo::collections::Vec::truncate(self, len);
+    }
+}
+
+/// Parses `js` into `storage` with a parser of its own, and returns the
+/// number of tokens. Storage that can grow is grown until the document
+/// fits, and is then truncated to its tokens. After an error it holds no
+/// tokens.
+pub fn parse_into<S: TokenStorage + ?Sized>(
+    js: &str,
+    storage: &mut S,
+) -> Result<usize, ParseError> {
+    parse_bytes_into(js.as_bytes(), storage)
+}
+
+/// Like [`parse_into`], but for input that is not known to be UTF-8, see
+/// [`crate::jsmn_parse_bytes`].
+pub fn parse_bytes_into<S: TokenStorage + ?Sized>(
+    js: &[u8],
+    storage: &mut S,
+) -> Result<usize, ParseError> {
+    let mut parser = JsmnParser::new();
+    storage.grow(js.len() / 8 + 8);
+    loop {
+        let tokens = storage.tokens_mut();
+        let len = tokens.len();
+        match parser.parse_bytes(js, tokens) {
+            Ok(count) => {
+                storage.truncate(count);
+                return Ok(count);
+            }
+            Err(JsmnErr::JsmErrorNoMem) if storage.grow((len * 2).max(8)) => {}
+            Err(err) => {
+                storage.truncate(0);
+                return Err(parser.error(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsmnType;
+    use crate::buf::TokenBuf;
+
+    const JS: &str = r#"{"a": [1, 2, 3], "b": {"c": null}}"#;
+
+    #[test]
+    fn growing_storage() {
+        let mut tokens = Vec::new();
+        assert_eq!(parse_into(JS, &mut tokens), Ok(10));
+        assert_eq!(tokens.len(), 10);
+        assert_eq!(tokens[2].typ, JsmnType::JsmnArray);
+
+        let mut tokens = vec![JsmnTok::default(); 20];
+        let long = format!("[{}]", vec!["1"; 100].join(", "));
+        assert_eq!(parse_into(&long, &mut tokens), Ok(101));
+        assert_eq!(tokens.len(), 101);
+
+        let err = parse_into("[1, 2", &mut tokens).unwrap_err();
+        assert_eq!(err.kind, JsmnErr::JsmErrorPart);
+        assert!(tokens.is_empty());
+    }
+
+    #[test]
+    fn fixed_storage() {
+        let mut tokens = [JsmnTok::default(); 10];
+        assert_eq!(parse_into(JS, &mut tokens[..]), Ok(10));
+        let err = parse_into(JS, &mut tokens[..9]).unwrap_err();
+        assert_eq!(err.kind, JsmnErr::JsmErrorNoMem);
+
+        let mut buf = TokenBuf::<10>::new();
+        assert_eq!(parse_into(JS, &mut buf), Ok(10));
+        assert_eq!(buf.tokens()[9].typ, JsmnType::JsmnPrimitive);
+        assert_eq!(parse_bytes_into(b"[1, 2]", &mut buf), Ok(3));
+        assert_eq!(buf.tokens().len(), 3);
+        assert!(parse_into("[1, 2", &mut buf).is_err());
+        assert!(buf.tokens().is_empty());
+        let err = parse_into("[]", &mut TokenBuf::<0>::new()).unwrap_err();
+        assert_eq!(err.kind, JsmnErr::JsmErrorNoMem);
+    }
+
+    #[cfg(feature = "bumpalo")]
+    #[test]
+    fn arena_storage() {
+        let mut arena = bumpalo::Bump::new();
+        for _ in 0..3 {
+            {
+                let mut tokens = bumpalo::collections::Vec::new_in(&arena);
+                assert_eq!(parse_into(JS, &mut tokens), Ok(10));
+                assert_eq!(tokens[1].as_str(JS), "a");
+            }
+            arena.reset();
+        }
+    }
+}
@@ -0,0 +1,801 @@
+//!
+//! This module provides streaming transforms that copy JSON from a reader
+//! to a writer while editing it on the fly, so multi-gigabyte documents can
+//! be sanitized or rewritten without ever holding them in memory.
+//!
+//! jsmn needs the whole document in a single buffer, so the streaming layer
+//! uses its own small lexer that only keeps track of nesting and of the path
+//! of the current value. Only the scalar being read is ever held in memory,
+//! everything is copied through as soon as it is read, byte for byte.
+//!
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::JsmnErr;
+use crate::decode::unescape;
+use crate::pointer::{PathItem, PathPattern, PointerErr};
+
+/// Error type for the streaming transforms.
+#[derive(Debug)]
+pub enum StreamError {
+    /// Reading the input or writing the output failed.
+    Io(io::Error),
+    /// The input is not valid JSON. `pos` is the byte offset in the input
+    /// where the offending value or character starts.
+    Parse { err: JsmnErr, pos: usize },
+}
+
+impl From<io::Error> for StreamError {
+    fn from(err: io::Error) -> Self {
+        StreamError::Io(err)
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(err) => write!(f, "I/O error: {}", err),
+            StreamError::Parse { err, pos } => write!(f, "{:?} at byte {}", err, pos),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StreamError::Io(err) => Some(err),
+            StreamError::Parse { .. } => None,
+        }
+    }
+}
+
+/// A streaming filter that drops object members and array elements while
+/// copying a document. Members can be selected by key, at any depth, or by
+/// JSON Pointer, and the commas around dropped values are fixed up so the
+/// output stays valid JSON.
+#[derive(Debug, Clone, Default)]
+pub struct DropFilter {
+    patterns: Vec<PathPattern>,
+}
+
+impl DropFilter {
+    pub fn new() -> Self {
+        DropFilter::default()
+    }
+
+    /// Drops every member named `key`, wherever it appears.
+    pub fn drop_key(&mut self, key: &str) -> &mut Self {
+        self.drop_pattern(PathPattern::key(key))
+    }
+
+    /// Drops the value at `pointer`, where `*` matches any key or index.
+    pub fn drop_pointer(&mut self, pointer: &str) -> Result<&mut Self, PointerErr> {
+        Ok(self.drop_pattern(PathPattern::pointer(pointer)?))
+    }
+
+    pub fn drop_pattern(&mut self, pattern: PathPattern) -> &mut Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Copies `input` to `output`, leaving out the selected values. The
+    /// input may hold several concatenated documents, such as NDJSON.
+    pub fn filter<R: Read, W: Write>(&self, input: R, output: W) -> Result<(), StreamError> {
+        let mut pipeline = Pipeline::new();
+        for pattern in &self.patterns {
+            pipeline.remove(pattern.clone());
+        }
+        pipeline.run(input, output)
+    }
+}
+
+/// What a pipeline callback wants done with the value it was handed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    /// Copy the value unchanged.
+    Keep,
+    /// Leave the value out, along with its key if it is an object member.
+    Drop,
+    /// Write this JSON text in place of the value. It is copied as-is, so
+    /// strings must include their quotes and escapes.
+    Replace(String),
+}
+
+/// A streaming transform pipeline. Each rule selects values by path
+/// pattern and either drops them or hands them to a callback that may
+/// rewrite them, for instance to truncate long strings or round numbers.
+/// Everything that no rule selects is copied through verbatim.
+///
+/// For each member, element and root, the first rule whose pattern matches
+/// applies, and rules are not applied again inside a value that was already
+/// selected. A value handed to a callback is held in memory in full, so
+/// callbacks are best targeted at scalars and small subtrees.
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    rules: Vec<Rule<'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Drops every value selected by `pattern`.
+    pub fn remove(&mut self, pattern: PathPattern) -> &mut Self {
+        self.rules.push(Rule {
+            pattern,
+            action: Action::Drop,
+        });
+        self
+    }
+
+    /// Calls `map` with the path and raw JSON text of every value selected
+    /// by `pattern`, and applies the returned edit.
+    pub fn map(
+        &mut self,
+        pattern: PathPattern,
+        map: impl FnMut(&[PathItem], &str) -> Edit + 'a,
+    ) -> &mut Self {
+        self.rules.push(Rule {
+            pattern,
+            action: Action::Map(Box::new(map)),
+        });
+        self
+    }
+
+    /// Streams `input` through the pipeline into `output`. The input may
+    /// hold several concatenated documents, such as NDJSON.
+    pub fn run<R: Read, W: Write>(&mut self, input: R, output: W) -> Result<(), StreamError> {
+        Copier::new(BufReader::new(input), output, &mut self.rules).run()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Lexeme {
+    Whitespace,
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    Colon,
+    Comma,
+    String,
+    Primitive,
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    is_whitespace(b) || matches!(b, b',' | b':' | b']' | b'}')
+}
+
+/// Splits a byte stream into lexemes, keeping their exact bytes.
+struct Lexer<R> {
+    reader: R,
+    pos: usize,
+    start: usize,
+}
+
+impl<R: BufRead> Lexer<R> {
+    fn new(reader: R) -> Self {
+        Lexer {
+            reader,
+            pos: 0,
+            start: 0,
+        }
+    }
+
+    fn error(&self, err: JsmnErr) -> StreamError {
+        StreamError::Parse {
+            err,
+            pos: self.start,
+        }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn bump(&mut self, buf: &mut Vec<u8>, b: u8) {
+        self.reader.consume(1);
+        self.pos += 1;
+        buf.push(b);
+    }
+
+    /// Moves bytes into `buf` while `accept` holds, returning the first
+    /// rejected byte without consuming it, or None at the end of input.
+    fn take_while(
+        &mut self,
+        buf: &mut Vec<u8>,
+        accept: impl Fn(u8) -> bool,
+    ) -> io::Result<Option<u8>> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(None);
+            }
+            let taken = available.iter().position(|&b| !accept(b));
+            let n = taken.unwrap_or(available.len());
+            buf.extend_from_slice(&available[..n]);
+            let stop = taken.map(|i| available[i]);
+            self.reader.consume(n);
+            self.pos += n;
+            if stop.is_some() {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// Reads the next lexeme into `buf`, returning None at the end of input.
+    fn next(&mut self, buf: &mut Vec<u8>) -> Result<Option<Lexeme>, StreamError> {
+        buf.clear();
+        self.start = self.pos;
+        let Some(c) = self.peek()? else {
+            return Ok(None);
+        };
+        self.bump(buf, c);
+
+        let lexeme = match c {
+            b'{' => Lexeme::BeginObject,
+            b'}' => Lexeme::EndObject,
+            b'[' => Lexeme::BeginArray,
+            b']' => Lexeme::EndArray,
+            b':' => Lexeme::Colon,
+            b',' => Lexeme::Comma,
+            b'"' => {
+                self.string(buf)?;
+                Lexeme::String
+            }
+            _ if is_whitespace(c) => {
+                self.take_while(buf, is_whitespace)?;
+                Lexeme::Whitespace
+            }
+            32..=126 => {
+                let stop = self.take_while(buf, |b| (32..127).contains(&b) && !is_delimiter(b))?;
+                if stop.is_some_and(|b| !is_delimiter(b)) {
+                    return Err(self.error(JsmnErr::JsmErrorInval));
+                }
+                Lexeme::Primitive
+            }
+            _ => return Err(self.error(JsmnErr::JsmErrorInval)),
+        };
+        Ok(Some(lexeme))
+    }
+
+    /// Reads the rest of a string whose opening quote was already consumed,
+    /// checking escapes the same way jsmn does.
+    fn string(&mut self, buf: &mut Vec<u8>) -> Result<(), StreamError> {
+        loop {
+            match self.take_while(buf, |b| b != b'"' && b != b'\\')? {
+                None => return Err(self.error(JsmnErr::JsmErrorPart)),
+                Some(b'"') => {
+                    self.bump(buf, b'"');
+                    return Ok(());
+                }
+                Some(_) => self.bump(buf, b'\\'),
+            }
+
+            match self.peek()? {
+                Some(c @ (b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't')) => {
+                    self.bump(buf, c)
+                }
+                Some(b'u') => {
+                    self.bump(buf, b'u');
+                    for _ in 0..4 {
+                        match self.peek()? {
+                            Some(h) if h.is_ascii_hexdigit() => self.bump(buf, h),
+                            Some(_) => return Err(self.error(JsmnErr::JsmErrorInval)),
+                            None => return Err(self.error(JsmnErr::JsmErrorPart)),
+                        }
+                    }
+                }
+                Some(_) => return Err(self.error(JsmnErr::JsmErrorInval)),
+                None => return Err(self.error(JsmnErr::JsmErrorPart)),
+            }
+        }
+    }
+}
+
+/// What the copier expects to read next.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Expect {
+    /// A value: a root, or the value of the current member.
+    Value,
+    /// An object key, or the closing brace if this is the first member.
+    Key {
+        first: bool,
+    },
+    Colon,
+    /// An array element, or the closing bracket if this is the first one.
+    Element {
+        first: bool,
+    },
+    /// A comma or the end of the current container.
+    Separator,
+}
+
+struct Frame {
+    array: bool,
+    /// Whether any member or element of this container was written, which
+    /// decides if the next one written needs a comma.
+    written: bool,
+    next_index: usize,
+    /// The whitespace in front of the first item, kept while leading items
+    /// are dropped so the first item written still gets it.
+    lead: Option<Vec<u8>>,
+}
+
+/// A pipeline rule: the values it selects and what happens to them.
+struct Rule<'a> {
+    pattern: PathPattern,
+    action: Action<'a>,
+}
+
+enum Action<'a> {
+    Drop,
+    Map(MapFn<'a>),
+}
+
+type MapFn<'a> = Box<dyn FnMut(&[PathItem], &str) -> Edit + 'a>;
+
+/// An item matched by a mapping rule, held back until it is complete.
+struct Capture {
+    /// Stack depth of the item, which is complete once the copier is back
+    /// at this depth.
+    depth: usize,
+    rule: usize,
+    buf: Vec<u8>,
+    /// Where the value starts in `buf`, after the key of a member.
+    value_start: Option<usize>,
+    /// The whitespace that was pending in front of the item.
+    pending: Vec<u8>,
+}
+
+/// The streaming driver behind the transforms. It walks the lexemes, keeps
+/// the path of the current value, and applies the first rule matching each
+/// member, element and root.
+///
+/// Commas between items are never copied, but written again in front of
+/// each item that is kept. Whitespace between items is held back until the
+/// next item is known to be kept, so dropping an item also drops the
+/// indentation in front of it. Items matched by a mapping rule are captured
+/// whole, since their fate is only decided once the callback has seen them.
+struct Copier<'p, 'a, R, W> {
+    lexer: Lexer<R>,
+    output: W,
+    rules: &'p mut [Rule<'a>],
+    expect: Expect,
+    stack: Vec<Frame>,
+    path: Vec<PathItem>,
+    pending: Vec<u8>,
+    /// While set, nothing is written. Holds the stack depth of the item
+    /// being dropped, so muting ends once that item is complete.
+    mute: Option<usize>,
+    capture: Option<Capture>,
+}
+
+impl<'p, 'a, R: BufRead, W: Write> Copier<'p, 'a, R, W> {
+    fn new(reader: R, output: W, rules: &'p mut [Rule<'a>]) -> Self {
+        Copier {
+            lexer: Lexer::new(reader),
+            output,
+            rules,
+            expect: Expect::Value,
+            stack: Vec::new(),
+            path: Vec::new(),
+            pending: Vec::new(),
+            mute: None,
+            capture: None,
+        }
+    }
+
+    fn run(&mut self) -> Result<(), StreamError> {
+        let mut buf = Vec::new();
+        while let Some(lexeme) = self.lexer.next(&mut buf)? {
+            self.step(lexeme, &buf)?;
+        }
+
+        if !self.stack.is_empty() {
+            return Err(self.lexer.error(JsmnErr::JsmErrorPart));
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn step(&mut self, lexeme: Lexeme, buf: &[u8]) -> Result<(), StreamError> {
+        let array = self.stack.last().map(|frame| frame.array);
+        match (self.expect, lexeme) {
+            (
+                Expect::Key { .. } | Expect::Element { .. } | Expect::Separator,
+                Lexeme::Whitespace,
+            ) => {
+                if self.mute.is_none() {
+                    self.pending.extend_from_slice(buf);
+                }
+            }
+            (_, Lexeme::Whitespace) => self.write(buf)?,
+
+            (Expect::Value, _) => {
+                if self.stack.is_empty() {
+                    self.start_item()?;
+                }
+                self.value(lexeme, buf)?;
+            }
+
+            (Expect::Key { first: true } | Expect::Separator, Lexeme::EndObject)
+                if array == Some(false) =>
+            {
+                self.close(buf)?
+            }
+            (Expect::Element { first: true } | Expect::Separator, Lexeme::EndArray)
+                if array == Some(true) =>
+            {
+                self.close(buf)?
+            }
+
+            (Expect::Key { .. }, Lexeme::String) => {
+                let key = String::from_utf8_lossy(&buf[1..buf.len() - 1]);
+                self.path.push(PathItem::Key(unescape(&key).into_owned()));
+                self.start_item()?;
+                self.write(buf)?;
+                self.expect = Expect::Colon;
+            }
+            (Expect::Colon, Lexeme::Colon) => {
+                self.write(buf)?;
+                self.expect = Expect::Value;
+            }
+            (Expect::Element { .. }, _) => {
+                let frame = self.stack.last_mut().unwrap();
+                self.path.push(PathItem::Index(frame.next_index));
+                frame.next_index += 1;
+                self.start_item()?;
+                self.value(lexeme, buf)?;
+            }
+            (Expect::Separator, Lexeme::Comma) => {
+                self.expect = match array {
+                    Some(true) => Expect::Element { first: false },
+                    _ => Expect::Key { first: false },
+                };
+            }
+            _ => return Err(self.lexer.error(JsmnErr::JsmErrorInval)),
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match (&self.mute, &mut self.capture) {
+            (Some(_), _) => Ok(()),
+            (None, Some(capture)) => {
+                capture.buf.extend_from_slice(bytes);
+                Ok(())
+            }
+            (None, None) => self.output.write_all(bytes),
+        }
+    }
+
+    /// Called on the first lexeme of a member, element or root, once its
+    /// path is known. Rules are not applied inside dropped or captured items.
+    fn start_item(&mut self) -> io::Result<()> {
+        if self.mute.is_some() {
+            return Ok(());
+        }
+        let rule = match self.capture {
+            Some(_) => None,
+            None => self
+                .rules
+                .iter()
+                .position(|rule| rule.pattern.matches(&self.path)),
+        };
+
+        match rule.map(|i| (i, &self.rules[i].action)) {
+            None => self.keep_item(),
+            Some((_, Action::Drop)) => {
+                self.mute = Some(self.stack.len());
+                self.forget_item();
+                Ok(())
+            }
+            Some((rule, Action::Map(_))) => {
+                self.capture = Some(Capture {
+                    depth: self.stack.len(),
+                    rule,
+                    buf: Vec::new(),
+                    value_start: None,
+                    pending: std::mem::take(&mut self.pending),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the comma and whitespace in front of an item that is kept.
+    fn keep_item(&mut self) -> io::Result<()> {
+        let mut comma = false;
+        if let Some(frame) = self.stack.last_mut() {
+            if frame.written {
+                comma = true;
+            } else if let Some(lead) = frame.lead.take() {
+                self.pending = lead;
+            }
+            frame.written = true;
+        }
+        if comma {
+            self.write(b",")?;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        self.write(&pending)
+    }
+
+    /// Discards the whitespace in front of an item that is dropped, unless
+    /// it leads the container and is still needed by the first kept item.
+    fn forget_item(&mut self) {
+        match self.stack.last_mut() {
+            Some(frame) if !frame.written && frame.lead.is_none() => {
+                frame.lead = Some(std::mem::take(&mut self.pending));
+            }
+            _ => self.pending.clear(),
+        }
+    }
+
+    fn value(&mut self, lexeme: Lexeme, buf: &[u8]) -> Result<(), StreamError> {
+        let depth = self.stack.len();
+        if let Some(capture) = &mut self.capture
+            && capture.depth == depth
+            && capture.value_start.is_none()
+        {
+            capture.value_start = Some(capture.buf.len());
+        }
+
+        match lexeme {
+            Lexeme::BeginObject | Lexeme::BeginArray => {
+                self.write(buf)?;
+                let array = lexeme == Lexeme::BeginArray;
+                self.stack.push(Frame {
+                    array,
+                    written: false,
+                    next_index: 0,
+                    lead: None,
+                });
+                self.expect = match array {
+                    true => Expect::Element { first: true },
+                    false => Expect::Key { first: true },
+                };
+            }
+            Lexeme::String | Lexeme::Primitive => {
+                self.write(buf)?;
+                self.finish_value()?;
+            }
+            _ => return Err(self.lexer.error(JsmnErr::JsmErrorInval)),
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.mute.is_none() {
+            let pending = std::mem::take(&mut self.pending);
+            self.write(&pending)?;
+        }
+        self.write(buf)?;
+        self.stack.pop();
+        self.finish_value()
+    }
+
+    fn finish_value(&mut self) -> io::Result<()> {
+        let depth = self.stack.len();
+        if self.mute == Some(depth) {
+            self.mute = None;
+        }
+        if self
+            .capture
+            .as_ref()
+            .is_some_and(|capture| capture.depth == depth)
+        {
+            self.finish_capture()?;
+        }
+
+        if self.stack.is_empty() {
+            self.expect = Expect::Value;
+        } else {
+            self.path.pop();
+            self.expect = Expect::Separator;
+        }
+        Ok(())
+    }
+
+    /// Hands a complete captured item to its rule's callback and writes
+    /// whatever the callback decided.
+    fn finish_capture(&mut self) -> io::Result<()> {
+        let capture = self.capture.take().unwrap();
+        let value_start = capture.value_start.unwrap_or(0);
+        let edit = match &mut self.rules[capture.rule].action {
+            Action::Map(map) => map(
+                &self.path,
+                &String::from_utf8_lossy(&capture.buf[value_start..]),
+            ),
+            Action::Drop => Edit::Drop,
+        };
+
+        self.pending = capture.pending;
+        match edit {
+            Edit::Keep => {
+                self.keep_item()?;
+                self.output.write_all(&capture.buf)
+            }
+            Edit::Replace(text) => {
+                self.keep_item()?;
+                self.output.write_all(&capture.buf[..value_start])?;
+                self.output.write_all(text.as_bytes())
+            }
+            Edit::Drop => {
+                self.forget_item();
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader handing out a single byte per call, to exercise lexemes
+    /// split across buffer refills.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn run(filter: &DropFilter, input: &str) -> Result<String, StreamError> {
+        let mut out = Vec::new();
+        filter.filter(Trickle(input.as_bytes()), &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn drop_keys_anywhere() {
+        let mut filter = DropFilter::new();
+        filter.drop_key("password");
+        assert_eq!(
+            run(
+                &filter,
+                r#"{"user":{"name":"a","password":"x"},"password":"y","n":1}"#
+            )
+            .unwrap(),
+            r#"{"user":{"name":"a"},"n":1}"#
+        );
+        assert_eq!(run(&filter, r#"{"password":[1,{"a":2}]}"#).unwrap(), "{}");
+        assert_eq!(
+            run(&filter, r#"{"password":1,"k":"v"}"#).unwrap(),
+            r#"{"k":"v"}"#
+        );
+    }
+
+    #[test]
+    fn drop_pointer_keeps_formatting() {
+        let mut filter = DropFilter::new();
+        filter.drop_pointer("/auth/token").unwrap();
+        let input = "{\n  \"auth\": {\n    \"user\": \"bob\",\n    \"token\": \"abc\"\n  },\n  \"token\": 1\n}\n";
+        let output = "{\n  \"auth\": {\n    \"user\": \"bob\"\n  },\n  \"token\": 1\n}\n";
+        assert_eq!(run(&filter, input).unwrap(), output);
+    }
+
+    #[test]
+    fn drop_array_elements() {
+        let mut filter = DropFilter::new();
+        filter
+            .drop_pointer("/items/0")
+            .unwrap()
+            .drop_pointer("/items/*/secret")
+            .unwrap();
+        assert_eq!(
+            run(&filter, r#"{"items": [1, {"secret": 2, "x": 3}, [4]]}"#).unwrap(),
+            r#"{"items": [{"x": 3}, [4]]}"#
+        );
+    }
+
+    #[test]
+    fn multiple_documents() {
+        let mut filter = DropFilter::new();
+        filter.drop_key("b");
+        assert_eq!(
+            run(&filter, "{\"a\":1,\"b\":2}\n{\"b\":3}\n").unwrap(),
+            "{\"a\":1}\n{}\n"
+        );
+    }
+
+    #[test]
+    fn pipeline_rewrites_values() {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .map(PathPattern::pointer("/items/*/price").unwrap(), |_, raw| {
+                let price: f64 = raw.parse().unwrap();
+                Edit::Replace(format!("{:.1}", price))
+            })
+            .map(PathPattern::key("note"), |_, raw| match raw.len() > 6 {
+                true => Edit::Replace(format!("{}...\"", &raw[..4])),
+                false => Edit::Keep,
+            })
+            .remove(PathPattern::key("internal"));
+
+        let input = r#"{"items": [{"price": 1.25, "note": "a long note"}, {"price": 3, "note": "ok"}], "internal": {}}"#;
+        let mut out = Vec::new();
+        pipeline.run(Trickle(input.as_bytes()), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"items": [{"price": 1.2, "note": "a l..."}, {"price": 3.0, "note": "ok"}]}"#
+        );
+    }
+
+    #[test]
+    fn pipeline_callbacks_see_paths_and_can_drop() {
+        let mut seen = Vec::new();
+        let mut pipeline = Pipeline::new();
+        pipeline.map(PathPattern::pointer("/*").unwrap(), |path, raw| {
+            seen.push((crate::pointer::to_pointer(path), raw.to_string()));
+            match raw.starts_with('[') {
+                true => Edit::Drop,
+                false => Edit::Keep,
+            }
+        });
+
+        let mut out = Vec::new();
+        pipeline
+            .run(&br#"{"a": [1, 2], "b": {"c": true} }"#[..], &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), r#"{"b": {"c": true} }"#);
+        drop(pipeline);
+        assert_eq!(
+            seen,
+            [
+                ("/a".to_string(), "[1, 2]".to_string()),
+                ("/b".to_string(), r#"{"c": true}"#.to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        let filter = DropFilter::new();
+        assert!(matches!(
+            run(&filter, r#"{"a":"#),
+            Err(StreamError::Parse {
+                err: JsmnErr::JsmErrorPart,
+                ..
+            })
+        ));
+        assert!(matches!(
+            run(&filter, r#"{"a" 1}"#),
+            Err(StreamError::Parse {
+                err: JsmnErr::JsmErrorInval,
+                pos: 5
+            })
+        ));
+        assert!(matches!(
+            run(&filter, r#"["\x"]"#),
+            Err(StreamError::Parse {
+                err: JsmnErr::JsmErrorInval,
+                pos: 1
+            })
+        ));
+        assert!(matches!(
+            run(&filter, r#"[1}"#),
+            Err(StreamError::Parse {
+                err: JsmnErr::JsmErrorInval,
+                pos: 2
+            })
+        ));
+    }
+}
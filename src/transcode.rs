@@ -0,0 +1,613 @@
+//!
+//! This module converts parsed documents into CBOR (RFC 8949) and
+//! MessagePack, for gateways that take in verbose JSON telemetry and pass
+//! it on in a compact binary form. No tree of values is built: the tokens
+//! are already in the order both formats want, with each container giving
+//! the number of its children up front, so the encoder goes through them
+//! once, front to back, writing every token as it comes.
+//!
+//! Strings are decoded. Integers are kept exact when the format can hold
+//! them, from -2^64 to 2^64 - 1 in CBOR and from `i64::MIN` to `u64::MAX`
+//! in MessagePack, and other numbers become the nearest `f64`, written as
+//! an `f32` when that loses nothing. Numbers written with a fraction or an
+//! exponent stay floats, so `1.0` does not turn into `1`. Members are
+//! copied as they are, keys appearing several times included. Without the
+//! strict feature jsmn accepts primitives that are neither literals nor
+//! numbers, which are reported as invalid.
+//!
+//! The other way, `msgpack_to_json` turns one MessagePack value back into
+//! JSON, so that a gateway can bridge in both directions. Only what JSON
+//! can spell is accepted: maps must have string keys, and binary data and
+//! extension types are refused. `f32` numbers are written in their own
+//! shortest form, so `0.1` comes back as `0.1`, and infinities and NaN
+//! become `null`, as in the ser module. The whole module is behind the
+//! "transcode" feature.
+//!
+
+use std::error::Error;
+use std::fmt::{self, Write};
+
+use crate::ser::JsonWriter;
+use crate::tree::subtree_end;
+use crate::value::{Value, ValueErr, ValueErrKind};
+use crate::{JsmnTok, JsmnType};
+
+/// Converts the first root of a parsed document into CBOR.
+pub fn to_cbor(js: &str, tokens: &[JsmnTok]) -> Result<Vec<u8>, ValueErr> {
+    let mut out = Vec::new();
+    value_to_cbor(root(js, tokens)?, &mut out)?;
+    Ok(out)
+}
+
+/// Appends the CBOR encoding of the value a cursor points to to `out`.
+pub fn value_to_cbor(value: Value, out: &mut Vec<u8>) -> Result<(), ValueErr> {
+    encode(&mut Cbor(out), value)
+}
+
+/// Converts the first root of a parsed document into MessagePack.
+pub fn to_msgpack(js: &str, tokens: &[JsmnTok]) -> Result<Vec<u8>, ValueErr> {
+    let mut out = Vec::new();
+    value_to_msgpack(root(js, tokens)?, &mut out)?;
+    Ok(out)
+}
+
+/// Appends the MessagePack encoding of the value a cursor points to to
+/// `out`.
+pub fn value_to_msgpack(value: Value, out: &mut Vec<u8>) -> Result<(), ValueErr> {
+    encode(&mut MsgPack(out), value)
+}
+
+fn root<'a>(js: &'a str, tokens: &'a [JsmnTok]) -> Result<Value<'a>, ValueErr> {
+    Value::new(js, tokens, 0).ok_or_else(|| ValueErr {
+        pointer: String::new(),
+        kind: ValueErrKind::Invalid("empty document".into()),
+    })
+}
+
+/// The items of a binary format, in the order they are written.
+trait Encoder {
+    fn null(&mut self);
+    fn bool(&mut self, b: bool);
+    /// Writes an integer, or returns false if the format cannot hold it.
+    fn int(&mut self, n: i128) -> bool;
+    fn f32(&mut self, n: f32);
+    fn f64(&mut self, n: f64);
+    /// Writes a string, or returns false if it is too long for the format.
+    fn str(&mut self, s: &str) -> bool;
+    /// Writes the start of an array of `len` elements, or returns false if
+    /// the format cannot hold that many.
+    fn array(&mut self, len: usize) -> bool;
+    /// Writes the start of a map of `len` members, or returns false if the
+    /// format cannot hold that many.
+    fn map(&mut self, len: usize) -> bool;
+}
+
+fn encode(encoder: &mut impl Encoder, value: Value) -> Result<(), ValueErr> {
+    let (js, tokens) = (value.js(), value.tokens());
+    for idx in value.index()..subtree_end(tokens, value.index()) {
+        let value = Value::new(js, tokens, idx).expect("within the tokens");
+        let len = value.token().size.max(0) as usize;
+        let fits = match value.typ() {
+            JsmnType::JsmnObject => encoder.map(len),
+            JsmnType::JsmnArray => encoder.array(len),
+            JsmnType::JsmnString => encoder.str(&value.read_str()?),
+            JsmnType::JsmnPrimitive => {
+                primitive(encoder, value)?;
+                true
+            }
+            typ => return Err(value.error(ValueErrKind::WrongType(typ))),
+        };
+        if !fits {
+            return Err(value.error(ValueErrKind::Invalid("too long".into())));
+        }
+    }
+    Ok(())
+}
+
+fn primitive(encoder: &mut impl Encoder, value: Value) -> Result<(), ValueErr> {
+    if value.is_null() {
+        encoder.null();
+    } else if let Some(b) = value.as_bool() {
+        encoder.bool(b);
+    } else if value.number_text().is_none() {
+        return Err(value.error(ValueErrKind::Invalid("not a literal or a number".into())));
+    } else if !value
+        .integer_text()
+        .and_then(|text| text.parse().ok())
+        .is_some_and(|n| encoder.int(n))
+    {
+        let n = value.read_f64()?;
+        if n as f32 as f64 == n {
+            encoder.f32(n as f32);
+        } else {
+            encoder.f64(n);
+        }
+    }
+    Ok(())
+}
+
+struct Cbor<'a>(&'a mut Vec<u8>);
+
+impl Cbor<'_> {
+    /// Writes the initial byte of an item of major type `major`, followed
+    /// by `n` in as few bytes as possible.
+    fn head(&mut self, major: u8, n: u64) {
+        let major = major << 5;
+        if n < 24 {
+            self.0.push(major | n as u8);
+        } else if let Ok(n) = u8::try_from(n) {
+            self.0.extend([major | 24, n]);
+        } else if let Ok(n) = u16::try_from(n) {
+            self.0.push(major | 25);
+            self.0.extend(n.to_be_bytes());
+        } else if let Ok(n) = u32::try_from(n) {
+            self.0.push(major | 26);
+            self.0.extend(n.to_be_bytes());
+        } else {
+            self.0.push(major | 27);
+            self.0.extend(n.to_be_bytes());
+        }
+    }
+}
+
+impl Encoder for Cbor<'_> {
+    fn null(&mut self) {
+        self.0.push(0xf6);
+    }
+
+    fn bool(&mut self, b: bool) {
+        self.0.push(if b { 0xf5 } else { 0xf4 });
+    }
+
+    fn int(&mut self, n: i128) -> bool {
+        match (u64::try_from(n), u64::try_from(-1 - n)) {
+            (Ok(n), _) => self.head(0, n),
+            (_, Ok(n)) => self.head(1, n),
+            _ => return false,
+        }
+        true
+    }
+
+    fn f32(&mut self, n: f32) {
+        self.0.push(0xfa);
+        self.0.extend(n.to_be_bytes());
+    }
+
+    fn f64(&mut self, n: f64) {
+        self.0.push(0xfb);
+        self.0.extend(n.to_be_bytes());
+    }
+
+    fn str(&mut self, s: &str) -> bool {
+        self.head(3, s.len() as u64);
+        self.0.extend(s.as_bytes());
+        true
+    }
+
+    fn array(&mut self, len: usize) -> bool {
+        self.head(4, len as u64);
+        true
+    }
+
+    fn map(&mut self, len: usize) -> bool {
+        self.head(5, len as u64);
+        true
+    }
+}
+
+struct MsgPack<'a>(&'a mut Vec<u8>);
+
+impl MsgPack<'_> {
+    /// Writes the marker of a string, an array or a map of `len` items:
+    /// `fix` with the length in its low bits when it is under `fix_max`,
+    /// or the first of `markers` that fits it, for 8, 16 and 32 bits.
+    fn len(&mut self, len: usize, fix: u8, fix_max: usize, markers: [Option<u8>; 3]) -> bool {
+        if len < fix_max {
+            self.0.push(fix | len as u8);
+        } else if let (Some(marker), Ok(len)) = (markers[0], u8::try_from(len)) {
+            self.0.extend([marker, len]);
+        } else if let (Some(marker), Ok(len)) = (markers[1], u16::try_from(len)) {
+            self.0.push(marker);
+            self.0.extend(len.to_be_bytes());
+        } else if let (Some(marker), Ok(len)) = (markers[2], u32::try_from(len)) {
+            self.0.push(marker);
+            self.0.extend(len.to_be_bytes());
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+impl Encoder for MsgPack<'_> {
+    fn null(&mut self) {
+        self.0.push(0xc0);
+    }
+
+    fn bool(&mut self, b: bool) {
+        self.0.push(if b { 0xc3 } else { 0xc2 });
+    }
+
+    fn int(&mut self, n: i128) -> bool {
+        if let Ok(n) = u64::try_from(n) {
+            if n < 0x80 {
+                self.0.push(n as u8);
+            } else if let Ok(n) = u8::try_from(n) {
+                self.0.extend([0xcc, n]);
+            } else if let Ok(n) = u16::try_from(n) {
+                self.0.push(0xcd);
+                self.0.extend(n.to_be_bytes());
+            } else if let Ok(n) = u32::try_from(n) {
+                self.0.push(0xce);
+                self.0.extend(n.to_be_bytes());
+            } else {
+                self.0.push(0xcf);
+                self.0.extend(n.to_be_bytes());
+            }
+        } else if let Ok(n) = i64::try_from(n) {
+            if n >= -32 {
+                self.0.push(n as u8);
+            } else if let Ok(n) = i8::try_from(n) {
+                self.0.extend([0xd0, n as u8]);
+            } else if let Ok(n) = i16::try_from(n) {
+                self.0.push(0xd1);
+                self.0.extend(n.to_be_bytes());
+            } else if let Ok(n) = i32::try_from(n) {
+                self.0.push(0xd2);
+                self.0.extend(n.to_be_bytes());
+            } else {
+                self.0.push(0xd3);
+                self.0.extend(n.to_be_bytes());
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn f32(&mut self, n: f32) {
+        self.0.push(0xca);
+        self.0.extend(n.to_be_bytes());
+    }
+
+    fn f64(&mut self, n: f64) {
+        self.0.push(0xcb);
+        self.0.extend(n.to_be_bytes());
+    }
+
+    fn str(&mut self, s: &str) -> bool {
+        let fits = self.len(s.len(), 0xa0, 32, [Some(0xd9), Some(0xda), Some(0xdb)]);
+        if fits {
+            self.0.extend(s.as_bytes());
+        }
+        fits
+    }
+
+    fn array(&mut self, len: usize) -> bool {
+        self.len(len, 0x90, 16, [None, Some(0xdc), Some(0xdd)])
+    }
+
+    fn map(&mut self, len: usize) -> bool {
+        self.len(len, 0x80, 16, [None, Some(0xde), Some(0xdf)])
+    }
+}
+
+/// Error type for converting MessagePack into JSON.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MsgPackErr {
+    /// The input ends in the middle of a value.
+    Truncated,
+    /// The value at `pos` is binary data, an extension type, or the unused
+    /// marker 0xc1, none of which JSON can spell.
+    Unsupported { pos: usize },
+    /// The map key at `pos` is not a string.
+    KeyNotString { pos: usize },
+    /// The string at `pos` is not UTF-8.
+    NotUtf8 { pos: usize },
+    /// The value ends at `pos`, before the end of the input.
+    TrailingBytes { pos: usize },
+    /// Writing the output failed.
+    Fmt(fmt::Error),
+}
+
+impl From<fmt::Error> for MsgPackErr {
+    fn from(err: fmt::Error) -> Self {
+        MsgPackErr::Fmt(err)
+    }
+}
+
+impl fmt::Display for MsgPackErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MsgPackErr::Truncated => write!(f, "unexpected end of input"),
+            MsgPackErr::Unsupported { pos } => write!(f, "no JSON equivalent at byte {}", pos),
+            MsgPackErr::KeyNotString { pos } => {
+                write!(f, "map key at byte {} is not a string", pos)
+            }
+            MsgPackErr::NotUtf8 { pos } => write!(f, "invalid UTF-8 in string at byte {}", pos),
+            MsgPackErr::TrailingBytes { pos } => write!(f, "trailing bytes at byte {}", pos),
+            MsgPackErr::Fmt(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for MsgPackErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MsgPackErr::Fmt(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Converts one MessagePack value, filling all of `bytes`, into JSON.
+pub fn msgpack_to_json(bytes: &[u8]) -> Result<String, MsgPackErr> {
+    let mut out = String::new();
+    write_msgpack_json(&mut out, bytes)?;
+    Ok(out)
+}
+
+/// Writes one MessagePack value, filling all of `bytes`, into `out` as
+/// JSON.
+pub fn write_msgpack_json<W: Write>(out: &mut W, bytes: &[u8]) -> Result<(), MsgPackErr> {
+    let mut writer = JsonWriter::new(out);
+    let mut reader = Reader { bytes, pos: 0 };
+    // For each open container, the items left in it, counting keys and
+    // values apart in maps, and whether it is a map.
+    let mut open: Vec<(u64, bool)> = Vec::new();
+
+    loop {
+        let is_key = matches!(open.last(), Some(&(left, true)) if left % 2 == 0);
+        if let Some((left, _)) = open.last_mut() {
+            *left -= 1;
+        }
+
+        let pos = reader.pos;
+        match reader.item()? {
+            Item::Str(s) if is_key => writer.key(s)?,
+            _ if is_key => return Err(MsgPackErr::KeyNotString { pos }),
+            Item::Nil => writer.null()?,
+            Item::Bool(b) => writer.bool(b)?,
+            Item::Int(n) => writer.i64(n)?,
+            Item::UInt(n) => writer.u64(n)?,
+            Item::Float(n) => writer.f64(n)?,
+            Item::Str(s) => writer.str(s)?,
+            Item::Array(len) => {
+                open.push((len.into(), false));
+                writer.begin_array()?
+            }
+            Item::Map(len) => {
+                open.push((2 * u64::from(len), true));
+                writer.begin_object()?
+            }
+        };
+
+        while let Some(&(0, is_map)) = open.last() {
+            open.pop();
+            if is_map {
+                writer.end_object()?;
+            } else {
+                writer.end_array()?;
+            }
+        }
+        if open.is_empty() {
+            break;
+        }
+    }
+
+    if reader.pos < bytes.len() {
+        return Err(MsgPackErr::TrailingBytes { pos: reader.pos });
+    }
+    Ok(())
+}
+
+/// A MessagePack item, with the length of containers in place of their
+/// contents.
+enum Item<'a> {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(&'a str),
+    Array(u32),
+    Map(u32),
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MsgPackErr> {
+        let bytes = self
+            .bytes
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(MsgPackErr::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn be<const N: usize>(&mut self) -> Result<[u8; N], MsgPackErr> {
+        Ok(self.take(N)?.try_into().expect("N bytes"))
+    }
+
+    fn len8(&mut self) -> Result<u32, MsgPackErr> {
+        Ok(u8::from_be_bytes(self.be()?).into())
+    }
+
+    fn len16(&mut self) -> Result<u32, MsgPackErr> {
+        Ok(u16::from_be_bytes(self.be()?).into())
+    }
+
+    fn len32(&mut self) -> Result<u32, MsgPackErr> {
+        Ok(u32::from_be_bytes(self.be()?))
+    }
+
+    fn str(&mut self, pos: usize, len: u32) -> Result<Item<'a>, MsgPackErr> {
+        let bytes = self.take(len as usize)?;
+        let s = std::str::from_utf8(bytes).map_err(|_| MsgPackErr::NotUtf8 { pos })?;
+        Ok(Item::Str(s))
+    }
+
+    fn item(&mut self) -> Result<Item<'a>, MsgPackErr> {
+        let pos = self.pos;
+        let marker = self.be::<1>()?[0];
+        Ok(match marker {
+            0x00..=0x7f => Item::UInt(marker.into()),
+            0x80..=0x8f => Item::Map((marker & 0x0f).into()),
+            0x90..=0x9f => Item::Array((marker & 0x0f).into()),
+            0xa0..=0xbf => return self.str(pos, (marker & 0x1f).into()),
+            0xc0 => Item::Nil,
+            0xc2 => Item::Bool(false),
+            0xc3 => Item::Bool(true),
+            0xca => Item::Float(f32_to_f64(f32::from_be_bytes(self.be()?))),
+            0xcb => Item::Float(f64::from_be_bytes(self.be()?)),
+            0xcc => Item::UInt(u8::from_be_bytes(self.be()?).into()),
+            0xcd => Item::UInt(u16::from_be_bytes(self.be()?).into()),
+            0xce => Item::UInt(u32::from_be_bytes(self.be()?).into()),
+            0xcf => Item::UInt(u64::from_be_bytes(self.be()?)),
+            0xd0 => Item::Int(i8::from_be_bytes(self.be()?).into()),
+            0xd1 => Item::Int(i16::from_be_bytes(self.be()?).into()),
+            0xd2 => Item::Int(i32::from_be_bytes(self.be()?).into()),
+            0xd3 => Item::Int(i64::from_be_bytes(self.be()?)),
+            0xd9 => {
+                let len = self.len8()?;
+                return self.str(pos, len);
+            }
+            0xda => {
+                let len = self.len16()?;
+                return self.str(pos, len);
+            }
+            0xdb => {
+                let len = self.len32()?;
+                return self.str(pos, len);
+            }
+            0xdc => Item::Array(self.len16()?),
+            0xdd => Item::Array(self.len32()?),
+            0xde => Item::Map(self.len16()?),
+            0xdf => Item::Map(self.len32()?),
+            0xe0..=0xff => Item::Int((marker as i8).into()),
+            0xc1 | 0xc4..=0xc9 | 0xd4..=0xd8 => return Err(MsgPackErr::Unsupported { pos }),
+        })
+    }
+}
+
+/// Widens an `f32` to the `f64` nearest to its shortest decimal form, so
+/// that it is written as it would be read.
+fn f32_to_f64(n: f32) -> f64 {
+    n.to_string().parse().unwrap_or(n.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_to_vec;
+
+    const DOC: &str = r#"{"id": 7, "t": -300, "v": [1.5, 0.1, 1e2], "ok": true,
+                          "s": "café", "n": null}"#;
+
+    #[test]
+    fn cbor() {
+        let tokens = parse_to_vec(DOC).unwrap();
+        let mut expected = vec![0xa6, 0x62, b'i', b'd', 0x07, 0x61, b't', 0x39, 0x01, 0x2b];
+        expected.extend([0x61, b'v', 0x83, 0xfa, 0x3f, 0xc0, 0x00, 0x00, 0xfb]);
+        expected.extend(0.1f64.to_be_bytes());
+        expected.extend([0xfa, 0x42, 0xc8, 0x00, 0x00]);
+        expected.extend([0x62, b'o', b'k', 0xf5, 0x61, b's', 0x65, b'c', b'a', b'f']);
+        expected.extend("é".as_bytes());
+        expected.extend([0x61, b'n', 0xf6]);
+        assert_eq!(to_cbor(DOC, &tokens).unwrap(), expected);
+
+        let js = "[18446744073709551615, -18446744073709551616, 18446744073709551616, 24]";
+        let tokens = parse_to_vec(js).unwrap();
+        let mut expected = vec![0x84, 0x1b];
+        expected.extend(u64::MAX.to_be_bytes());
+        expected.push(0x3b);
+        expected.extend(u64::MAX.to_be_bytes());
+        expected.extend([0xfa, 0x5f, 0x80, 0x00, 0x00, 0x18, 24]);
+        assert_eq!(to_cbor(js, &tokens).unwrap(), expected);
+    }
+
+    #[test]
+    fn msgpack() {
+        let tokens = parse_to_vec(DOC).unwrap();
+        let mut expected = vec![0x86, 0xa2, b'i', b'd', 0x07, 0xa1, b't', 0xd1, 0xfe, 0xd4];
+        expected.extend([0xa1, b'v', 0x93, 0xca, 0x3f, 0xc0, 0x00, 0x00, 0xcb]);
+        expected.extend(0.1f64.to_be_bytes());
+        expected.extend([0xca, 0x42, 0xc8, 0x00, 0x00]);
+        expected.extend([0xa2, b'o', b'k', 0xc3, 0xa1, b's', 0xa5, b'c', b'a', b'f']);
+        expected.extend("é".as_bytes());
+        expected.extend([0xa1, b'n', 0xc0]);
+        assert_eq!(to_msgpack(DOC, &tokens).unwrap(), expected);
+
+        let js = r#"[-32, -33, 128, 65536, "abcdefghijklmnopqrstuvwxyz012345"]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let mut expected = vec![
+            0x95, 0xe0, 0xd0, 0xdf, 0xcc, 0x80, 0xce, 0, 1, 0, 0, 0xd9, 32,
+        ];
+        expected.extend(b"abcdefghijklmnopqrstuvwxyz012345");
+        assert_eq!(to_msgpack(js, &tokens).unwrap(), expected);
+    }
+
+    #[test]
+    fn invalid_documents() {
+        assert_eq!(to_cbor("", &[]).unwrap_err().pointer, "");
+
+        let js = r#"{"a": [1, 1e999]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(to_msgpack(js, &tokens).unwrap_err().pointer, "/a/1");
+
+        #[cfg(not(feature = "strict"))]
+        {
+            let js = r#"{"a": yes}"#;
+            let tokens = parse_to_vec(js).unwrap();
+            assert_eq!(to_cbor(js, &tokens).unwrap_err().pointer, "/a");
+        }
+    }
+
+    #[test]
+    fn msgpack_to_json_round_trip() {
+        let tokens = parse_to_vec(DOC).unwrap();
+        assert_eq!(
+            msgpack_to_json(&to_msgpack(DOC, &tokens).unwrap()).unwrap(),
+            r#"{"id":7,"t":-300,"v":[1.5,0.1,100],"ok":true,"s":"café","n":null}"#
+        );
+
+        let js = r#"[{}, [[]], {"a": {"b": []}}, -9223372036854775808, 18446744073709551615]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(
+            msgpack_to_json(&to_msgpack(js, &tokens).unwrap()).unwrap(),
+            r#"[{},[[]],{"a":{"b":[]}},-9223372036854775808,18446744073709551615]"#
+        );
+        assert_eq!(msgpack_to_json(&[0xca, 0x7f, 0x80, 0, 0]).unwrap(), "null");
+    }
+
+    #[test]
+    fn invalid_msgpack() {
+        assert_eq!(msgpack_to_json(&[]), Err(MsgPackErr::Truncated));
+        assert_eq!(msgpack_to_json(&[0x92, 0x01]), Err(MsgPackErr::Truncated));
+        assert_eq!(
+            msgpack_to_json(&[0xdd, 0xff, 0xff, 0xff, 0xff]),
+            Err(MsgPackErr::Truncated)
+        );
+        assert_eq!(
+            msgpack_to_json(&[0x91, 0xc4, 0x01, 0x00]),
+            Err(MsgPackErr::Unsupported { pos: 1 })
+        );
+        assert_eq!(
+            msgpack_to_json(&[0x81, 0x01, 0x02]),
+            Err(MsgPackErr::KeyNotString { pos: 1 })
+        );
+        assert_eq!(
+            msgpack_to_json(&[0x81, 0xa1, b'k', 0xa1, 0xff]),
+            Err(MsgPackErr::NotUtf8 { pos: 3 })
+        );
+        assert_eq!(
+            msgpack_to_json(&[0x01, 0x02]),
+            Err(MsgPackErr::TrailingBytes { pos: 1 })
+        );
+    }
+}
@@ -0,0 +1,514 @@
+//!
+//! Helpers for walking the flat token array produced by jsmn as a tree.
+//!
+//! jsmn records structure only through each token's `size`: an object's
+//! children are its keys, each key has its value as a single child, and an
+//! array's children are its elements. Everything in this module is built on
+//! that rule, so it also works on tokens built by hand without their
+//! parent links filled in.
+//!
+//! `TokenTree` does that work once for a whole token array, after which
+//! moving to a token's parent, children or next sibling takes constant time.
+//! A value's parent is its key when it belongs to an object, as with jsmn's
+//! own parent links.
+//!
+
+use std::ops::Range;
+
+use crate::decode::raw_eq;
+use crate::{JsmnTok, JsmnType};
+
+/// Returns the index one past the last token of the subtree rooted at `idx`.
+pub(crate) fn subtree_end(tokens: &[JsmnTok], idx: usize) -> usize {
+    let mut pending = 1usize;
+    let mut i = idx;
+    while pending > 0 && i < tokens.len() {
+        pending += tokens[i].size.max(0) as usize;
+        pending -= 1;
+        i += 1;
+    }
+    i
+}
+
+/// Computes the subtree end of every token in a single backwards pass.
+pub(crate) fn subtree_ends(tokens: &[JsmnTok]) -> Vec<usize> {
+    let mut ends = vec![0; tokens.len()];
+    for i in (0..tokens.len()).rev() {
+        let mut j = i + 1;
+        for _ in 0..tokens[i].size.max(0) {
+            if j >= tokens.len() {
+                break;
+            }
+            j = ends[j];
+        }
+        ends[i] = j;
+    }
+    ends
+}
+
+/// Returns the indices of the direct children of the token at `idx`.
+pub(crate) fn children(tokens: &[JsmnTok], idx: usize) -> Children<'_> {
+    Children {
+        tokens,
+        next: idx + 1,
+        remaining: tokens[idx].size.max(0) as usize,
+    }
+}
+
+/// Like [`children`], but with none unless the token at `idx` is of type
+/// `typ`.
+fn children_of_type(tokens: &[JsmnTok], idx: usize, typ: JsmnType) -> Children<'_> {
+    let remaining = match tokens.get(idx) {
+        Some(tok) if tok.typ == typ => tok.size.max(0) as usize,
+        _ => 0,
+    };
+    Children {
+        tokens,
+        next: idx + 1,
+        remaining,
+    }
+}
+
+/// Returns the indices of the top-level tokens, in order.
+pub(crate) fn roots(tokens: &[JsmnTok]) -> Vec<usize> {
+    let mut roots = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        roots.push(i);
+        i = subtree_end(tokens, i);
+    }
+    roots
+}
+
+/// Iterator over the direct children of a token, see [`children`].
+#[derive(Debug, Clone)]
+pub(crate) struct Children<'a> {
+    tokens: &'a [JsmnTok],
+    next: usize,
+    remaining: usize,
+}
+
+impl Iterator for Children<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining == 0 || self.next >= self.tokens.len() {
+            return None;
+        }
+        let child = self.next;
+        self.remaining -= 1;
+        self.next = subtree_end(self.tokens, child);
+        Some(child)
+    }
+}
+
+/// Returns the byte range of the value rooted at `idx`, including the quotes
+/// of strings. For an object key this covers the whole member, from the
+/// opening quote of the key to the end of its value.
+pub(crate) fn value_span(tokens: &[JsmnTok], idx: usize) -> Range<usize> {
+    let tok = &tokens[idx];
+    let quoted = tok.typ == JsmnType::JsmnString;
+    let start = tok.start as usize - quoted as usize;
+    if quoted && tok.size > 0 && idx + 1 < tokens.len() {
+        return start..value_span(tokens, idx + 1).end;
+    }
+    start..tok.end as usize + quoted as usize
+}
+
+/// Returns the index of the token holding the one at `idx`, or None for a
+/// root, by reading its parent link: a value's parent is its key when it
+/// belongs to an object. Unlike the rest of this module, this needs the
+/// links jsmn fills in, and [`TokenTree::parent`] gives the same answer
+/// from the sizes alone.
+pub fn parent_of(tokens: &[JsmnTok], idx: usize) -> Option<usize> {
+    usize::try_from(tokens.get(idx)?.parent).ok()
+}
+
+/// Returns the value token of the member named `key` in the object that
+/// starts the token slice, or None if it has none or is not an object. Keys
+/// are compared with their escapes decoded, without allocating, and when a
+/// key appears several times the last member wins. To look inside a nested
+/// object, pass the slice starting at its token.
+pub fn object_get<'t>(js: &str, tokens: &'t [JsmnTok], key: &str) -> Option<&'t JsmnTok> {
+    if tokens.first()?.typ != JsmnType::JsmnObject {
+        return None;
+    }
+    let key = children(tokens, 0)
+        .filter(|&k| {
+            let tok = &tokens[k];
+            tok.typ == JsmnType::JsmnString
+                && raw_eq(&js[tok.start as usize..tok.end as usize], key)
+        })
+        .last()?;
+    tokens.get(key + 1)
+}
+
+/// Returns an iterator over the top-level values of a parsed buffer, such
+/// as the records of newline-delimited JSON, which jsmn parses as several
+/// roots. Each value comes as its text, with the quotes of a string, and
+/// the tokens of its subtree, whose offsets are still relative to `js`.
+pub fn values<'a>(js: &'a str, tokens: &'a [JsmnTok]) -> Values<'a> {
+    Values {
+        js,
+        tokens,
+        next: 0,
+    }
+}
+
+/// Where one of the top-level values of a buffer is, see
+/// [`root_boundaries`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RootBoundary {
+    /// The index of the root token.
+    pub index: usize,
+    /// The byte offset just past the value, after the closing quote of a
+    /// string.
+    pub end: usize,
+}
+
+/// Returns the index of every root token of a buffer of concatenated
+/// documents, like `{"a":1}{"b":2}`, with the byte offset where each of
+/// them ends. The input before the end of a root can be consumed once its
+/// tokens have been used, and whatever follows the last root is left over.
+pub fn root_boundaries(tokens: &[JsmnTok]) -> Vec<RootBoundary> {
+    roots(tokens)
+        .into_iter()
+        .map(|index| RootBoundary {
+            index,
+            end: value_span(tokens, index).end,
+        })
+        .collect()
+}
+
+/// Iterator over the top-level values of a buffer, see [`values`].
+#[derive(Debug, Clone)]
+pub struct Values<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+    next: usize,
+}
+
+impl<'a> Iterator for Values<'a> {
+    type Item = (&'a str, &'a [JsmnTok]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.tokens.len() {
+            return None;
+        }
+        let root = self.next;
+        self.next = subtree_end(self.tokens, root);
+        let text = &self.js[value_span(self.tokens, root)];
+        Some((text, &self.tokens[root..self.next]))
+    }
+}
+
+/// Iterator over the members of an object, as the indices of each key token
+/// and of its value token, in document order.
+///
+/// It walks the children by their sizes, skipping over nested values, so it
+/// takes time in the number of tokens of the object rather than of its
+/// members. Build a [`TokenTree`] first when walking many objects of the
+/// same document.
+#[derive(Debug, Clone)]
+pub struct ObjectIter<'t> {
+    keys: Children<'t>,
+}
+
+impl<'t> ObjectIter<'t> {
+    /// Iterates over the members of the object at `idx`. Anything other
+    /// than an object has none.
+    pub fn new(tokens: &'t [JsmnTok], idx: usize) -> Self {
+        ObjectIter {
+            keys: children_of_type(tokens, idx, JsmnType::JsmnObject),
+        }
+    }
+}
+
+impl Iterator for ObjectIter<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let key = self.keys.next()?;
+        Some((key, key + 1))
+    }
+}
+
+/// Iterator over the elements of an array, as the indices of their tokens,
+/// skipping over the tokens nested inside them. Like [`ObjectIter`], it
+/// walks the elements by their sizes.
+#[derive(Debug, Clone)]
+pub struct ArrayIter<'t> {
+    elements: Children<'t>,
+}
+
+impl<'t> ArrayIter<'t> {
+    /// Iterates over the elements of the array at `idx`. Anything other
+    /// than an array has none.
+    pub fn new(tokens: &'t [JsmnTok], idx: usize) -> Self {
+        ArrayIter {
+            elements: children_of_type(tokens, idx, JsmnType::JsmnArray),
+        }
+    }
+}
+
+impl Iterator for ArrayIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.elements.next()
+    }
+}
+
+/// Hierarchical navigation over a parsed token array.
+#[derive(Debug, Clone)]
+pub struct TokenTree<'a> {
+    tokens: &'a [JsmnTok],
+    ends: Vec<usize>,
+    parents: Vec<Option<usize>>,
+}
+
+impl<'a> TokenTree<'a> {
+    pub fn new(tokens: &'a [JsmnTok]) -> Self {
+        let ends = subtree_ends(tokens);
+        let mut parents = Vec::with_capacity(tokens.len());
+        let mut open: Vec<usize> = Vec::new();
+        for i in 0..tokens.len() {
+            while open.last().is_some_and(|&top| ends[top] <= i) {
+                open.pop();
+            }
+            parents.push(open.last().copied());
+            open.push(i);
+        }
+        TokenTree {
+            tokens,
+            ends,
+            parents,
+        }
+    }
+
+    pub fn tokens(&self) -> &'a [JsmnTok] {
+        self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Returns the indices of the top-level tokens, in order.
+    pub fn roots(&self) -> TreeChildren<'_> {
+        TreeChildren {
+            ends: &self.ends,
+            next: 0,
+            end: self.tokens.len(),
+        }
+    }
+
+    /// Returns the index of the token holding the one at `idx`, or None for
+    /// a root.
+    pub fn parent(&self, idx: usize) -> Option<usize> {
+        self.parents.get(idx).copied().flatten()
+    }
+
+    /// Returns the indices of the direct children of the token at `idx`:
+    /// the keys of an object, the value of a key, or the elements of an
+    /// array.
+    pub fn children(&self, idx: usize) -> TreeChildren<'_> {
+        let end = self.subtree_end(idx);
+        TreeChildren {
+            ends: &self.ends,
+            next: (idx + 1).min(end),
+            end,
+        }
+    }
+
+    /// Returns the index of the first child of the token at `idx`.
+    pub fn first_child(&self, idx: usize) -> Option<usize> {
+        self.children(idx).next()
+    }
+
+    /// Returns the index of the token following the one at `idx` under the
+    /// same parent, or the next root for a root.
+    pub fn next_sibling(&self, idx: usize) -> Option<usize> {
+        let next = *self.ends.get(idx)?;
+        let end = self
+            .parent(idx)
+            .map_or(self.tokens.len(), |parent| self.ends[parent]);
+        (next < end).then_some(next)
+    }
+
+    /// Returns the index one past the last token of the subtree rooted at
+    /// `idx`.
+    pub fn subtree_end(&self, idx: usize) -> usize {
+        self.ends.get(idx).copied().unwrap_or(self.tokens.len())
+    }
+
+    /// Returns the number of tokens holding the one at `idx`.
+    pub fn depth(&self, idx: usize) -> usize {
+        std::iter::successors(self.parent(idx), |&parent| self.parent(parent)).count()
+    }
+}
+
+/// Iterator over the indices of sibling tokens, returned by
+/// [`TokenTree::children`] and [`TokenTree::roots`].
+#[derive(Debug, Clone)]
+pub struct TreeChildren<'t> {
+    ends: &'t [usize],
+    next: usize,
+    end: usize,
+}
+
+impl Iterator for TreeChildren<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.next >= self.end {
+            return None;
+        }
+        let child = self.next;
+        self.next = self.ends[child];
+        Some(child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    #[test]
+    fn token_tree_navigation() {
+        // 0:{ 1:"a" 2:[ 3:1 4:{ 5:"b" 6:null 7:true 8:"c" 9:2 10:[ 11:3
+        let js = r#"{"a": [1, {"b": null}, true], "c": 2} [3]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let tree = TokenTree::new(&tokens);
+
+        assert_eq!(tree.roots().collect::<Vec<_>>(), [0, 10]);
+        assert_eq!(tree.children(0).collect::<Vec<_>>(), [1, 8]);
+        assert_eq!(tree.children(2).collect::<Vec<_>>(), [3, 4, 7]);
+        assert_eq!(tree.children(1).collect::<Vec<_>>(), [2]);
+        assert_eq!(tree.children(3).count(), 0);
+
+        assert_eq!(tree.parent(2), Some(1));
+        assert_eq!(tree.parent(6), Some(5));
+        assert_eq!(tree.parent(8), Some(0));
+        assert_eq!(tree.parent(11), Some(10));
+        assert_eq!(tree.parent(10), None);
+
+        assert_eq!(tree.next_sibling(1), Some(8));
+        assert_eq!(tree.next_sibling(4), Some(7));
+        assert_eq!(tree.next_sibling(7), None);
+        assert_eq!(tree.next_sibling(2), None);
+        assert_eq!(tree.next_sibling(0), Some(10));
+        assert_eq!(tree.first_child(4), Some(5));
+        assert_eq!(tree.depth(6), 5);
+        assert_eq!(parent_of(&tokens, 6), Some(5));
+        assert_eq!(parent_of(&tokens, 11), Some(10));
+        assert_eq!(parent_of(&tokens, 10), None);
+        assert_eq!(parent_of(&tokens, 12), None);
+        assert_eq!(tree.subtree_end(2), 8);
+    }
+
+    #[test]
+    fn object_lookup() {
+        let js = r#"{"a": 1, "n\u0065sted": {"a": [2]}, "a": 3}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let text = |tok: &JsmnTok| &js[tok.start as usize..tok.end as usize];
+
+        assert_eq!(object_get(js, &tokens, "a").map(text), Some("3"));
+        let nested = object_get(js, &tokens, "nested").unwrap();
+        assert_eq!(nested.typ, JsmnType::JsmnObject);
+        assert_eq!(
+            object_get(js, &tokens[4..], "a").map(|tok| tok.typ),
+            Some(JsmnType::JsmnArray)
+        );
+        assert_eq!(object_get(js, &tokens, "missing"), None);
+        assert_eq!(object_get(js, &tokens[6..], "a"), None);
+        assert_eq!(object_get(js, &[], "a"), None);
+    }
+
+    #[test]
+    fn top_level_values() {
+        let js = "{\"id\": 1, \"tags\": [\"a\"]}\n\"two\"\n3\n[[], {}]\n";
+        let tokens = parse_to_vec(js).unwrap();
+        let roots: Vec<_> = values(js, &tokens).collect();
+
+        let texts: Vec<_> = roots.iter().map(|&(text, _)| text).collect();
+        assert_eq!(
+            texts,
+            [r#"{"id": 1, "tags": ["a"]}"#, r#""two""#, "3", "[[], {}]"]
+        );
+        let lens: Vec<_> = roots.iter().map(|(_, tokens)| tokens.len()).collect();
+        assert_eq!(lens, [6, 1, 1, 3]);
+        assert_eq!(
+            object_get(js, roots[0].1, "id").map(|tok| tok.start),
+            Some(7)
+        );
+        assert_eq!(values("", &[]).count(), 0);
+    }
+
+    #[test]
+    fn concatenated_roots() {
+        let js = r#"{"a":1}{"b":[2]} "c"3 "#;
+        let tokens = parse_to_vec(js).unwrap();
+        let bounds: Vec<_> = root_boundaries(&tokens)
+            .iter()
+            .map(|root| (root.index, root.end))
+            .collect();
+        assert_eq!(bounds, [(0, 7), (3, 16), (7, 20), (8, 21)]);
+        assert_eq!(root_boundaries(&[]), []);
+    }
+
+    #[test]
+    fn object_members() {
+        // 0:{ 1:"a" 2:{ 3:"b" 4:[ 5:1 6:"c" 7:null
+        let js = r#"{"a": {"b": [1]}, "c": null}"#;
+        let tokens = parse_to_vec(js).unwrap();
+
+        assert_eq!(
+            ObjectIter::new(&tokens, 0).collect::<Vec<_>>(),
+            [(1, 2), (6, 7)]
+        );
+        assert_eq!(ObjectIter::new(&tokens, 2).collect::<Vec<_>>(), [(3, 4)]);
+        assert_eq!(ObjectIter::new(&tokens, 4).count(), 0);
+        assert_eq!(ObjectIter::new(&tokens, 8).count(), 0);
+        assert_eq!(
+            ObjectIter::new(&tokens[..3], 0).collect::<Vec<_>>(),
+            [(1, 2)]
+        );
+    }
+
+    #[test]
+    fn array_elements() {
+        // 0:[ 1:"s" 2:{ 3:"k" 4:[ 5:[ 6:true 7:[ 8:2.5
+        let js = r#"["s", {"k": [[], true]}, [2.5]]"#;
+        let tokens = parse_to_vec(js).unwrap();
+
+        assert_eq!(ArrayIter::new(&tokens, 0).collect::<Vec<_>>(), [1, 2, 7]);
+        assert_eq!(ArrayIter::new(&tokens, 4).collect::<Vec<_>>(), [5, 6]);
+        assert_eq!(ArrayIter::new(&tokens, 5).count(), 0);
+        assert_eq!(ArrayIter::new(&tokens, 2).count(), 0);
+        assert_eq!(ArrayIter::new(&[], 0).count(), 0);
+    }
+
+    #[test]
+    fn matches_size_walking() {
+        let js = r#"[{"x": [[], {}], "y": {"z": [1, [2]]}}, "s", [[[]]]]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let tree = TokenTree::new(&tokens);
+        for idx in 0..tokens.len() {
+            let walked: Vec<_> = children(&tokens, idx).collect();
+            assert_eq!(tree.children(idx).collect::<Vec<_>>(), walked);
+            assert_eq!(tree.subtree_end(idx), subtree_end(&tokens, idx));
+            assert_eq!(tree.parent(idx), usize::try_from(tokens[idx].parent).ok());
+            for child in walked {
+                assert_eq!(tree.parent(child), Some(idx));
+            }
+            assert_eq!(parent_of(&tokens, idx), tree.parent(idx));
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//!
+//! This module checks that a parsed document has an expected shape: which
+//! keys its objects must have and what type their values are, what the
+//! elements of its arrays are, and how deeply it may nest. It covers the
+//! usual needs of configuration files without building a tree of the
+//! document, and checking does not allocate.
+//!
+//! Shapes are made of borrowed parts and built with const functions, so
+//! that a whole schema can be declared as a constant, with nested shapes
+//! referring to one another.
+//!
+//! Members that a shape does not mention are allowed, with any value.
+//!
+
+use std::error::Error;
+use std::fmt;
+
+use crate::decode::raw_eq;
+use crate::tree::object_get;
+use crate::value::is_number;
+use crate::{JsmnTok, JsmnType};
+
+/// The expected shape of a value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Shape<'s> {
+    /// Any value at all.
+    Any,
+    Null,
+    Bool,
+    /// A number, following the JSON number grammar.
+    Number,
+    /// A number without a fraction or an exponent.
+    Integer,
+    String,
+    /// An array whose elements all have the given shape.
+    Array(&'s Shape<'s>),
+    /// An object with the given members.
+    Object(&'s [Member<'s>]),
+    /// `null`, or a value of the given shape.
+    Nullable(&'s Shape<'s>),
+}
+
+impl Shape<'_> {
+    /// How the shape is named in errors.
+    fn name(&self) -> &'static str {
+        match self {
+            Shape::Any => "any value",
+            Shape::Null => "null",
+            Shape::Bool => "a boolean",
+            Shape::Number => "a number",
+            Shape::Integer => "an integer",
+            Shape::String => "a string",
+            Shape::Array(_) => "an array",
+            Shape::Object(_) => "an object",
+            Shape::Nullable(shape) => shape.name(),
+        }
+    }
+}
+
+/// A member expected in an object.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Member<'s> {
+    pub key: &'s str,
+    pub shape: Shape<'s>,
+    pub required: bool,
+}
+
+impl<'s> Member<'s> {
+    pub const fn required(key: &'s str, shape: Shape<'s>) -> Self {
+        Member {
+            key,
+            shape,
+            required: true,
+        }
+    }
+
+    pub const fn optional(key: &'s str, shape: Shape<'s>) -> Self {
+        Member {
+            key,
+            shape,
+            required: false,
+        }
+    }
+}
+
+/// Error type for documents that do not have the expected shape.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ShapeErr<'s> {
+    /// The index of the offending token.
+    pub index: usize,
+    pub kind: ShapeErrKind<'s>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShapeErrKind<'s> {
+    /// The value does not have the expected type.
+    WrongType { expected: &'static str },
+    /// The object does not have this required member.
+    MissingKey(&'s str),
+    /// The array or object is nested more deeply than allowed.
+    TooDeep,
+    /// There is no value where one is expected, as in an empty document.
+    Missing,
+}
+
+impl fmt::Display for ShapeErr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "token {}: ", self.index)?;
+        match self.kind {
+            ShapeErrKind::WrongType { expected } => write!(f, "expected {}", expected),
+            ShapeErrKind::MissingKey(key) => write!(f, "missing key {:?}", key),
+            ShapeErrKind::TooDeep => write!(f, "nested too deeply"),
+            ShapeErrKind::Missing => write!(f, "missing value"),
+        }
+    }
+}
+
+impl Error for ShapeErr<'_> {}
+
+/// A shape to check documents against, with a limit on their nesting.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Validator<'s> {
+    shape: Shape<'s>,
+    max_depth: usize,
+}
+
+impl<'s> Validator<'s> {
+    /// A validator for documents of the given shape, nesting arrays and
+    /// objects at most 128 deep.
+    pub const fn new(shape: Shape<'s>) -> Self {
+        Validator {
+            shape,
+            max_depth: 128,
+        }
+    }
+
+    /// Sets how many arrays and objects may be nested inside each other,
+    /// counting the outermost one. A document of scalars has depth 0.
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Checks the first root of a parsed document, and reports the first
+    /// mismatch found.
+    pub fn validate(&self, js: &str, tokens: &[JsmnTok]) -> Result<(), ShapeErr<'s>> {
+        self.check(js, tokens, 0, &self.shape, 0).map(|_| ())
+    }
+
+    /// Checks the value at `idx` and returns the index past its subtree.
+    /// The recursion goes no deeper than the depth limit.
+    fn check(
+        &self,
+        js: &str,
+        tokens: &[JsmnTok],
+        idx: usize,
+        shape: &Shape<'s>,
+        depth: usize,
+    ) -> Result<usize, ShapeErr<'s>> {
+        let err = |kind| ShapeErr { index: idx, kind };
+        let tok = tokens.get(idx).ok_or(err(ShapeErrKind::Missing))?;
+        let text = tok.try_as_str(js).ok_or(err(ShapeErrKind::Missing))?;
+        let primitive = tok.typ == JsmnType::JsmnPrimitive;
+        if let Shape::Nullable(inner) = shape {
+            if primitive && text == "null" {
+                return Ok(idx + 1);
+            }
+            return self.check(js, tokens, idx, inner, depth);
+        }
+
+        let matches = match shape {
+            Shape::Any | Shape::Nullable(_) => true,
+            Shape::Null => primitive && text == "null",
+            Shape::Bool => primitive && (text == "true" || text == "false"),
+            Shape::Number => primitive && is_number(text),
+            Shape::Integer => primitive && is_number(text) && !text.contains(['.', 'e', 'E']),
+            Shape::String => tok.typ == JsmnType::JsmnString,
+            Shape::Array(_) => tok.typ == JsmnType::JsmnArray,
+            Shape::Object(_) => tok.typ == JsmnType::JsmnObject,
+        };
+        if !matches {
+            let expected = shape.name();
+            return Err(err(ShapeErrKind::WrongType { expected }));
+        }
+
+        let mut next = idx + 1;
+        match tok.typ {
+            JsmnType::JsmnObject | JsmnType::JsmnArray if depth == self.max_depth => {
+                return Err(err(ShapeErrKind::TooDeep));
+            }
+            JsmnType::JsmnObject => {
+                let members = match shape {
+                    Shape::Object(members) => members,
+                    _ => &[][..],
+                };
+                for _ in 0..tok.size.max(0) {
+                    let name = tokens.get(next).and_then(|key| key.try_as_str(js));
+                    let name = name.ok_or(err(ShapeErrKind::Missing))?;
+                    let member = members.iter().find(|member| raw_eq(name, member.key));
+                    let shape = member.map_or(&Shape::Any, |member| &member.shape);
+                    next = self.check(js, tokens, next + 1, shape, depth + 1)?;
+                }
+                let tokens = &tokens[idx..next];
+                if let Some(missing) = members
+                    .iter()
+                    .find(|member| member.required && object_get(js, tokens, member.key).is_none())
+                {
+                    return Err(err(ShapeErrKind::MissingKey(missing.key)));
+                }
+            }
+            JsmnType::JsmnArray => {
+                let element = match shape {
+                    Shape::Array(element) => element,
+                    _ => &Shape::Any,
+                };
+                for _ in 0..tok.size.max(0) {
+                    next = self.check(js, tokens, next, element, depth + 1)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    const SERVER: Shape = Shape::Object(&[
+        Member::required("host", Shape::String),
+        Member::optional("port", Shape::Integer),
+        Member::optional("weight", Shape::Nullable(&Shape::Number)),
+    ]);
+    const CONFIG: Validator = Validator::new(Shape::Object(&[
+        Member::required("servers", Shape::Array(&SERVER)),
+        Member::optional("debug", Shape::Bool),
+    ]));
+
+    fn validate<'s>(validator: &Validator<'s>, js: &str) -> Result<(), ShapeErr<'s>> {
+        validator.validate(js, &parse_to_vec(js).unwrap())
+    }
+
+    #[test]
+    fn matching_documents() {
+        let js = r#"{"servers": [{"host": "a", "port": 80}, {"h\u006fst": "b", "weight": null}],
+            "debug": false, "extra": {"anything": [1, "x"]}}"#;
+        assert_eq!(validate(&CONFIG, js), Ok(()));
+        assert_eq!(validate(&CONFIG, r#"{"servers": []}"#), Ok(()));
+        assert_eq!(validate(&Validator::new(Shape::Any), "[[], {}]"), Ok(()));
+    }
+
+    #[test]
+    fn mismatches() {
+        let kind = |js| validate(&CONFIG, js).map_err(|err| (err.index, err.kind));
+        let wrong = |index, expected| Err((index, ShapeErrKind::WrongType { expected }));
+
+        assert_eq!(
+            kind(r#"{"servers": [{"host": "a", "port": 8.5}]}"#),
+            wrong(7, "an integer")
+        );
+        assert_eq!(kind(r#"{"servers": [{"host": 1}]}"#), wrong(5, "a string"));
+        assert_eq!(
+            kind(r#"{"servers": [{"host": "a", "weight": "1"}]}"#),
+            wrong(7, "a number")
+        );
+        assert_eq!(kind(r#"{"servers": {}}"#), wrong(2, "an array"));
+        assert_eq!(kind("[]"), wrong(0, "an object"));
+        assert_eq!(
+            kind(r#"{"debug": true}"#),
+            Err((0, ShapeErrKind::MissingKey("servers")))
+        );
+        assert_eq!(
+            kind(r#"{"servers": [{"port": 1}]}"#),
+            Err((3, ShapeErrKind::MissingKey("host")))
+        );
+        assert_eq!(
+            CONFIG.validate("", &[]).unwrap_err().kind,
+            ShapeErrKind::Missing
+        );
+
+        let err = validate(&CONFIG, r#"{"servers": [1]}"#).unwrap_err();
+        assert_eq!(err.to_string(), "token 3: expected an object");
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let shallow = Validator::new(Shape::Any).max_depth(2);
+        assert_eq!(validate(&shallow, r#"[{"a": 1}, []]"#), Ok(()));
+        assert_eq!(validate(&shallow, r#""s""#), Ok(()));
+        let err = validate(&shallow, r#"[{"a": [1]}]"#).unwrap_err();
+        assert_eq!((err.index, err.kind), (3, ShapeErrKind::TooDeep));
+
+        let deep = "[".repeat(10_000) + &"]".repeat(10_000);
+        let err = validate(&Validator::new(Shape::Any), &deep).unwrap_err();
+        assert_eq!((err.index, err.kind), (128, ShapeErrKind::TooDeep));
+    }
+}
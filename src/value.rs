@@ -0,0 +1,1343 @@
+//!
+//! This module provides `Value`, a cursor over a parsed document that
+//! navigates the tokens like a tree and converts scalars to Rust types.
+//!
+//! A `Value` is only a reference to a token and to the text and token array
+//! it came from, so it is cheap to copy and nothing gets decoded until one
+//! of its accessors is called. Accessors return None when the token does
+//! not hold a value of the requested type, except for the ones parsing
+//! richer types out of strings, which return a `ValueErr` telling where the
+//! offending value is and what is wrong with it.
+//!
+//! The `token_as_*` functions read primitives straight from a token and
+//! the text it was parsed from, for code that works with tokens rather than
+//! values, and say what is wrong through a `PrimitiveErr`. Which of them
+//! can read a number without losing anything is told by `classify_number`,
+//! from the JSON number grammar rather than from looking for a dot.
+//!
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::decode::{RawStr, raw_eq, unescape};
+use crate::pointer::{KeyMatch, PathItem, PointerErr, parse_pointer, resolve, to_pointer};
+use crate::tree::{ArrayIter, Children, children, roots, subtree_end};
+use crate::{JsmnTok, JsmnType};
+
+/// Error type for the accessors that parse values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueErr {
+    /// The JSON Pointer of the value, from the root it belongs to.
+    pub pointer: String,
+    pub kind: ValueErrKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueErrKind {
+    /// The value does not have a type the accessor can convert.
+    WrongType(JsmnType),
+    /// The value has the right type, but its contents are not valid.
+    Invalid(String),
+    /// The pointer given to a getter is malformed.
+    Pointer(PointerErr),
+    /// The string is not one of the variants of an enum.
+    UnknownVariant {
+        found: String,
+        allowed: &'static [&'static str],
+    },
+}
+
+impl fmt::Display for ValueErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ValueErrKind::WrongType(typ) => write!(f, "{}: unexpected {:?}", self.pointer, typ),
+            ValueErrKind::Invalid(reason) => write!(f, "{}: {}", self.pointer, reason),
+            ValueErrKind::Pointer(err) => write!(f, "{}: invalid pointer: {:?}", self.pointer, err),
+            ValueErrKind::UnknownVariant { found, allowed } => {
+                write!(
+                    f,
+                    "{}: unknown variant {:?}, expected ",
+                    self.pointer, found
+                )?;
+                for (i, variant) in allowed.iter().enumerate() {
+                    match i {
+                        0 => write!(f, "{:?}", variant)?,
+                        _ if i + 1 == allowed.len() => write!(f, " or {:?}", variant)?,
+                        _ => write!(f, ", {:?}", variant)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ValueErr {}
+
+/// Where a value read with a fallback came from.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Source<'p> {
+    /// The value found at this pointer.
+    Pointer(&'p str),
+    /// The default, as no pointer gave a usable value.
+    Default,
+}
+
+/// An enum that values can be read into with `Value::as_enum`, from the
+/// strings naming its variants.
+pub trait JsonEnum: Sized {
+    /// The strings naming the variants, listed in errors.
+    const VARIANTS: &'static [&'static str];
+
+    /// Returns the variant named `s`, or None if there is none.
+    fn from_json_str(s: &str) -> Option<Self>;
+}
+
+/// A reference to one token of a parsed document.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Value<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+    idx: usize,
+}
+
+impl<'a> Value<'a> {
+    /// Returns the value of the token at `idx`, or None if there is none.
+    pub fn new(js: &'a str, tokens: &'a [JsmnTok], idx: usize) -> Option<Self> {
+        (idx < tokens.len()).then_some(Value { js, tokens, idx })
+    }
+
+    /// The index of the token in the token array.
+    pub fn index(&self) -> usize {
+        self.idx
+    }
+
+    pub fn token(&self) -> &'a JsmnTok {
+        &self.tokens[self.idx]
+    }
+
+    /// The text of the document the value is in.
+    #[cfg(feature = "transcode")]
+    pub(crate) fn js(&self) -> &'a str {
+        self.js
+    }
+
+    /// All the tokens of the document the value is in.
+    #[cfg(feature = "transcode")]
+    pub(crate) fn tokens(&self) -> &'a [JsmnTok] {
+        self.tokens
+    }
+
+    pub fn typ(&self) -> JsmnType {
+        self.token().typ
+    }
+
+    /// The raw text of the token. For strings this is the text between the
+    /// quotes, with escapes left as they are.
+    pub fn text(&self) -> &'a str {
+        let tok = self.token();
+        &self.js[tok.start as usize..tok.end as usize]
+    }
+
+    /// Returns the value of the member named `key` of an object. When the
+    /// key appears several times, the last member wins.
+    pub fn get(&self, key: &str) -> Option<Value<'a>> {
+        if self.typ() != JsmnType::JsmnObject {
+            return None;
+        }
+        let key = children(self.tokens, self.idx)
+            .filter(|&k| Value { idx: k, ..*self }.str_eq(key))
+            .last()?;
+        Value::new(self.js, self.tokens, key + 1)
+    }
+
+    /// Returns the element at `index` of an array.
+    pub fn at(&self, index: usize) -> Option<Value<'a>> {
+        if self.typ() != JsmnType::JsmnArray {
+            return None;
+        }
+        let element = children(self.tokens, self.idx).nth(index)?;
+        Value::new(self.js, self.tokens, element)
+    }
+
+    /// Returns an iterator over the elements of an array, in order. Values
+    /// other than arrays have none. Unlike calling `at` with each index in
+    /// turn, this walks the array once.
+    pub fn elements(&self) -> Elements<'a> {
+        Elements {
+            value: *self,
+            elements: ArrayIter::new(self.tokens, self.idx),
+        }
+    }
+
+    /// Returns an iterator over the keys and values of the members of an
+    /// object, in document order. Values other than objects have none.
+    pub fn members(&self) -> Members<'a> {
+        let keys = (self.typ() == JsmnType::JsmnObject).then(|| children(self.tokens, self.idx));
+        Members { value: *self, keys }
+    }
+
+    /// Returns the value at a JSON Pointer relative to this one.
+    pub fn pointer(&self, pointer: &str) -> Result<Option<Value<'a>>, PointerErr> {
+        let pointer = parse_pointer(pointer)?;
+        let idx = resolve(self.js, self.tokens, self.idx, &pointer, KeyMatch::Exact);
+        Ok(idx.and_then(|idx| Value::new(self.js, self.tokens, idx)))
+    }
+
+    /// Returns the value at the first of several JSON Pointers that leads
+    /// to a value other than `null`, along with that pointer. This covers
+    /// members that were renamed, such as `timeout_ms` becoming `timeoutMs`.
+    pub fn get_at_any<'p>(
+        &self,
+        pointers: &[&'p str],
+    ) -> Result<Option<(Value<'a>, Source<'p>)>, PointerErr> {
+        for &pointer in pointers {
+            if let Some(value) = self.pointer(pointer)?.filter(|value| !value.is_null()) {
+                return Ok(Some((value, Source::Pointer(pointer))));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Converts the value at the first of several JSON Pointers that gives
+    /// a usable value, or returns `default`. Values that `convert` rejects,
+    /// like ones of the wrong type, are passed over just like missing ones.
+    pub fn get_or<'p, T>(
+        &self,
+        pointers: &[&'p str],
+        default: T,
+        convert: impl FnMut(Value<'a>) -> Option<T>,
+    ) -> Result<(T, Source<'p>), PointerErr> {
+        self.get_or_else(pointers, || default, convert)
+    }
+
+    /// Like `get_or`, but only computes the default when it is needed.
+    pub fn get_or_else<'p, T>(
+        &self,
+        pointers: &[&'p str],
+        default: impl FnOnce() -> T,
+        mut convert: impl FnMut(Value<'a>) -> Option<T>,
+    ) -> Result<(T, Source<'p>), PointerErr> {
+        for &pointer in pointers {
+            if let Some(value) = self.pointer(pointer)?.and_then(&mut convert) {
+                return Ok((value, Source::Pointer(pointer)));
+            }
+        }
+        Ok((default(), Source::Default))
+    }
+
+    /// Converts the value at a JSON Pointer, treating a missing value and
+    /// `null` alike as None. Errors, including a malformed pointer, carry
+    /// the path of the value they are about.
+    pub fn get_opt<T>(
+        &self,
+        pointer: &str,
+        convert: impl FnOnce(Value<'a>) -> Result<T, ValueErr>,
+    ) -> Result<Option<T>, ValueErr> {
+        match self.pointer(pointer) {
+            Ok(Some(value)) if !value.is_null() => convert(value).map(Some),
+            Ok(_) => Ok(None),
+            Err(err) => Err(self.error(ValueErrKind::Pointer(err))),
+        }
+    }
+
+    /// Returns the string at a JSON Pointer, if it is neither missing nor
+    /// `null`.
+    pub fn get_opt_str(&self, pointer: &str) -> Result<Option<Cow<'a, str>>, ValueErr> {
+        self.get_opt(pointer, |value| value.read_str())
+    }
+
+    /// Returns the boolean at a JSON Pointer, if it is neither missing nor
+    /// `null`.
+    pub fn get_opt_bool(&self, pointer: &str) -> Result<Option<bool>, ValueErr> {
+        self.get_opt(pointer, |value| value.read_bool())
+    }
+
+    /// Returns the integer at a JSON Pointer, if it is neither missing nor
+    /// `null`. Numbers with a fraction or out of range are invalid.
+    pub fn get_opt_i64(&self, pointer: &str) -> Result<Option<i64>, ValueErr> {
+        self.get_opt(pointer, |value| value.read_i64())
+    }
+
+    /// Returns the number at a JSON Pointer, if it is neither missing nor
+    /// `null`. Numbers too large for an `f64` are invalid.
+    pub fn get_opt_f64(&self, pointer: &str) -> Result<Option<f64>, ValueErr> {
+        self.get_opt(pointer, |value| value.read_f64())
+    }
+
+    /// Returns the path from the root this value belongs to down to it. A
+    /// key has the same path as its value.
+    pub fn path(&self) -> Vec<PathItem> {
+        let mut path = Vec::new();
+        let contains = |idx: usize| idx <= self.idx && self.idx < subtree_end(self.tokens, idx);
+        let Some(mut idx) = roots(self.tokens).into_iter().find(|&root| contains(root)) else {
+            return path;
+        };
+
+        while idx != self.idx {
+            let object = self.tokens[idx].typ == JsmnType::JsmnObject;
+            let Some((i, child)) = children(self.tokens, idx)
+                .enumerate()
+                .find(|&(_, child)| contains(child))
+            else {
+                break;
+            };
+            if object {
+                let key = Value {
+                    idx: child,
+                    ..*self
+                };
+                path.push(PathItem::Key(key.as_str().unwrap_or_default().into_owned()));
+                idx = if child == self.idx { child } else { child + 1 };
+            } else {
+                path.push(PathItem::Index(i));
+                idx = child;
+            }
+        }
+        path
+    }
+
+    /// Builds an error about this value, for use in custom accessors.
+    pub fn error(&self, kind: ValueErrKind) -> ValueErr {
+        ValueErr {
+            pointer: to_pointer(&self.path()),
+            kind,
+        }
+    }
+
+    /// Builds an error saying this value has the wrong type.
+    pub fn wrong_type(&self) -> ValueErr {
+        self.error(ValueErrKind::WrongType(self.typ()))
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.typ() == JsmnType::JsmnPrimitive && self.text() == "null"
+    }
+
+    /// Returns true if this is a string equal to `text` once decoded. This
+    /// does not allocate, even when the string has escapes, which makes it
+    /// the cheap way to look for a key.
+    pub fn str_eq(&self, text: &str) -> bool {
+        self.typ() == JsmnType::JsmnString && raw_eq(self.text(), text)
+    }
+
+    /// Returns the decoded contents of a string.
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        (self.typ() == JsmnType::JsmnString).then(|| unescape(self.text()))
+    }
+
+    /// Returns a string as written, for callers that can use its raw text
+    /// when it has no escapes and only decode it otherwise.
+    pub fn as_raw_str(&self) -> Option<RawStr<'a>> {
+        RawStr::from_token(self.js, self.token())
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match (self.typ(), self.text()) {
+            (JsmnType::JsmnPrimitive, "true") => Some(true),
+            (JsmnType::JsmnPrimitive, "false") => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns an integer number that fits in an `i64`. Numbers with a
+    /// fraction or an exponent are not integers, even if their value is.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.integer_text()?.parse().ok()
+    }
+
+    /// Returns the text of a number exactly as written, for handing it to
+    /// a decimal or big number type without going through an `f64`.
+    pub fn as_number_str(&self) -> Option<&'a str> {
+        self.number_text()
+    }
+
+    /// Returns a non-negative integer number that fits in a `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.integer_text()?.parse().ok()
+    }
+
+    /// Returns an integer number that fits in an `i128`.
+    pub fn as_i128(&self) -> Option<i128> {
+        self.integer_text()?.parse().ok()
+    }
+
+    /// Returns a non-negative integer number that fits in a `u128`.
+    pub fn as_u128(&self) -> Option<u128> {
+        self.integer_text()?.parse().ok()
+    }
+
+    /// Returns the kind of a number, or None if this is not one.
+    pub fn number_kind(&self) -> Option<NumberKind> {
+        classify_number(self.number_text()?)
+    }
+
+    /// Returns an integer number of any length, without losing precision.
+    #[cfg(feature = "num-bigint")]
+    pub fn as_bigint(&self) -> Option<num_bigint::BigInt> {
+        self.integer_text()?.parse().ok()
+    }
+
+    /// Returns a number as an exact decimal, or None if it has more
+    /// significant digits than a `Decimal` can hold.
+    #[cfg(feature = "decimal")]
+    pub fn as_decimal(&self) -> Option<rust_decimal::Decimal> {
+        let text = self.number_text()?;
+        if text.contains(['e', 'E']) {
+            rust_decimal::Decimal::from_scientific(text).ok()
+        } else {
+            rust_decimal::Decimal::from_str_exact(text).ok()
+        }
+    }
+
+    /// Returns a timestamp from an RFC 3339 string, like
+    /// `"2024-05-01T12:00:00.5+02:00"`, or from an integer number of seconds
+    /// since the Unix epoch, which comes out in UTC.
+    #[cfg(feature = "chrono")]
+    pub fn as_datetime(&self) -> Result<chrono::DateTime<chrono::FixedOffset>, ValueErr> {
+        if let Some(seconds) = self.as_i64() {
+            return chrono::DateTime::from_timestamp(seconds, 0)
+                .map(|utc| utc.fixed_offset())
+                .ok_or_else(|| self.error(ValueErrKind::Invalid("timestamp out of range".into())));
+        }
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        chrono::DateTime::parse_from_rfc3339(&text)
+            .map_err(|err| self.error(ValueErrKind::Invalid(err.to_string())))
+    }
+
+    /// The same as [`Value::as_datetime`], for the time crate.
+    #[cfg(feature = "time")]
+    pub fn as_offset_datetime(&self) -> Result<time::OffsetDateTime, ValueErr> {
+        use time::format_description::well_known::Rfc3339;
+
+        if let Some(seconds) = self.as_i64() {
+            return time::OffsetDateTime::from_unix_timestamp(seconds)
+                .map_err(|err| self.error(ValueErrKind::Invalid(err.to_string())));
+        }
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        time::OffsetDateTime::parse(&text, &Rfc3339)
+            .map_err(|err| self.error(ValueErrKind::Invalid(err.to_string())))
+    }
+
+    /// Returns a duration from a string like `"30s"`, `"5m"` or
+    /// `"1h 30m"`, in the syntax of the `humantime` crate.
+    #[cfg(feature = "humantime")]
+    pub fn as_duration(&self) -> Result<std::time::Duration, ValueErr> {
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        humantime::parse_duration(&text)
+            .map_err(|err| self.error(ValueErrKind::Invalid(err.to_string())))
+    }
+
+    /// Returns a UUID from a string in the hyphenated form, like
+    /// `"67e55044-10b1-426f-9247-bb680e5fe0c8"`, or in the simple form made
+    /// of the 32 hex digits alone.
+    #[cfg(feature = "uuid")]
+    pub fn as_uuid(&self) -> Result<uuid::Uuid, ValueErr> {
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        if text.len() != 32 && text.len() != 36 {
+            return Err(self.error(ValueErrKind::Invalid("not a UUID".into())));
+        }
+        uuid::Uuid::try_parse(&text)
+            .map_err(|err| self.error(ValueErrKind::Invalid(err.to_string())))
+    }
+
+    /// Returns the bytes of a string holding standard, padded base64. The
+    /// digits are decoded straight from the document unless the string
+    /// contains escapes.
+    #[cfg(feature = "base64")]
+    pub fn as_base64_bytes(&self) -> Result<Vec<u8>, ValueErr> {
+        use base64::Engine;
+
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        base64::engine::general_purpose::STANDARD
+            .decode(text.as_bytes())
+            .map_err(|err| self.error(ValueErrKind::Invalid(err.to_string())))
+    }
+
+    /// Returns the bytes of a string holding pairs of hex digits, in either
+    /// case.
+    pub fn as_hex_bytes(&self) -> Result<Vec<u8>, ValueErr> {
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        let invalid = |reason: &str| self.error(ValueErrKind::Invalid(reason.into()));
+        if text.len() % 2 != 0 {
+            return Err(invalid("odd number of hex digits"));
+        }
+        text.as_bytes()
+            .chunks(2)
+            .map(|pair| match (hex_digit(pair[0]), hex_digit(pair[1])) {
+                (Some(high), Some(low)) => Ok(high << 4 | low),
+                _ => Err(invalid("invalid hex digit")),
+            })
+            .collect()
+    }
+
+    /// Returns an IPv4 or IPv6 address from a string.
+    pub fn as_ip_addr(&self) -> Result<IpAddr, ValueErr> {
+        self.parse_str()
+    }
+
+    /// Returns a socket address from a string, like `"127.0.0.1:8080"` or
+    /// `"[::1]:8080"`. Host names are not resolved.
+    pub fn as_socket_addr(&self) -> Result<SocketAddr, ValueErr> {
+        self.parse_str()
+    }
+
+    /// Returns an absolute URL from a string.
+    #[cfg(feature = "url")]
+    pub fn as_url(&self) -> Result<url::Url, ValueErr> {
+        self.parse_str()
+    }
+
+    /// Returns the variant of an enum named by a string.
+    pub fn as_enum<T: JsonEnum>(&self) -> Result<T, ValueErr> {
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        T::from_json_str(&text).ok_or_else(|| {
+            self.error(ValueErrKind::UnknownVariant {
+                found: text.into_owned(),
+                allowed: T::VARIANTS,
+            })
+        })
+    }
+
+    /// Returns a number as the `f64` nearest to its exact decimal value,
+    /// with ties going to the even significand, which is the correctly
+    /// rounded result required by IEEE 754. Together with the shortest
+    /// round-trip output of the writer, this means any `f64` survives being
+    /// written and read back bit for bit. Numbers too large for an `f64`
+    /// give None rather than an infinity.
+    pub fn as_f64_exact(&self) -> Option<f64> {
+        self.number_text()?
+            .parse()
+            .ok()
+            .filter(|f: &f64| f.is_finite())
+    }
+
+    /// Parses a string with `FromStr`, reporting failures at this value.
+    fn parse_str<T>(&self) -> Result<T, ValueErr>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let text = self.as_str().ok_or_else(|| self.wrong_type())?;
+        text.parse()
+            .map_err(|err: T::Err| self.error(ValueErrKind::Invalid(err.to_string())))
+    }
+
+    // The conversions of the typed getters, with errors for the wrong type.
+
+    pub(crate) fn read_str(&self) -> Result<Cow<'a, str>, ValueErr> {
+        self.as_str().ok_or_else(|| self.wrong_type())
+    }
+
+    pub(crate) fn read_bool(&self) -> Result<bool, ValueErr> {
+        self.as_bool().ok_or_else(|| self.wrong_type())
+    }
+
+    pub(crate) fn read_i64(&self) -> Result<i64, ValueErr> {
+        self.number_text().ok_or_else(|| self.wrong_type())?;
+        self.as_i64()
+            .ok_or_else(|| self.error(ValueErrKind::Invalid("not an i64".into())))
+    }
+
+    pub(crate) fn read_f64(&self) -> Result<f64, ValueErr> {
+        self.number_text().ok_or_else(|| self.wrong_type())?;
+        self.as_f64_exact()
+            .ok_or_else(|| self.error(ValueErrKind::Invalid("out of range".into())))
+    }
+
+    pub(crate) fn read_number_str(&self) -> Result<&'a str, ValueErr> {
+        self.number_text().ok_or_else(|| self.wrong_type())
+    }
+
+    #[cfg(feature = "decimal")]
+    pub(crate) fn read_decimal(&self) -> Result<rust_decimal::Decimal, ValueErr> {
+        self.read_number_str()?;
+        self.as_decimal()
+            .ok_or_else(|| self.error(ValueErrKind::Invalid("not a Decimal".into())))
+    }
+
+    /// The text of a primitive that follows the JSON number grammar.
+    pub(crate) fn number_text(&self) -> Option<&'a str> {
+        let text = self.text();
+        (self.typ() == JsmnType::JsmnPrimitive && is_number(text)).then_some(text)
+    }
+
+    /// The text of a number without a fraction or exponent.
+    pub(crate) fn integer_text(&self) -> Option<&'a str> {
+        self.number_text()
+            .filter(|text| !text.contains(['.', 'e', 'E']))
+    }
+}
+
+/// Error type for the token-level primitive accessors.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PrimitiveErr {
+    /// The token is not a primitive.
+    WrongType(JsmnType),
+    /// The primitive does not follow the JSON number grammar, like `012`,
+    /// `.5` or `true`.
+    NotANumber,
+    /// The number has a fraction or an exponent.
+    NotAnInteger,
+    /// The number does not fit in the requested type.
+    OutOfRange,
+    /// The primitive is neither `true` nor `false`.
+    NotABool,
+}
+
+impl fmt::Display for PrimitiveErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrimitiveErr::WrongType(typ) => write!(f, "unexpected {:?}", typ),
+            PrimitiveErr::NotANumber => write!(f, "not a number"),
+            PrimitiveErr::NotAnInteger => write!(f, "not an integer"),
+            PrimitiveErr::OutOfRange => write!(f, "number out of range"),
+            PrimitiveErr::NotABool => write!(f, "not a boolean"),
+        }
+    }
+}
+
+impl Error for PrimitiveErr {}
+
+fn primitive_text<'a>(js: &'a str, tok: &JsmnTok) -> Result<&'a str, PrimitiveErr> {
+    if tok.typ != JsmnType::JsmnPrimitive {
+        return Err(PrimitiveErr::WrongType(tok.typ));
+    }
+    js.get(tok.start as usize..tok.end as usize)
+        .ok_or(PrimitiveErr::WrongType(tok.typ))
+}
+
+/// Returns true if the token of `js` is `null`.
+pub fn token_is_null(js: &str, tok: &JsmnTok) -> bool {
+    primitive_text(js, tok) == Ok("null")
+}
+
+/// Reads a `true` or `false` token of `js`.
+pub fn token_as_bool(js: &str, tok: &JsmnTok) -> Result<bool, PrimitiveErr> {
+    match primitive_text(js, tok)? {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(PrimitiveErr::NotABool),
+    }
+}
+
+/// Reads an integer token of `js`. Numbers with a fraction or an exponent
+/// are not integers, even if their value is.
+pub fn token_as_i64(js: &str, tok: &JsmnTok) -> Result<i64, PrimitiveErr> {
+    token_as_int(js, tok)
+}
+
+/// Reads a non-negative integer token of `js`, like `token_as_i64`.
+pub fn token_as_u64(js: &str, tok: &JsmnTok) -> Result<u64, PrimitiveErr> {
+    token_as_int(js, tok)
+}
+
+/// Reads an integer token of `js` too large for an `i64`, such as a 128-bit
+/// identifier, like `token_as_i64`.
+pub fn token_as_i128(js: &str, tok: &JsmnTok) -> Result<i128, PrimitiveErr> {
+    token_as_int(js, tok)
+}
+
+/// Reads a non-negative integer token of `js` too large for a `u64`, like
+/// `token_as_i64`.
+pub fn token_as_u128(js: &str, tok: &JsmnTok) -> Result<u128, PrimitiveErr> {
+    token_as_int(js, tok)
+}
+
+fn token_as_int<T: FromStr>(js: &str, tok: &JsmnTok) -> Result<T, PrimitiveErr> {
+    let text = primitive_text(js, tok)?;
+    if !is_number(text) {
+        return Err(PrimitiveErr::NotANumber);
+    }
+    if text.contains(['.', 'e', 'E']) {
+        return Err(PrimitiveErr::NotAnInteger);
+    }
+    text.parse().map_err(|_| PrimitiveErr::OutOfRange)
+}
+
+/// Reads a number token of `js` as the nearest `f64`, like
+/// `Value::as_f64_exact`.
+pub fn token_as_f64(js: &str, tok: &JsmnTok) -> Result<f64, PrimitiveErr> {
+    let text = primitive_text(js, tok)?;
+    if !is_number(text) {
+        return Err(PrimitiveErr::NotANumber);
+    }
+    text.parse()
+        .ok()
+        .filter(|f: &f64| f.is_finite())
+        .ok_or(PrimitiveErr::OutOfRange)
+}
+
+/// What a number can be read into without losing anything.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NumberKind {
+    /// An integer that fits in an `i64`.
+    Int,
+    /// A non-negative integer too large for an `i64` that fits in a `u64`.
+    UInt,
+    /// A number with a fraction or an exponent, even one like `1.0` or
+    /// `2e3` whose value is an integer.
+    Float,
+    /// An integer too large for both, to be read as an `i128`, a `u128` or
+    /// a big integer.
+    BigInt,
+}
+
+/// Classifies the text of a number token, or returns None if it does not
+/// follow the JSON number grammar.
+pub fn classify_number(text: &str) -> Option<NumberKind> {
+    if !is_number(text) {
+        return None;
+    }
+    Some(if text.contains(['.', 'e', 'E']) {
+        NumberKind::Float
+    } else if text.parse::<i64>().is_ok() {
+        NumberKind::Int
+    } else if text.parse::<u64>().is_ok() {
+        NumberKind::UInt
+    } else {
+        NumberKind::BigInt
+    })
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+/// Checks `text` against the JSON number grammar. jsmn itself accepts any
+/// primitive that is not a literal, unless the strict feature is enabled.
+pub(crate) fn is_number(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut i = usize::from(bytes.first() == Some(&b'-'));
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    let int_start = i;
+    let int_len = digits(&mut i);
+    if int_len == 0 || (int_len > 1 && bytes[int_start] == b'0') {
+        return false;
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
+
+/// Iterator over the elements of an array, returned by `Value::elements`.
+#[derive(Debug, Clone)]
+pub struct Elements<'a> {
+    value: Value<'a>,
+    elements: ArrayIter<'a>,
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Value<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.elements.next()?;
+        Some(Value { idx, ..self.value })
+    }
+}
+
+/// Iterator over the members of an object, returned by `Value::members`.
+pub struct Members<'a> {
+    value: Value<'a>,
+    keys: Option<Children<'a>>,
+}
+
+impl<'a> Members<'a> {
+    fn next_member(&mut self) -> Option<(Value<'a>, Value<'a>)> {
+        let key = self.keys.as_mut()?.next()?;
+        let key = Value {
+            idx: key,
+            ..self.value
+        };
+        Some((
+            key,
+            Value::new(self.value.js, self.value.tokens, key.idx + 1)?,
+        ))
+    }
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (Cow<'a, str>, Value<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.next_member()?;
+        Some((key.as_str()?, value))
+    }
+}
+
+/// The decoded keys of a document, for loops over objects that should
+/// neither allocate nor decode the same keys again. Keys without escapes
+/// are borrowed from the document, and the others are decoded once, when
+/// the arena is built, into a single buffer.
+#[derive(Debug, Clone)]
+pub struct KeyArena<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+    decoded: String,
+    /// The token indices of the keys with escapes, in order, with their
+    /// spans in `decoded`.
+    escaped: Vec<(usize, Range<usize>)>,
+}
+
+impl<'a> KeyArena<'a> {
+    pub fn new(js: &'a str, tokens: &'a [JsmnTok]) -> Self {
+        let mut arena = KeyArena {
+            js,
+            tokens,
+            decoded: String::new(),
+            escaped: Vec::new(),
+        };
+        for (idx, tok) in tokens.iter().enumerate() {
+            let raw = &js[tok.start as usize..tok.end as usize];
+            if tok.typ == JsmnType::JsmnString && tok.size > 0 && raw.contains('\\') {
+                let start = arena.decoded.len();
+                arena.decoded.push_str(&unescape(raw));
+                arena.escaped.push((idx, start..arena.decoded.len()));
+            }
+        }
+        arena
+    }
+
+    /// Returns the decoded key at token `idx`, or None if it is not a key.
+    pub fn key(&self, idx: usize) -> Option<&str> {
+        let tok = self.tokens.get(idx)?;
+        if tok.typ != JsmnType::JsmnString || tok.size <= 0 {
+            return None;
+        }
+        Some(
+            match self.escaped.binary_search_by_key(&idx, |(key, _)| *key) {
+                Ok(i) => &self.decoded[self.escaped[i].1.clone()],
+                Err(_) => &self.js[tok.start as usize..tok.end as usize],
+            },
+        )
+    }
+
+    /// Like `Value::members`, but with the keys taken from the arena. The
+    /// value must come from the document the arena was built for.
+    pub fn members<'k>(&'k self, object: Value<'a>) -> ArenaMembers<'k, 'a> {
+        ArenaMembers {
+            arena: self,
+            members: object.members(),
+        }
+    }
+}
+
+/// Iterator over the members of an object, returned by
+/// `KeyArena::members`.
+pub struct ArenaMembers<'k, 'a> {
+    arena: &'k KeyArena<'a>,
+    members: Members<'a>,
+}
+
+impl<'k, 'a> Iterator for ArenaMembers<'k, 'a> {
+    type Item = (&'k str, Value<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.members.next_member()?;
+        Some((self.arena.key(key.idx)?, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    #[test]
+    fn navigate_and_convert() {
+        let js = r#"{"a": [1], "\u0062": {"c": 7}, "a": [0]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let root = Value::new(js, &tokens, 0).unwrap();
+
+        let b = root.get("b").unwrap();
+        assert_eq!(b.get("c").and_then(|c| c.as_i64()), Some(7));
+        assert_eq!(root.pointer("/b/c").unwrap(), b.get("c"));
+        assert_eq!(root.get("a").unwrap().at(0).unwrap().as_i64(), Some(0));
+        assert_eq!(root.get("missing"), None);
+        assert_eq!(b.at(0), None);
+
+        let js = r#"[1, "x\ty", true, null, -2.5e1]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let array = Value::new(js, &tokens, 0).unwrap();
+        let items: Vec<_> = (0..5).map(|i| array.at(i).unwrap()).collect();
+        assert_eq!(array.elements().collect::<Vec<_>>(), items);
+        assert_eq!(b.elements().count(), 0);
+        assert_eq!(items[1].as_str().as_deref(), Some("x\ty"));
+        assert!(items[1].str_eq("x\ty") && !items[1].str_eq("x\\ty"));
+        assert!(items[1].as_raw_str().is_some_and(|s| s.has_escapes()));
+        assert_eq!(items[0].as_raw_str(), None);
+        assert!(!items[0].str_eq("1"));
+        assert_eq!(items[1].as_i64(), None);
+        assert_eq!(items[2].as_bool(), Some(true));
+        assert!(items[3].is_null());
+        assert_eq!(items[4].as_f64_exact(), Some(-25.0));
+        assert_eq!(items[4].as_i64(), None);
+    }
+
+    #[test]
+    fn member_iteration() {
+        let js = r#"{"plain": 1, "tab\there": [2], "\u00e9": {"x": null}}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let root = Value::new(js, &tokens, 0).unwrap();
+
+        let keys: Vec<_> = root.members().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["plain", "tab\there", "é"]);
+        assert!(matches!(keys[0], Cow::Borrowed(_)));
+        assert_eq!(root.get("plain").unwrap().members().count(), 0);
+
+        let arena = KeyArena::new(js, &tokens);
+        let members: Vec<_> = arena
+            .members(root)
+            .map(|(key, value)| (key, value.index()))
+            .collect();
+        assert_eq!(members, [("plain", 2), ("tab\there", 4), ("é", 7)]);
+        let inner = root.get("é").unwrap();
+        assert_eq!(arena.members(inner).next().map(|(key, _)| key), Some("x"));
+        assert_eq!(arena.key(2), None);
+    }
+
+    #[test]
+    fn big_integers() {
+        let js = "[170141183460469231731687303715884105727, -170141183460469231731687303715884105728, 340282366920938463463374607431768211455, 1e3, -1]";
+        let tokens = parse_to_vec(js).unwrap();
+        let array = Value::new(js, &tokens, 0).unwrap();
+        let item = |i| array.at(i).unwrap();
+
+        assert_eq!(item(0).as_i128(), Some(i128::MAX));
+        assert_eq!(item(0).as_i64(), None);
+        assert_eq!(item(1).as_i128(), Some(i128::MIN));
+        assert_eq!(item(1).as_u128(), None);
+        assert_eq!(item(2).as_u128(), Some(u128::MAX));
+        assert_eq!(item(2).as_i128(), None);
+        assert_eq!(item(3).as_i128(), None);
+        assert_eq!(item(4).as_i128(), Some(-1));
+
+        #[cfg(feature = "num-bigint")]
+        {
+            let js = "[-123456789012345678901234567890123456789012345678901234567890, 1.5]";
+            let tokens = parse_to_vec(js).unwrap();
+            let array = Value::new(js, &tokens, 0).unwrap();
+            let big = array.at(0).unwrap().as_bigint().unwrap();
+            assert_eq!(big.to_string(), array.at(0).unwrap().text());
+            assert_eq!(array.at(1).unwrap().as_bigint(), None);
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn decimals() {
+        use rust_decimal::Decimal;
+
+        let js = r#"[19.99, -0.10, 2.5e-3, "1.0", 1.0.0]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let array = Value::new(js, &tokens, 0).unwrap();
+        let item = |i| array.at(i).unwrap().as_decimal();
+
+        assert_eq!(item(0), Decimal::from_str_exact("19.99").ok());
+        assert_eq!(item(1), Decimal::from_str_exact("-0.10").ok());
+        assert_eq!(item(2), Decimal::from_scientific("2.5e-3").ok());
+        assert_eq!(item(3), None);
+        assert_eq!(item(4), None);
+        assert_eq!(array.at(1).unwrap().as_number_str(), Some("-0.10"));
+        assert_eq!(array.at(3).unwrap().as_number_str(), None);
+    }
+
+    #[test]
+    fn fallbacks() {
+        let js = r#"{"timeout_ms": null, "timeoutMs": 250, "retries": "3"}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let root = Value::new(js, &tokens, 0).unwrap();
+        let timeout = ["/timeout_ms", "/timeoutMs"];
+
+        let (value, source) = root.get_at_any(&timeout).unwrap().unwrap();
+        assert_eq!(
+            (value.text(), source),
+            ("250", Source::Pointer("/timeoutMs"))
+        );
+        assert_eq!(root.get_at_any(&["/a", "/b"]), Ok(None));
+        assert_eq!(root.get_at_any(&["a"]), Err(PointerErr::MissingSlash));
+
+        assert_eq!(
+            root.get_or(&timeout, 1000, |v| v.as_i64()),
+            Ok((250, Source::Pointer("/timeoutMs")))
+        );
+        assert_eq!(
+            root.get_or(&["/retries"], 5, |v| v.as_i64()),
+            Ok((5, Source::Default))
+        );
+        assert_eq!(
+            root.get_or_else(&["/missing"], || 7, |v| v.as_i64()),
+            Ok((7, Source::Default))
+        );
+    }
+
+    #[test]
+    fn optional_getters() {
+        let js = r#"{"a": {"n": 5, "x": null, "f": 1.5, "s": "hi", "b": true, "big": 1e400}}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let root = Value::new(js, &tokens, 0).unwrap();
+
+        assert_eq!(root.get_opt_i64("/a/n"), Ok(Some(5)));
+        assert_eq!(root.get_opt_i64("/a/x"), Ok(None));
+        assert_eq!(root.get_opt_i64("/a/missing"), Ok(None));
+        assert_eq!(root.get_opt_i64("/nope/n"), Ok(None));
+        assert_eq!(root.get_opt_f64("/a/f"), Ok(Some(1.5)));
+        assert_eq!(root.get_opt_str("/a/s").unwrap().as_deref(), Some("hi"));
+        assert_eq!(root.get_opt_bool("/a/b"), Ok(Some(true)));
+
+        let err = root.get_opt_i64("/a/s").unwrap_err();
+        assert_eq!(err.pointer, "/a/s");
+        assert_eq!(err.kind, ValueErrKind::WrongType(JsmnType::JsmnString));
+        let err = root.get_opt_i64("/a/f").unwrap_err();
+        assert_eq!(err.to_string(), "/a/f: not an i64");
+        assert_eq!(root.get_opt_f64("/a/big").unwrap_err().pointer, "/a/big");
+        assert_eq!(root.get_opt_bool("/a/n").unwrap_err().pointer, "/a/n");
+
+        let a = root.get("a").unwrap();
+        assert_eq!(
+            a.get_opt_str("n").unwrap_err(),
+            ValueErr {
+                pointer: "/a".into(),
+                kind: ValueErrKind::Pointer(PointerErr::MissingSlash),
+            }
+        );
+    }
+
+    #[test]
+    fn value_paths() {
+        let js = r#"{"a": [1, {"b/c": [true]}], "d": 2} [3]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let pointer = |idx| to_pointer(&Value::new(js, &tokens, idx).unwrap().path());
+
+        assert_eq!(pointer(0), "");
+        assert_eq!(pointer(1), "/a");
+        assert_eq!(pointer(3), "/a/0");
+        assert_eq!(pointer(7), "/a/1/b~1c/0");
+        assert_eq!(pointer(9), "/d");
+        assert_eq!(pointer(11), "/0");
+    }
+
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    #[test]
+    fn timestamps() {
+        let js = r#"{"events": [
+            {"at": "2024-01-02T03:04:05Z"},
+            {"at": "2024-01-02T05:04:05.250+02:00"},
+            {"at": 1704164645},
+            {"at": "yesterday"},
+            {"at": true}
+        ]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let root = Value::new(js, &tokens, 0).unwrap();
+        let at = |i: usize| root.pointer(&format!("/events/{}/at", i)).unwrap().unwrap();
+
+        #[cfg(feature = "chrono")]
+        {
+            let seconds: Vec<_> = (0..3)
+                .map(|i| at(i).as_datetime().unwrap().timestamp())
+                .collect();
+            assert_eq!(seconds, [1704164645; 3]);
+            let err = at(3).as_datetime().unwrap_err();
+            assert_eq!(err.pointer, "/events/3/at");
+            assert!(matches!(err.kind, ValueErrKind::Invalid(_)));
+            assert_eq!(
+                at(4).as_datetime().unwrap_err().kind,
+                ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+            );
+        }
+
+        #[cfg(feature = "time")]
+        {
+            let seconds: Vec<_> = (0..3)
+                .map(|i| at(i).as_offset_datetime().unwrap().unix_timestamp())
+                .collect();
+            assert_eq!(seconds, [1704164645; 3]);
+            assert_eq!(
+                at(3).as_offset_datetime().unwrap_err().pointer,
+                "/events/3/at"
+            );
+        }
+    }
+
+    #[cfg(feature = "humantime")]
+    #[test]
+    fn durations() {
+        use std::time::Duration;
+
+        let js = r#"{"timeout": "30s", "retry": "1h 5m", "bad": "5 parsecs", "n": 30}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let root = Value::new(js, &tokens, 0).unwrap();
+        let field = |key| root.get(key).unwrap().as_duration();
+
+        assert_eq!(field("timeout"), Ok(Duration::from_secs(30)));
+        assert_eq!(field("retry"), Ok(Duration::from_secs(3900)));
+        assert_eq!(field("bad").unwrap_err().pointer, "/bad");
+        assert_eq!(
+            field("n").unwrap_err().kind,
+            ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuids() {
+        let js = r#"[
+            "67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "67E5504410B1426F9247BB680E5FE0C8",
+            "{67e55044-10b1-426f-9247-bb680e5fe0c8}",
+            "67e55044-10b1-426f-9247-bb680e5fe0cz",
+            67
+        ]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let array = Value::new(js, &tokens, 0).unwrap();
+        let item = |i| array.at(i).unwrap().as_uuid();
+
+        let expected = uuid::Uuid::from_u128(0x67e55044_10b1_426f_9247_bb680e5fe0c8);
+        assert_eq!(item(0), Ok(expected));
+        assert_eq!(item(1), Ok(expected));
+        assert_eq!(item(2).unwrap_err().pointer, "/2");
+        assert!(matches!(
+            item(3).unwrap_err().kind,
+            ValueErrKind::Invalid(_)
+        ));
+        assert_eq!(
+            item(4).unwrap_err().kind,
+            ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+        );
+    }
+
+    #[test]
+    fn binary_strings() {
+        let js = r#"["00ffA5", "0f0", "0g", "aGk\u003d", "aGk=", "aGk", 1]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let array = Value::new(js, &tokens, 0).unwrap();
+        let item = |i| array.at(i).unwrap();
+
+        assert_eq!(item(0).as_hex_bytes(), Ok(vec![0x00, 0xff, 0xa5]));
+        assert!(matches!(
+            item(1).as_hex_bytes().unwrap_err().kind,
+            ValueErrKind::Invalid(_)
+        ));
+        assert_eq!(item(2).as_hex_bytes().unwrap_err().pointer, "/2");
+        assert_eq!(
+            item(6).as_hex_bytes().unwrap_err().kind,
+            ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+        );
+
+        #[cfg(feature = "base64")]
+        {
+            assert_eq!(item(3).as_base64_bytes(), Ok(b"hi".to_vec()));
+            assert_eq!(item(4).as_base64_bytes(), Ok(b"hi".to_vec()));
+            assert!(item(5).as_base64_bytes().is_err());
+            assert!(item(6).as_base64_bytes().is_err());
+        }
+    }
+
+    #[test]
+    fn network_addresses() {
+        let js = r#"{"host": "10.0.0.1", "v6": "::1", "listen": "[::1]:8080", "port": 80}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let root = Value::new(js, &tokens, 0).unwrap();
+        let field = |key| root.get(key).unwrap();
+
+        assert_eq!(field("host").as_ip_addr(), Ok([10, 0, 0, 1].into()));
+        assert_eq!(
+            field("v6").as_ip_addr(),
+            Ok(std::net::Ipv6Addr::LOCALHOST.into())
+        );
+        assert_eq!(
+            field("listen").as_socket_addr(),
+            Ok(SocketAddr::new(std::net::Ipv6Addr::LOCALHOST.into(), 8080))
+        );
+        assert_eq!(field("host").as_socket_addr().unwrap_err().pointer, "/host");
+        assert_eq!(
+            field("port").as_ip_addr().unwrap_err().kind,
+            ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn urls() {
+        let js = r#"{"api": {"base": "https://example.com/v1/", "proxy": "not a url"}}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let api = Value::new(js, &tokens, 0).unwrap().get("api").unwrap();
+
+        let base = api.get("base").unwrap().as_url().unwrap();
+        assert_eq!(base.host_str(), Some("example.com"));
+        let err = api.get("proxy").unwrap().as_url().unwrap_err();
+        assert_eq!(err.pointer, "/api/proxy");
+        assert!(matches!(err.kind, ValueErrKind::Invalid(_)));
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Level {
+        Debug,
+        Info,
+        Warn,
+    }
+
+    impl JsonEnum for Level {
+        const VARIANTS: &'static [&'static str] = &["debug", "info", "warn"];
+
+        fn from_json_str(s: &str) -> Option<Self> {
+            match s {
+                "debug" => Some(Level::Debug),
+                "info" => Some(Level::Info),
+                "warn" => Some(Level::Warn),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn enums() {
+        let js = r#"{"levels": ["info", "warn", "loud", 3]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let levels = Value::new(js, &tokens, 0).unwrap().get("levels").unwrap();
+        let item = |i| levels.at(i).unwrap().as_enum::<Level>();
+
+        assert_eq!(item(0), Ok(Level::Info));
+        assert_eq!(item(1), Ok(Level::Warn));
+        let err = item(2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"/levels/2: unknown variant "loud", expected "debug", "info" or "warn""#
+        );
+        assert_eq!(
+            item(3).unwrap_err().kind,
+            ValueErrKind::WrongType(JsmnType::JsmnPrimitive)
+        );
+    }
+
+    #[test]
+    fn number_kinds() {
+        let kind = classify_number;
+        assert_eq!(kind("0"), Some(NumberKind::Int));
+        assert_eq!(kind("-0"), Some(NumberKind::Int));
+        assert_eq!(kind("-9223372036854775808"), Some(NumberKind::Int));
+        assert_eq!(kind("9223372036854775808"), Some(NumberKind::UInt));
+        assert_eq!(kind("18446744073709551616"), Some(NumberKind::BigInt));
+        assert_eq!(kind("-9223372036854775809"), Some(NumberKind::BigInt));
+        assert_eq!(kind("1.0"), Some(NumberKind::Float));
+        assert_eq!(kind("2E3"), Some(NumberKind::Float));
+        assert_eq!(kind("-1e-7"), Some(NumberKind::Float));
+        assert_eq!(kind("01"), None);
+        assert_eq!(kind("1."), None);
+        assert_eq!(kind("true"), None);
+
+        let js = r#"[340282366920938463463374607431768211455, -3, "7"]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let array = Value::new(js, &tokens, 0).unwrap();
+        let id = array.at(0).unwrap();
+        assert_eq!(id.number_kind(), Some(NumberKind::BigInt));
+        assert_eq!(id.as_u128(), Some(u128::MAX));
+        assert_eq!(id.as_u64(), None);
+        assert_eq!(array.at(1).unwrap().as_u64(), None);
+        assert_eq!(array.at(2).unwrap().number_kind(), None);
+    }
+
+    #[test]
+    fn token_primitives() {
+        let js = r#"[0, -12, 1.5e3, 9223372036854775808, 1e999, true, null, "1"]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let tok = |i: usize| &tokens[i + 1];
+
+        assert_eq!(token_as_i64(js, tok(0)), Ok(0));
+        assert_eq!(token_as_i64(js, tok(1)), Ok(-12));
+        assert_eq!(token_as_i64(js, tok(2)), Err(PrimitiveErr::NotAnInteger));
+        assert_eq!(token_as_i64(js, tok(3)), Err(PrimitiveErr::OutOfRange));
+        assert_eq!(token_as_i64(js, tok(5)), Err(PrimitiveErr::NotANumber));
+        assert_eq!(
+            token_as_i64(js, tok(7)),
+            Err(PrimitiveErr::WrongType(JsmnType::JsmnString))
+        );
+
+        assert_eq!(token_as_f64(js, tok(2)), Ok(1500.0));
+        assert_eq!(token_as_f64(js, tok(3)), Ok(9223372036854775808.0));
+        assert_eq!(token_as_f64(js, tok(4)), Err(PrimitiveErr::OutOfRange));
+        assert_eq!(token_as_f64(js, tok(6)), Err(PrimitiveErr::NotANumber));
+
+        assert_eq!(token_as_u64(js, tok(3)), Ok(9223372036854775808));
+        assert_eq!(token_as_u64(js, tok(1)), Err(PrimitiveErr::OutOfRange));
+        assert_eq!(token_as_i128(js, tok(3)), Ok(9223372036854775808));
+        assert_eq!(token_as_u128(js, tok(2)), Err(PrimitiveErr::NotAnInteger));
+
+        assert_eq!(token_as_bool(js, tok(5)), Ok(true));
+        assert_eq!(token_as_bool(js, tok(0)), Err(PrimitiveErr::NotABool));
+        assert!(token_is_null(js, tok(6)));
+        assert!(!token_is_null(js, tok(5)));
+
+        #[cfg(not(feature = "strict"))]
+        {
+            let js = "[012, .5, +1, 1.]";
+            let tokens = parse_to_vec(js).unwrap();
+            for tok in &tokens[1..] {
+                assert_eq!(token_as_f64(js, tok), Err(PrimitiveErr::NotANumber));
+            }
+        }
+    }
+
+    #[test]
+    fn number_grammar() {
+        for ok in ["0", "-0", "12", "1.5", "-0.25e-3", "1E+9"] {
+            assert!(is_number(ok), "{}", ok);
+        }
+        for bad in [
+            "", "-", "01", "1.", ".5", "1e", "+1", "0x10", "NaN", "1.5.2",
+        ] {
+            assert!(!is_number(bad), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn exact_floats() {
+        let cases = [
+            ("9007199254740993", 9007199254740992.0_f64),
+            ("9007199254740995", 9007199254740996.0),
+            ("2.2250738585072011e-308", 2.225073858507201e-308),
+            ("0.1", 0.1),
+            ("4.9e-324", 5e-324),
+            ("1e-400", 0.0),
+        ];
+        for (text, expected) in cases {
+            let js = format!("[{}]", text);
+            let tokens = parse_to_vec(&js).unwrap();
+            let value = Value::new(&js, &tokens, 1).unwrap();
+            assert_eq!(
+                value.as_f64_exact().map(f64::to_bits),
+                Some(expected.to_bits())
+            );
+        }
+
+        let tokens = parse_to_vec("[1e400, 0x1]").unwrap();
+        let array = Value::new("[1e400, 0x1]", &tokens, 0).unwrap();
+        assert_eq!(array.at(0).unwrap().as_f64_exact(), None);
+        assert_eq!(array.at(1).unwrap().as_f64_exact(), None);
+    }
+}
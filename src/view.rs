@@ -0,0 +1,125 @@
+//!
+//! This module provides `TokenView`, which pairs a token array with the
+//! text it was parsed from after checking, once, that every token lies in
+//! the text on character boundaries. Reading the text of a token through
+//! the view can then never panic, which matters for tokens that were not
+//! produced from that text in the same run, such as tokens stored next to
+//! a document and loaded back.
+//!
+
+use std::error::Error;
+use std::fmt;
+
+use crate::JsmnTok;
+
+/// Error type for tokens that do not lie in the text they are viewed with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TokenOutOfBounds {
+    /// The index of the first offending token.
+    pub index: usize,
+}
+
+impl fmt::Display for TokenOutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "token {} does not lie on character boundaries of the text",
+            self.index
+        )
+    }
+}
+
+impl Error for TokenOutOfBounds {}
+
+/// Tokens whose text is known to be in bounds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TokenView<'a> {
+    js: &'a str,
+    tokens: &'a [JsmnTok],
+}
+
+impl<'a> TokenView<'a> {
+    /// Checks every token against `js`. Objects and arrays left open by a
+    /// partial parse, whose end is still -1, do not pass.
+    pub fn new(js: &'a str, tokens: &'a [JsmnTok]) -> Result<Self, TokenOutOfBounds> {
+        match tokens.iter().position(|tok| tok.try_as_str(js).is_none()) {
+            Some(index) => Err(TokenOutOfBounds { index }),
+            None => Ok(TokenView { js, tokens }),
+        }
+    }
+
+    pub fn js(&self) -> &'a str {
+        self.js
+    }
+
+    pub fn tokens(&self) -> &'a [JsmnTok] {
+        self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The text of the token at `idx`, see [`JsmnTok::as_str`]. Like
+    /// slice indexing, this panics if there is no such token.
+    pub fn text(&self, idx: usize) -> &'a str {
+        self.tokens[idx].as_str(self.js)
+    }
+
+    /// The text of the token at `idx`, or None if there is no such token.
+    pub fn get(&self, idx: usize) -> Option<&'a str> {
+        self.tokens.get(idx).map(|tok| tok.as_str(self.js))
+    }
+
+    /// Returns an iterator over the tokens with their text.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a JsmnTok, &'a str)> + use<'a> {
+        let js = self.js;
+        self.tokens.iter().map(move |tok| (tok, tok.as_str(js)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_to_vec;
+
+    #[test]
+    fn checked_once() {
+        let js = r#"{"ключ": ["значение", 1]}"#;
+        let tokens = parse_to_vec(js).unwrap();
+        let view = TokenView::new(js, &tokens).unwrap();
+
+        assert_eq!(view.len(), 5);
+        assert_eq!(view.text(1), "ключ");
+        assert_eq!(view.get(3), Some("значение"));
+        assert_eq!(view.get(5), None);
+        let texts: Vec<_> = view.iter().skip(3).map(|(_, text)| text).collect();
+        assert_eq!(texts, ["значение", "1"]);
+    }
+
+    #[test]
+    fn rejects_foreign_tokens() {
+        let js = r#"["é", "abc"]"#;
+        let tokens = parse_to_vec(js).unwrap();
+        assert_eq!(
+            TokenView::new(&js[..6], &tokens),
+            Err(TokenOutOfBounds { index: 0 })
+        );
+
+        let mut shifted = tokens.clone();
+        shifted[1].start += 1;
+        assert_eq!(
+            TokenView::new(js, &shifted),
+            Err(TokenOutOfBounds { index: 1 })
+        );
+        assert_eq!(
+            TokenView::new(js, &tokens[1..]).map(|view| view.text(1)),
+            Ok("abc")
+        );
+    }
+}
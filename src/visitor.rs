@@ -0,0 +1,306 @@
+//!
+//! This module reads a document as a sequence of events handed to a
+//! visitor, in the manner of SAX, for consumers that act on each value as
+//! it goes by and keep nothing of the document's structure.
+//!
+//! The events come from a small tokenizer of their own that walks the text
+//! once and never fills in a token array: besides the visitor, the only
+//! memory it needs is one entry per level of nesting. Strings and keys are
+//! handed over as raw spans, escapes included, so nothing is copied unless
+//! the visitor decodes them with [`crate::decode::unescape`]. Primitives
+//! must be `true`, `false`, `null` or a number, as in jsmn's strict mode,
+//! and several values in a row are read as several roots.
+//!
+//! Errors are reported as by the parser, with the byte offset where reading
+//! stopped and the number of tokens jsmn would have produced by then. The
+//! events before an error have already been delivered.
+//!
+
+use crate::value::is_number;
+use crate::{JsmnErr, ParseError};
+
+/// Receives the events of a document. Every method does nothing unless
+/// overridden, so a visitor only implements the events it cares about.
+pub trait JsonVisitor {
+    fn start_object(&mut self) {}
+    fn end_object(&mut self) {}
+    fn start_array(&mut self) {}
+    fn end_array(&mut self) {}
+    /// An object key, without its quotes and with its escapes.
+    fn key(&mut self, _raw: &str) {}
+    /// A string value, without its quotes and with its escapes.
+    fn string(&mut self, _raw: &str) {}
+    /// A number, as written.
+    fn number(&mut self, _text: &str) {}
+    fn bool(&mut self, _value: bool) {}
+    fn null(&mut self) {}
+}
+
+/// What the tokenizer expects to read next.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Expect {
+    /// A root, or the value of the current member.
+    Value,
+    /// An object key, or the closing brace if this is the first member.
+    Key {
+        first: bool,
+    },
+    Colon,
+    /// An array element, or the closing bracket if this is the first one.
+    Element {
+        first: bool,
+    },
+    /// A comma or the end of the current container.
+    Separator,
+}
+
+/// Reads `js` and hands its events to `visitor`, in document order.
+pub fn parse_events<V: JsonVisitor + ?Sized>(js: &str, visitor: &mut V) -> Result<(), ParseError> {
+    let bytes = js.as_bytes();
+    // One entry per open container, true for arrays.
+    let mut stack: Vec<bool> = Vec::new();
+    let mut expect = Expect::Value;
+    let mut pos = 0;
+    let mut tokens = 0;
+    let error = |kind, pos, tokens| ParseError { kind, pos, tokens };
+
+    while let Some(&c) = bytes.get(pos) {
+        if matches!(c, b' ' | b'\t' | b'\n' | b'\r') {
+            pos += 1;
+            continue;
+        }
+        let in_array = stack.last().copied();
+
+        match (expect, c) {
+            (Expect::Value | Expect::Element { .. }, b'{' | b'[') => {
+                tokens += 1;
+                if c == b'{' {
+                    visitor.start_object();
+                    expect = Expect::Key { first: true };
+                } else {
+                    visitor.start_array();
+                    expect = Expect::Element { first: true };
+                }
+                stack.push(c == b'[');
+                pos += 1;
+                continue;
+            }
+            (Expect::Key { first: true } | Expect::Separator, b'}') if in_array == Some(false) => {
+                stack.pop();
+                visitor.end_object();
+                pos += 1;
+            }
+            (Expect::Element { first: true } | Expect::Separator, b']')
+                if in_array == Some(true) =>
+            {
+                stack.pop();
+                visitor.end_array();
+                pos += 1;
+            }
+            (Expect::Separator, b',') => {
+                expect = if in_array == Some(true) {
+                    Expect::Element { first: false }
+                } else {
+                    Expect::Key { first: false }
+                };
+                pos += 1;
+                continue;
+            }
+            (Expect::Colon, b':') => {
+                expect = Expect::Value;
+                pos += 1;
+                continue;
+            }
+            (Expect::Key { .. }, b'"') => {
+                let end = string_end(bytes, pos).map_err(|kind| error(kind, pos, tokens))?;
+                tokens += 1;
+                visitor.key(&js[pos + 1..end]);
+                expect = Expect::Colon;
+                pos = end + 1;
+                continue;
+            }
+            (Expect::Value | Expect::Element { .. }, b'"') => {
+                let end = string_end(bytes, pos).map_err(|kind| error(kind, pos, tokens))?;
+                tokens += 1;
+                visitor.string(&js[pos + 1..end]);
+                pos = end + 1;
+            }
+            (Expect::Value | Expect::Element { .. }, _) => {
+                let len = bytes[pos..]
+                    .iter()
+                    .position(|&b| matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}'))
+                    .unwrap_or(bytes.len() - pos);
+                let text = &js[pos..pos + len];
+                match text {
+                    "true" => visitor.bool(true),
+                    "false" => visitor.bool(false),
+                    "null" => visitor.null(),
+                    _ if is_number(text) => visitor.number(text),
+                    _ => return Err(error(JsmnErr::JsmErrorInval, pos, tokens)),
+                }
+                tokens += 1;
+                pos += len;
+            }
+            _ => return Err(error(JsmnErr::JsmErrorInval, pos, tokens)),
+        }
+
+        // A value or a container just ended.
+        expect = if stack.is_empty() {
+            Expect::Value
+        } else {
+            Expect::Separator
+        };
+    }
+
+    if stack.is_empty() {
+        Ok(())
+    } else {
+        Err(error(JsmnErr::JsmErrorPart, js.len(), tokens))
+    }
+}
+
+/// Returns the offset of the closing quote of the string starting at
+/// `start`, checking escapes the same way jsmn does.
+fn string_end(bytes: &[u8], start: usize) -> Result<usize, JsmnErr> {
+    let mut pos = start + 1;
+    loop {
+        match bytes.get(pos) {
+            None => return Err(JsmnErr::JsmErrorPart),
+            Some(b'"') => return Ok(pos),
+            Some(b'\\') => {
+                pos += 1;
+                match bytes.get(pos) {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {}
+                    Some(b'u') => {
+                        for _ in 0..4 {
+                            pos += 1;
+                            match bytes.get(pos) {
+                                Some(h) if h.is_ascii_hexdigit() => {}
+                                Some(_) => return Err(JsmnErr::JsmErrorInval),
+                                None => return Err(JsmnErr::JsmErrorPart),
+                            }
+                        }
+                    }
+                    Some(_) => return Err(JsmnErr::JsmErrorInval),
+                    None => return Err(JsmnErr::JsmErrorPart),
+                }
+            }
+            Some(_) => {}
+        }
+        pos += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_tokens;
+
+    /// Writes every event down as a line.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl JsonVisitor for Recorder {
+        fn start_object(&mut self) {
+            self.0.push("{".into());
+        }
+        fn end_object(&mut self) {
+            self.0.push("}".into());
+        }
+        fn start_array(&mut self) {
+            self.0.push("[".into());
+        }
+        fn end_array(&mut self) {
+            self.0.push("]".into());
+        }
+        fn key(&mut self, raw: &str) {
+            self.0.push(format!("key {}", raw));
+        }
+        fn string(&mut self, raw: &str) {
+            self.0.push(format!("string {}", raw));
+        }
+        fn number(&mut self, text: &str) {
+            self.0.push(format!("number {}", text));
+        }
+        fn bool(&mut self, value: bool) {
+            self.0.push(format!("bool {}", value));
+        }
+        fn null(&mut self) {
+            self.0.push("null".into());
+        }
+    }
+
+    fn events(js: &str) -> Result<Vec<String>, ParseError> {
+        let mut recorder = Recorder::default();
+        parse_events(js, &mut recorder).map(|()| recorder.0)
+    }
+
+    #[test]
+    fn events_in_order() {
+        let js = r#"{"id": -1.5e3, "tags": ["a\"b", true, null, {}], "ok": false} [[]] 7"#;
+        assert_eq!(
+            events(js).unwrap(),
+            [
+                "{",
+                "key id",
+                "number -1.5e3",
+                "key tags",
+                "[",
+                r#"string a\"b"#,
+                "bool true",
+                "null",
+                "{",
+                "}",
+                "]",
+                "key ok",
+                "bool false",
+                "}",
+                "[",
+                "[",
+                "]",
+                "]",
+                "number 7",
+            ]
+        );
+        assert_eq!(events("").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn only_some_events() {
+        /// Sums every number, whatever the nesting.
+        struct Sum(f64);
+
+        impl JsonVisitor for Sum {
+            fn number(&mut self, text: &str) {
+                self.0 += text.parse::<f64>().unwrap();
+            }
+        }
+
+        let mut sum = Sum(0.0);
+        parse_events(r#"{"a": [1, 2, {"b": 3.5}], "c": "4"}"#, &mut sum).unwrap();
+        assert_eq!(sum.0, 6.5);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |js| events(js).unwrap_err();
+        assert_eq!(
+            err(r#"{"a": [1, 2"#),
+            ParseError {
+                kind: JsmnErr::JsmErrorPart,
+                pos: 11,
+                tokens: 5,
+            }
+        );
+        assert_eq!(count_tokens(r#"{"a": [1, 2]}"#), Ok(5));
+        assert_eq!(err(r#"["a", "b\q"]"#).pos, 6);
+        assert_eq!(err(r#"["a", "b"#).kind, JsmnErr::JsmErrorPart);
+        assert_eq!(err(r#"{"a": 1]"#).pos, 7);
+        assert_eq!(err(r#"{"a": }"#).pos, 6);
+        assert_eq!(err(r#"{"a" 1}"#).pos, 5);
+        assert_eq!(err("[1,]").pos, 3);
+        assert_eq!(err("[tru]").pos, 1);
+        assert_eq!(err(r#"{1: 2}"#).pos, 1);
+        assert_eq!(err(r#"["a" "b"]"#).kind, JsmnErr::JsmErrorInval);
+    }
+}
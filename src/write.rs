@@ -0,0 +1,323 @@
+//!
+//! This module re-emits parsed documents, either compact or pretty-printed.
+//! Strings and keys are copied with their original escapes, and numbers
+//! keep their source spelling unless number normalization is turned on.
+//!
+//! Normalized numbers use the shortest digits that read back as the same
+//! `f64`, laid out the way ECMAScript prints numbers: `1e21` and up, and
+//! below `1e-6`, get an exponent, everything else is written out in full.
+//! Integers that fit in an `i64` are written exactly. The digits come from
+//! the standard library, or from `ryu` and `itoa` with the fast-numbers
+//! feature, and the output is the same either way.
+//!
+//! Numbers survive a round trip exactly: reading a normalized number back
+//! with a correctly rounded parser, such as `Value::as_f64_exact`, gives
+//! the very same bits, and unnormalized numbers are copied verbatim.
+//!
+
+use std::fmt::Write;
+
+use crate::{JsmnErr, JsmnTok, JsmnType, parse_to_vec};
+
+/// Re-emits documents from their tokens.
+#[derive(Debug, Clone, Default)]
+pub struct Writer {
+    indent: Option<String>,
+    normalize_numbers: bool,
+}
+
+impl Writer {
+    /// A writer producing compact output, without any whitespace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A writer putting every member and element on its own line, indented
+    /// by `indent` spaces per level.
+    pub fn pretty(indent: usize) -> Self {
+        Writer {
+            indent: Some(" ".repeat(indent)),
+            ..Self::default()
+        }
+    }
+
+    /// Rewrites numbers in their shortest round-trip form instead of
+    /// copying their source spelling.
+    pub fn normalize_numbers(&mut self, normalize: bool) -> &mut Self {
+        self.normalize_numbers = normalize;
+        self
+    }
+
+    /// Parses `js` and re-emits it.
+    pub fn write_str(&self, js: &str) -> Result<String, JsmnErr> {
+        let tokens = parse_to_vec(js)?;
+        Ok(self.write(js, &tokens))
+    }
+
+    /// Re-emits an already parsed document. Several roots are written one
+    /// per line.
+    pub fn write(&self, js: &str, tokens: &[JsmnTok]) -> String {
+        let mut out = String::with_capacity(js.len());
+        // Open containers, as Some(is_object), and keys, as None, with the
+        // number of children they have and how many were written so far.
+        let mut stack: Vec<(Option<bool>, usize, usize)> = Vec::new();
+        let mut depth = 0;
+
+        for (idx, tok) in tokens.iter().enumerate() {
+            match stack.last_mut() {
+                None if idx > 0 => out.push('\n'),
+                None => {}
+                Some((None, _, written)) => *written += 1,
+                Some((Some(_), _, written)) => {
+                    if *written > 0 {
+                        out.push(',');
+                    }
+                    *written += 1;
+                    self.newline(&mut out, depth);
+                }
+            }
+
+            let text = &js[tok.start as usize..tok.end as usize];
+            match tok.typ {
+                JsmnType::JsmnObject | JsmnType::JsmnArray => {
+                    let object = tok.typ == JsmnType::JsmnObject;
+                    out.push(if object { '{' } else { '[' });
+                    stack.push((Some(object), tok.size.max(0) as usize, 0));
+                    depth += 1;
+                }
+                JsmnType::JsmnString => {
+                    out.push('"');
+                    out.push_str(text);
+                    out.push('"');
+                    if tok.size > 0 {
+                        out.push(':');
+                        if self.indent.is_some() {
+                            out.push(' ');
+                        }
+                        stack.push((None, 1, 0));
+                    }
+                }
+                _ => match self.normalize_numbers.then(|| normalize_number(text)) {
+                    Some(Some(number)) => out.push_str(&number),
+                    _ => out.push_str(text),
+                },
+            }
+
+            while let Some(&(kind, size, written)) = stack.last() {
+                if written < size {
+                    break;
+                }
+                stack.pop();
+                if let Some(object) = kind {
+                    depth -= 1;
+                    if written > 0 {
+                        self.newline(&mut out, depth);
+                    }
+                    out.push(if object { '}' } else { ']' });
+                }
+            }
+        }
+        out
+    }
+
+    fn newline(&self, out: &mut String, depth: usize) {
+        if let Some(indent) = &self.indent {
+            out.push('\n');
+            for _ in 0..depth {
+                out.push_str(indent);
+            }
+        }
+    }
+}
+
+/// The layouts [`reformat`] can give a document.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Style {
+    /// Every member and element on its own line, indented by this many
+    /// spaces per level.
+    Indent(usize),
+    /// No whitespace at all, as for minifying.
+    Compact,
+}
+
+/// Re-emits a parsed document in `style`, copying strings and numbers
+/// from their spans as they are. This is a shorthand for a [`Writer`]
+/// without number normalization.
+pub fn reformat(js: &str, tokens: &[JsmnTok], style: Style) -> String {
+    let writer = match style {
+        Style::Indent(indent) => Writer::pretty(indent),
+        Style::Compact => Writer::new(),
+    };
+    writer.write(js, tokens)
+}
+
+/// Returns the shortest spelling of a JSON number that reads back as the
+/// same `i64` or `f64`, or None if `text` is not a finite number.
+pub fn normalize_number(text: &str) -> Option<String> {
+    let number = text.strip_prefix('-').unwrap_or(text);
+    if !number.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    if !text.contains(['.', 'e', 'E'])
+        && let Ok(int) = text.parse::<i64>()
+    {
+        return Some(format_int(int));
+    }
+
+    let float: f64 = text.parse().ok().filter(|f: &f64| f.is_finite())?;
+    Some(format_float(float))
+}
+
+#[cfg(feature = "fast-numbers")]
+pub(crate) fn format_int(int: i64) -> String {
+    itoa::Buffer::new().format(int).to_string()
+}
+
+#[cfg(not(feature = "fast-numbers"))]
+pub(crate) fn format_int(int: i64) -> String {
+    int.to_string()
+}
+
+#[cfg(feature = "fast-numbers")]
+fn shortest_digits(float: f64) -> (String, i32) {
+    split_digits(ryu::Buffer::new().format_finite(float))
+}
+
+#[cfg(not(feature = "fast-numbers"))]
+fn shortest_digits(float: f64) -> (String, i32) {
+    split_digits(&format!("{:e}", float))
+}
+
+/// Splits a decimal or exponent spelling of a positive number into its
+/// significant digits and the exponent `n` such that the number is
+/// `0.digits * 10^n`.
+fn split_digits(text: &str) -> (String, i32) {
+    let (mantissa, exp) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => (mantissa, exp.parse().unwrap_or(0)),
+        None => (text, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let all = format!("{}{}", int, frac);
+    let leading = all.bytes().take_while(|&b| b == b'0').count();
+    let digits = all[leading..].trim_end_matches('0').to_string();
+    (digits, exp + int.len() as i32 - leading as i32)
+}
+
+pub(crate) fn format_float(float: f64) -> String {
+    if float == 0.0 {
+        return if float.is_sign_negative() { "-0" } else { "0" }.to_string();
+    }
+
+    let mut out = String::new();
+    if float < 0.0 {
+        out.push('-');
+    }
+    let (digits, n) = shortest_digits(float.abs());
+    let k = digits.len() as i32;
+
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        write!(out, "e{}{}", if n > 0 { "+" } else { "-" }, (n - 1).abs()).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_and_pretty() {
+        let js = "{ \"a\" : [1, 2.50, {}], \"b\\n\": {\"c\": []} }";
+        assert_eq!(
+            Writer::new().write_str(js).unwrap(),
+            r#"{"a":[1,2.50,{}],"b\n":{"c":[]}}"#
+        );
+        assert_eq!(
+            Writer::pretty(2).write_str(js).unwrap(),
+            "{\n  \"a\": [\n    1,\n    2.50,\n    {}\n  ],\n  \"b\\n\": {\n    \"c\": []\n  }\n}"
+        );
+        assert_eq!(Writer::new().write_str(" 1 [ ] ").unwrap(), "1\n[]");
+    }
+
+    #[test]
+    fn reformat_styles() {
+        let js = "{\"a\":[1e3,\"\\u00e9\"],\"b\":{}}";
+        let tokens = parse_to_vec(js).unwrap();
+        let pretty = reformat(js, &tokens, Style::Indent(4));
+        assert_eq!(
+            pretty,
+            "{\n    \"a\": [\n        1e3,\n        \"\\u00e9\"\n    ],\n    \"b\": {}\n}"
+        );
+        let tokens = parse_to_vec(&pretty).unwrap();
+        assert_eq!(reformat(&pretty, &tokens, Style::Compact), js);
+    }
+
+    #[test]
+    fn normalized_numbers() {
+        let mut writer = Writer::new();
+        writer.normalize_numbers(true);
+        assert_eq!(
+            writer.write_str("[2.50, 1E3, -0.0, 100, true]").unwrap(),
+            "[2.5,1000,-0,100,true]"
+        );
+
+        let cases = [
+            ("007", "7"),
+            ("-12", "-12"),
+            ("9223372036854775807", "9223372036854775807"),
+            ("9223372036854775808", "9223372036854776000"),
+            ("1e21", "1e+21"),
+            ("123456789012345678901", "123456789012345680000"),
+            ("0.000001", "0.000001"),
+            ("1.5e-7", "1.5e-7"),
+            ("0.1", "0.1"),
+            ("1.0000000000000002", "1.0000000000000002"),
+            ("5e-324", "5e-324"),
+            ("-1.7976931348623157e308", "-1.7976931348623157e+308"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                normalize_number(input).as_deref(),
+                Some(expected),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(normalize_number("1e999"), None);
+        assert_eq!(normalize_number("null"), None);
+    }
+
+    #[test]
+    fn normalized_floats_round_trip() {
+        let mut bits: u64 = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..20_000 {
+            bits = bits
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            let float = f64::from_bits(bits);
+            if !float.is_finite() {
+                continue;
+            }
+            let text = format_float(float);
+            assert_eq!(text.parse::<f64>().map(f64::to_bits), Ok(bits), "{}", text);
+        }
+    }
+}